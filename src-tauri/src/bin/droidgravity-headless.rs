@@ -0,0 +1,6 @@
+// Headless 反代服务入口 (无 GUI，适用于服务器 / Docker)
+// 配置与账号数据与桌面版共用同一数据目录
+
+fn main() {
+    droidgravity_manager_lib::run_headless()
+}
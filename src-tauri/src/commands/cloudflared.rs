@@ -40,9 +40,7 @@ pub async fn cloudflared_check(
         Ok(CloudflaredStatus {
             installed,
             version,
-            running: false,
-            url: None,
-            error: None,
+            ..Default::default()
         })
     } else {
         Err("Manager not initialized".to_string())
@@ -117,4 +115,3 @@ pub async fn cloudflared_get_status(
         Ok(CloudflaredStatus::default())
     }
 }
-
@@ -9,6 +9,7 @@ pub mod proxy;
 pub mod autostart;
 pub mod security;
 pub mod cloudflared;
+pub mod vault;
 
 /// 列出所有账号
 #[tauri::command]
@@ -25,7 +26,7 @@ pub async fn add_account(
 ) -> Result<Account, String> {
     // 1. 使用 refresh_token 获取 access_token
     // 注意：这里我们忽略传入的 _email，而是直接去 Google 获取真实的邮箱
-    let token_res = modules::oauth::refresh_access_token(&refresh_token).await?;
+    let token_res = modules::oauth::refresh_access_token(&refresh_token, None).await?;
 
     // 2. 获取用户信息
     let user_info = modules::oauth::get_user_info(&token_res.access_token).await?;
@@ -295,13 +296,26 @@ pub async fn load_config() -> Result<AppConfig, String> {
 pub async fn save_config(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
-    config: AppConfig,
+    mut config: AppConfig,
 ) -> Result<(), String> {
+    // 明文管理员密码在落盘前替换为哈希
+    crate::proxy::admin_auth::normalize_admin_password(&mut config.proxy)?;
+    config.proxy.model_fallback.validate()?;
+    config.proxy.model_router.validate()?;
+    config.proxy.response_cache.validate()?;
+    config.proxy.monitor_policy.validate()?;
     modules::save_app_config(&config)?;
 
     // 通知托盘配置已更新
     let _ = app.emit("config://updated", ());
 
+    // 账号独立代理的回退规则为全局状态，服务未运行时也同步
+    crate::proxy::upstream::proxy_pool::pool().configure(&config.proxy.account_proxy);
+    // 路由规则、响应缓存与监控日志策略同样是全局状态
+    crate::proxy::model_router::router().configure(&config.proxy.model_router);
+    crate::proxy::response_cache::cache().configure(&config.proxy.response_cache);
+    crate::proxy::monitor_policy::policy().configure(&config.proxy.monitor_policy);
+
     // 热更新正在运行的服务
     let instance_lock = proxy_state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
@@ -316,6 +330,18 @@ pub async fn save_config(
         instance.axum_server.update_security(&config.proxy).await;
         // 更新 z.ai 配置
         instance.axum_server.update_zai(&config.proxy).await;
+        // 更新 MCP 网关
+        instance.axum_server.update_mcp_gateway(&config.proxy).await;
+        // 更新路由策略
+        instance
+            .token_manager
+            .update_routing_config(config.proxy.routing.clone())
+            .await;
+        // 更新模型降级链
+        instance
+            .token_manager
+            .update_model_fallback_config(config.proxy.model_fallback.clone())
+            .await;
         tracing::debug!("已同步热更新反代服务配置");
     }
 
@@ -349,7 +375,7 @@ pub async fn start_oauth_login(app_handle: tauri::AppHandle) -> Result<Account,
     modules::logger::log_info(&format!("获取用户信息成功: {}", user_info.email));
 
     // 4. 尝试获取项目ID
-    let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token)
+    let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token, None)
         .await
         .ok();
 
@@ -415,7 +441,7 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
     modules::logger::log_info(&format!("获取用户信息成功: {}", user_info.email));
 
     // 4. 尝试获取项目ID
-    let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token)
+    let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token, None)
         .await
         .ok();
 
@@ -705,12 +731,9 @@ pub async fn toggle_proxy_status(
         return Err(format!("账号文件不存在: {}", account_id));
     }
 
-    let content = std::fs::read_to_string(&account_path)
+    let mut account_json = modules::vault::read_account_json(&account_path)
         .map_err(|e| format!("读取账号文件失败: {}", e))?;
 
-    let mut account_json: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("解析账号文件失败: {}", e))?;
-
     // 2. 更新 proxy_disabled 字段
     if enable {
         // 启用反代
@@ -728,7 +751,7 @@ pub async fn toggle_proxy_status(
     }
 
     // 3. 保存到磁盘
-    std::fs::write(&account_path, serde_json::to_string_pretty(&account_json).unwrap())
+    modules::vault::write_account_json(&account_path, &account_json)
         .map_err(|e| format!("写入账号文件失败: {}", e))?;
 
     modules::logger::log_info(&format!(
@@ -755,11 +778,21 @@ pub async fn clear_cache() -> Result<modules::cache::ClearResult, String> {
 /// 更新账号的个人代理设置
 #[tauri::command]
 pub async fn update_account_individual_proxy(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
     account_id: String,
     proxy_url: Option<String>,
 ) -> Result<(), String> {
+    let proxy_url = proxy_url.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(url) = &proxy_url {
+        reqwest::Proxy::all(url).map_err(|e| format!("无效的代理地址: {}", e))?;
+    }
     let mut account = modules::load_account(&account_id)?;
     account.individual_proxy = proxy_url;
     modules::save_account(&account)?;
+
+    // 运行中的反代服务立即切换到新代理
+    if let Some(instance) = proxy_state.instance.read().await.as_ref() {
+        instance.token_manager.reload_account(&account_id).await?;
+    }
     Ok(())
 }
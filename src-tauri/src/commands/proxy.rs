@@ -127,6 +127,16 @@ pub async fn internal_start_proxy_service(
     // 同步配置到运行中的 TokenManager
     token_manager.start_auto_cleanup();
     token_manager.update_sticky_config(config.scheduling.clone()).await;
+    token_manager.update_routing_config(config.routing.clone()).await;
+    token_manager.update_model_fallback_config(config.model_fallback.clone()).await;
+    crate::proxy::model_router::router().configure(&config.model_router);
+    crate::proxy::response_cache::cache().configure(&config.response_cache);
+    crate::proxy::monitor_policy::policy().configure(&config.monitor_policy);
+
+    // [NEW] 账号独立代理: 回退规则与后台健康检查
+    let proxy_pool = crate::proxy::upstream::proxy_pool::pool();
+    proxy_pool.configure(&config.account_proxy);
+    proxy_pool.start_health_checker();
     
     // [NEW] 加载熔断配置 (从主配置加载)
     let app_config = crate::modules::config::load_app_config().unwrap_or_else(|_| crate::models::AppConfig::new());
@@ -169,6 +179,7 @@ pub async fn internal_start_proxy_service(
     };
     
     // [FIX] Ensure the server is logically running
+    axum_server.update_mcp_gateway(&config).await;
     axum_server.set_running(true).await;
     
     *instance_lock = Some(instance);
@@ -258,6 +269,12 @@ pub async fn stop_proxy_service(
     // 停止 Axum 服务器 (仅逻辑停止，不杀死进程)
     if let Some(instance) = instance_lock.take() {
         instance.axum_server.set_running(false).await;
+        // [NEW] 停止前落盘限流状态
+        if let Err(e) = instance.token_manager.persist_limit_state().await {
+            tracing::warn!("Failed to persist rate limit state: {}", e);
+        }
+        // [NEW] 关闭 MCP 网关会话 (终止 stdio 子进程)
+        instance.axum_server.mcp_gateway.close_all().await;
         // 已移除 instance.axum_server.stop() 调用，防止杀死 Admin Server
     }
     
@@ -449,6 +466,20 @@ pub async fn get_proxy_logs_filtered(
     crate::modules::proxy_db::get_logs_filtered(&filter, errors_only, limit, offset)
 }
 
+/// [NEW] 全文检索日志 (语法见 modules::log_search)
+#[tauri::command]
+pub async fn search_proxy_logs(
+    query: String,
+    limit: usize,
+    offset: usize,
+) -> Result<crate::modules::log_search::LogSearchResult, String> {
+    let parsed = crate::modules::log_search::parse_query(&query, chrono::Utc::now().timestamp_millis())?;
+    let limit = if limit == 0 { 50 } else { limit.min(500) };
+    tokio::task::spawn_blocking(move || crate::modules::proxy_db::search_logs(&parsed, limit, offset))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
 /// 生成 API Key
 #[tauri::command]
 pub fn generate_api_key() -> String {
@@ -653,6 +684,18 @@ pub async fn clear_proxy_session_bindings(
     }
 }
 
+/// 获取响应缓存统计
+#[tauri::command]
+pub async fn get_response_cache_stats() -> Result<crate::modules::response_cache_db::ResponseCacheStats, String> {
+    crate::proxy::response_cache::cache().stats().await
+}
+
+/// 清空响应缓存，返回删除的条目数
+#[tauri::command]
+pub async fn clear_response_cache() -> Result<usize, String> {
+    crate::proxy::response_cache::cache().clear().await
+}
+
 // ===== [FIX #820] 固定账号模式命令 =====
 
 /// 设置优先使用的账号（固定账号模式）
@@ -710,7 +753,9 @@ pub async fn clear_proxy_rate_limit(
 ) -> Result<bool, String> {
     let instance_lock = state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
-        Ok(instance.token_manager.clear_rate_limit(&account_id))
+        let cleared = instance.token_manager.clear_rate_limit(&account_id);
+        let _ = instance.token_manager.persist_limit_state().await;
+        Ok(cleared)
     } else {
         Err("服务未运行".to_string())
     }
@@ -724,6 +769,7 @@ pub async fn clear_all_proxy_rate_limits(
     let instance_lock = state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
         instance.token_manager.clear_all_rate_limits();
+        let _ = instance.token_manager.persist_limit_state().await;
         Ok(())
     } else {
         Err("服务未运行".to_string())
@@ -1120,7 +1166,12 @@ pub async fn manual_kiro_token_input(
     crate::modules::logger::log_info("=== FETCHING KIRO QUOTA ===");
     
     // Fetch quota for Kiro account
-    match crate::modules::quota::fetch_quota(&final_account.token.access_token, &final_account.email).await {
+    match crate::modules::quota::fetch_quota(
+        &final_account.token.access_token,
+        &final_account.email,
+        final_account.individual_proxy.as_deref(),
+    )
+    .await {
         Ok((quota_data, _subscription_tier)) => {
             crate::modules::logger::log_info(&format!(
                 "✅ Quota fetched successfully: {} models, tier: {:?}",
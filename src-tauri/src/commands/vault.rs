@@ -0,0 +1,32 @@
+// 账号保险库命令 (口令模式需在桌面端输入口令解锁)
+use crate::modules::vault;
+use tauri::State;
+
+use super::proxy::ProxyServiceState;
+
+#[tauri::command]
+pub async fn get_vault_status() -> Result<vault::VaultStatus, String> {
+    vault::status()
+}
+
+/// 使用口令解锁保险库，并重新加载运行中反代服务的账号池
+#[tauri::command]
+pub async fn unlock_vault(
+    passphrase: String,
+    proxy_state: State<'_, ProxyServiceState>,
+) -> Result<vault::VaultStatus, String> {
+    tokio::task::spawn_blocking(move || {
+        vault::unlock(&vault::VaultCredential::Passphrase { passphrase })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    if let Some(instance) = proxy_state.instance.read().await.as_ref() {
+        instance
+            .token_manager
+            .load_accounts()
+            .await
+            .map_err(|e| format!("重新加载账号失败: {}", e))?;
+    }
+    vault::status()
+}
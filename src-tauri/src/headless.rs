@@ -0,0 +1,112 @@
+// Headless 模式 - 不启动 Tauri GUI，仅运行反代服务与管理 API (适用于无显示器的 Linux 服务器)
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::commands::cloudflared::CloudflaredState;
+use crate::commands::proxy::{internal_start_proxy_service, ProxyServiceState};
+use crate::modules::{config, integration::SystemManager, vault};
+use crate::proxy::admin_auth;
+
+/// 等待服务器监听循环退出的最长时间
+const SHUTDOWN_TIMEOUT_SECS: u64 = 10;
+
+/// 启动 Headless 反代服务，阻塞直到收到 SIGTERM / Ctrl+C
+pub async fn run() -> Result<(), String> {
+    let mut app_config = config::load_app_config()?;
+    admin_auth::apply_env_admin_password(&mut app_config)?;
+    let proxy_config = app_config.proxy.clone();
+
+    // 账号保险库: 通过密钥文件或 DROIDGRAVITY_VAULT_PASSPHRASE 在加载账号前解锁
+    vault::auto_unlock();
+
+    let state = ProxyServiceState::new();
+    let cloudflared_state = Arc::new(CloudflaredState::new());
+
+    // 复用桌面版的启动逻辑：SystemManager::Headless 下 TokenManager 与 ProxyMonitor 均不依赖 AppHandle
+    let status = internal_start_proxy_service(
+        proxy_config.clone(),
+        &state,
+        SystemManager::Headless,
+        cloudflared_state.clone(),
+    )
+    .await?;
+
+    if status.running {
+        info!(
+            "[Headless] 反代服务已启动: {} ({} 个可用账号)",
+            status.base_url, status.active_accounts
+        );
+    } else {
+        warn!(
+            "[Headless] 管理 API 已启动于 {}，但没有可用账号，请通过 /api/accounts 添加",
+            status.base_url
+        );
+    }
+
+    wait_for_shutdown_signal().await;
+    info!("[Headless] 收到停止信号，正在关闭反代服务...");
+
+    shutdown(&state, &cloudflared_state).await;
+    Ok(())
+}
+
+/// 通过 AxumServer 的 shutdown_tx 停止监听，并等待监听循环退出
+async fn shutdown(state: &ProxyServiceState, cloudflared_state: &CloudflaredState) {
+    // 逻辑停止反代实例
+    if let Some(instance) = state.instance.write().await.take() {
+        instance.axum_server.set_running(false).await;
+        instance.axum_server.mcp_gateway.close_all().await;
+        if let Err(e) = instance.token_manager.persist_limit_state().await {
+            warn!("[Headless] 限流状态落盘失败: {}", e);
+        }
+    }
+
+    if let Some(admin) = state.admin_server.write().await.take() {
+        admin.axum_server.stop();
+        match tokio::time::timeout(
+            Duration::from_secs(SHUTDOWN_TIMEOUT_SECS),
+            admin.server_handle,
+        )
+        .await
+        {
+            Ok(Ok(())) => info!("[Headless] 反代服务器已停止"),
+            Ok(Err(e)) => error!("[Headless] 反代服务器任务异常退出: {}", e),
+            Err(_) => warn!(
+                "[Headless] 等待反代服务器停止超时 ({}s)",
+                SHUTDOWN_TIMEOUT_SECS
+            ),
+        }
+    }
+
+    // 如果通过管理 API 启动过 Cloudflared 隧道，一并停止
+    if let Some(manager) = cloudflared_state.manager.read().await.as_ref() {
+        if let Err(e) = manager.stop().await {
+            warn!("[Headless] 停止 Cloudflared 失败: {}", e);
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("[Headless] 无法注册 SIGTERM 处理器: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+
+    tokio::select! {
+        _ = sigterm.recv() => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_shutdown_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
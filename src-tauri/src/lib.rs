@@ -4,6 +4,7 @@ mod modules;
 mod commands;
 mod utils;
 mod proxy;  // 反代服务模块
+mod headless; // Headless 模式 (无 GUI)
 pub mod error;
 
 use tauri::Manager;
@@ -16,6 +17,25 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+/// Headless 入口：不创建 Tauri 窗口，仅运行反代服务与管理 API
+pub fn run_headless() {
+    // 初始化日志
+    logger::init_logger();
+
+    let runtime = match tokio::runtime::Builder::new_multi_thread().enable_all().build() {
+        Ok(rt) => rt,
+        Err(e) => {
+            error!("创建 Tokio 运行时失败: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = runtime.block_on(headless::run()) {
+        error!("Headless 反代服务启动失败: {}", e);
+        std::process::exit(1);
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // 初始化日志
@@ -49,8 +69,15 @@ pub fn run() {
             // 自动启动反代服务
             let handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
+                // 账号保险库需在加载账号前解锁 (口令模式无环境变量时由前端弹窗解锁)
+                modules::vault::auto_unlock();
+
                 // 加载配置
-                if let Ok(config) = modules::config::load_app_config() {
+                if let Ok(mut config) = modules::config::load_app_config() {
+                    // 设置界面不提供管理员密码字段，与 headless 一样通过环境变量设置
+                    if let Err(e) = proxy::admin_auth::apply_env_admin_password(&mut config) {
+                        error!("应用 {} 失败: {}", proxy::admin_auth::ENV_ADMIN_PASSWORD, e);
+                    }
                     if config.proxy.auto_start {
                         let state = handle.state::<commands::proxy::ProxyServiceState>();
                         let cf_state = handle.state::<crate::commands::cloudflared::CloudflaredState>();
@@ -138,6 +165,7 @@ pub fn run() {
             commands::proxy::get_proxy_status,
             commands::proxy::get_proxy_stats,
             commands::proxy::get_proxy_logs,
+            commands::proxy::search_proxy_logs,
             commands::proxy::set_proxy_monitor_enabled,
             commands::proxy::clear_proxy_logs,
             commands::proxy::generate_api_key,
@@ -147,6 +175,8 @@ pub fn run() {
             commands::proxy::get_proxy_scheduling_config,
             commands::proxy::update_proxy_scheduling_config,
             commands::proxy::clear_proxy_session_bindings,
+            commands::proxy::get_response_cache_stats,
+            commands::proxy::clear_response_cache,
             // Kiro OAuth commands
             commands::proxy::prepare_kiro_oauth_url,
             commands::proxy::start_kiro_oauth_login,
@@ -181,6 +211,9 @@ pub fn run() {
             commands::clear_cache,
             // Account 命令
             commands::update_account_individual_proxy,
+            // Vault 命令
+            commands::vault::get_vault_status,
+            commands::vault::unlock_vault,
             // Autostart 命令
             commands::autostart::toggle_auto_launch,
             commands::autostart::is_auto_launch_enabled,
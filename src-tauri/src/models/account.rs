@@ -148,6 +148,13 @@ pub struct DeviceProfileVersion {
 pub struct AccountExportItem {
     pub email: String,
     pub refresh_token: String,
+    /// 账号提供商 ("gemini" | "kiro")，旧版导出文件缺省为 gemini
+    #[serde(default = "default_provider")]
+    pub provider: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kiro_profile_arn: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kiro_user_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,10 +95,11 @@ pub fn load_account(account_id: &str) -> Result<Account, String> {
         return Err(format!("账号不存在: {}", account_id));
     }
     
-    let content = fs::read_to_string(&account_path)
+    // 经由保险库读取，透明解密 Token 字段
+    let content = crate::modules::vault::read_account_json(&account_path)
         .map_err(|e| format!("读取账号数据失败: {}", e))?;
     
-    serde_json::from_str(&content)
+    serde_json::from_value(content)
         .map_err(|e| format!("解析账号数据失败: {}", e))
 }
 
@@ -107,10 +108,11 @@ pub fn save_account(account: &Account) -> Result<(), String> {
     let accounts_dir = get_accounts_dir()?;
     let account_path = accounts_dir.join(format!("{}.json", account.id));
     
-    let content = serde_json::to_string_pretty(account)
+    let content = serde_json::to_value(account)
         .map_err(|e| format!("序列化账号数据失败: {}", e))?;
     
-    fs::write(&account_path, content)
+    // 保险库启用时 Token 字段加密落盘
+    crate::modules::vault::write_account_json(&account_path, &content)
         .map_err(|e| format!("保存账号数据失败: {}", e))
 }
 
@@ -376,7 +378,7 @@ pub async fn switch_account(account_id: &str, integration: &crate::modules::inte
     crate::modules::logger::log_info(&format!("正在切换到账号: {} (ID: {})", account.email, account.id));
     
     // 2. 确保 Token 有效（自动刷新）
-    let fresh_token = crate::modules::oauth::ensure_fresh_token(&account.token).await
+    let fresh_token = crate::modules::oauth::ensure_fresh_token(&account.token, account.individual_proxy.as_deref()).await
         .map_err(|e| format!("Token 刷新失败: {}", e))?;
         
     // 如果 Token 更新了，保存回账号文件
@@ -573,6 +575,10 @@ pub fn set_current_account_id(account_id: &str) -> Result<(), String> {
 /// 更新账号配额
 pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), String> {
     let mut account = load_account(account_id)?;
+    // [NEW] 记录配额时间序列 (失败不影响配额更新)
+    if let Err(e) = crate::modules::quota_history::record_snapshot(account_id, &account.email, &quota) {
+        crate::modules::logger::log_warn(&format!("记录配额历史失败: {}", e));
+    }
     account.update_quota(quota);
     save_account(&account)?;
     
@@ -607,6 +613,9 @@ pub fn export_accounts_by_ids(ids: &[String]) -> Result<AccountExportResponse, S
             items.push(AccountExportItem {
                 email: account.email,
                 refresh_token: account.token.refresh_token,
+                provider: account.provider,
+                kiro_profile_arn: account.kiro_profile_arn,
+                kiro_user_id: account.kiro_user_id,
             });
         }
     }
@@ -727,7 +736,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
     use reqwest::StatusCode;
     
     // 1. 基于时间的检查 (Time-based check) - 先确保 Token 有效
-    let token = match oauth::ensure_fresh_token(&account.token).await {
+    let token = match oauth::ensure_fresh_token(&account.token, account.individual_proxy.as_deref()).await {
         Ok(t) => t,
         Err(e) => {
             if e.contains("invalid_grant") {
@@ -783,7 +792,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
     }
 
     // 2. 尝试查询
-    let result: crate::error::AppResult<(QuotaData, Option<String>)> = modules::fetch_quota(&account.token.access_token, &account.email).await;
+    let result: crate::error::AppResult<(QuotaData, Option<String>)> = modules::fetch_quota(&account.token.access_token, &account.email, account.individual_proxy.as_deref()).await;
     
     // 捕获可能更新的 project_id 并保存
     if let Ok((ref _q, ref project_id)) = result {
@@ -803,7 +812,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                 modules::logger::log_warn(&format!("401 Unauthorized for {}, forcing refresh...", account.email));
                 
                 // 强制刷新
-                let token_res = match oauth::refresh_access_token(&account.token.refresh_token).await {
+                let token_res = match oauth::refresh_access_token(&account.token.refresh_token, account.individual_proxy.as_deref()).await {
                     Ok(t) => t,
                     Err(e) => {
                         if e.contains("invalid_grant") {
@@ -844,7 +853,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                 upsert_account(account.email.clone(), name, new_token.clone()).map_err(AppError::Account)?;
                 
                 // 重试查询
-                let retry_result: crate::error::AppResult<(QuotaData, Option<String>)> = modules::fetch_quota(&new_token.access_token, &account.email).await;
+                let retry_result: crate::error::AppResult<(QuotaData, Option<String>)> = modules::fetch_quota(&new_token.access_token, &account.email, account.individual_proxy.as_deref()).await;
                 
                 // 同样处理重试时的 project_id 保存
                 if let Ok((ref _q, ref project_id)) = retry_result {
@@ -14,13 +14,13 @@ impl AccountService {
     /// 添加账号逻辑
     pub async fn add_account(&self, refresh_token: &str) -> Result<Account, String> {
         // 1. 获取 Token
-        let token_res = modules::oauth::refresh_access_token(refresh_token).await?;
+        let token_res = modules::oauth::refresh_access_token(refresh_token, None).await?;
 
         // 2. 获取用户信息
         let user_info = modules::oauth::get_user_info(&token_res.access_token).await?;
 
         // 3. 获取项目 ID (尝试)
-        let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token)
+        let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token, None)
             .await
             .ok();
 
@@ -41,7 +41,7 @@ impl AccountService {
         // 6. [NEW] 自动获取配额信息（用于刷新时间排序）
         let email_for_log = account.email.clone();
         let access_token = token_res.access_token.clone();
-        match modules::quota::fetch_quota(&access_token, &email_for_log).await {
+        match modules::quota::fetch_quota(&access_token, &email_for_log, None).await {
             Ok((quota_data, new_project_id)) => {
                 account.quota = Some(quota_data);
                 if let Some(pid) = new_project_id {
@@ -75,6 +75,36 @@ impl AccountService {
         Ok(account)
     }
 
+    /// 通过 refresh_token 恢复 Kiro 账号 (账号包导入)
+    pub async fn add_kiro_account(
+        &self,
+        email: &str,
+        refresh_token: &str,
+        profile_arn: Option<&str>,
+        user_id: Option<&str>,
+    ) -> Result<Account, String> {
+        // 1. 校验并换取新 Token (Cognito 可能轮换 refresh_token)
+        let token_res = modules::oauth_kiro::refresh_access_token(refresh_token).await?;
+
+        // 2. 持久化，并写回 Kiro 专有字段
+        let token = TokenData::new(
+            token_res.access_token,
+            token_res.refresh_token,
+            token_res.expires_in,
+            Some(email.to_string()),
+            None, // Kiro 不使用 project_id
+            None,
+        );
+        let mut account = modules::upsert_account(email.to_string(), None, token)?;
+        account.provider = "kiro".to_string();
+        account.kiro_profile_arn = Some(profile_arn.map(str::to_string).unwrap_or(token_res.profile_arn));
+        account.kiro_user_id = user_id.map(str::to_string).or(account.kiro_user_id);
+        modules::account::save_account(&account)?;
+
+        modules::logger::log_info(&format!("[Service] Added/Updated Kiro account: {}", account.email));
+        Ok(account)
+    }
+
     /// 删除账号逻辑
     pub fn delete_account(&self, account_id: &str) -> Result<(), String> {
         modules::delete_account(account_id)?;
@@ -146,7 +176,7 @@ impl AccountService {
             .ok_or_else(|| "未获取到 Refresh Token。请撤销权限后重试。".to_string())?;
 
         let user_info = modules::oauth::get_user_info(&token_res.access_token).await?;
-        let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token)
+        let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token, None)
             .await
             .ok();
 
@@ -0,0 +1,162 @@
+//! Audit Log Database Module
+//! 管理端操作审计日志 (登录、账号删除/导出、配置保存等写操作)
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// 审计日志保留天数，超过的记录在写入时清理
+const RETENTION_DAYS: i64 = 180;
+
+/// 一条审计记录
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: i64,
+    pub timestamp: i64,
+    /// 操作者: 管理会话 ID，或登录失败时的 "anonymous"
+    pub actor: String,
+    /// 操作: 例如 "auth.login" 或 "DELETE /api/accounts/:accountId"
+    pub action: String,
+    /// 操作对象 (实际请求路径等)
+    pub target: Option<String>,
+    /// HTTP 状态码
+    pub status: u16,
+    pub client_ip: Option<String>,
+}
+
+/// 获取审计数据库路径 (与账号目录同级)
+pub fn get_audit_db_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("audit.db")
+}
+
+/// 连接数据库
+fn connect_db(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            actor TEXT NOT NULL,
+            action TEXT NOT NULL,
+            target TEXT,
+            status INTEGER NOT NULL,
+            client_ip TEXT
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log (timestamp DESC)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+/// 写入审计记录，并清理超过保留期的旧记录
+pub fn record(db_path: &Path, entry: &AuditEntry) -> Result<(), String> {
+    let conn = connect_db(db_path)?;
+
+    conn.execute(
+        "INSERT INTO audit_log (timestamp, actor, action, target, status, client_ip)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            entry.timestamp,
+            entry.actor,
+            entry.action,
+            entry.target,
+            entry.status,
+            entry.client_ip,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let cutoff = entry.timestamp - RETENTION_DAYS * 86400;
+    conn.execute("DELETE FROM audit_log WHERE timestamp < ?1", params![cutoff])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// 按时间倒序分页读取审计记录，可按操作前缀过滤
+pub fn list(
+    db_path: &Path,
+    limit: usize,
+    offset: usize,
+    action_prefix: Option<&str>,
+) -> Result<Vec<AuditEntry>, String> {
+    let conn = connect_db(db_path)?;
+    let pattern = format!("{}%", action_prefix.unwrap_or("").replace('%', "\\%").replace('_', "\\_"));
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, timestamp, actor, action, target, status, client_ip
+             FROM audit_log WHERE action LIKE ?1 ESCAPE '\\'
+             ORDER BY timestamp DESC, id DESC LIMIT ?2 OFFSET ?3",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(params![pattern, limit as i64, offset as i64], |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+                actor: row.get(2)?,
+                action: row.get(3)?,
+                target: row.get(4)?,
+                status: row.get(5)?,
+                client_ip: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_list() {
+        let dir = std::env::temp_dir().join(format!("audit-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = get_audit_db_path(&dir);
+        let now = 1_700_000_000;
+
+        let entry = |action: &str, timestamp: i64| AuditEntry {
+            id: 0,
+            timestamp,
+            actor: "session-1".to_string(),
+            action: action.to_string(),
+            target: Some("/api/accounts/abc".to_string()),
+            status: 200,
+            client_ip: Some("127.0.0.1".to_string()),
+        };
+
+        // 超过保留期的记录会在下一次写入时被清理
+        record(&db_path, &entry("auth.login", now - 181 * 86400)).unwrap();
+        record(&db_path, &entry("auth.login", now)).unwrap();
+        record(&db_path, &entry("DELETE /api/accounts/:accountId", now + 1)).unwrap();
+        record(&db_path, &entry("POST /api/accounts/export", now + 2)).unwrap();
+
+        let all = list(&db_path, 10, 0, None).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].action, "POST /api/accounts/export");
+
+        let logins = list(&db_path, 10, 0, Some("auth.")).unwrap();
+        assert_eq!(logins.len(), 1);
+        assert_eq!(list(&db_path, 1, 1, None).unwrap()[0].action, "DELETE /api/accounts/:accountId");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,11 +1,13 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::{Child, Command};
 use tokio::sync::RwLock;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
@@ -17,6 +19,21 @@ const DETACHED_PROCESS: u32 = 0x00000008;
 #[cfg(target_os = "windows")]
 const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
 
+/// 进程退出后的首次重启等待，之后指数退避
+const INITIAL_RESTART_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(300);
+/// 稳定运行超过该时长后退避重置
+const STABLE_RUN_DURATION: Duration = Duration::from_secs(60);
+/// 连续健康检查失败达到该次数时重启隧道进程
+const MAX_HEALTH_FAILURES: u32 = 3;
+
+/// 隧道是否处于启用状态 (含退避重启期间)，供鉴权策略判断是否暴露在公网
+static TUNNEL_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn is_tunnel_active() -> bool {
+    TUNNEL_ACTIVE.load(Ordering::SeqCst)
+}
+
 /// Cloudflared隧道模式
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -25,6 +42,8 @@ pub enum TunnelMode {
     Quick,
     /// 认证隧道(使用Token)
     Auth,
+    /// 命名隧道(本地生成 config.yml，ingress 只放行反代路径)
+    Named,
 }
 
 impl Default for TunnelMode {
@@ -48,6 +67,39 @@ pub struct CloudflaredConfig {
     /// 使用http2协议(更兼容)
     #[serde(default)]
     pub use_http2: bool,
+    /// 命名隧道名称 (`cloudflared tunnel create <name>`)
+    #[serde(default)]
+    pub tunnel_name: Option<String>,
+    /// 命名隧道的公网域名 (需已通过 route dns 指向该隧道)
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// 命名隧道凭据文件，为空时由 cloudflared 在默认目录查找
+    #[serde(default)]
+    pub credentials_file: Option<String>,
+    /// ingress 放行的路径正则，默认只暴露反代 API (不含管理后台)
+    #[serde(default = "default_ingress_paths")]
+    pub ingress_paths: Vec<String>,
+    /// 进程退出后按指数退避自动重启
+    #[serde(default = "default_auto_restart")]
+    pub auto_restart: bool,
+    /// 通过隧道访问 /healthz 的间隔(秒)，0 表示关闭
+    #[serde(default = "default_health_check_interval")]
+    pub health_check_interval_secs: u64,
+}
+
+fn default_ingress_paths() -> Vec<String> {
+    ["^/v1/", "^/v1beta/", "^/kiro/", "^/mcp/", "^/healthz$"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn default_auto_restart() -> bool {
+    true
+}
+
+fn default_health_check_interval() -> u64 {
+    60
 }
 
 impl Default for CloudflaredConfig {
@@ -58,10 +110,38 @@ impl Default for CloudflaredConfig {
             port: 8045,
             token: None,
             use_http2: true, // 默认启用http2，更稳定
+            tunnel_name: None,
+            hostname: None,
+            credentials_file: None,
+            ingress_paths: default_ingress_paths(),
+            auto_restart: default_auto_restart(),
+            health_check_interval_secs: default_health_check_interval(),
+        }
+    }
+}
+
+impl CloudflaredConfig {
+    /// 隧道对外的基础 URL (命名隧道由 hostname 决定，其余从日志解析)
+    fn public_url(&self) -> Option<String> {
+        match self.mode {
+            TunnelMode::Named => self
+                .hostname
+                .as_deref()
+                .filter(|h| !h.is_empty())
+                .map(|h| format!("https://{}", h)),
+            _ => None,
         }
     }
 }
 
+/// 命名隧道创建结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedTunnelInfo {
+    pub name: String,
+    pub id: String,
+    pub credentials_file: Option<String>,
+}
+
 /// Cloudflared状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CloudflaredStatus {
@@ -70,6 +150,17 @@ pub struct CloudflaredStatus {
     pub running: bool,
     pub url: Option<String>,
     pub error: Option<String>,
+    /// 自动重启次数 (本次启动以来)
+    #[serde(default)]
+    pub restart_count: u32,
+    /// 最近一次健康检查结果 (未检查时为 None)
+    #[serde(default)]
+    pub healthy: Option<bool>,
+    #[serde(default)]
+    pub last_health_check: Option<i64>,
+    /// 命名隧道生成的 config.yml 路径
+    #[serde(default)]
+    pub config_path: Option<String>,
 }
 
 impl Default for CloudflaredStatus {
@@ -80,6 +171,10 @@ impl Default for CloudflaredStatus {
             running: false,
             url: None,
             error: None,
+            restart_count: 0,
+            healthy: None,
+            last_health_check: None,
+            config_path: None,
         }
     }
 }
@@ -89,6 +184,8 @@ pub struct CloudflaredManager {
     process: Arc<RwLock<Option<Child>>>,
     status: Arc<RwLock<CloudflaredStatus>>,
     bin_path: PathBuf,
+    /// 命名隧道 config.yml 存放目录
+    config_dir: PathBuf,
     /// 用于通知进程监控任务停止
     shutdown_tx: RwLock<Option<tokio::sync::oneshot::Sender<()>>>,
 }
@@ -106,6 +203,7 @@ impl CloudflaredManager {
             process: Arc::new(RwLock::new(None)),
             status: Arc::new(RwLock::new(CloudflaredStatus::default())),
             bin_path,
+            config_dir: data_dir.join("cloudflared"),
             shutdown_tx: RwLock::new(None),
         }
     }
@@ -234,169 +332,505 @@ impl CloudflaredManager {
             return Err("Cloudflared not installed".to_string());
         }
 
-        let local_url = format!("http://localhost:{}", config.port);
-        info!("[cloudflared] Starting tunnel to: {}", local_url);
+        // [NEW] 命名隧道: 生成 config.yml 并校验 ingress 规则
+        let config_path = match config.mode {
+            TunnelMode::Named => Some(self.prepare_named_config(&config).await?),
+            _ => None,
+        };
+
+        let child = spawn_tunnel(
+            &self.bin_path,
+            &config,
+            config_path.as_deref(),
+            self.status.clone(),
+        )?;
+
+        *self.process.write().await = Some(child);
+        self.update_status(|s| {
+            s.installed = installed;
+            s.version = version.clone();
+            s.running = true;
+            s.error = None;
+            s.url = config.public_url();
+            s.restart_count = 0;
+            s.healthy = None;
+            s.last_health_check = None;
+            s.config_path = config_path.as_ref().map(|p| p.to_string_lossy().to_string());
+        }).await;
+        TUNNEL_ACTIVE.store(true, Ordering::SeqCst);
+
+        // 启动进程监控任务 (自动重启 + 健康检查)
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        tokio::spawn(supervise(
+            self.bin_path.clone(),
+            config,
+            config_path,
+            self.process.clone(),
+            self.status.clone(),
+            shutdown_rx,
+        ));
+
+        Ok(self.get_status().await)
+    }
+
+    /// 停止隧道
+    pub async fn stop(&self) -> Result<CloudflaredStatus, String> {
+        // 先停止监控任务，避免被当作异常退出而自动重启
+        if let Some(tx) = self.shutdown_tx.write().await.take() {
+            let _ = tx.send(());
+        }
+        TUNNEL_ACTIVE.store(false, Ordering::SeqCst);
 
+        let mut proc_lock = self.process.write().await;
+        if let Some(mut child) = proc_lock.take() {
+            let _ = child.kill().await;
+            info!("[cloudflared] Tunnel stopped");
+        }
+
+        self.update_status(|s| {
+            s.running = false;
+            s.url = None;
+            s.error = None;
+            s.healthy = None;
+        }).await;
+
+        Ok(self.get_status().await)
+    }
+
+    /// 生成命名隧道的 config.yml 并用 `cloudflared tunnel ingress validate` 校验
+    async fn prepare_named_config(&self, config: &CloudflaredConfig) -> Result<PathBuf, String> {
+        let name = config
+            .tunnel_name
+            .as_deref()
+            .filter(|n| !n.trim().is_empty())
+            .ok_or("Tunnel name required for named mode")?;
+        let content = render_named_config(config)?;
+
+        std::fs::create_dir_all(&self.config_dir)
+            .map_err(|e| format!("Failed to create tunnel config directory: {}", e))?;
+        let path = self.config_dir.join(format!("{}.yml", sanitize_file_name(name)));
+        std::fs::write(&path, content)
+            .map_err(|e| format!("Failed to write tunnel config: {}", e))?;
+
+        let path_str = path.to_string_lossy().to_string();
+        self.run_cli(&["tunnel", "--config", &path_str, "ingress", "validate"])
+            .await
+            .map_err(|e| format!("Invalid tunnel ingress config: {}", e))?;
+
+        info!("[cloudflared] Named tunnel config written to {:?}", path);
+        Ok(path)
+    }
+
+    /// 执行一次性的 cloudflared 子命令，返回合并后的输出 (cloudflared 日志写到 stderr)
+    async fn run_cli(&self, args: &[&str]) -> Result<String, String> {
         let mut cmd = Command::new(&self.bin_path);
-        
-        // 设置工作目录
-        // 设置工作目录
-        if let Some(bin_dir) = self.bin_path.parent() {
-            cmd.current_dir(bin_dir);
-            debug!("[cloudflared] Working directory: {:?}", bin_dir);
+        cmd.args(args);
+
+        #[cfg(target_os = "windows")]
+        cmd.creation_flags(CREATE_NO_WINDOW);
+
+        let output = cmd
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run cloudflared: {}", e))?;
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        if !output.status.success() {
+            return Err(text.trim().to_string());
+        }
+        Ok(text)
+    }
+
+    /// 创建命名隧道 (需已执行 `cloudflared tunnel login` 生成 cert.pem)
+    pub async fn create_named_tunnel(&self, name: &str) -> Result<NamedTunnelInfo, String> {
+        validate_tunnel_name(name)?;
+        let output = self.run_cli(&["tunnel", "create", name]).await?;
+        let info = parse_create_output(name, &output)
+            .ok_or_else(|| format!("Unexpected cloudflared output: {}", output.trim()))?;
+        info!("[cloudflared] Created named tunnel {} ({})", info.name, info.id);
+        Ok(info)
+    }
+
+    /// 为命名隧道创建 DNS CNAME 记录
+    pub async fn route_dns(&self, name: &str, hostname: &str) -> Result<(), String> {
+        validate_tunnel_name(name)?;
+        if hostname.trim().is_empty() {
+            return Err("Hostname required".to_string());
+        }
+        self.run_cli(&["tunnel", "route", "dns", name, hostname.trim()]).await?;
+        info!("[cloudflared] Routed {} to tunnel {}", hostname, name);
+        Ok(())
+    }
+
+    /// 删除命名隧道及其生成的配置文件
+    pub async fn delete_named_tunnel(&self, name: &str) -> Result<(), String> {
+        validate_tunnel_name(name)?;
+        let config_path = self.config_dir.join(format!("{}.yml", sanitize_file_name(name)));
+        {
+            let s = self.status.read().await;
+            let in_use = s.config_path.as_deref().map(Path::new) == Some(config_path.as_path());
+            if s.running && in_use {
+                return Err("Stop the tunnel before deleting it".to_string());
+            }
         }
+        // -f: 清理残留连接
+        self.run_cli(&["tunnel", "delete", "-f", name]).await?;
+        let _ = std::fs::remove_file(&config_path);
+        info!("[cloudflared] Deleted named tunnel {}", name);
+        Ok(())
+    }
+}
+
+/// 构造隧道启动命令
+fn build_tunnel_command(
+    bin_path: &Path,
+    config: &CloudflaredConfig,
+    config_path: Option<&Path>,
+) -> Result<Command, String> {
+    let local_url = format!("http://localhost:{}", config.port);
+    info!("[cloudflared] Starting tunnel to: {}", local_url);
+
+    let mut cmd = Command::new(bin_path);
+
+    // 设置工作目录
+    if let Some(bin_dir) = bin_path.parent() {
+        cmd.current_dir(bin_dir);
+        debug!("[cloudflared] Working directory: {:?}", bin_dir);
+    }
+
+    match config.mode {
+        TunnelMode::Quick => {
+            cmd.arg("tunnel")
+                .arg("--url")
+                .arg(&local_url);
 
-        match config.mode {
-            TunnelMode::Quick => {
+            // 注意：--no-autoupdate 参数在较新版本的 cloudflared 中已不被支持，会导致进程立即退出
+            // cmd.arg("--no-autoupdate");
+
+            if config.use_http2 {
+                cmd.arg("--protocol").arg("http2");
+            }
+
+            // 注意：--loglevel 参数在此上下文中也会导致 Incorrect Usage 错误，故移除以使用默认值
+            // cmd.arg("--loglevel").arg("info");
+
+            info!("[cloudflared] Command args: tunnel --url {} ...", local_url);
+        }
+        TunnelMode::Auth => {
+            if let Some(token) = &config.token {
                 cmd.arg("tunnel")
-                    .arg("--url")
-                    .arg(&local_url);
-                
-                // 注意：--no-autoupdate 参数在较新版本的 cloudflared 中已不被支持，会导致进程立即退出
+                    .arg("run")
+                    .arg("--token")
+                    .arg(token);
+
+                // 注意：--no-autoupdate 参数不被支持
                 // cmd.arg("--no-autoupdate");
 
                 if config.use_http2 {
                     cmd.arg("--protocol").arg("http2");
                 }
-                
-                // 注意：--loglevel 参数在此上下文中也会导致 Incorrect Usage 错误，故移除以使用默认值
+
+                // 注意：--loglevel 参数不被支持
                 // cmd.arg("--loglevel").arg("info");
-                
-                info!("[cloudflared] Command args: tunnel --url {} ...", local_url);
+
+                info!("[cloudflared] Command args: tunnel run --token [HIDDEN] ...");
+            } else {
+                return Err("Token required for auth mode".to_string());
             }
-            TunnelMode::Auth => {
-                if let Some(token) = &config.token {
-                    cmd.arg("tunnel")
-                        .arg("run")
-                        .arg("--token")
-                        .arg(token);
-                    
-                    // 注意：--no-autoupdate 参数不被支持
-                    // cmd.arg("--no-autoupdate");
-                    
-                    if config.use_http2 {
-                        cmd.arg("--protocol").arg("http2");
-                    }
-                    
-                    // 注意：--loglevel 参数不被支持
-                    // cmd.arg("--loglevel").arg("info");
-                    
-                    info!("[cloudflared] Command args: tunnel run --token [HIDDEN] ...");
-                } else {
-                    return Err("Token required for auth mode".to_string());
-                }
+        }
+        TunnelMode::Named => {
+            let config_path = config_path.ok_or("Tunnel config not generated")?;
+            let name = config.tunnel_name.as_deref().unwrap_or_default();
+            // --config / --protocol 属于 tunnel 级参数，需放在 run 之前
+            cmd.arg("tunnel").arg("--config").arg(config_path);
+            if config.use_http2 {
+                cmd.arg("--protocol").arg("http2");
             }
+            cmd.arg("run").arg(name);
+
+            info!("[cloudflared] Command args: tunnel --config {:?} run {}", config_path, name);
         }
+    }
 
-        // 恢复管道
-        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
-        
-        // 使用 DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP 隐藏窗口
-        #[cfg(target_os = "windows")]
-        cmd.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    // 恢复管道
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
-        let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn: {}", e))?;
+    // 使用 DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP 隐藏窗口
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
 
-        let stdout = child.stdout.take();
-        let stderr = child.stderr.take();
+    Ok(cmd)
+}
 
-        let status_clone = self.status.clone();
-        if let Some(stdout) = stdout {
-            spawn_log_reader(stdout, status_clone.clone());
-        }
+/// 启动隧道进程并挂载日志读取
+fn spawn_tunnel(
+    bin_path: &Path,
+    config: &CloudflaredConfig,
+    config_path: Option<&Path>,
+    status_ref: Arc<RwLock<CloudflaredStatus>>,
+) -> Result<Child, String> {
+    let mut cmd = build_tunnel_command(bin_path, config, config_path)?;
+    // 监控任务被取消时不留下孤儿进程
+    cmd.kill_on_drop(true);
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn: {}", e))?;
+
+    if let Some(stdout) = child.stdout.take() {
+        spawn_log_reader(stdout, status_ref.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_log_reader(stderr, status_ref);
+    }
+    Ok(child)
+}
 
-        if let Some(stderr) = stderr {
-            spawn_log_reader(stderr, status_clone.clone());
+/// 指数退避: 每次翻倍，封顶 MAX_RESTART_BACKOFF
+fn next_backoff(current: Duration) -> Duration {
+    (current * 2).min(MAX_RESTART_BACKOFF)
+}
+
+/// 进程监控: 退出后按退避自动重启，并定期通过隧道访问 /healthz
+async fn supervise(
+    bin_path: PathBuf,
+    config: CloudflaredConfig,
+    config_path: Option<PathBuf>,
+    process_ref: Arc<RwLock<Option<Child>>>,
+    status_ref: Arc<RwLock<CloudflaredStatus>>,
+    mut shutdown_rx: tokio::sync::oneshot::Receiver<()>,
+) {
+    let health_interval = Duration::from_secs(config.health_check_interval_secs);
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_default();
+
+    let mut backoff = INITIAL_RESTART_BACKOFF;
+    let mut started_at = Instant::now();
+    let mut last_health_check = Instant::now();
+    let mut health_failures = 0u32;
+
+    loop {
+        tokio::select! {
+            _ = &mut shutdown_rx => {
+                debug!("[cloudflared] Process monitor shutdown");
+                return;
+            }
+            _ = tokio::time::sleep(Duration::from_secs(3)) => {}
         }
 
-        *self.process.write().await = Some(child);
-        self.update_status(|s| {
-            s.installed = installed.clone();
-            s.version = version.clone();
-            s.running = true;
-            s.error = None;
-        }).await;
+        let exit_reason = {
+            let mut proc_lock = process_ref.write().await;
+            let reason = match proc_lock.as_mut().map(|child| child.try_wait()) {
+                Some(Ok(Some(exit_status))) => {
+                    info!("[cloudflared] Process exited with status: {:?}", exit_status);
+                    Some(format!("Tunnel process exited (status: {:?})", exit_status))
+                }
+                Some(Ok(None)) => None, // 进程仍在运行
+                Some(Err(e)) => {
+                    info!("[cloudflared] Error checking process: {}", e);
+                    Some(format!("Error checking tunnel: {}", e))
+                }
+                None => Some("Tunnel process not found".to_string()),
+            };
+            if reason.is_some() {
+                *proc_lock = None;
+            }
+            reason
+        };
 
-        // 启动进程监控任务
-        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
-        *self.shutdown_tx.write().await = Some(shutdown_tx);
+        if let Some(reason) = exit_reason {
+            if !config.auto_restart {
+                let mut s = status_ref.write().await;
+                s.running = false;
+                s.healthy = None;
+                s.error = Some(reason);
+                TUNNEL_ACTIVE.store(false, Ordering::SeqCst);
+                return;
+            }
 
-        let process_ref = self.process.clone();
-        let status_ref = self.status.clone();
+            // 稳定运行过一段时间则视为新的故障，重置退避
+            if started_at.elapsed() >= STABLE_RUN_DURATION {
+                backoff = INITIAL_RESTART_BACKOFF;
+            }
+            warn!("[cloudflared] {}; restarting in {:?}", reason, backoff);
+            {
+                let mut s = status_ref.write().await;
+                s.running = false;
+                s.healthy = None;
+                s.error = Some(format!("{}; restarting in {}s", reason, backoff.as_secs()));
+            }
 
-        tokio::spawn(async move {
             tokio::select! {
-                _ = shutdown_rx => {
+                _ = &mut shutdown_rx => {
                     debug!("[cloudflared] Process monitor shutdown");
+                    return;
+                }
+                _ = tokio::time::sleep(backoff) => {}
+            }
+            backoff = next_backoff(backoff);
+
+            match spawn_tunnel(&bin_path, &config, config_path.as_deref(), status_ref.clone()) {
+                Ok(child) => {
+                    *process_ref.write().await = Some(child);
+                    let mut s = status_ref.write().await;
+                    s.running = true;
+                    s.error = None;
+                    s.restart_count += 1;
+                    // 快速隧道每次重启 URL 都会变化，等待日志重新解析
+                    s.url = config.public_url();
+                    started_at = Instant::now();
+                    last_health_check = Instant::now();
+                    health_failures = 0;
+                }
+                Err(e) => {
+                    // 下一轮检测到进程不存在后继续退避重试
+                    warn!("[cloudflared] Restart failed: {}", e);
+                    status_ref.write().await.error = Some(e);
                 }
-                _ = async {
-                    loop {
-                        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-
-                        let mut proc_lock = process_ref.write().await;
-                        if let Some(ref mut child) = *proc_lock {
-                            match child.try_wait() {
-                                Ok(Some(exit_status)) => {
-                                    // 进程已退出
-                                    info!("[cloudflared] Process exited with status: {:?}", exit_status);
-                                    *proc_lock = None;
-                                    drop(proc_lock);
-
-                                    let mut s = status_ref.write().await;
-                                    s.running = false;
-                                    s.error = Some(format!("Tunnel process exited (status: {:?})", exit_status));
-                                    break;
-                                }
-                                Ok(None) => {
-                                    // 进程仍在运行
-                                }
-                                Err(e) => {
-                                    info!("[cloudflared] Error checking process: {}", e);
-                                    *proc_lock = None;
-                                    drop(proc_lock);
-
-                                    let mut s = status_ref.write().await;
-                                    s.running = false;
-                                    s.error = Some(format!("Error checking tunnel: {}", e));
-                                    break;
-                                }
-                            }
-                        } else {
-                            // 进程不存在
-                            drop(proc_lock);
-                            let mut s = status_ref.write().await;
-                            if s.running {
-                                s.running = false;
-                                s.error = Some("Tunnel process not found".to_string());
-                            }
-                            break;
-                        }
-                    }
-                } => {}
             }
-        });
+            continue;
+        }
 
-        Ok(self.get_status().await)
+        // 健康检查: 通过公网 URL 访问 /healthz，验证 边缘 -> 隧道 -> 反代 整条链路
+        if health_interval.is_zero() || last_health_check.elapsed() < health_interval {
+            continue;
+        }
+        last_health_check = Instant::now();
+        let Some(url) = status_ref.read().await.url.clone() else {
+            continue;
+        };
+
+        let healthy = check_tunnel_health(&client, &url).await;
+        {
+            let mut s = status_ref.write().await;
+            s.healthy = Some(healthy);
+            s.last_health_check = Some(chrono::Utc::now().timestamp());
+        }
+        if healthy {
+            health_failures = 0;
+            continue;
+        }
+
+        health_failures += 1;
+        warn!(
+            "[cloudflared] Health check via {} failed ({}/{})",
+            url, health_failures, MAX_HEALTH_FAILURES
+        );
+        if health_failures >= MAX_HEALTH_FAILURES && config.auto_restart {
+            warn!("[cloudflared] Tunnel unhealthy, restarting process");
+            health_failures = 0;
+            if let Some(child) = process_ref.write().await.as_mut() {
+                // 下一轮循环检测到退出后按退避重启
+                let _ = child.start_kill();
+            }
+        }
     }
+}
 
-    /// 停止隧道
-    pub async fn stop(&self) -> Result<CloudflaredStatus, String> {
-        let mut proc_lock = self.process.write().await;
-        if let Some(mut child) = proc_lock.take() {
-            let _ = child.kill().await;
-            info!("[cloudflared] Tunnel stopped");
+/// 隧道链路是否可用: 连接失败或边缘返回网关类错误 (502/504/52x/530) 视为不健康。
+/// 反代自身返回的 401/503 等说明请求已经穿过隧道，仍视为健康。
+async fn check_tunnel_health(client: &reqwest::Client, base_url: &str) -> bool {
+    let url = format!("{}/healthz", base_url.trim_end_matches('/'));
+    match client.get(&url).send().await {
+        Ok(resp) => !is_edge_error(resp.status().as_u16()),
+        Err(e) => {
+            debug!("[cloudflared] Health check request failed: {}", e);
+            false
         }
+    }
+}
 
-        self.update_status(|s| {
-            s.running = false;
-            s.url = None;
-            s.error = None;
-        }).await;
+fn is_edge_error(status: u16) -> bool {
+    matches!(status, 502 | 504 | 520..=530)
+}
 
-        Ok(self.get_status().await)
+/// 隧道名只允许字母数字、`-`、`_`、`.`，避免注入命令行参数或路径
+fn validate_tunnel_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid tunnel name: '{}'", name))
     }
 }
 
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') { c } else { '_' })
+        .collect()
+}
+
+/// YAML 单引号字符串
+fn yaml_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// 生成命名隧道 config.yml: 每个放行路径一条 ingress 规则，其余请求一律 404
+pub fn render_named_config(config: &CloudflaredConfig) -> Result<String, String> {
+    let name = config
+        .tunnel_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .ok_or("Tunnel name required for named mode")?;
+    validate_tunnel_name(name)?;
+    if config.ingress_paths.is_empty() {
+        return Err("At least one ingress path is required".to_string());
+    }
+    for path in &config.ingress_paths {
+        regex::Regex::new(path).map_err(|e| format!("Invalid ingress path '{}': {}", path, e))?;
+    }
+
+    let service = format!("http://localhost:{}", config.port);
+    let hostname = config.hostname.as_deref().map(str::trim).filter(|h| !h.is_empty());
+
+    let mut out = String::from("# Generated by DroidGravity Manager. Manual edits will be overwritten.\n");
+    out.push_str(&format!("tunnel: {}\n", yaml_quote(name)));
+    if let Some(credentials) = config.credentials_file.as_deref().filter(|c| !c.is_empty()) {
+        out.push_str(&format!("credentials-file: {}\n", yaml_quote(credentials)));
+    }
+    out.push_str("ingress:\n");
+    for path in &config.ingress_paths {
+        out.push_str(&format!("  - service: {}\n", yaml_quote(&service)));
+        out.push_str(&format!("    path: {}\n", yaml_quote(path)));
+        if let Some(host) = hostname {
+            out.push_str(&format!("    hostname: {}\n", yaml_quote(host)));
+        }
+    }
+    // 兜底规则: 管理后台等其它路径不对外暴露
+    out.push_str("  - service: http_status:404\n");
+    Ok(out)
+}
+
+/// 解析 `cloudflared tunnel create` 输出:
+/// `Tunnel credentials written to /path/<id>.json. ...`
+/// `Created tunnel <name> with id <id>`
+fn parse_create_output(name: &str, output: &str) -> Option<NamedTunnelInfo> {
+    let id = output.lines().find_map(|line| {
+        let (_, rest) = line.split_once("Created tunnel ")?;
+        let (_, id) = rest.split_once(" with id ")?;
+        Some(id.split_whitespace().next()?.to_string())
+    })?;
+    let credentials_file = output.lines().find_map(|line| {
+        let (_, rest) = line.split_once("Tunnel credentials written to ")?;
+        let end = rest.find(".json").map(|i| i + 5)?;
+        Some(rest[..end].to_string())
+    });
+    Some(NamedTunnelInfo {
+        name: name.to_string(),
+        id,
+        credentials_file,
+    })
+}
+
 /// 获取下载URL
 fn get_download_url() -> Result<String, String> {
     let os = std::env::consts::OS;
@@ -466,3 +900,62 @@ fn extract_tunnel_url(line: &str) -> Option<String> {
     None
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_named_config() {
+        let config = CloudflaredConfig {
+            mode: TunnelMode::Named,
+            tunnel_name: Some("team-proxy".into()),
+            hostname: Some("proxy.example.com".into()),
+            credentials_file: Some("/home/me/.cloudflared/it's.json".into()),
+            ingress_paths: vec!["^/v1/".into(), "^/healthz$".into()],
+            ..Default::default()
+        };
+        let yaml = render_named_config(&config).unwrap();
+        assert!(yaml.contains("tunnel: 'team-proxy'\n"));
+        assert!(yaml.contains("credentials-file: '/home/me/.cloudflared/it''s.json'\n"));
+        assert!(yaml.contains(
+            "  - service: 'http://localhost:8045'\n    path: '^/v1/'\n    hostname: 'proxy.example.com'\n"
+        ));
+        assert!(yaml.ends_with("  - service: http_status:404\n"));
+        assert_eq!(config.public_url().as_deref(), Some("https://proxy.example.com"));
+
+        // 默认规则不暴露管理接口
+        let defaults = CloudflaredConfig::default().ingress_paths;
+        assert!(defaults.iter().all(|p| !p.contains("api")));
+
+        let missing_name = CloudflaredConfig { mode: TunnelMode::Named, ..Default::default() };
+        assert!(render_named_config(&missing_name).is_err());
+        let bad_regex = CloudflaredConfig { ingress_paths: vec!["^/v1/(".into()], ..config.clone() };
+        assert!(render_named_config(&bad_regex).is_err());
+        let bad_name = CloudflaredConfig { tunnel_name: Some("--token".into()), ..config };
+        assert!(render_named_config(&bad_name).is_err());
+    }
+
+    #[test]
+    fn test_lifecycle_helpers() {
+        let output = "2026-10-17T10:00:00Z INF Tunnel credentials written to /root/.cloudflared/6ff42ae2-765d-4adf-8112-31c55c1551ef.json. cloudflared chose this file based on where your origin certificate was found.\n\
+                      2026-10-17T10:00:00Z INF Created tunnel team-proxy with id 6ff42ae2-765d-4adf-8112-31c55c1551ef\n";
+        let info = parse_create_output("team-proxy", output).unwrap();
+        assert_eq!(info.id, "6ff42ae2-765d-4adf-8112-31c55c1551ef");
+        assert_eq!(
+            info.credentials_file.as_deref(),
+            Some("/root/.cloudflared/6ff42ae2-765d-4adf-8112-31c55c1551ef.json")
+        );
+        assert!(parse_create_output("x", "error").is_none());
+
+        let mut backoff = INITIAL_RESTART_BACKOFF;
+        for _ in 0..20 {
+            backoff = next_backoff(backoff);
+        }
+        assert_eq!(backoff, MAX_RESTART_BACKOFF);
+        assert_eq!(next_backoff(Duration::from_secs(2)), Duration::from_secs(4));
+
+        assert!(is_edge_error(530) && is_edge_error(502));
+        assert!(!is_edge_error(200) && !is_edge_error(401) && !is_edge_error(503));
+    }
+}
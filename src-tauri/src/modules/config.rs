@@ -79,6 +79,8 @@ pub fn load_app_config() -> Result<AppConfig, String> {
 pub fn save_app_config(config: &AppConfig) -> Result<(), String> {
     let data_dir = get_data_dir()?;
     let config_path = data_dir.join(CONFIG_FILE);
+
+    config.proxy.mcp_gateway.validate()?;
     
     let content = serde_json::to_string_pretty(config)
         .map_err(|e| format!("序列化配置失败: {}", e))?;
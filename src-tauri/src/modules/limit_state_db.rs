@@ -0,0 +1,272 @@
+//! Limit State Database Module
+//! 限流锁定 / 失败计数 / 健康分的持久化，避免重启后重新"撞墙"
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 限流锁定记录 (model 为 None 表示账号级)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockoutRecord {
+    pub account_id: String,
+    pub model: Option<String>,
+    pub reason: String,
+    /// 解锁时间 (Unix 秒)
+    pub reset_time: i64,
+    pub detected_at: i64,
+    pub retry_after_sec: u64,
+}
+
+/// 连续失败计数 (驱动熔断退避阶梯)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailureCountRecord {
+    pub account_id: String,
+    pub count: u32,
+    /// 最近一次失败时间 (Unix 秒)
+    pub updated_at: i64,
+}
+
+/// 账号健康分
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthScoreRecord {
+    pub account_id: String,
+    pub score: f32,
+    pub updated_at: i64,
+}
+
+/// 完整的限流状态快照
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LimitState {
+    pub lockouts: Vec<LockoutRecord>,
+    pub failure_counts: Vec<FailureCountRecord>,
+    pub health_scores: Vec<HealthScoreRecord>,
+}
+
+/// 获取限流状态数据库路径 (与账号目录同级)
+pub fn get_limit_state_db_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("limit_state.db")
+}
+
+/// 连接数据库
+fn connect_db(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS rate_limit_lockouts (
+            account_id TEXT NOT NULL,
+            model TEXT NOT NULL DEFAULT '',
+            reason TEXT NOT NULL,
+            reset_time INTEGER NOT NULL,
+            detected_at INTEGER NOT NULL,
+            retry_after_sec INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (account_id, model)
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS failure_counts (
+            account_id TEXT PRIMARY KEY,
+            count INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS health_scores (
+            account_id TEXT PRIMARY KEY,
+            score REAL NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+/// 用内存快照整体覆盖数据库 (单事务，内存即真相)
+pub fn save_state(db_path: &Path, state: &LimitState) -> Result<(), String> {
+    let mut conn = connect_db(db_path)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    tx.execute("DELETE FROM rate_limit_lockouts", [])
+        .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM failure_counts", [])
+        .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM health_scores", [])
+        .map_err(|e| e.to_string())?;
+
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT OR REPLACE INTO rate_limit_lockouts
+                 (account_id, model, reason, reset_time, detected_at, retry_after_sec)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .map_err(|e| e.to_string())?;
+        for l in &state.lockouts {
+            stmt.execute(params![
+                l.account_id,
+                l.model.as_deref().unwrap_or(""),
+                l.reason,
+                l.reset_time,
+                l.detected_at,
+                l.retry_after_sec as i64,
+            ])
+            .map_err(|e| e.to_string())?;
+        }
+
+        let mut stmt = tx
+            .prepare("INSERT OR REPLACE INTO failure_counts (account_id, count, updated_at) VALUES (?1, ?2, ?3)")
+            .map_err(|e| e.to_string())?;
+        for f in &state.failure_counts {
+            stmt.execute(params![f.account_id, f.count, f.updated_at])
+                .map_err(|e| e.to_string())?;
+        }
+
+        let mut stmt = tx
+            .prepare("INSERT OR REPLACE INTO health_scores (account_id, score, updated_at) VALUES (?1, ?2, ?3)")
+            .map_err(|e| e.to_string())?;
+        for h in &state.health_scores {
+            stmt.execute(params![h.account_id, h.score as f64, h.updated_at])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// 读取持久化状态，并先删除已过期的记录
+///
+/// - 锁定: reset_time <= now
+/// - 失败计数: 超过 `failure_expiry_secs` 未再失败
+pub fn load_state(db_path: &Path, now: i64, failure_expiry_secs: i64) -> Result<LimitState, String> {
+    let conn = connect_db(db_path)?;
+
+    let pruned_lockouts = conn
+        .execute("DELETE FROM rate_limit_lockouts WHERE reset_time <= ?1", params![now])
+        .map_err(|e| e.to_string())?;
+    let pruned_failures = conn
+        .execute(
+            "DELETE FROM failure_counts WHERE updated_at < ?1 OR count <= 0",
+            params![now - failure_expiry_secs],
+        )
+        .map_err(|e| e.to_string())?;
+    if pruned_lockouts + pruned_failures > 0 {
+        tracing::debug!(
+            "Pruned {} expired lockout(s) and {} stale failure count(s)",
+            pruned_lockouts,
+            pruned_failures
+        );
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT account_id, model, reason, reset_time, detected_at, retry_after_sec FROM rate_limit_lockouts")
+        .map_err(|e| e.to_string())?;
+    let lockouts = stmt
+        .query_map([], |row| {
+            let model: String = row.get(1)?;
+            Ok(LockoutRecord {
+                account_id: row.get(0)?,
+                model: if model.is_empty() { None } else { Some(model) },
+                reason: row.get(2)?,
+                reset_time: row.get(3)?,
+                detected_at: row.get(4)?,
+                retry_after_sec: row.get::<_, i64>(5)?.max(0) as u64,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare("SELECT account_id, count, updated_at FROM failure_counts")
+        .map_err(|e| e.to_string())?;
+    let failure_counts = stmt
+        .query_map([], |row| {
+            Ok(FailureCountRecord {
+                account_id: row.get(0)?,
+                count: row.get(1)?,
+                updated_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare("SELECT account_id, score, updated_at FROM health_scores")
+        .map_err(|e| e.to_string())?;
+    let health_scores = stmt
+        .query_map([], |row| {
+            Ok(HealthScoreRecord {
+                account_id: row.get(0)?,
+                score: row.get::<_, f64>(1)? as f32,
+                updated_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(LimitState {
+        lockouts,
+        failure_counts,
+        health_scores,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load_roundtrip_prunes_expired() {
+        let dir = std::env::temp_dir().join(format!("limit-state-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = get_limit_state_db_path(&dir);
+        let now = 1_700_000_000;
+
+        let lockout = |model: Option<&str>, reset_time: i64| LockoutRecord {
+            account_id: "acc-1".to_string(),
+            model: model.map(|m| m.to_string()),
+            reason: "quota_exhausted".to_string(),
+            reset_time,
+            detected_at: now - 10,
+            retry_after_sec: 3600,
+        };
+        let state = LimitState {
+            lockouts: vec![lockout(None, now + 3600), lockout(Some("gemini-3-pro"), now - 1)],
+            failure_counts: vec![
+                FailureCountRecord { account_id: "acc-1".to_string(), count: 3, updated_at: now - 60 },
+                FailureCountRecord { account_id: "acc-2".to_string(), count: 1, updated_at: now - 7200 },
+            ],
+            health_scores: vec![HealthScoreRecord { account_id: "acc-1".to_string(), score: 0.4, updated_at: now }],
+        };
+        save_state(&db_path, &state).unwrap();
+
+        let loaded = load_state(&db_path, now, 3600).unwrap();
+        assert_eq!(loaded.lockouts, vec![lockout(None, now + 3600)]);
+        assert_eq!(loaded.failure_counts.len(), 1);
+        assert_eq!(loaded.failure_counts[0].account_id, "acc-1");
+        assert_eq!(loaded.failure_counts[0].count, 3);
+        assert!((loaded.health_scores[0].score - 0.4).abs() < 1e-6);
+
+        // 过期记录已从数据库中删除
+        let again = load_state(&db_path, now, 3600).unwrap();
+        assert_eq!(again, loaded);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,355 @@
+//! Proxy Log Search
+//! 反代日志全文检索的查询语法解析 (FTS5 + 字段过滤)
+//!
+//! 语法 (空格分隔，字段之间为 AND，同一字段多次出现为 OR):
+//! - `tool_use_id` / `"exact phrase"` / `prefix*` / `a OR b` / `-exclude`  全文检索请求体、响应体与错误
+//!   (排除词作用于整个表达式，不能与 OR 相邻)
+//! - `model:claude-opus*`   客户端模型或映射后的模型 (默认包含匹配，`*` 为通配符)
+//! - `account:alice`        账号邮箱
+//! - `key:ci-bot`           客户端 API Key 名称或 ID
+//! - `protocol:anthropic`   anthropic / openai / gemini / kiro / mcp / other
+//! - `status:429` / `status:5xx` / `status:>=400` / `status:error` / `status:ok`
+//! - `after:2026-10-01` / `before:2026-10-17T12:00:00Z` / `date:2026-10-01..2026-10-07` / `after:24h`
+//!   (日期按 UTC 解释；相对时间支持 m / h / d)
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::proxy::monitor::ProxyRequestLog;
+
+/// 解析后的检索条件
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogSearchQuery {
+    /// FTS5 MATCH 表达式 (无全文条件时为 None)
+    pub text: Option<String>,
+    /// LIKE 模式 (已转义)
+    pub models: Vec<String>,
+    pub accounts: Vec<String>,
+    pub client_keys: Vec<String>,
+    pub protocols: Vec<String>,
+    /// 状态码区间 [lo, hi)
+    pub statuses: Vec<(u16, u16)>,
+    /// 时间范围 (毫秒时间戳) [after, before)
+    pub after: Option<i64>,
+    pub before: Option<i64>,
+}
+
+/// 一条检索结果 (不含请求/响应体，详情通过 /api/logs/:id 获取)
+#[derive(Debug, Clone, Serialize)]
+pub struct LogSearchHit {
+    #[serde(flatten)]
+    pub log: ProxyRequestLog,
+    /// 命中片段，文本已做 HTML 转义，匹配词以 <mark></mark> 包裹 (仅全文检索时存在)
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogSearchResult {
+    pub total: u64,
+    pub hits: Vec<LogSearchHit>,
+}
+
+const PROTOCOLS: &[&str] = &["anthropic", "openai", "gemini", "kiro", "mcp", "other"];
+
+const DAY_MS: i64 = 24 * 3600 * 1000;
+
+/// FTS5 snippet() 使用的匹配标记 (Unicode 私用区字符)，HTML 转义之后再替换为 <mark>
+pub const SNIPPET_OPEN: char = '\u{E000}';
+pub const SNIPPET_CLOSE: char = '\u{E001}';
+
+/// 将 snippet() 的输出转为可直接渲染的 HTML: 先转义日志原文，再插入 <mark> 标签
+pub fn render_snippet(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len() + 16);
+    for c in raw.chars() {
+        match c {
+            SNIPPET_OPEN => out.push_str("<mark>"),
+            SNIPPET_CLOSE => out.push_str("</mark>"),
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// 按空白切分，双引号内的空白保留
+fn split_tokens(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("查询中的引号未闭合".to_string());
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// 包含匹配的 LIKE 模式 (配合 ESCAPE '\')，带 `*` 时按通配符整体匹配
+fn like_pattern(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    if escaped.contains('*') {
+        escaped.replace('*', "%")
+    } else {
+        format!("%{}%", escaped)
+    }
+}
+
+fn parse_status(value: &str) -> Result<(u16, u16), String> {
+    let invalid = || format!("无效的状态码过滤: status:{}", value);
+    let number = |s: &str| s.parse::<u16>().map_err(|_| invalid());
+    let lower = value.to_ascii_lowercase();
+    match lower.as_str() {
+        "error" | "errors" => return Ok((400, 1000)),
+        "ok" | "success" => return Ok((200, 400)),
+        _ => {}
+    }
+    if let Some(rest) = lower.strip_prefix(">=") {
+        return Ok((number(rest)?, 1000));
+    }
+    if let Some(rest) = lower.strip_prefix("<=") {
+        return Ok((0, number(rest)?.saturating_add(1)));
+    }
+    if let Some(rest) = lower.strip_prefix('>') {
+        return Ok((number(rest)?.saturating_add(1), 1000));
+    }
+    if let Some(rest) = lower.strip_prefix('<') {
+        return Ok((0, number(rest)?));
+    }
+    if lower.len() == 3 && lower.ends_with("xx") {
+        let class = number(&lower[..1])?;
+        return Ok((class * 100, class * 100 + 100));
+    }
+    let code = number(&lower)?;
+    Ok((code, code + 1))
+}
+
+/// 解析时间点；`end` 为 true 时日期取当天结束 (用于区间右端)
+fn parse_time(value: &str, end: bool, now_ms: i64) -> Result<i64, String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.timestamp_millis());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let start = Utc
+            .from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())
+            .timestamp_millis();
+        return Ok(if end { start + DAY_MS } else { start });
+    }
+    if value.len() > 1 {
+        let (amount, unit) = value.split_at(value.len() - 1);
+        if let Ok(amount) = amount.parse::<i64>() {
+            let unit_ms = match unit {
+                "m" => Some(60 * 1000),
+                "h" => Some(3600 * 1000),
+                "d" => Some(DAY_MS),
+                _ => None,
+            };
+            if let Some(unit_ms) = unit_ms {
+                return amount
+                    .checked_mul(unit_ms)
+                    .and_then(|offset| now_ms.checked_sub(offset))
+                    .ok_or_else(|| format!("时间超出范围: {}", value));
+            }
+        }
+    }
+    Err(format!("无效的时间: {} (支持 YYYY-MM-DD、RFC3339 或 30m / 24h / 7d)", value))
+}
+
+/// FTS5 字符串字面量
+fn fts_term(term: &str) -> String {
+    match term.strip_suffix('*') {
+        Some(prefix) if !prefix.is_empty() => format!("\"{}\"*", prefix.replace('"', "\"\"")),
+        _ => format!("\"{}\"", term.replace('"', "\"\"")),
+    }
+}
+
+/// 解析查询字符串 (now_ms 用于相对时间)
+pub fn parse_query(input: &str, now_ms: i64) -> Result<LogSearchQuery, String> {
+    let mut query = LogSearchQuery::default();
+    let mut positive: Vec<String> = Vec::new();
+    let mut negative: Vec<String> = Vec::new();
+    // 排除词统一追加在表达式末尾 (NOT)，与 OR 相邻会改变分组 (`a -b OR c` -> `(a OR c) NOT b`)，直接拒绝
+    let mut last_text_negated = false;
+    let or_with_negation = || "OR 不能与排除词 (-term) 相邻".to_string();
+
+    for token in split_tokens(input)? {
+        if token == "OR" {
+            if last_text_negated {
+                return Err(or_with_negation());
+            }
+            if matches!(positive.last().map(String::as_str), None | Some("OR")) {
+                return Err("OR 必须位于两个检索词之间".to_string());
+            }
+            positive.push("OR".to_string());
+            continue;
+        }
+
+        let (negated, raw) = match token.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, token.as_str()),
+        };
+
+        if let Some((field, value)) = raw.split_once(':').filter(|(f, _)| !f.starts_with('"')) {
+            let value = unquote(value);
+            let field = field.to_ascii_lowercase();
+            let known = ["model", "account", "key", "protocol", "status", "after", "before", "date"];
+            if known.contains(&field.as_str()) {
+                if negated {
+                    return Err(format!("字段过滤不支持取反: -{}", raw));
+                }
+                if value.is_empty() {
+                    return Err(format!("字段 {} 缺少取值", field));
+                }
+                match field.as_str() {
+                    "model" => query.models.push(like_pattern(value)),
+                    "account" => query.accounts.push(like_pattern(value)),
+                    "key" => query.client_keys.push(value.to_string()),
+                    "protocol" => {
+                        let protocol = value.to_ascii_lowercase();
+                        if !PROTOCOLS.contains(&protocol.as_str()) {
+                            return Err(format!("未知的协议: {} (可选: {})", value, PROTOCOLS.join(", ")));
+                        }
+                        query.protocols.push(protocol);
+                    }
+                    "status" => query.statuses.push(parse_status(value)?),
+                    "after" => query.after = Some(parse_time(value, false, now_ms)?),
+                    "before" => query.before = Some(parse_time(value, false, now_ms)?),
+                    _ => {
+                        let (from, to) = value.split_once("..").unwrap_or((value, value));
+                        if !from.is_empty() {
+                            query.after = Some(parse_time(from, false, now_ms)?);
+                        }
+                        if !to.is_empty() {
+                            query.before = Some(parse_time(to, true, now_ms)?);
+                        }
+                    }
+                }
+                continue;
+            }
+        }
+
+        // 全文检索词 (带引号为短语，结尾 * 为前缀匹配)
+        let term = if raw.starts_with('"') {
+            let phrase = unquote(raw);
+            if phrase.is_empty() {
+                continue;
+            }
+            format!("\"{}\"", phrase.replace('"', "\"\""))
+        } else {
+            fts_term(raw)
+        };
+        if negated {
+            if positive.last().is_some_and(|last| last == "OR") {
+                return Err(or_with_negation());
+            }
+            negative.push(term);
+        } else {
+            positive.push(term);
+        }
+        last_text_negated = negated;
+    }
+
+    if positive.last().is_some_and(|last| last == "OR") {
+        return Err("OR 必须位于两个检索词之间".to_string());
+    }
+    if positive.is_empty() && !negative.is_empty() {
+        return Err("排除词 (-term) 需要至少一个检索词".to_string());
+    }
+    if !positive.is_empty() {
+        let mut expr = format!("({})", positive.join(" "));
+        for term in negative {
+            expr.push_str(" NOT ");
+            expr.push_str(&term);
+        }
+        query.text = Some(expr);
+    }
+    Ok(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query_fields_and_text() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap().timestamp_millis();
+        let query = parse_query(
+            r#"toolu_01 "permission denied" read_fi* OR bash -timeout model:claude-opus* model:gemini account:alice@example.com status:5xx status:429 protocol:Anthropic key:ci date:2026-10-01..2026-10-07"#,
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(
+            query.text.as_deref(),
+            Some(r#"("toolu_01" "permission denied" "read_fi"* OR "bash") NOT "timeout""#)
+        );
+        assert_eq!(query.models, vec!["claude-opus%", "%gemini%"]);
+        assert_eq!(query.accounts, vec!["%alice@example.com%"]);
+        assert_eq!(query.statuses, vec![(500, 600), (429, 430)]);
+        assert_eq!(query.protocols, vec!["anthropic"]);
+        assert_eq!(query.client_keys, vec!["ci"]);
+        let oct1 = Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap().timestamp_millis();
+        assert_eq!(query.after, Some(oct1));
+        assert_eq!(query.before, Some(oct1 + 7 * DAY_MS));
+
+        let query = parse_query("after:24h status:error", now).unwrap();
+        assert!(query.text.is_none());
+        assert_eq!(query.after, Some(now - DAY_MS));
+        assert_eq!(query.statuses, vec![(400, 1000)]);
+
+        // 未知字段按普通文本处理 (如 URL)
+        let query = parse_query("https://example.com", now).unwrap();
+        assert_eq!(query.text.as_deref(), Some(r#"("https://example.com")"#));
+
+        assert!(parse_query("\"unterminated", now).is_err());
+        assert!(parse_query("-only", now).is_err());
+        assert!(parse_query("a OR", now).is_err());
+        // OR 与排除词相邻会被重新分组，拒绝而不是静默改变语义
+        assert!(parse_query("a -b OR c", now).is_err());
+        assert!(parse_query("a OR -b", now).is_err());
+        assert!(parse_query("a OR c -b", now).is_ok());
+        assert!(parse_query("protocol:grpc", now).is_err());
+        assert!(parse_query("status:abc", now).is_err());
+        assert!(parse_query("after:yesterday", now).is_err());
+        // 相对时间溢出返回错误而不是 panic / 回绕
+        assert!(parse_query("after:9223372036854775807d", now).is_err());
+        assert!(parse_query("before:-9223372036854775807m", now).is_err());
+    }
+
+    #[test]
+    fn test_render_snippet_escapes_log_text() {
+        let raw = format!(
+            "…<img src=x onerror=\"alert('{}')\"> & {}toolu_01{}",
+            1, SNIPPET_OPEN, SNIPPET_CLOSE
+        );
+        assert_eq!(
+            render_snippet(&raw),
+            "…&lt;img src=x onerror=&quot;alert(&#39;1&#39;)&quot;&gt; &amp; <mark>toolu_01</mark>"
+        );
+    }
+}
@@ -12,6 +12,7 @@ pub mod migration;
 pub mod tray;
 pub mod i18n;
 pub mod proxy_db;
+pub mod log_search;
 pub mod device;
 pub mod update_checker;
 pub mod integration;
@@ -23,6 +24,12 @@ pub mod token_stats;
 pub mod http_api;
 pub mod cloudflared;
 pub mod scheduler;
+pub mod vault;
+pub mod limit_state_db;
+pub mod quota_history;
+pub mod responses_db;
+pub mod response_cache_db;
+pub mod audit_db;
 
 use crate::models;
 
@@ -35,6 +42,10 @@ pub use config::*;
 pub use logger::*;
 // pub use device::*;
 
-pub async fn fetch_quota(access_token: &str, email: &str) -> crate::error::AppResult<(models::QuotaData, Option<String>)> {
-    quota::fetch_quota(access_token, email).await
+pub async fn fetch_quota(
+    access_token: &str,
+    email: &str,
+    individual_proxy: Option<&str>,
+) -> crate::error::AppResult<(models::QuotaData, Option<String>)> {
+    quota::fetch_quota(access_token, email, individual_proxy).await
 }
@@ -125,8 +125,12 @@ pub async fn exchange_code(code: &str, redirect_uri: &str) -> Result<TokenRespon
 }
 
 /// 使用 refresh_token 刷新 access_token
-pub async fn refresh_access_token(refresh_token: &str) -> Result<TokenResponse, String> {
-    let client = crate::utils::http::create_client(15);
+/// `individual_proxy` 为账号独立代理，None 时走全局上游代理
+pub async fn refresh_access_token(
+    refresh_token: &str,
+    individual_proxy: Option<&str>,
+) -> Result<TokenResponse, String> {
+    let client = crate::utils::http::create_client_for_account(15, individual_proxy)?;
     
     let params = [
         ("client_id", CLIENT_ID),
@@ -183,6 +187,7 @@ pub async fn get_user_info(access_token: &str) -> Result<UserInfo, String> {
 /// 返回最新的 access_token
 pub async fn ensure_fresh_token(
     current_token: &crate::models::TokenData,
+    individual_proxy: Option<&str>,
 ) -> Result<crate::models::TokenData, String> {
     let now = chrono::Local::now().timestamp();
     
@@ -193,7 +198,7 @@ pub async fn ensure_fresh_token(
     
     // 需要刷新
     crate::modules::logger::log_info("Token 即将过期，正在刷新...");
-    let response = refresh_access_token(&current_token.refresh_token).await?;
+    let response = refresh_access_token(&current_token.refresh_token, individual_proxy).await?;
     
     // 构造新 TokenData
     Ok(crate::models::TokenData::new(
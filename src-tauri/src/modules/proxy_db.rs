@@ -18,7 +18,10 @@ pub fn get_proxy_db_path() -> Result<PathBuf, String> {
 pub fn init_db() -> Result<(), String> {
     let db_path = get_proxy_db_path()?;
     let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
-    
+    init_schema(&conn)
+}
+
+fn init_schema(conn: &Connection) -> Result<(), String> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS request_logs (
             id TEXT PRIMARY KEY,
@@ -40,22 +43,80 @@ pub fn init_db() -> Result<(), String> {
     let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN output_tokens INTEGER", []);
     let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN account_email TEXT", []);
     let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN mapped_model TEXT", []);
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN client_key_id TEXT", []);
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN client_key_name TEXT", []);
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN fallback_from TEXT", []);
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN cache_status TEXT", []);
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN protocol TEXT", []);
 
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_timestamp ON request_logs (timestamp DESC)",
         [],
     ).map_err(|e| e.to_string())?;
 
+    // [NEW] 旧日志没有 protocol 列，按 URL 补齐 (与 metrics::protocol_from_path 一致)
+    conn.execute(
+        "UPDATE request_logs SET protocol = CASE
+            WHEN url LIKE '/kiro/%' THEN 'kiro'
+            WHEN url LIKE '/v1beta/%' THEN 'gemini'
+            WHEN url LIKE '/v1/messages%' THEN 'anthropic'
+            WHEN url LIKE '/mcp/%' THEN 'mcp'
+            WHEN url LIKE '/v1/%' THEN 'openai'
+            ELSE 'other' END
+         WHERE protocol IS NULL",
+        [],
+    ).map_err(|e| e.to_string())?;
+
+    init_search_index(conn)
+}
+
+/// [NEW] 请求体 / 响应体 / 错误的 FTS5 全文索引
+///
+/// VACUUM 可能重排 request_logs 的 rowid，因此索引按 log_id 关联而不是 rowid；
+/// 写入由触发器同步，删除日志后由 prune_search_index 清理。
+fn init_search_index(conn: &Connection) -> Result<(), String> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE name = 'request_logs_fts'",
+        [],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS request_logs_fts USING fts5(
+            log_id UNINDEXED, request_body, response_body, error, tokenize = 'unicode61'
+         );
+         CREATE TRIGGER IF NOT EXISTS request_logs_fts_insert AFTER INSERT ON request_logs BEGIN
+            INSERT INTO request_logs_fts (log_id, request_body, response_body, error)
+            VALUES (new.id, new.request_body, new.response_body, new.error);
+         END;",
+    ).map_err(|e| e.to_string())?;
+
+    // 首次创建时为已有日志建立索引
+    if exists == 0 {
+        conn.execute(
+            "INSERT INTO request_logs_fts (log_id, request_body, response_body, error)
+             SELECT id, request_body, response_body, error FROM request_logs",
+            [],
+        ).map_err(|e| e.to_string())?;
+    }
     Ok(())
 }
 
+/// 删除已不存在的日志对应的索引
+fn prune_search_index(conn: &Connection) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM request_logs_fts WHERE log_id NOT IN (SELECT id FROM request_logs)",
+        [],
+    ).map_err(|e| e.to_string())
+}
+
 pub fn save_log(log: &ProxyRequestLog) -> Result<(), String> {
     let db_path = get_proxy_db_path()?;
     let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
 
     conn.execute(
-        "INSERT INTO request_logs (id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        "INSERT INTO request_logs (id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model, client_key_id, client_key_name, fallback_from, cache_status, protocol)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
         params![
             log.id,
             log.timestamp,
@@ -71,6 +132,11 @@ pub fn save_log(log: &ProxyRequestLog) -> Result<(), String> {
             log.output_tokens,
             log.account_email,
             log.mapped_model,
+            log.client_key_id,
+            log.client_key_name,
+            log.fallback_from,
+            log.cache_status,
+            log.protocol,
         ],
     ).map_err(|e| e.to_string())?;
 
@@ -82,7 +148,7 @@ pub fn get_logs(limit: usize) -> Result<Vec<ProxyRequestLog>, String> {
     let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
 
     let mut stmt = conn.prepare(
-        "SELECT id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model
+        "SELECT id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model, client_key_id, client_key_name, fallback_from, cache_status
          FROM request_logs 
          ORDER BY timestamp DESC 
          LIMIT ?1"
@@ -98,6 +164,8 @@ pub fn get_logs(limit: usize) -> Result<Vec<ProxyRequestLog>, String> {
             duration: row.get(5)?,
             model: row.get(6)?,
             mapped_model: row.get(13).unwrap_or(None),
+            fallback_from: row.get(16).unwrap_or(None),
+            cache_status: row.get(17).unwrap_or(None),
             account_email: row.get(12).unwrap_or(None),
             client_ip: None,
             error: row.get(7)?,
@@ -108,6 +176,8 @@ pub fn get_logs(limit: usize) -> Result<Vec<ProxyRequestLog>, String> {
             cached_tokens: None,
             reasoning_tokens: None,
             protocol: None,
+            client_key_id: row.get(14).unwrap_or(None),
+            client_key_name: row.get(15).unwrap_or(None),
         })
     }).map_err(|e| e.to_string())?;
 
@@ -151,6 +221,7 @@ pub fn clear_logs() -> Result<(), String> {
     let db_path = get_proxy_db_path()?;
     let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
     conn.execute("DELETE FROM request_logs", []).map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM request_logs_fts", []).map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -158,7 +229,7 @@ pub fn get_logs_count_filtered(filter: &str, errors_only: bool) -> Result<u64, S
     let db_path = get_proxy_db_path()?;
     let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
 
-    let mut query = "SELECT COUNT(*) FROM request_logs WHERE (url LIKE ?1 OR method LIKE ?1 OR model LIKE ?1 OR error LIKE ?1 OR account_email LIKE ?1)".to_string();
+    let mut query = "SELECT COUNT(*) FROM request_logs WHERE (url LIKE ?1 OR method LIKE ?1 OR model LIKE ?1 OR error LIKE ?1 OR account_email LIKE ?1 OR client_key_name LIKE ?1)".to_string();
     if errors_only {
         query.push_str(" AND (status < 200 OR status >= 400)");
     }
@@ -171,9 +242,9 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
     let db_path = get_proxy_db_path()?;
     let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
 
-    let mut query = "SELECT id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model 
+    let mut query = "SELECT id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model, client_key_id, client_key_name, fallback_from, cache_status
                      FROM request_logs 
-                     WHERE (url LIKE ?1 OR method LIKE ?1 OR model LIKE ?1 OR error LIKE ?1 OR account_email LIKE ?1)".to_string();
+                     WHERE (url LIKE ?1 OR method LIKE ?1 OR model LIKE ?1 OR error LIKE ?1 OR account_email LIKE ?1 OR client_key_name LIKE ?1)".to_string();
     
     if errors_only {
         query.push_str(" AND (status < 200 OR status >= 400)");
@@ -192,6 +263,8 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
             duration: row.get(5)?,
             model: row.get(6)?,
             mapped_model: row.get(13).unwrap_or(None),
+            fallback_from: row.get(16).unwrap_or(None),
+            cache_status: row.get(17).unwrap_or(None),
             account_email: row.get(12).unwrap_or(None),
             error: row.get(7)?,
             request_body: row.get(8).unwrap_or(None),
@@ -202,6 +275,8 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
             cached_tokens: None,
             reasoning_tokens: None,
             protocol: None,
+            client_key_id: row.get(14).unwrap_or(None),
+            client_key_name: row.get(15).unwrap_or(None),
         })
     }).map_err(|e| e.to_string())?;
 
@@ -212,9 +287,28 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
     Ok(logs)
 }
 
-pub fn get_logs_summary(_filter: &str, _errors_only: bool) -> Result<Vec<ProxyRequestLog>, String> {
-    // 简版实现，直接复用 get_logs_filtered
-    get_logs_filtered(_filter, _errors_only, 100, 0)
+/// 最近 100 条匹配日志的摘要 (不含请求/响应体)
+pub fn get_logs_summary(filter: &str, errors_only: bool) -> Result<Vec<ProxyRequestLog>, String> {
+    let db_path = get_proxy_db_path()?;
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    let mut query = format!(
+        "SELECT {} FROM request_logs l
+         WHERE (l.url LIKE ?1 OR l.method LIKE ?1 OR l.model LIKE ?1 OR l.error LIKE ?1 OR l.account_email LIKE ?1 OR l.client_key_name LIKE ?1)",
+        SUMMARY_COLUMNS
+    );
+    if errors_only {
+        query.push_str(" AND (l.status < 200 OR l.status >= 400)");
+    }
+    query.push_str(" ORDER BY l.timestamp DESC LIMIT 100");
+
+    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+    let logs = stmt
+        .query_map([format!("%{}%", filter)], summary_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(logs)
 }
 
 pub fn get_log_detail(log_id: &str) -> Result<Option<ProxyRequestLog>, String> {
@@ -222,7 +316,7 @@ pub fn get_log_detail(log_id: &str) -> Result<Option<ProxyRequestLog>, String> {
     let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
 
     let mut stmt = conn.prepare(
-        "SELECT id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model 
+        "SELECT id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model, client_key_id, client_key_name, fallback_from, cache_status
          FROM request_logs WHERE id = ?1"
     ).map_err(|e| e.to_string())?;
 
@@ -236,6 +330,8 @@ pub fn get_log_detail(log_id: &str) -> Result<Option<ProxyRequestLog>, String> {
             duration: row.get(5)?,
             model: row.get(6)?,
             mapped_model: row.get(13).unwrap_or(None),
+            fallback_from: row.get(16).unwrap_or(None),
+            cache_status: row.get(17).unwrap_or(None),
             account_email: row.get(12).unwrap_or(None),
             error: row.get(7)?,
             request_body: row.get(8).unwrap_or(None),
@@ -246,6 +342,8 @@ pub fn get_log_detail(log_id: &str) -> Result<Option<ProxyRequestLog>, String> {
             cached_tokens: None,
             reasoning_tokens: None,
             protocol: None,
+            client_key_id: row.get(14).unwrap_or(None),
+            client_key_name: row.get(15).unwrap_or(None),
         })
     }).optional().map_err(|e| e.to_string())?;
 
@@ -273,5 +371,268 @@ pub fn cleanup_old_logs(days: i64) -> Result<usize, String> {
     let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
     let timestamp = (chrono::Utc::now() - chrono::Duration::days(days)).timestamp() * 1000;
     let deleted = conn.execute("DELETE FROM request_logs WHERE timestamp < ?1", params![timestamp]).map_err(|e| e.to_string())?;
+    if deleted > 0 {
+        prune_search_index(&conn)?;
+    }
+    Ok(deleted)
+}
+
+/// 数据库的有效数据大小 (总页数减去空闲页，单位字节)
+fn live_db_size(conn: &Connection) -> Result<u64, String> {
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0)).map_err(|e| e.to_string())?;
+    let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0)).map_err(|e| e.to_string())?;
+    let freelist: i64 = conn.query_row("PRAGMA freelist_count", [], |row| row.get(0)).map_err(|e| e.to_string())?;
+    Ok(((page_count - freelist).max(0) * page_size) as u64)
+}
+
+/// 从最旧的日志开始分批删除，直到有效大小不超过 max_bytes
+fn trim_to_size(conn: &Connection, max_bytes: u64) -> Result<usize, String> {
+    const BATCH: i64 = 500;
+    let mut deleted = 0;
+    while live_db_size(conn)? > max_bytes {
+        let removed = conn.execute(
+            "DELETE FROM request_logs WHERE id IN (SELECT id FROM request_logs ORDER BY timestamp ASC LIMIT ?1)",
+            params![BATCH],
+        ).map_err(|e| e.to_string())?;
+        if removed == 0 {
+            break;
+        }
+        prune_search_index(conn)?;
+        deleted += removed;
+    }
     Ok(deleted)
 }
+
+/// [NEW] 按总大小清理日志 (超过 max_bytes 时删除最旧的日志)
+pub fn cleanup_logs_by_size(max_bytes: u64) -> Result<usize, String> {
+    let db_path = get_proxy_db_path()?;
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    trim_to_size(&conn, max_bytes)
+}
+
+/// [NEW] 回收已删除日志占用的磁盘空间
+pub fn vacuum() -> Result<(), String> {
+    let db_path = get_proxy_db_path()?;
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    conn.execute_batch("VACUUM").map_err(|e| e.to_string())
+}
+
+/// 摘要查询的列 (不含请求/响应体)，表别名为 l，与 summary_from_row 对应
+const SUMMARY_COLUMNS: &str = "l.id, l.timestamp, l.method, l.url, l.status, l.duration, l.model, l.error, l.input_tokens, l.output_tokens, l.account_email, l.mapped_model, l.client_key_id, l.client_key_name, l.fallback_from, l.cache_status, l.protocol";
+
+fn summary_from_row(row: &rusqlite::Row) -> rusqlite::Result<ProxyRequestLog> {
+    Ok(ProxyRequestLog {
+        id: row.get(0)?,
+        timestamp: row.get(1)?,
+        method: row.get(2)?,
+        url: row.get(3)?,
+        status: row.get(4)?,
+        duration: row.get(5)?,
+        model: row.get(6)?,
+        error: row.get(7)?,
+        input_tokens: row.get(8).unwrap_or(None),
+        output_tokens: row.get(9).unwrap_or(None),
+        account_email: row.get(10).unwrap_or(None),
+        mapped_model: row.get(11).unwrap_or(None),
+        client_key_id: row.get(12).unwrap_or(None),
+        client_key_name: row.get(13).unwrap_or(None),
+        fallback_from: row.get(14).unwrap_or(None),
+        cache_status: row.get(15).unwrap_or(None),
+        protocol: row.get(16).unwrap_or(None),
+        request_body: None,
+        response_body: None,
+        client_ip: None,
+        cached_tokens: None,
+        reasoning_tokens: None,
+    })
+}
+
+/// [NEW] 全文检索 + 字段过滤 (有全文条件时按相关度排序，否则按时间倒序)
+pub fn search_logs(
+    query: &crate::modules::log_search::LogSearchQuery,
+    limit: usize,
+    offset: usize,
+) -> Result<crate::modules::log_search::LogSearchResult, String> {
+    let db_path = get_proxy_db_path()?;
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    search_logs_in(&conn, query, limit, offset)
+}
+
+fn search_logs_in(
+    conn: &Connection,
+    query: &crate::modules::log_search::LogSearchQuery,
+    limit: usize,
+    offset: usize,
+) -> Result<crate::modules::log_search::LogSearchResult, String> {
+    use crate::modules::log_search::{LogSearchHit, LogSearchResult};
+    use rusqlite::types::Value as SqlValue;
+
+    let mut conditions: Vec<String> = Vec::new();
+    let mut args: Vec<SqlValue> = Vec::new();
+
+    let mut any_of = |clauses: Vec<(String, Vec<SqlValue>)>| {
+        if clauses.is_empty() {
+            return;
+        }
+        let mut parts = Vec::new();
+        for (clause, values) in clauses {
+            parts.push(clause);
+            args.extend(values);
+        }
+        conditions.push(format!("({})", parts.join(" OR ")));
+    };
+
+    if let Some(text) = &query.text {
+        any_of(vec![("request_logs_fts MATCH ?".to_string(), vec![SqlValue::Text(text.clone())])]);
+    }
+    any_of(query.models.iter().map(|m| (
+        "l.model LIKE ? ESCAPE '\\' OR l.mapped_model LIKE ? ESCAPE '\\'".to_string(),
+        vec![SqlValue::Text(m.clone()), SqlValue::Text(m.clone())],
+    )).collect());
+    any_of(query.accounts.iter().map(|a| (
+        "l.account_email LIKE ? ESCAPE '\\'".to_string(),
+        vec![SqlValue::Text(a.clone())],
+    )).collect());
+    any_of(query.client_keys.iter().map(|k| (
+        "l.client_key_name = ? OR l.client_key_id = ?".to_string(),
+        vec![SqlValue::Text(k.clone()), SqlValue::Text(k.clone())],
+    )).collect());
+    any_of(query.protocols.iter().map(|p| (
+        "l.protocol = ?".to_string(),
+        vec![SqlValue::Text(p.clone())],
+    )).collect());
+    any_of(query.statuses.iter().map(|(lo, hi)| (
+        "(l.status >= ? AND l.status < ?)".to_string(),
+        vec![SqlValue::Integer(*lo as i64), SqlValue::Integer(*hi as i64)],
+    )).collect());
+    if let Some(after) = query.after {
+        any_of(vec![("l.timestamp >= ?".to_string(), vec![SqlValue::Integer(after)])]);
+    }
+    if let Some(before) = query.before {
+        any_of(vec![("l.timestamp < ?".to_string(), vec![SqlValue::Integer(before)])]);
+    }
+
+    let from = if query.text.is_some() {
+        "request_logs_fts JOIN request_logs l ON l.id = request_logs_fts.log_id"
+    } else {
+        "request_logs l"
+    };
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+
+    let total: u64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {}{}", from, where_clause),
+        rusqlite::params_from_iter(args.iter()),
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+
+    let (snippet, order) = if query.text.is_some() {
+        (
+            // 匹配标记用私用区字符，转义日志原文后再换成 <mark> (见 render_snippet)
+            "snippet(request_logs_fts, -1, char(57344), char(57345), '…', 16)",
+            "bm25(request_logs_fts), l.timestamp DESC",
+        )
+    } else {
+        ("NULL", "l.timestamp DESC")
+    };
+    let sql = format!(
+        "SELECT {}, {} FROM {}{} ORDER BY {} LIMIT {} OFFSET {}",
+        SUMMARY_COLUMNS, snippet, from, where_clause, order, limit, offset
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let hits = stmt
+        .query_map(rusqlite::params_from_iter(args.iter()), |row| {
+            Ok(LogSearchHit {
+                log: summary_from_row(row)?,
+                snippet: row
+                    .get::<_, Option<String>>(17)?
+                    .map(|raw| crate::modules::log_search::render_snippet(&raw)),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(LogSearchResult { total, hits })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_to_size_removes_oldest_first() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let body = "x".repeat(4096);
+        for i in 0..600 {
+            conn.execute(
+                "INSERT INTO request_logs (id, timestamp, response_body) VALUES (?1, ?2, ?3)",
+                params![format!("log-{}", i), i, body],
+            ).unwrap();
+        }
+
+        let limit = live_db_size(&conn).unwrap() / 2;
+        let deleted = trim_to_size(&conn, limit).unwrap();
+        assert!(deleted > 0);
+        assert!(live_db_size(&conn).unwrap() <= limit);
+
+        // 保留的是最新的日志
+        let newest: i64 = conn.query_row("SELECT MAX(timestamp) FROM request_logs", [], |row| row.get(0)).unwrap();
+        let oldest: i64 = conn.query_row("SELECT MIN(timestamp) FROM request_logs", [], |row| row.get(0)).unwrap();
+        assert_eq!(newest, 599);
+        assert_eq!(oldest, deleted as i64);
+        // 全文索引随日志一起清理
+        let indexed: i64 = conn.query_row("SELECT COUNT(*) FROM request_logs_fts", [], |row| row.get(0)).unwrap();
+        assert_eq!(indexed, 600 - deleted as i64);
+    }
+
+    #[test]
+    fn test_search_logs_fts_and_filters() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let insert = |id: &str, ts: i64, status: u16, model: &str, url: &str, account: &str, request: &str, error: Option<&str>| {
+            conn.execute(
+                "INSERT INTO request_logs (id, timestamp, method, url, status, duration, model, error, request_body, response_body, account_email, protocol)
+                 VALUES (?1, ?2, 'POST', ?3, ?4, 10, ?5, ?6, ?7, '{}', ?8, NULL)",
+                params![id, ts, url, status, model, error, request, account],
+            ).unwrap();
+        };
+        insert("a", 1_000, 200, "claude-opus-4-6", "/v1/messages", "alice@example.com",
+            r#"{"tool_use":{"id":"toolu_01ABC","name":"read_file"}}"#, None);
+        insert("b", 2_000, 500, "claude-sonnet-4-5", "/v1/messages", "bob@example.com",
+            r#"{"messages":"hello"}"#, Some("upstream permission denied for read_file"));
+        insert("c", 3_000, 429, "gemini-3-flash", "/v1beta/models/gemini-3-flash:generateContent", "alice@example.com",
+            r#"{"contents":"read_file please"}"#, Some("quota exhausted"));
+        // 初始化前写入的日志按 URL 补齐 protocol
+        init_schema(&conn).unwrap();
+
+        let search = |q: &str| {
+            let query = crate::modules::log_search::parse_query(q, 10_000).unwrap();
+            search_logs_in(&conn, &query, 50, 0).unwrap()
+        };
+        let ids = |q: &str| search(q).hits.into_iter().map(|h| h.log.id).collect::<Vec<_>>();
+
+        let result = search("toolu_01ABC");
+        assert_eq!(result.total, 1);
+        assert_eq!(result.hits[0].log.id, "a");
+        assert!(result.hits[0].snippet.as_deref().unwrap().contains("<mark>toolu_01ABC</mark>"));
+        assert!(result.hits[0].log.request_body.is_none());
+
+        let mut found = ids("read_file");
+        found.sort();
+        assert_eq!(found, vec!["a", "b", "c"]);
+        let mut found = ids("read_file model:claude-*");
+        found.sort();
+        assert_eq!(found, vec!["a", "b"]);
+        assert_eq!(ids("\"permission denied\""), vec!["b"]);
+        assert_eq!(ids("read_file -quota account:alice"), vec!["a"]);
+        assert_eq!(ids("status:error protocol:gemini"), vec!["c"]);
+        assert!(ids("toolu_01ABC status:5xx").is_empty());
+        assert_eq!(ids("after:1970-01-01T00:00:01.500Z status:>=400"), vec!["c", "b"]);
+        assert_eq!(search("model:claude").total, 2);
+    }
+}
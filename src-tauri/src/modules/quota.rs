@@ -46,16 +46,26 @@ struct Tier {
     slug: Option<String>,
 }
 
-/// 创建配置好的 HTTP Client
-fn create_client() -> reqwest::Client {
-    crate::utils::http::create_client(15)
+/// 创建配置好的 HTTP Client (优先走账号独立代理)
+fn create_client(individual_proxy: Option<&str>) -> Result<reqwest::Client, String> {
+    crate::utils::http::create_client_for_account(15, individual_proxy)
 }
 
 const CLOUD_CODE_BASE_URL: &str = "https://cloudcode-pa.googleapis.com";
 
 /// 获取项目 ID 和订阅类型
-async fn fetch_project_id(access_token: &str, email: &str) -> (Option<String>, Option<String>) {
-    let client = create_client();
+async fn fetch_project_id(
+    access_token: &str,
+    email: &str,
+    individual_proxy: Option<&str>,
+) -> (Option<String>, Option<String>) {
+    let client = match create_client(individual_proxy) {
+        Ok(c) => c,
+        Err(e) => {
+            crate::modules::logger::log_warn(&format!("⚠️  [{}] loadCodeAssist 跳过: {}", email, e));
+            return (None, None);
+        }
+    };
     let meta = json!({"metadata": {"ideType": "ANTIGRAVITY"}});
 
     let res = client
@@ -108,23 +118,37 @@ pub async fn warmup_model_directly(_token: &str, _model: &str, _pid: &str, _emai
 
 /// 获取用于预热的有效 Token
 pub async fn get_valid_token_for_warmup(account: &crate::models::Account) -> Result<(String, String), String> {
-    let token = crate::modules::oauth::ensure_fresh_token(&account.token).await?;
+    let token = crate::modules::oauth::ensure_fresh_token(&account.token, account.individual_proxy.as_deref()).await?;
     let pid = account.token.project_id.clone().unwrap_or_else(|| "bamboo-precept-lgxtn".to_string());
     Ok((token.access_token, pid))
 }
 
 /// 带缓存的配额查询
-pub async fn fetch_quota_with_cache(access_token: &str, email: &str, _pid: Option<&str>) -> crate::error::AppResult<(QuotaData, Option<String>)> {
-    fetch_quota(access_token, email).await
+pub async fn fetch_quota_with_cache(
+    access_token: &str,
+    email: &str,
+    _pid: Option<&str>,
+    individual_proxy: Option<&str>,
+) -> crate::error::AppResult<(QuotaData, Option<String>)> {
+    fetch_quota(access_token, email, individual_proxy).await
 }
 
 /// 查询账号配额的统一入口
-pub async fn fetch_quota(access_token: &str, email: &str) -> crate::error::AppResult<(QuotaData, Option<String>)> {
-    fetch_quota_inner(access_token, email).await
+/// `individual_proxy` 为账号独立代理 (Account.individual_proxy)
+pub async fn fetch_quota(
+    access_token: &str,
+    email: &str,
+    individual_proxy: Option<&str>,
+) -> crate::error::AppResult<(QuotaData, Option<String>)> {
+    fetch_quota_inner(access_token, email, individual_proxy).await
 }
 
 /// 查询账号配额逻辑
-pub async fn fetch_quota_inner(access_token: &str, email: &str) -> crate::error::AppResult<(QuotaData, Option<String>)> {
+pub async fn fetch_quota_inner(
+    access_token: &str,
+    email: &str,
+    individual_proxy: Option<&str>,
+) -> crate::error::AppResult<(QuotaData, Option<String>)> {
     use crate::error::AppError;
     
     // Detect provider by token format
@@ -140,11 +164,11 @@ pub async fn fetch_quota_inner(access_token: &str, email: &str) -> crate::error:
     crate::modules::logger::log_info(&format!("[{}] Using Gemini API", email));
     
     // 1. 获取 Project ID 和订阅类型
-    let (project_id, subscription_tier) = fetch_project_id(access_token, email).await;
+    let (project_id, subscription_tier) = fetch_project_id(access_token, email, individual_proxy).await;
     
     let final_project_id = project_id.as_deref().unwrap_or("bamboo-precept-lgxtn");
     
-    let client = create_client();
+    let client = create_client(individual_proxy).map_err(AppError::Unknown)?;
     let payload = json!({
         "project": final_project_id
     });
@@ -382,17 +406,3 @@ async fn fetch_kiro_quota(access_token: &str, email: &str) -> crate::error::AppR
         }
     }
 }
-
-/// 批量查询所有账号配额 (备用功能)
-#[allow(dead_code)]
-pub async fn fetch_all_quotas(accounts: Vec<(String, String)>) -> Vec<(String, crate::error::AppResult<QuotaData>)> {
-    let mut results = Vec::new();
-    
-    for (account_id, access_token) in accounts {
-        // 在批量查询中，我们将 account_id 传入以供日志标识
-        let result = fetch_quota(&access_token, &account_id).await.map(|(q, _)| q);
-        results.push((account_id, result));
-    }
-    
-    results
-}
@@ -0,0 +1,375 @@
+//! Quota History Module
+//! 配额快照时间序列 + 按模型的消耗速率 / 耗尽时间预测
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use crate::models::QuotaData;
+
+/// 历史保留天数，超过的快照在写入时清理
+const HISTORY_RETENTION_DAYS: i64 = 30;
+
+/// 一次配额拉取中单个模型的快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaSnapshot {
+    pub timestamp: i64,
+    pub account_id: String,
+    pub account_email: String,
+    pub model: String,
+    pub percentage: i32,
+    /// 配额刷新时间 (Unix 秒)，无法解析时为 None
+    pub reset_time: Option<i64>,
+    pub tier: Option<String>,
+}
+
+/// 单账号的消耗情况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountBurn {
+    pub account_id: String,
+    pub account_email: String,
+    pub remaining_percent: i32,
+    /// 百分点 / 小时
+    pub burn_rate_per_hour: f64,
+    pub reset_time: Option<i64>,
+    /// 按当前速率耗尽的时间 (Unix 秒)，速率为 0 时为 None
+    pub exhausts_at: Option<i64>,
+}
+
+/// 单模型的账号池预测
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelForecast {
+    pub model: String,
+    pub accounts: usize,
+    /// 各账号剩余百分比之和 (满额为 accounts * 100)
+    pub remaining_percent_total: i64,
+    /// 整个池子的消耗速率 (百分点 / 小时)
+    pub burn_rate_per_hour: f64,
+    pub exhausts_at: Option<i64>,
+    pub earliest_reset: Option<i64>,
+    /// 是否会在最早的配额刷新前耗尽 (没有速率或刷新时间时为 None)
+    pub exhausts_before_reset: Option<bool>,
+    pub account_burn: Vec<AccountBurn>,
+}
+
+fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join("quota_history.db"))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    let db_path = get_db_path()?;
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+/// Initialize the quota history database
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quota_snapshots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            account_id TEXT NOT NULL,
+            account_email TEXT NOT NULL,
+            model TEXT NOT NULL,
+            percentage INTEGER NOT NULL,
+            reset_time INTEGER,
+            tier TEXT
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_quota_model_time ON quota_snapshots (model, timestamp)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_quota_account_time ON quota_snapshots (account_id, timestamp)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// 清空历史
+///
+/// 通过 SQL 删除所有行而不是删除文件: WAL 模式下单独删除 .db 会留下 -wal / -shm，
+/// 重建后旧的 WAL 可能被重放回来。
+pub fn clear() -> Result<(), String> {
+    init_db()?;
+    let conn = connect_db()?;
+    conn.execute("DELETE FROM quota_snapshots", [])
+        .map_err(|e| e.to_string())?;
+    // 截断 WAL，释放被删除数据占用的空间
+    conn.pragma_update(None, "wal_checkpoint", "TRUNCATE")
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 查询窗口的起始时间，`hours` 限制在 [0, 保留天数] 之内，避免乘法溢出
+fn window_cutoff(now: i64, hours: i64) -> i64 {
+    let hours = hours.clamp(0, HISTORY_RETENTION_DAYS * 24);
+    now - hours * 3600
+}
+
+fn parse_reset_time(reset_time: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(reset_time)
+        .ok()
+        .map(|dt| dt.timestamp())
+}
+
+/// 记录一次配额拉取结果 (每个模型一行)
+pub fn record_snapshot(account_id: &str, account_email: &str, quota: &QuotaData) -> Result<(), String> {
+    if quota.is_forbidden || quota.models.is_empty() {
+        return Ok(());
+    }
+
+    init_db()?;
+    let mut conn = connect_db()?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT INTO quota_snapshots (timestamp, account_id, account_email, model, percentage, reset_time, tier)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )
+            .map_err(|e| e.to_string())?;
+        for m in &quota.models {
+            stmt.execute(params![
+                quota.last_updated,
+                account_id,
+                account_email,
+                m.name,
+                m.percentage,
+                parse_reset_time(&m.reset_time),
+                quota.subscription_tier,
+            ])
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let cutoff = chrono::Utc::now().timestamp() - HISTORY_RETENTION_DAYS * 86400;
+    tx.execute("DELETE FROM quota_snapshots WHERE timestamp < ?1", params![cutoff])
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// 查询最近 `hours` 小时的快照，可按账号 / 模型过滤
+pub fn get_history(hours: i64, account_id: Option<&str>, model: Option<&str>) -> Result<Vec<QuotaSnapshot>, String> {
+    init_db()?;
+    let conn = connect_db()?;
+    let cutoff = window_cutoff(chrono::Utc::now().timestamp(), hours);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp, account_id, account_email, model, percentage, reset_time, tier
+             FROM quota_snapshots
+             WHERE timestamp >= ?1
+               AND (?2 IS NULL OR account_id = ?2)
+               AND (?3 IS NULL OR model = ?3)
+             ORDER BY timestamp ASC, id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(params![cutoff, account_id, model], |row| {
+            Ok(QuotaSnapshot {
+                timestamp: row.get(0)?,
+                account_id: row.get(1)?,
+                account_email: row.get(2)?,
+                model: row.get(3)?,
+                percentage: row.get(4)?,
+                reset_time: row.get(5)?,
+                tier: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows)
+}
+
+/// 基于最近 `hours` 小时的历史预测各模型耗尽时间
+pub fn get_forecast(hours: i64) -> Result<Vec<ModelForecast>, String> {
+    let rows = get_history(hours, None, None)?;
+    Ok(build_forecast(&rows, chrono::Utc::now().timestamp()))
+}
+
+/// 账号的消耗速率 (百分点 / 小时)
+///
+/// 只统计相邻快照之间的下降；百分比上升或 reset_time 变化视为配额已刷新，
+/// 该区间不计入。样本不足时返回 0。
+fn account_burn_rate(points: &[&QuotaSnapshot]) -> f64 {
+    let mut consumed = 0.0;
+    let mut elapsed = 0.0;
+    for pair in points.windows(2) {
+        let (prev, next) = (pair[0], pair[1]);
+        let dt = (next.timestamp - prev.timestamp) as f64;
+        if dt <= 0.0 || next.percentage > prev.percentage || next.reset_time != prev.reset_time {
+            continue;
+        }
+        consumed += (prev.percentage - next.percentage) as f64;
+        elapsed += dt;
+    }
+    if elapsed <= 0.0 {
+        0.0
+    } else {
+        consumed / elapsed * 3600.0
+    }
+}
+
+/// 由快照序列计算预测 (纯函数，便于测试)
+pub fn build_forecast(rows: &[QuotaSnapshot], now: i64) -> Vec<ModelForecast> {
+    // model -> account_id -> 按时间排序的快照
+    let mut grouped: BTreeMap<&str, HashMap<&str, Vec<&QuotaSnapshot>>> = BTreeMap::new();
+    for row in rows {
+        grouped
+            .entry(row.model.as_str())
+            .or_default()
+            .entry(row.account_id.as_str())
+            .or_default()
+            .push(row);
+    }
+
+    grouped
+        .into_iter()
+        .map(|(model, accounts)| {
+            let mut account_burn: Vec<AccountBurn> = accounts
+                .into_values()
+                .filter_map(|mut points| {
+                    points.sort_by_key(|p| p.timestamp);
+                    let latest = *points.last()?;
+                    // 最近一次快照之后已经过了刷新时间，视为已满额
+                    let refreshed = latest.reset_time.map(|t| t <= now).unwrap_or(false);
+                    let remaining = if refreshed { 100 } else { latest.percentage };
+                    let burn_rate = account_burn_rate(&points);
+                    let exhausts_at = (burn_rate > 0.0)
+                        .then(|| now + (remaining as f64 / burn_rate * 3600.0) as i64);
+                    Some(AccountBurn {
+                        account_id: latest.account_id.clone(),
+                        account_email: latest.account_email.clone(),
+                        remaining_percent: remaining,
+                        burn_rate_per_hour: burn_rate,
+                        reset_time: latest.reset_time.filter(|t| *t > now),
+                        exhausts_at,
+                    })
+                })
+                .collect();
+            account_burn.sort_by(|a, b| a.account_email.cmp(&b.account_email));
+
+            let remaining_total: i64 = account_burn.iter().map(|a| a.remaining_percent as i64).sum();
+            let burn_rate: f64 = account_burn.iter().map(|a| a.burn_rate_per_hour).sum();
+            let exhausts_at = (burn_rate > 0.0)
+                .then(|| now + (remaining_total as f64 / burn_rate * 3600.0) as i64);
+            let earliest_reset = account_burn.iter().filter_map(|a| a.reset_time).min();
+            let exhausts_before_reset = match (exhausts_at, earliest_reset) {
+                (Some(e), Some(r)) => Some(e < r),
+                _ => None,
+            };
+
+            ModelForecast {
+                model: model.to_string(),
+                accounts: account_burn.len(),
+                remaining_percent_total: remaining_total,
+                burn_rate_per_hour: burn_rate,
+                exhausts_at,
+                earliest_reset,
+                exhausts_before_reset,
+                account_burn,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap(account: &str, model: &str, timestamp: i64, percentage: i32, reset_time: i64) -> QuotaSnapshot {
+        QuotaSnapshot {
+            timestamp,
+            account_id: account.to_string(),
+            account_email: format!("{}@example.com", account),
+            model: model.to_string(),
+            percentage,
+            reset_time: Some(reset_time),
+            tier: Some("PRO".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_window_cutoff_caps_hours() {
+        let now = 1_000_000_000;
+        assert_eq!(window_cutoff(now, 2), now - 7200);
+        assert_eq!(window_cutoff(now, -5), now);
+        assert_eq!(window_cutoff(now, i64::MAX), now - HISTORY_RETENTION_DAYS * 86400);
+    }
+
+    #[test]
+    fn test_forecast_sums_pool_burn_rate() {
+        let now = 10 * 3600;
+        let reset = now + 5 * 3600;
+        let rows = vec![
+            // a: 100 -> 90 -> 80 over 2h = 10 %/h
+            snap("a", "claude-opus-4-5-thinking", now - 2 * 3600, 100, reset),
+            snap("a", "claude-opus-4-5-thinking", now - 3600, 90, reset),
+            snap("a", "claude-opus-4-5-thinking", now, 80, reset),
+            // b: 50 -> 40 over 1h = 10 %/h
+            snap("b", "claude-opus-4-5-thinking", now - 3600, 50, reset),
+            snap("b", "claude-opus-4-5-thinking", now, 40, reset),
+            // 无消耗的模型
+            snap("a", "gemini-3-flash", now - 3600, 100, reset),
+            snap("a", "gemini-3-flash", now, 100, reset),
+        ];
+
+        let forecast = build_forecast(&rows, now);
+        assert_eq!(forecast.len(), 2);
+
+        let opus = forecast.iter().find(|f| f.model == "claude-opus-4-5-thinking").unwrap();
+        assert_eq!(opus.accounts, 2);
+        assert_eq!(opus.remaining_percent_total, 120);
+        assert!((opus.burn_rate_per_hour - 20.0).abs() < 1e-9);
+        // 120 / 20 = 6h，晚于 5h 后的刷新 -> 撑得到刷新
+        assert_eq!(opus.exhausts_at, Some(now + 6 * 3600));
+        assert_eq!(opus.exhausts_before_reset, Some(false));
+
+        let flash = forecast.iter().find(|f| f.model == "gemini-3-flash").unwrap();
+        assert_eq!(flash.burn_rate_per_hour, 0.0);
+        assert_eq!(flash.exhausts_at, None);
+        assert_eq!(flash.exhausts_before_reset, None);
+    }
+
+    #[test]
+    fn test_forecast_ignores_refresh_between_samples() {
+        let now = 10 * 3600;
+        let rows = vec![
+            snap("a", "claude-sonnet-4-5", now - 3 * 3600, 40, now - 2 * 3600),
+            // 刷新后回到 100，不应计为负消耗
+            snap("a", "claude-sonnet-4-5", now - 3600, 100, now + 4 * 3600),
+            snap("a", "claude-sonnet-4-5", now, 70, now + 4 * 3600),
+        ];
+
+        let forecast = build_forecast(&rows, now);
+        let sonnet = &forecast[0];
+        assert!((sonnet.burn_rate_per_hour - 30.0).abs() < 1e-9);
+        assert_eq!(sonnet.remaining_percent_total, 70);
+        // 70 / 30 h ≈ 2.33h < 4h -> 会在刷新前耗尽
+        assert_eq!(sonnet.exhausts_before_reset, Some(true));
+    }
+}
@@ -0,0 +1,249 @@
+//! Response Cache Database Module
+//! 确定性请求的响应缓存存储 (TTL + 条目数 / 总大小上限，超出时按最近使用时间淘汰)
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// 一条缓存的响应 (始终以非流式 JSON 保存)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResponse {
+    pub key: String,
+    /// "anthropic" / "openai" / "gemini"
+    pub protocol: String,
+    /// 实际使用的模型 (命中时写回 X-Mapped-Model)
+    pub model: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub response: Value,
+}
+
+/// 缓存占用统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResponseCacheStats {
+    pub entries: u64,
+    pub total_bytes: u64,
+    /// 现存条目的累计命中次数
+    pub hits: u64,
+}
+
+/// 获取响应缓存数据库路径 (与账号目录同级)
+pub fn get_response_cache_db_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("response_cache.db")
+}
+
+/// 连接数据库
+fn connect_db(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS response_cache (
+            key TEXT PRIMARY KEY,
+            protocol TEXT NOT NULL,
+            model TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            last_used_at INTEGER NOT NULL,
+            hits INTEGER NOT NULL DEFAULT 0,
+            size_bytes INTEGER NOT NULL,
+            response TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_response_cache_last_used ON response_cache (last_used_at DESC)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+/// 读取未过期的缓存，命中时累加命中次数并刷新最近使用时间
+pub fn get_entry(db_path: &Path, key: &str, now: i64) -> Result<Option<CachedResponse>, String> {
+    let conn = connect_db(db_path)?;
+
+    let row = conn
+        .query_row(
+            "SELECT key, protocol, model, created_at, expires_at, response
+             FROM response_cache WHERE key = ?1 AND expires_at > ?2",
+            params![key, now],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    let Some((key, protocol, model, created_at, expires_at, response)) = row else {
+        return Ok(None);
+    };
+    conn.execute(
+        "UPDATE response_cache SET hits = hits + 1, last_used_at = ?2 WHERE key = ?1",
+        params![key, now],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(Some(CachedResponse {
+        key,
+        protocol,
+        model,
+        created_at,
+        expires_at,
+        response: serde_json::from_str(&response).map_err(|e| e.to_string())?,
+    }))
+}
+
+/// 写入缓存，随后清理过期条目并按最近使用时间淘汰超出上限的条目
+///
+/// 单条响应超过 max_bytes 时不写入，返回 Ok(false)。
+pub fn put_entry(db_path: &Path, entry: &CachedResponse, max_entries: usize, max_bytes: u64) -> Result<bool, String> {
+    let response = serde_json::to_string(&entry.response).map_err(|e| e.to_string())?;
+    let size = response.len() as u64;
+    if max_entries == 0 || size > max_bytes {
+        return Ok(false);
+    }
+
+    let mut conn = connect_db(db_path)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT OR REPLACE INTO response_cache
+         (key, protocol, model, created_at, expires_at, last_used_at, hits, size_bytes, response)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?4, 0, ?6, ?7)",
+        params![
+            entry.key,
+            entry.protocol,
+            entry.model,
+            entry.created_at,
+            entry.expires_at,
+            size as i64,
+            response,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    tx.execute("DELETE FROM response_cache WHERE expires_at <= ?1", params![entry.created_at])
+        .map_err(|e| e.to_string())?;
+
+    let evicted: Vec<String> = {
+        let mut stmt = tx
+            .prepare("SELECT key, size_bytes FROM response_cache ORDER BY last_used_at DESC, created_at DESC")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+            .map_err(|e| e.to_string())?;
+        let mut total = 0u64;
+        let mut evicted = Vec::new();
+        for (index, row) in rows.enumerate() {
+            let (key, size) = row.map_err(|e| e.to_string())?;
+            total += size.max(0) as u64;
+            if index >= max_entries || total > max_bytes {
+                evicted.push(key);
+            }
+        }
+        evicted
+    };
+    for key in &evicted {
+        tx.execute("DELETE FROM response_cache WHERE key = ?1", params![key])
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+/// 清空缓存，返回删除的条目数
+pub fn clear(db_path: &Path) -> Result<usize, String> {
+    let conn = connect_db(db_path)?;
+    conn.execute("DELETE FROM response_cache", [])
+        .map_err(|e| e.to_string())
+}
+
+/// 未过期条目的统计
+pub fn stats(db_path: &Path, now: i64) -> Result<ResponseCacheStats, String> {
+    let conn = connect_db(db_path)?;
+    conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(size_bytes), 0), COALESCE(SUM(hits), 0)
+         FROM response_cache WHERE expires_at > ?1",
+        params![now],
+        |row| {
+            Ok(ResponseCacheStats {
+                entries: row.get::<_, i64>(0)? as u64,
+                total_bytes: row.get::<_, i64>(1)? as u64,
+                hits: row.get::<_, i64>(2)? as u64,
+            })
+        },
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_ttl_and_eviction() {
+        let dir = std::env::temp_dir().join(format!("response-cache-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = get_response_cache_db_path(&dir);
+        let now = 1_700_000_000;
+
+        let entry = |key: &str, created_at: i64, text: &str| CachedResponse {
+            key: key.to_string(),
+            protocol: "anthropic".to_string(),
+            model: "gemini-3-flash".to_string(),
+            created_at,
+            expires_at: created_at + 60,
+            response: json!({ "content": [{ "type": "text", "text": text }] }),
+        };
+
+        assert!(put_entry(&db_path, &entry("a", now, "title"), 2, 1024).unwrap());
+        assert!(put_entry(&db_path, &entry("b", now + 1, "summary"), 2, 1024).unwrap());
+        let hit = get_entry(&db_path, "a", now + 2).unwrap().unwrap();
+        assert_eq!(hit.response["content"][0]["text"], "title");
+        assert!(get_entry(&db_path, "a", now + 60).unwrap().is_none());
+
+        // 条目数上限: 淘汰最久未使用的 b (a 刚被命中)
+        assert!(put_entry(&db_path, &entry("c", now + 3, "probe"), 2, 1024).unwrap());
+        assert!(get_entry(&db_path, "b", now + 4).unwrap().is_none());
+        let stats = stats(&db_path, now + 4).unwrap();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.hits, 1);
+
+        // 总大小上限: 单条超限不写入；总量超限时淘汰最久未使用的 a
+        assert!(!put_entry(&db_path, &entry("huge", now + 5, &"x".repeat(2048)), 10, 1024).unwrap());
+        let size = |text: &str| serde_json::to_string(&entry("", 0, text).response).unwrap().len() as u64;
+        assert!(put_entry(&db_path, &entry("d", now + 5, "d"), 10, size("d") + size("probe")).unwrap());
+        assert_eq!(stats_entries(&db_path, now + 5), 2);
+        assert!(get_entry(&db_path, "a", now + 5).unwrap().is_none());
+
+        // 过期条目在写入时被清理
+        assert!(put_entry(&db_path, &entry("e", now + 200, "e"), 10, 1024).unwrap());
+        assert_eq!(stats_entries(&db_path, 0), 1);
+
+        assert_eq!(clear(&db_path).unwrap(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn stats_entries(db_path: &Path, now: i64) -> u64 {
+        stats(db_path, now).unwrap().entries
+    }
+}
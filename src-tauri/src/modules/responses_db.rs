@@ -0,0 +1,240 @@
+//! Responses Database Module
+//! OpenAI Responses API 的本地存储，支撑 previous_response_id 续接对话与 GET / DELETE /v1/responses/{id}
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// 存储保留天数，超过的记录在写入时清理
+const RETENTION_DAYS: i64 = 30;
+
+/// previous_response_id 链的最大深度，防止异常数据导致死循环
+pub const MAX_CHAIN_DEPTH: usize = 1000;
+
+/// 一次已存储的响应
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredResponse {
+    pub id: String,
+    pub created_at: i64,
+    pub model: String,
+    pub previous_response_id: Option<String>,
+    /// 创建该响应的客户端 API Key ID (使用全局 API Key 或未鉴权时为 None)
+    pub owner: Option<String>,
+    /// 本轮请求新增的输入项 (已规范化为 Responses item 数组，不含历史)
+    pub input_items: Vec<Value>,
+    /// 返回给客户端的完整 response 对象
+    pub response: Value,
+}
+
+/// 获取 Responses 数据库路径 (与账号目录同级)
+pub fn get_responses_db_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("responses.db")
+}
+
+/// 连接数据库
+fn connect_db(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS responses (
+            id TEXT PRIMARY KEY,
+            created_at INTEGER NOT NULL,
+            model TEXT NOT NULL,
+            previous_response_id TEXT,
+            input_items TEXT NOT NULL,
+            response TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    // Try to add new columns (ignore errors if they exist)
+    let _ = conn.execute("ALTER TABLE responses ADD COLUMN owner TEXT", []);
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_responses_created_at ON responses (created_at)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+/// 保存响应，并清理超过保留期的旧记录
+pub fn save_response(db_path: &Path, stored: &StoredResponse) -> Result<(), String> {
+    let conn = connect_db(db_path)?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO responses
+         (id, created_at, model, previous_response_id, input_items, response, owner)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            stored.id,
+            stored.created_at,
+            stored.model,
+            stored.previous_response_id,
+            serde_json::to_string(&stored.input_items).map_err(|e| e.to_string())?,
+            serde_json::to_string(&stored.response).map_err(|e| e.to_string())?,
+            stored.owner,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let cutoff = stored.created_at - RETENTION_DAYS * 86400;
+    conn.execute("DELETE FROM responses WHERE created_at < ?1", params![cutoff])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// 按 ID 读取响应，只返回 `owner` 创建的记录 (其它调用方视为不存在)
+pub fn get_response(db_path: &Path, id: &str, owner: Option<&str>) -> Result<Option<StoredResponse>, String> {
+    let conn = connect_db(db_path)?;
+
+    let row = conn
+        .query_row(
+            "SELECT id, created_at, model, previous_response_id, input_items, response, owner
+             FROM responses WHERE id = ?1 AND owner IS ?2",
+            params![id, owner],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    let Some((id, created_at, model, previous_response_id, input_items, response, owner)) = row else {
+        return Ok(None);
+    };
+    Ok(Some(StoredResponse {
+        id,
+        created_at,
+        model,
+        previous_response_id,
+        owner,
+        input_items: serde_json::from_str(&input_items).map_err(|e| e.to_string())?,
+        response: serde_json::from_str(&response).map_err(|e| e.to_string())?,
+    }))
+}
+
+/// 删除 `owner` 创建的响应，返回是否存在
+pub fn delete_response(db_path: &Path, id: &str, owner: Option<&str>) -> Result<bool, String> {
+    let conn = connect_db(db_path)?;
+    let deleted = conn
+        .execute("DELETE FROM responses WHERE id = ?1 AND owner IS ?2", params![id, owner])
+        .map_err(|e| e.to_string())?;
+    Ok(deleted > 0)
+}
+
+/// 沿 previous_response_id 链向上回溯，按时间顺序返回完整对话 (每轮的输入项 + 输出项)
+///
+/// 链上任一响应不存在 (被删除、已过期或属于其它 `owner`) 时返回 Ok(None)。
+pub fn load_conversation(
+    db_path: &Path,
+    previous_response_id: &str,
+    owner: Option<&str>,
+) -> Result<Option<Vec<Value>>, String> {
+    let Some(chain) = load_chain(db_path, previous_response_id, owner)? else {
+        return Ok(None);
+    };
+
+    let mut items = Vec::new();
+    for stored in chain.into_iter().rev() {
+        items.extend(stored.input_items);
+        if let Some(output) = stored.response.get("output").and_then(|o| o.as_array()) {
+            items.extend(output.iter().cloned());
+        }
+    }
+    Ok(Some(items))
+}
+
+/// 从 `id` 开始沿 previous_response_id 链回溯，按从新到旧返回各轮记录
+///
+/// 链上任一响应对 `owner` 不可见时返回 Ok(None)，超过 MAX_CHAIN_DEPTH 时返回错误。
+fn load_chain(db_path: &Path, id: &str, owner: Option<&str>) -> Result<Option<Vec<StoredResponse>>, String> {
+    let mut chain = Vec::new();
+    let mut next = Some(id.to_string());
+
+    while let Some(id) = next {
+        if chain.len() >= MAX_CHAIN_DEPTH {
+            return Err(format!("Conversation chain exceeds {} responses", MAX_CHAIN_DEPTH));
+        }
+        let Some(stored) = get_response(db_path, &id, owner)? else {
+            return Ok(None);
+        };
+        next = stored.previous_response_id.clone();
+        chain.push(stored);
+    }
+    Ok(Some(chain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_conversation_chain_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("responses-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = get_responses_db_path(&dir);
+        let now = 1_700_000_000;
+
+        let stored = |id: &str, prev: Option<&str>, user: &str, answer: &str, created_at: i64| StoredResponse {
+            id: id.to_string(),
+            created_at,
+            model: "gemini-3-flash".to_string(),
+            previous_response_id: prev.map(|p| p.to_string()),
+            owner: None,
+            input_items: vec![json!({ "type": "message", "role": "user", "content": user })],
+            response: json!({
+                "id": id,
+                "output": [{ "type": "message", "role": "assistant", "content": [{ "type": "output_text", "text": answer }] }]
+            }),
+        };
+
+        // 超过保留期的记录会在下一次写入时被清理
+        save_response(&db_path, &stored("resp_old", None, "stale", "stale", now - 31 * 86400)).unwrap();
+        save_response(&db_path, &stored("resp_1", None, "hi", "hello", now)).unwrap();
+        save_response(&db_path, &stored("resp_2", Some("resp_1"), "again", "hello again", now + 1)).unwrap();
+        assert!(get_response(&db_path, "resp_old", None).unwrap().is_none());
+
+        let items = load_conversation(&db_path, "resp_2", None).unwrap().unwrap();
+        let texts: Vec<String> = items
+            .iter()
+            .map(|i| match i.get("content") {
+                Some(Value::String(s)) => s.clone(),
+                Some(c) => c[0]["text"].as_str().unwrap().to_string(),
+                None => String::new(),
+            })
+            .collect();
+        assert_eq!(texts, vec!["hi", "hello", "again", "hello again"]);
+
+        // 其它客户端 Key 看不到、也删不掉这些响应
+        assert!(get_response(&db_path, "resp_2", Some("key_b")).unwrap().is_none());
+        assert!(load_conversation(&db_path, "resp_2", Some("key_b")).unwrap().is_none());
+        assert!(!delete_response(&db_path, "resp_1", Some("key_b")).unwrap());
+
+        assert!(delete_response(&db_path, "resp_1", None).unwrap());
+        assert!(!delete_response(&db_path, "resp_1", None).unwrap());
+        // 链断开后无法续接
+        assert!(load_conversation(&db_path, "resp_2", None).unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
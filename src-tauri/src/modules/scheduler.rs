@@ -100,7 +100,7 @@ pub fn start_scheduler(app_handle: Option<tauri::AppHandle>, _proxy_state: crate
                 };
 
                 // Get fresh quota
-                let Ok((fresh_quota, _)) = quota::fetch_quota_with_cache(&token, &account.email, Some(&pid)).await else {
+                let Ok((fresh_quota, _)) = quota::fetch_quota_with_cache(&token, &account.email, Some(&pid), account.individual_proxy.as_deref()).await else {
                     continue;
                 };
 
@@ -273,7 +273,7 @@ pub async fn trigger_warmup_for_account(account: &Account) {
     };
 
     // Get quota info (prefer cache as refresh command likely just updated disk/cache)
-    let Ok((fresh_quota, _)) = quota::fetch_quota_with_cache(&token, &account.email, Some(&pid)).await else {
+    let Ok((fresh_quota, _)) = quota::fetch_quota_with_cache(&token, &account.email, Some(&pid), account.individual_proxy.as_deref()).await else {
         return;
     };
 
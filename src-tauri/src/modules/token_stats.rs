@@ -48,6 +48,17 @@ pub struct ModelTrendPoint {
     pub model_data: std::collections::HashMap<String, u64>,
 }
 
+/// Per client API key usage statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientKeyTokenStats {
+    pub client_key_id: String,
+    pub client_key_name: String,
+    pub total_input_tokens: u64,
+    pub total_output_tokens: u64,
+    pub total_tokens: u64,
+    pub request_count: u64,
+}
+
 /// Account trend data point (for stacked area chart)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccountTrendPoint {
@@ -126,6 +137,22 @@ pub fn init_db() -> Result<(), String> {
     )
     .map_err(|e| e.to_string())?;
 
+    // Per client API key daily usage (used for attribution and daily budgets)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS client_key_usage_daily (
+            day_bucket TEXT NOT NULL,
+            client_key_id TEXT NOT NULL,
+            client_key_name TEXT NOT NULL DEFAULT '',
+            total_input_tokens INTEGER NOT NULL DEFAULT 0,
+            total_output_tokens INTEGER NOT NULL DEFAULT 0,
+            total_tokens INTEGER NOT NULL DEFAULT 0,
+            request_count INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (day_bucket, client_key_id)
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
     Ok(())
 }
 
@@ -176,6 +203,142 @@ pub fn record_usage_v2(
     Ok(())
 }
 
+fn current_day_bucket() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// Count one request against a client API key's daily usage
+pub fn record_client_key_request(client_key_id: &str, client_key_name: &str) -> Result<(), String> {
+    let conn = connect_db()?;
+    conn.execute(
+        "INSERT INTO client_key_usage_daily (day_bucket, client_key_id, client_key_name, request_count)
+         VALUES (?1, ?2, ?3, 1)
+         ON CONFLICT(day_bucket, client_key_id) DO UPDATE SET
+            client_key_name = ?3,
+            request_count = request_count + 1",
+        params![current_day_bucket(), client_key_id, client_key_name],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Atomically count one request against a client API key's daily budget
+///
+/// The check and the increment run as a single upsert, so concurrent requests cannot
+/// both pass on the last remaining slot. Returns `false` (and records nothing) when
+/// the request limit is reached or today's tokens already reached the token limit.
+/// Tokens are only known after a response finishes, so the token limit is soft:
+/// requests admitted while under the limit may still push the total past it.
+pub fn try_reserve_client_key_request(
+    client_key_id: &str,
+    client_key_name: &str,
+    daily_request_limit: Option<u64>,
+    daily_token_limit: Option<u64>,
+) -> Result<bool, String> {
+    // A fresh row starts at one request and zero tokens, which the upsert below would accept
+    if daily_request_limit == Some(0) || daily_token_limit == Some(0) {
+        return Ok(false);
+    }
+    let to_sql = |limit: Option<u64>| limit.map(|l| i64::try_from(l).unwrap_or(i64::MAX));
+    let conn = connect_db()?;
+    let changed = conn
+        .execute(
+            "INSERT INTO client_key_usage_daily (day_bucket, client_key_id, client_key_name, request_count)
+             VALUES (?1, ?2, ?3, 1)
+             ON CONFLICT(day_bucket, client_key_id) DO UPDATE SET
+                client_key_name = ?3,
+                request_count = request_count + 1
+             WHERE (?4 IS NULL OR request_count < ?4)
+               AND (?5 IS NULL OR total_tokens < ?5)",
+            params![
+                current_day_bucket(),
+                client_key_id,
+                client_key_name,
+                to_sql(daily_request_limit),
+                to_sql(daily_token_limit),
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(changed > 0)
+}
+
+/// Attribute token usage to a client API key
+pub fn record_client_key_tokens(
+    client_key_id: &str,
+    client_key_name: &str,
+    input_tokens: u32,
+    output_tokens: u32,
+) -> Result<(), String> {
+    let conn = connect_db()?;
+    let total_tokens = input_tokens + output_tokens;
+    conn.execute(
+        "INSERT INTO client_key_usage_daily (day_bucket, client_key_id, client_key_name, total_input_tokens, total_output_tokens, total_tokens)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(day_bucket, client_key_id) DO UPDATE SET
+            client_key_name = ?3,
+            total_input_tokens = total_input_tokens + ?4,
+            total_output_tokens = total_output_tokens + ?5,
+            total_tokens = total_tokens + ?6",
+        params![current_day_bucket(), client_key_id, client_key_name, input_tokens, output_tokens, total_tokens],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Get today's (UTC) usage of a client API key as `(request_count, total_tokens)`
+pub fn get_client_key_daily_usage(client_key_id: &str) -> Result<(u64, u64), String> {
+    let conn = connect_db()?;
+    conn.query_row(
+        "SELECT COALESCE(SUM(request_count), 0), COALESCE(SUM(total_tokens), 0)
+         FROM client_key_usage_daily
+         WHERE day_bucket = ?1 AND client_key_id = ?2",
+        params![current_day_bucket(), client_key_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Get per client API key statistics for a time range (day granularity)
+pub fn get_client_key_stats(days: i64) -> Result<Vec<ClientKeyTokenStats>, String> {
+    let conn = connect_db()?;
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(days);
+    let cutoff_bucket = cutoff.format("%Y-%m-%d").to_string();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT client_key_id,
+                MAX(client_key_name) as name,
+                SUM(total_input_tokens) as input,
+                SUM(total_output_tokens) as output,
+                SUM(total_tokens) as total,
+                SUM(request_count) as count
+         FROM client_key_usage_daily
+         WHERE day_bucket >= ?1
+         GROUP BY client_key_id
+         ORDER BY total DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([cutoff_bucket], |row| {
+            Ok(ClientKeyTokenStats {
+                client_key_id: row.get(0)?,
+                client_key_name: row.get(1)?,
+                total_input_tokens: row.get(2)?,
+                total_output_tokens: row.get(3)?,
+                total_tokens: row.get(4)?,
+                request_count: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(result)
+}
+
 /// Get hourly aggregated stats for a time range
 pub fn get_hourly_stats(hours: i64) -> Result<Vec<TokenStatsAggregated>, String> {
     let conn = connect_db()?;
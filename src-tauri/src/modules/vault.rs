@@ -0,0 +1,515 @@
+// 账号加密保险库 (可选)
+//
+// 启用后账号文件中的 token.access_token / token.refresh_token 以 AES-256-GCM 加密存储，
+// 其余字段 (配额、禁用状态等) 仍为明文，便于 TokenManager 直接修改。
+// 密钥来源二选一:
+// - 用户口令 (PBKDF2-HMAC-SHA256 派生)
+// - 密钥文件 (32 字节随机数，Base64 存储；适用于 headless 部署)
+use base64::Engine as _;
+use once_cell::sync::Lazy;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::models::AccountExportResponse;
+
+const VAULT_FILE: &str = "vault.json";
+const DEFAULT_KEY_FILE: &str = "vault.key";
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const VERIFIER_PLAINTEXT: &str = "droidgravity-vault";
+const PBKDF2_ITERATIONS: u32 = 310_000;
+const BUNDLE_FORMAT: &str = "droidgravity-account-bundle";
+
+/// headless 环境变量: 密钥文件路径 / 口令
+pub const ENV_KEY_FILE: &str = "DROIDGRAVITY_VAULT_KEY_FILE";
+pub const ENV_PASSPHRASE: &str = "DROIDGRAVITY_VAULT_PASSPHRASE";
+
+/// 账号 JSON 中需要加密的字段 (位于 token 对象内)
+const SECRET_TOKEN_FIELDS: [&str; 2] = ["access_token", "refresh_token"];
+
+/// 解锁后的密钥仅保存在内存中
+static VAULT_KEY: Lazy<RwLock<Option<[u8; 32]>>> = Lazy::new(|| RwLock::new(None));
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultMode {
+    Passphrase,
+    KeyFile,
+}
+
+/// 解锁凭据
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "mode")]
+pub enum VaultCredential {
+    #[serde(rename = "passphrase")]
+    Passphrase { passphrase: String },
+    /// 未指定路径时使用数据目录下的 vault.key
+    #[serde(rename = "key_file")]
+    KeyFile { path: Option<PathBuf> },
+}
+
+/// vault.json: 只保存 KDF 参数与校验密文，不保存密钥
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VaultMeta {
+    version: u32,
+    mode: VaultMode,
+    #[serde(default)]
+    salt: String,
+    #[serde(default)]
+    iterations: u32,
+    verifier: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+    pub mode: Option<VaultMode>,
+}
+
+fn vault_path() -> Result<PathBuf, String> {
+    Ok(crate::modules::account::get_data_dir()?.join(VAULT_FILE))
+}
+
+fn load_meta() -> Result<Option<VaultMeta>, String> {
+    let path = vault_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("读取保险库配置失败: {}", e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("解析保险库配置失败: {}", e))
+}
+
+fn save_meta(meta: &VaultMeta) -> Result<(), String> {
+    let path = vault_path()?;
+    let tmp = path.with_extension("json.tmp");
+    let content = serde_json::to_string_pretty(meta).map_err(|e| format!("序列化保险库配置失败: {}", e))?;
+    fs::write(&tmp, content).map_err(|e| format!("写入保险库配置失败: {}", e))?;
+    fs::rename(&tmp, &path).map_err(|e| format!("写入保险库配置失败: {}", e))
+}
+
+fn current_key() -> Option<[u8; 32]> {
+    *VAULT_KEY.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn set_key(key: Option<[u8; 32]>) {
+    *VAULT_KEY.write().unwrap_or_else(|e| e.into_inner()) = key;
+}
+
+pub fn is_enabled() -> bool {
+    vault_path().map(|p| p.exists()).unwrap_or(false)
+}
+
+pub fn status() -> Result<VaultStatus, String> {
+    let meta = load_meta()?;
+    Ok(VaultStatus {
+        enabled: meta.is_some(),
+        unlocked: meta.is_some() && current_key().is_some(),
+        mode: meta.map(|m| m.mode),
+    })
+}
+
+// ===== 密钥派生与加解密 =====
+
+fn b64() -> base64::engine::general_purpose::GeneralPurpose {
+    base64::engine::general_purpose::STANDARD
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], String> {
+    let mut buf = [0u8; N];
+    SystemRandom::new()
+        .fill(&mut buf)
+        .map_err(|_| "生成随机数失败".to_string())?;
+    Ok(buf)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<[u8; 32], String> {
+    if passphrase.is_empty() {
+        return Err("口令不能为空".to_string());
+    }
+    let iterations = NonZeroU32::new(iterations).ok_or("无效的 KDF 迭代次数")?;
+    let mut key = [0u8; 32];
+    ring::pbkdf2::derive(
+        ring::pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    Ok(key)
+}
+
+fn encrypt_bytes(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let sealing_key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).map_err(|_| "无效的密钥")?);
+    let nonce_bytes = random_bytes::<NONCE_LEN>()?;
+    let mut in_out = plaintext.to_vec();
+    sealing_key
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce_bytes), Aad::empty(), &mut in_out)
+        .map_err(|_| "加密失败".to_string())?;
+    let mut out = nonce_bytes.to_vec();
+    out.extend_from_slice(&in_out);
+    Ok(out)
+}
+
+fn decrypt_bytes(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < NONCE_LEN {
+        return Err("密文长度无效".to_string());
+    }
+    let opening_key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).map_err(|_| "无效的密钥")?);
+    let (nonce_bytes, ciphertext) = data.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce_bytes).map_err(|_| "无效的 nonce")?;
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = opening_key
+        .open_in_place(nonce, Aad::empty(), &mut in_out)
+        .map_err(|_| "解密失败: 密钥错误或数据已损坏".to_string())?;
+    Ok(plaintext.to_vec())
+}
+
+fn encrypt_field(key: &[u8; 32], plaintext: &str) -> Result<String, String> {
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, b64().encode(encrypt_bytes(key, plaintext.as_bytes())?)))
+}
+
+fn decrypt_field(key: &[u8; 32], value: &str) -> Result<String, String> {
+    let encoded = value.strip_prefix(ENCRYPTED_PREFIX).ok_or("字段未加密")?;
+    let data = b64().decode(encoded).map_err(|e| format!("密文解码失败: {}", e))?;
+    String::from_utf8(decrypt_bytes(key, &data)?).map_err(|_| "解密结果不是有效的 UTF-8".to_string())
+}
+
+fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// 加密账号 JSON 中的敏感字段，返回实际加密的字段数
+fn seal_account(account: &mut Value, key: &[u8; 32]) -> Result<usize, String> {
+    let mut sealed = 0;
+    if let Some(token) = account.get_mut("token").and_then(|t| t.as_object_mut()) {
+        for field in SECRET_TOKEN_FIELDS {
+            if let Some(Value::String(s)) = token.get_mut(field) {
+                if !s.is_empty() && !is_encrypted(s) {
+                    *s = encrypt_field(key, s)?;
+                    sealed += 1;
+                }
+            }
+        }
+    }
+    Ok(sealed)
+}
+
+/// 解密账号 JSON 中的敏感字段 (明文字段原样保留)
+fn open_account(account: &mut Value, key: Option<&[u8; 32]>) -> Result<(), String> {
+    if let Some(token) = account.get_mut("token").and_then(|t| t.as_object_mut()) {
+        for field in SECRET_TOKEN_FIELDS {
+            if let Some(Value::String(s)) = token.get_mut(field) {
+                if is_encrypted(s) {
+                    let key = key.ok_or("账号保险库已锁定，请先解锁")?;
+                    *s = decrypt_field(key, s)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// ===== 账号文件读写 (所有账号文件访问都应经过这里) =====
+
+/// 读取账号文件并透明解密敏感字段
+pub fn read_account_json(path: &Path) -> Result<Value, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let mut account: Value = serde_json::from_str(&content).map_err(|e| format!("解析 JSON 失败: {}", e))?;
+    open_account(&mut account, current_key().as_ref())?;
+    Ok(account)
+}
+
+/// 写入账号文件；保险库启用时加密敏感字段，锁定状态下拒绝写入以免落盘明文
+pub fn write_account_json(path: &Path, account: &Value) -> Result<(), String> {
+    let mut on_disk = account.clone();
+    if is_enabled() {
+        let key = current_key().ok_or("账号保险库已锁定，拒绝写入明文 Token")?;
+        seal_account(&mut on_disk, &key)?;
+    }
+    let content = serde_json::to_string_pretty(&on_disk).map_err(|e| format!("序列化账号数据失败: {}", e))?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, content).map_err(|e| format!("写入文件失败: {}", e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("写入文件失败: {}", e))
+}
+
+/// 对 accounts 目录下所有账号文件执行变换，返回发生变化的文件数
+fn rewrite_all_accounts(transform: impl Fn(&mut Value) -> Result<bool, String>) -> Result<usize, String> {
+    let accounts_dir = crate::modules::account::get_accounts_dir()?;
+    let entries = fs::read_dir(&accounts_dir).map_err(|e| format!("读取账号目录失败: {}", e))?;
+    let mut changed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        let content = fs::read_to_string(&path).map_err(|e| format!("读取文件失败: {}", e))?;
+        let Ok(mut account) = serde_json::from_str::<Value>(&content) else {
+            tracing::warn!("[Vault] 跳过无法解析的账号文件: {:?}", path);
+            continue;
+        };
+        if transform(&mut account)? {
+            let content = serde_json::to_string_pretty(&account).map_err(|e| format!("序列化账号数据失败: {}", e))?;
+            let tmp = path.with_extension("json.tmp");
+            fs::write(&tmp, content).map_err(|e| format!("写入文件失败: {}", e))?;
+            fs::rename(&tmp, &path).map_err(|e| format!("写入文件失败: {}", e))?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// 一次性迁移: 将仍为明文的账号文件加密
+fn migrate_plaintext_accounts(key: &[u8; 32]) -> Result<usize, String> {
+    let migrated = rewrite_all_accounts(|account| Ok(seal_account(account, key)? > 0))?;
+    if migrated > 0 {
+        tracing::info!("[Vault] 已加密 {} 个明文账号文件", migrated);
+    }
+    Ok(migrated)
+}
+
+// ===== 启用 / 解锁 / 锁定 / 停用 =====
+
+fn default_key_file() -> Result<PathBuf, String> {
+    Ok(crate::modules::account::get_data_dir()?.join(DEFAULT_KEY_FILE))
+}
+
+fn read_key_file(path: &Path) -> Result<[u8; 32], String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("读取密钥文件失败: {}", e))?;
+    let bytes = b64()
+        .decode(content.trim())
+        .map_err(|e| format!("密钥文件格式无效: {}", e))?;
+    bytes.try_into().map_err(|_| "密钥文件长度无效 (需要 32 字节)".to_string())
+}
+
+fn create_key_file(path: &Path) -> Result<[u8; 32], String> {
+    let key = random_bytes::<32>()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建密钥目录失败: {}", e))?;
+    }
+    fs::write(path, b64().encode(key)).map_err(|e| format!("写入密钥文件失败: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o600));
+    }
+    Ok(key)
+}
+
+fn key_for(meta: &VaultMeta, credential: &VaultCredential) -> Result<[u8; 32], String> {
+    match (meta.mode, credential) {
+        (VaultMode::Passphrase, VaultCredential::Passphrase { passphrase }) => {
+            let salt = b64().decode(&meta.salt).map_err(|e| format!("保险库 salt 无效: {}", e))?;
+            derive_key(passphrase, &salt, meta.iterations)
+        }
+        (VaultMode::KeyFile, VaultCredential::KeyFile { path }) => {
+            let path = match path {
+                Some(p) => p.clone(),
+                None => default_key_file()?,
+            };
+            read_key_file(&path)
+        }
+        _ => Err("解锁方式与保险库模式不匹配".to_string()),
+    }
+}
+
+/// 启用保险库并加密现有账号，返回迁移的账号数
+pub fn enable(credential: &VaultCredential) -> Result<usize, String> {
+    if is_enabled() {
+        return Err("账号保险库已启用".to_string());
+    }
+    let (mode, salt, iterations, key) = match credential {
+        VaultCredential::Passphrase { passphrase } => {
+            let salt = random_bytes::<16>()?;
+            let key = derive_key(passphrase, &salt, PBKDF2_ITERATIONS)?;
+            (VaultMode::Passphrase, b64().encode(salt), PBKDF2_ITERATIONS, key)
+        }
+        VaultCredential::KeyFile { path } => {
+            let path = match path {
+                Some(p) => p.clone(),
+                None => default_key_file()?,
+            };
+            let key = if path.exists() { read_key_file(&path)? } else { create_key_file(&path)? };
+            (VaultMode::KeyFile, String::new(), 0, key)
+        }
+    };
+
+    save_meta(&VaultMeta {
+        version: 1,
+        mode,
+        salt,
+        iterations,
+        verifier: encrypt_field(&key, VERIFIER_PLAINTEXT)?,
+    })?;
+    set_key(Some(key));
+    crate::modules::logger::log_info("[Vault] 账号保险库已启用");
+    migrate_plaintext_accounts(&key)
+}
+
+/// 解锁保险库，并顺带加密启用后仍以明文落盘的账号
+pub fn unlock(credential: &VaultCredential) -> Result<usize, String> {
+    let meta = load_meta()?.ok_or("账号保险库未启用")?;
+    let key = key_for(&meta, credential)?;
+    if decrypt_field(&key, &meta.verifier).ok().as_deref() != Some(VERIFIER_PLAINTEXT) {
+        return Err("口令或密钥文件错误".to_string());
+    }
+    set_key(Some(key));
+    crate::modules::logger::log_info("[Vault] 账号保险库已解锁");
+    migrate_plaintext_accounts(&key)
+}
+
+pub fn lock() {
+    set_key(None);
+    crate::modules::logger::log_info("[Vault] 账号保险库已锁定");
+}
+
+/// 停用保险库: 解密全部账号后删除 vault.json (需已解锁)
+pub fn disable() -> Result<usize, String> {
+    if !is_enabled() {
+        return Err("账号保险库未启用".to_string());
+    }
+    let key = current_key().ok_or("请先解锁账号保险库")?;
+    let restored = rewrite_all_accounts(|account| {
+        let before = account.clone();
+        open_account(account, Some(&key))?;
+        Ok(*account != before)
+    })?;
+    fs::remove_file(vault_path()?).map_err(|e| format!("删除保险库配置失败: {}", e))?;
+    set_key(None);
+    crate::modules::logger::log_info(&format!("[Vault] 账号保险库已停用，{} 个账号已恢复为明文", restored));
+    Ok(restored)
+}
+
+/// 启动时自动解锁: 密钥文件模式读取环境变量或默认 vault.key；口令模式读取环境变量
+pub fn auto_unlock() {
+    let meta = match load_meta() {
+        Ok(Some(meta)) => meta,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("[Vault] {}", e);
+            return;
+        }
+    };
+    if current_key().is_some() {
+        return;
+    }
+    let credential = match meta.mode {
+        VaultMode::KeyFile => VaultCredential::KeyFile {
+            path: std::env::var(ENV_KEY_FILE).ok().map(PathBuf::from),
+        },
+        VaultMode::Passphrase => match std::env::var(ENV_PASSPHRASE) {
+            Ok(passphrase) => VaultCredential::Passphrase { passphrase },
+            Err(_) => {
+                tracing::warn!("[Vault] 账号保险库已锁定，需在桌面端解锁弹窗或 /api/vault/unlock 输入口令后才能加载账号");
+                return;
+            }
+        },
+    };
+    match unlock(&credential) {
+        Ok(_) => tracing::info!("[Vault] 启动时自动解锁成功"),
+        Err(e) => tracing::error!("[Vault] 启动时自动解锁失败: {}", e),
+    }
+}
+
+// ===== 口令保护的导出/导入包 =====
+
+/// 使用独立口令加密导出数据 (与保险库密钥无关，可在其他机器导入)
+pub fn seal_bundle(export: &AccountExportResponse, password: &str) -> Result<Value, String> {
+    seal_bundle_with_iterations(export, password, PBKDF2_ITERATIONS)
+}
+
+fn seal_bundle_with_iterations(export: &AccountExportResponse, password: &str, iterations: u32) -> Result<Value, String> {
+    let salt = random_bytes::<16>()?;
+    let key = derive_key(password, &salt, iterations)?;
+    let plaintext = serde_json::to_vec(export).map_err(|e| format!("序列化导出数据失败: {}", e))?;
+    Ok(serde_json::json!({
+        "format": BUNDLE_FORMAT,
+        "version": 1,
+        "kdf": "pbkdf2-sha256",
+        "iterations": iterations,
+        "salt": b64().encode(salt),
+        "data": b64().encode(encrypt_bytes(&key, &plaintext)?),
+    }))
+}
+
+pub fn open_bundle(bundle: &Value, password: &str) -> Result<AccountExportResponse, String> {
+    if bundle.get("format").and_then(|v| v.as_str()) != Some(BUNDLE_FORMAT) {
+        return Err("不是有效的账号导出包".to_string());
+    }
+    let field = |name: &str| bundle.get(name).and_then(|v| v.as_str()).ok_or(format!("导出包缺少字段: {}", name));
+    let salt = b64().decode(field("salt")?).map_err(|e| format!("导出包 salt 无效: {}", e))?;
+    let data = b64().decode(field("data")?).map_err(|e| format!("导出包数据无效: {}", e))?;
+    let iterations = bundle
+        .get("iterations")
+        .and_then(|v| v.as_u64())
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or("导出包缺少字段: iterations")?;
+    let key = derive_key(password, &salt, iterations)?;
+    let plaintext = decrypt_bytes(&key, &data).map_err(|_| "导出包口令错误或数据已损坏".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("解析导出数据失败: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AccountExportItem;
+    use serde_json::json;
+
+    #[test]
+    fn test_seal_and_open_account_fields() {
+        let key = [7u8; 32];
+        let mut account = json!({
+            "email": "a@example.com",
+            "token": { "access_token": "ya29.abc", "refresh_token": "1//refresh", "expires_in": 3599 }
+        });
+
+        assert_eq!(seal_account(&mut account, &key).unwrap(), 2);
+        let sealed_refresh = account["token"]["refresh_token"].as_str().unwrap().to_string();
+        assert!(sealed_refresh.starts_with(ENCRYPTED_PREFIX));
+        assert_eq!(account["email"], "a@example.com");
+        assert_eq!(account["token"]["expires_in"], 3599);
+        // 重复加密不应改变已加密字段
+        assert_eq!(seal_account(&mut account, &key).unwrap(), 0);
+
+        assert!(open_account(&mut account.clone(), None).is_err());
+        assert!(open_account(&mut account.clone(), Some(&[8u8; 32])).is_err());
+
+        open_account(&mut account, Some(&key)).unwrap();
+        assert_eq!(account["token"]["access_token"], "ya29.abc");
+        assert_eq!(account["token"]["refresh_token"], "1//refresh");
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let export = AccountExportResponse {
+            accounts: vec![AccountExportItem {
+                email: "a@example.com".to_string(),
+                refresh_token: "1//refresh".to_string(),
+                provider: "kiro".to_string(),
+                kiro_profile_arn: Some("arn:aws:codewhisperer:us-east-1:1:profile/X".to_string()),
+                kiro_user_id: None,
+            }],
+        };
+        let bundle = seal_bundle_with_iterations(&export, "correct horse", 1_000).unwrap();
+        assert!(!bundle.to_string().contains("1//refresh"));
+
+        assert!(open_bundle(&bundle, "wrong").is_err());
+        let opened = open_bundle(&bundle, "correct horse").unwrap();
+        assert_eq!(opened.accounts.len(), 1);
+        assert_eq!(opened.accounts[0].refresh_token, "1//refresh");
+        assert_eq!(opened.accounts[0].provider, "kiro");
+
+        // 旧版导出项没有 provider 字段，按 gemini 处理
+        let legacy: AccountExportItem =
+            serde_json::from_value(serde_json::json!({ "email": "b@example.com", "refresh_token": "1//x" })).unwrap();
+        assert_eq!(legacy.provider, "gemini");
+    }
+}
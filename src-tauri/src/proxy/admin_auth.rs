@@ -0,0 +1,385 @@
+// 管理端认证 - 独立于客户端 API Key 的管理员密码、会话与 CSRF 防护
+//
+// - 管理员密码以 PBKDF2-HMAC-SHA256 哈希保存在 proxy.admin_password
+//   (兼容旧版明文配置：首次登录成功后自动升级为哈希)
+// - 登录签发有过期时间的会话令牌，可通过 Authorization: Bearer 或 Cookie 携带
+// - 通过 Cookie 认证的写操作必须附带 X-CSRF-Token (登录时返回)
+use base64::Engine as _;
+use once_cell::sync::Lazy;
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::sync::RwLock;
+
+use crate::modules::audit_db::{self, AuditEntry};
+use crate::proxy::config::ProxyConfig;
+
+const HASH_PREFIX: &str = "pbkdf2-sha256";
+const PBKDF2_ITERATIONS: u32 = 310_000;
+const MIN_PASSWORD_LEN: usize = 8;
+
+/// 会话有效期
+pub const SESSION_TTL_SECS: i64 = 12 * 3600;
+/// 会话 Cookie 名 (HttpOnly, SameSite=Strict, Path=/api)
+pub const SESSION_COOKIE: &str = "dg_admin_session";
+/// CSRF 令牌请求头
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// 登录失败限流: 窗口内最多失败次数
+const MAX_LOGIN_FAILURES: usize = 5;
+const LOGIN_FAILURE_WINDOW_SECS: i64 = 15 * 60;
+/// 限流表最多跟踪的 IP 数 (超出时先清理过期记录，再淘汰最早的 IP)
+const MAX_TRACKED_IPS: usize = 10_000;
+
+/// 环境变量: 设置 / 重置管理员密码 (headless 与桌面版启动时均生效)
+pub const ENV_ADMIN_PASSWORD: &str = "DROIDGRAVITY_ADMIN_PASSWORD";
+
+fn b64() -> base64::engine::general_purpose::GeneralPurpose {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+}
+
+fn random_token<const N: usize>() -> Result<String, String> {
+    let mut buf = [0u8; N];
+    SystemRandom::new()
+        .fill(&mut buf)
+        .map_err(|_| "生成随机数失败".to_string())?;
+    Ok(b64().encode(buf))
+}
+
+// ===== 密码哈希 =====
+
+/// 通过 DROIDGRAVITY_ADMIN_PASSWORD 设置 / 重置管理员密码 (哈希后写回配置)
+pub fn apply_env_admin_password(app_config: &mut crate::models::AppConfig) -> Result<(), String> {
+    let Ok(password) = std::env::var(ENV_ADMIN_PASSWORD) else {
+        return Ok(());
+    };
+    let current = app_config.proxy.admin_password.as_deref().unwrap_or("");
+    if verify_password(current, &password) && is_password_hash(current) {
+        return Ok(());
+    }
+    app_config.proxy.admin_password = Some(hash_password(&password)?);
+    crate::modules::config::save_app_config(app_config)?;
+    tracing::info!("[AdminAuth] 已根据 {} 更新管理员密码", ENV_ADMIN_PASSWORD);
+    Ok(())
+}
+
+/// 哈希管理员密码，格式: pbkdf2-sha256$<迭代次数>$<salt>$<hash>
+pub fn hash_password(password: &str) -> Result<String, String> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(format!("管理员密码至少需要 {} 个字符", MIN_PASSWORD_LEN));
+    }
+    let mut salt = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .map_err(|_| "生成随机数失败".to_string())?;
+    let mut hash = [0u8; 32];
+    ring::pbkdf2::derive(
+        ring::pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).expect("non-zero iterations"),
+        &salt,
+        password.as_bytes(),
+        &mut hash,
+    );
+    Ok(format!(
+        "{}${}${}${}",
+        HASH_PREFIX,
+        PBKDF2_ITERATIONS,
+        b64().encode(salt),
+        b64().encode(hash)
+    ))
+}
+
+/// 配置中的值是否已是哈希格式
+pub fn is_password_hash(stored: &str) -> bool {
+    stored.starts_with(&format!("{}$", HASH_PREFIX))
+}
+
+/// 校验密码 (旧版明文配置按常量时间比较)
+pub fn verify_password(stored: &str, candidate: &str) -> bool {
+    if !is_password_hash(stored) {
+        return !stored.is_empty() && constant_time_eq(stored.as_bytes(), candidate.as_bytes());
+    }
+
+    let parts: Vec<&str> = stored.split('$').collect();
+    let [_, iterations, salt, hash] = parts.as_slice() else {
+        return false;
+    };
+    let (Some(iterations), Ok(salt), Ok(hash)) = (
+        iterations.parse::<u32>().ok().and_then(NonZeroU32::new),
+        b64().decode(salt),
+        b64().decode(hash),
+    ) else {
+        return false;
+    };
+    ring::pbkdf2::verify(
+        ring::pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        &salt,
+        candidate.as_bytes(),
+        &hash,
+    )
+    .is_ok()
+}
+
+/// 在阻塞线程池中哈希密码 (PBKDF2 约数百毫秒，避免占用异步运行时)
+pub async fn hash_password_async(password: String) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .map_err(|e| format!("密码哈希任务失败: {}", e))?
+}
+
+/// 在阻塞线程池中校验密码
+pub async fn verify_password_async(stored: String, candidate: String) -> bool {
+    tokio::task::spawn_blocking(move || verify_password(&stored, &candidate))
+        .await
+        .unwrap_or(false)
+}
+
+/// 常量时间比较 (先取摘要，避免长度差异提前返回)
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let (a, b) = (Sha256::digest(a), Sha256::digest(b));
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 保存配置前调用：将明文管理员密码替换为哈希，空字符串视为未设置
+pub fn normalize_admin_password(proxy: &mut ProxyConfig) -> Result<(), String> {
+    match proxy.admin_password.as_deref() {
+        Some("") => proxy.admin_password = None,
+        Some(p) if !is_password_hash(p) => proxy.admin_password = Some(hash_password(p)?),
+        _ => {}
+    }
+    Ok(())
+}
+
+// ===== 会话 =====
+
+/// 一个已登录的管理会话
+#[derive(Debug, Clone)]
+pub struct AdminSession {
+    /// 会话 ID (非机密，写入审计日志)
+    pub id: String,
+    pub csrf_token: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub client_ip: Option<String>,
+}
+
+/// 会话存储 (仅内存，重启后需重新登录)
+#[derive(Default)]
+pub struct AdminSessions {
+    /// key: 会话令牌
+    sessions: RwLock<HashMap<String, AdminSession>>,
+    /// key: 客户端 IP, value: 窗口内的失败 / 进行中尝试的时间戳
+    failures: RwLock<HashMap<String, Vec<i64>>>,
+}
+
+impl AdminSessions {
+    /// 创建会话，返回 (令牌, 会话)
+    pub fn create(&self, client_ip: Option<String>, now: i64) -> Result<(String, AdminSession), String> {
+        let token = random_token::<32>()?;
+        let session = AdminSession {
+            id: uuid::Uuid::new_v4().to_string(),
+            csrf_token: random_token::<24>()?,
+            created_at: now,
+            expires_at: now + SESSION_TTL_SECS,
+            client_ip,
+        };
+        if let Ok(mut sessions) = self.sessions.write() {
+            sessions.retain(|_, s| s.expires_at > now);
+            sessions.insert(token.clone(), session.clone());
+        }
+        Ok((token, session))
+    }
+
+    /// 查找未过期的会话
+    pub fn validate(&self, token: &str, now: i64) -> Option<AdminSession> {
+        let sessions = self.sessions.read().ok()?;
+        sessions.get(token).filter(|s| s.expires_at > now).cloned()
+    }
+
+    pub fn revoke(&self, token: &str) -> bool {
+        self.sessions
+            .write()
+            .map(|mut s| s.remove(token).is_some())
+            .unwrap_or(false)
+    }
+
+    /// 修改密码后使所有会话失效
+    pub fn revoke_all(&self) {
+        if let Ok(mut sessions) = self.sessions.write() {
+            sessions.clear();
+        }
+    }
+
+    /// 原子地为一次登录尝试占位: 窗口内失败 (含进行中的尝试) 已达上限时返回 false
+    ///
+    /// 占位先按失败计入，校验成功后由 `clear_login_failures` 释放，
+    /// 这样并发的密码校验也无法绕过次数限制。
+    pub fn reserve_login_attempt(&self, client_ip: &str, now: i64) -> bool {
+        let Ok(mut failures) = self.failures.write() else {
+            return false;
+        };
+        if !failures.contains_key(client_ip) && failures.len() >= MAX_TRACKED_IPS {
+            failures.retain(|_, times| {
+                times.retain(|t| now - *t < LOGIN_FAILURE_WINDOW_SECS);
+                !times.is_empty()
+            });
+            if failures.len() >= MAX_TRACKED_IPS {
+                let oldest = failures
+                    .iter()
+                    .min_by_key(|(_, times)| times.last().copied().unwrap_or(i64::MIN))
+                    .map(|(ip, _)| ip.clone());
+                if let Some(ip) = oldest {
+                    failures.remove(&ip);
+                }
+            }
+        }
+
+        let times = failures.entry(client_ip.to_string()).or_default();
+        times.retain(|t| now - *t < LOGIN_FAILURE_WINDOW_SECS);
+        if times.len() >= MAX_LOGIN_FAILURES {
+            return false;
+        }
+        times.push(now);
+        true
+    }
+
+    /// 登录成功: 释放占位并清空该 IP 的失败记录
+    pub fn clear_login_failures(&self, client_ip: &str) {
+        if let Ok(mut failures) = self.failures.write() {
+            failures.remove(client_ip);
+        }
+    }
+}
+
+static SESSIONS: Lazy<AdminSessions> = Lazy::new(AdminSessions::default);
+
+/// 全局会话存储 (桌面版重启反代服务时会话保持)
+pub fn sessions() -> &'static AdminSessions {
+    &SESSIONS
+}
+
+/// 常量时间比较 CSRF 令牌
+pub fn csrf_matches(session: &AdminSession, provided: Option<&str>) -> bool {
+    provided
+        .map(|p| constant_time_eq(session.csrf_token.as_bytes(), p.as_bytes()))
+        .unwrap_or(false)
+}
+
+/// 通过认证的管理会话，写入 Request extensions 供处理器使用 (登出 / 会话信息)
+#[derive(Debug, Clone)]
+pub struct AuthenticatedAdmin {
+    pub token: String,
+    pub session: AdminSession,
+}
+
+/// 异步写入审计日志 (失败只记录警告，不影响请求本身)
+pub fn audit(actor: &str, action: &str, target: Option<String>, status: u16, client_ip: Option<String>) {
+    let entry = AuditEntry {
+        id: 0,
+        timestamp: chrono::Utc::now().timestamp(),
+        actor: actor.to_string(),
+        action: action.to_string(),
+        target,
+        status,
+        client_ip,
+    };
+    tokio::task::spawn_blocking(move || {
+        let result = crate::modules::account::get_data_dir()
+            .and_then(|dir| audit_db::record(&audit_db::get_audit_db_path(&dir), &entry));
+        if let Err(e) = result {
+            tracing::warn!("[AdminAuth] 写入审计日志失败: {}", e);
+        }
+    });
+}
+
+/// 生成会话 Cookie
+pub fn session_cookie(token: &str, max_age: i64) -> String {
+    format!(
+        "{}={}; Path=/api; HttpOnly; SameSite=Strict; Max-Age={}",
+        SESSION_COOKIE, token, max_age
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_hash_and_legacy_plaintext() {
+        assert!(hash_password("short").is_err());
+
+        let hashed = hash_password("correct horse").unwrap();
+        assert!(is_password_hash(&hashed));
+        assert!(verify_password(&hashed, "correct horse"));
+        assert!(!verify_password(&hashed, "wrong horse"));
+        assert!(!verify_password("pbkdf2-sha256$broken", "correct horse"));
+
+        // 旧版明文配置仍可登录，随后由调用方升级为哈希
+        assert!(verify_password("plain-secret", "plain-secret"));
+        assert!(!verify_password("plain-secret", "plain-secreT"));
+        assert!(!verify_password("", ""));
+
+        let mut proxy = ProxyConfig {
+            admin_password: Some("plain-secret".to_string()),
+            ..Default::default()
+        };
+        normalize_admin_password(&mut proxy).unwrap();
+        let stored = proxy.admin_password.clone().unwrap();
+        assert!(verify_password(&stored, "plain-secret"));
+        // 已是哈希的值保持不变
+        normalize_admin_password(&mut proxy).unwrap();
+        assert_eq!(proxy.admin_password.as_deref(), Some(stored.as_str()));
+    }
+
+    #[test]
+    fn test_sessions_expire_and_csrf() {
+        let store = AdminSessions::default();
+        let now = 1_700_000_000;
+        let (token, session) = store.create(Some("10.0.0.1".into()), now).unwrap();
+
+        assert!(store.validate(&token, now + 60).is_some());
+        assert!(store.validate(&token, now + SESSION_TTL_SECS).is_none());
+        assert!(store.validate("forged", now).is_none());
+
+        assert!(csrf_matches(&session, Some(&session.csrf_token)));
+        assert!(!csrf_matches(&session, Some("nope")));
+        assert!(!csrf_matches(&session, None));
+
+        assert!(store.revoke(&token));
+        assert!(store.validate(&token, now + 60).is_none());
+
+        // 占位即计数: 未完成的并发尝试同样占用名额
+        for _ in 0..MAX_LOGIN_FAILURES {
+            assert!(store.reserve_login_attempt("10.0.0.2", now));
+        }
+        assert!(!store.reserve_login_attempt("10.0.0.2", now));
+        assert!(store.reserve_login_attempt("10.0.0.2", now + LOGIN_FAILURE_WINDOW_SECS));
+
+        // 登录成功释放占位
+        store.clear_login_failures("10.0.0.2");
+        assert!(store.reserve_login_attempt("10.0.0.2", now));
+    }
+
+    #[test]
+    fn test_login_limiter_is_bounded() {
+        let store = AdminSessions::default();
+        let now = 1_700_000_000;
+        for i in 0..MAX_TRACKED_IPS {
+            assert!(store.reserve_login_attempt(&format!("ip-{}", i), now - LOGIN_FAILURE_WINDOW_SECS));
+        }
+        // 过期记录在表满时被清理
+        assert!(store.reserve_login_attempt("fresh", now));
+        assert_eq!(store.failures.read().unwrap().len(), 1);
+
+        for i in 1..MAX_TRACKED_IPS {
+            assert!(store.reserve_login_attempt(&format!("ip-{}", i), now + 1));
+        }
+        // 全部未过期时淘汰最早的 IP
+        assert!(store.reserve_login_attempt("newest", now + 1));
+        let failures = store.failures.read().unwrap();
+        assert_eq!(failures.len(), MAX_TRACKED_IPS);
+        assert!(!failures.contains_key("fresh"));
+    }
+}
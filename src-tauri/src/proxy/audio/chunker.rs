@@ -0,0 +1,405 @@
+// 大音频切分
+// WAV: 解析 PCM，在窗口末尾附近寻找能量最低处 (静音) 切分
+// MP3: 解析帧头，按固定时长窗口在帧边界切分
+// 其他容器无法在不解码的情况下安全切分
+
+/// 单个切片的最大时长 (秒)，过长的片段会让模型给出的时间戳漂移
+pub const MAX_CHUNK_SECS: f64 = 600.0;
+
+/// 在窗口末尾的这一比例范围内寻找静音切点
+const SILENCE_SEARCH_RATIO: f64 = 0.25;
+
+/// 静音检测的能量窗口 (秒)
+const ENERGY_WINDOW_SECS: f64 = 0.02;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioChunk {
+    pub data: Vec<u8>,
+    /// 在原始音频中的起始时间 (秒)
+    pub offset_secs: f64,
+    /// 切片时长 (无法解析时为 None)
+    pub duration_secs: Option<f64>,
+}
+
+/// 按需切分音频：未超过 max_bytes 时原样返回单个切片
+pub fn split_audio(data: &[u8], mime_type: &str, max_bytes: usize) -> Result<Vec<AudioChunk>, String> {
+    if data.len() <= max_bytes {
+        return Ok(vec![AudioChunk {
+            data: data.to_vec(),
+            offset_secs: 0.0,
+            duration_secs: audio_duration(data, mime_type),
+        }]);
+    }
+
+    match mime_type {
+        "audio/wav" => split_wav(data, max_bytes),
+        "audio/mp3" => split_mp3(data, max_bytes),
+        _ => Err(format!(
+            "{} 格式超过单次上传限制且无法自动切分，请转换为 WAV 或 MP3",
+            mime_type
+        )),
+    }
+}
+
+/// 解析音频时长 (仅 WAV / MP3)
+pub fn audio_duration(data: &[u8], mime_type: &str) -> Option<f64> {
+    match mime_type {
+        "audio/wav" => parse_wav(data).ok().map(|w| w.duration_secs(w.data_len)),
+        "audio/mp3" => {
+            let frames = parse_mp3_frames(data);
+            (!frames.is_empty()).then(|| frames.iter().map(|f| f.duration).sum())
+        }
+        _ => None,
+    }
+}
+
+// ===== WAV =====
+
+struct WavInfo<'a> {
+    fmt_chunk: &'a [u8],
+    audio_format: u16,
+    channels: u16,
+    sample_rate: u32,
+    block_align: u16,
+    bits_per_sample: u16,
+    data_offset: usize,
+    data_len: usize,
+}
+
+impl WavInfo<'_> {
+    fn duration_secs(&self, bytes: usize) -> f64 {
+        (bytes / self.block_align as usize) as f64 / self.sample_rate as f64
+    }
+
+    /// 第一个声道的归一化幅度 (0.0 - 1.0)，不支持的编码返回 None
+    fn amplitude(&self, frame: &[u8]) -> Option<f32> {
+        match (self.audio_format, self.bits_per_sample) {
+            (1, 8) => Some((frame[0] as f32 - 128.0).abs() / 128.0),
+            (1, 16) => Some((i16::from_le_bytes([frame[0], frame[1]]) as f32).abs() / 32768.0),
+            (1, 24) => {
+                let v = i32::from_le_bytes([0, frame[0], frame[1], frame[2]]) >> 8;
+                Some((v as f32).abs() / 8_388_608.0)
+            }
+            (1, 32) => Some((i32::from_le_bytes([frame[0], frame[1], frame[2], frame[3]]) as f32).abs() / 2_147_483_648.0),
+            (3, 32) => Some(f32::from_le_bytes([frame[0], frame[1], frame[2], frame[3]]).abs().min(1.0)),
+            _ => None,
+        }
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+fn parse_wav(data: &[u8]) -> Result<WavInfo<'_>, String> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err("不是有效的 WAV 文件".to_string());
+    }
+
+    let mut fmt: Option<&[u8]> = None;
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let size = read_u32(data, pos + 4) as usize;
+        let body = pos + 8;
+        if id == b"fmt " {
+            let end = body.checked_add(size).filter(|e| *e <= data.len()).ok_or("WAV fmt 块损坏")?;
+            fmt = Some(&data[body..end]);
+        } else if id == b"data" {
+            let fmt_chunk = fmt.filter(|f| f.len() >= 16).ok_or("WAV 缺少 fmt 块")?;
+            let mut audio_format = read_u16(fmt_chunk, 0);
+            // WAVE_FORMAT_EXTENSIBLE: 真实编码在 SubFormat GUID 的前两个字节
+            if audio_format == 0xFFFE && fmt_chunk.len() >= 26 {
+                audio_format = read_u16(fmt_chunk, 24);
+            }
+            let info = WavInfo {
+                fmt_chunk,
+                audio_format,
+                channels: read_u16(fmt_chunk, 2),
+                sample_rate: read_u32(fmt_chunk, 4),
+                block_align: read_u16(fmt_chunk, 12),
+                bits_per_sample: read_u16(fmt_chunk, 14),
+                data_offset: body,
+                // 流式写出的 WAV 可能把 size 写成 0 / 0xFFFFFFFF，以实际长度为准
+                data_len: if size == 0 || body + size > data.len() { data.len() - body } else { size },
+            };
+            if info.block_align == 0 || info.sample_rate == 0 || info.channels == 0 {
+                return Err("WAV 头部参数无效".to_string());
+            }
+            // amplitude 按位深读取帧的前几个字节，帧宽 (block_align) 必须容纳所有声道的一个采样
+            if !matches!(info.bits_per_sample, 8 | 16 | 24 | 32) {
+                return Err(format!("不支持的 WAV 位深: {}", info.bits_per_sample));
+            }
+            if (info.block_align as u32) < info.channels as u32 * info.bits_per_sample as u32 / 8 {
+                return Err("WAV 头部参数无效: block_align 小于单帧采样宽度".to_string());
+            }
+            return Ok(info);
+        }
+        pos = body + size + (size & 1);
+    }
+    Err("WAV 缺少 data 块".to_string())
+}
+
+fn build_wav(info: &WavInfo, pcm: &[u8]) -> Vec<u8> {
+    let fmt_len = info.fmt_chunk.len();
+    let fmt_pad = fmt_len & 1;
+    let riff_len = 4 + 8 + fmt_len + fmt_pad + 8 + pcm.len();
+    let mut out = Vec::with_capacity(riff_len + 8);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(riff_len as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&(fmt_len as u32).to_le_bytes());
+    out.extend_from_slice(info.fmt_chunk);
+    if fmt_pad == 1 {
+        out.push(0);
+    }
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(pcm.len() as u32).to_le_bytes());
+    out.extend_from_slice(pcm);
+    out
+}
+
+/// 在 [from, to) 帧范围内寻找能量最低的窗口中点
+fn quietest_frame(info: &WavInfo, pcm: &[u8], from: usize, to: usize) -> Option<usize> {
+    let block = info.block_align as usize;
+    let window = ((info.sample_rate as f64 * ENERGY_WINDOW_SECS) as usize).max(1);
+    let mut best: Option<(f32, usize)> = None;
+    let mut start = from;
+    while start + window <= to {
+        let mut energy = 0.0;
+        for frame in start..start + window {
+            energy += info.amplitude(&pcm[frame * block..(frame + 1) * block])?;
+        }
+        if best.map(|(e, _)| energy < e).unwrap_or(true) {
+            best = Some((energy, start + window / 2));
+        }
+        start += window;
+    }
+    best.map(|(_, frame)| frame)
+}
+
+fn split_wav(data: &[u8], max_bytes: usize) -> Result<Vec<AudioChunk>, String> {
+    let info = parse_wav(data)?;
+    let pcm = &data[info.data_offset..info.data_offset + info.data_len];
+    let block = info.block_align as usize;
+    let total_frames = pcm.len() / block;
+
+    let header_len = 8 + 4 + 8 + info.fmt_chunk.len() + 1 + 8;
+    let max_frames = (max_bytes.saturating_sub(header_len) / block)
+        .min((MAX_CHUNK_SECS * info.sample_rate as f64) as usize);
+    if max_frames == 0 {
+        return Err("切分窗口过小".to_string());
+    }
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < total_frames {
+        let mut end = (start + max_frames).min(total_frames);
+        if end < total_frames {
+            let search_from = end - (max_frames as f64 * SILENCE_SEARCH_RATIO) as usize;
+            if let Some(cut) = quietest_frame(&info, pcm, search_from.max(start + 1), end) {
+                end = cut;
+            }
+        }
+        let bytes = &pcm[start * block..end * block];
+        chunks.push(AudioChunk {
+            data: build_wav(&info, bytes),
+            offset_secs: start as f64 / info.sample_rate as f64,
+            duration_secs: Some(info.duration_secs(bytes.len())),
+        });
+        start = end;
+    }
+    Ok(chunks)
+}
+
+// ===== MP3 =====
+
+#[derive(Debug, Clone, Copy)]
+struct Mp3Frame {
+    offset: usize,
+    len: usize,
+    duration: f64,
+}
+
+/// 解析 MPEG Layer III 帧头，返回 (帧长度, 帧时长)
+fn parse_mp3_header(h: &[u8]) -> Option<(usize, f64)> {
+    if h.len() < 4 || h[0] != 0xFF || h[1] & 0xE0 != 0xE0 {
+        return None;
+    }
+    let version = (h[1] >> 3) & 0x03; // 3 = MPEG1, 2 = MPEG2, 0 = MPEG2.5
+    let layer = (h[1] >> 1) & 0x03; // 1 = Layer III
+    if version == 1 || layer != 1 {
+        return None;
+    }
+    let bitrate_index = (h[2] >> 4) as usize;
+    let sample_rate_index = ((h[2] >> 2) & 0x03) as usize;
+    let padding = ((h[2] >> 1) & 0x01) as usize;
+    if bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 {
+        return None;
+    }
+
+    const MPEG1_KBPS: [usize; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+    const MPEG2_KBPS: [usize; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    const RATES: [[usize; 3]; 3] = [[11025, 12000, 8000], [22050, 24000, 16000], [44100, 48000, 32000]];
+
+    let mpeg1 = version == 3;
+    let bitrate = if mpeg1 { MPEG1_KBPS } else { MPEG2_KBPS }[bitrate_index] * 1000;
+    let sample_rate = RATES[match version {
+        3 => 2,
+        2 => 1,
+        _ => 0,
+    }][sample_rate_index];
+    let samples = if mpeg1 { 1152 } else { 576 };
+    let len = samples / 8 * bitrate / sample_rate + padding;
+    Some((len, samples as f64 / sample_rate as f64))
+}
+
+fn parse_mp3_frames(data: &[u8]) -> Vec<Mp3Frame> {
+    let mut pos = 0;
+    // 跳过 ID3v2 标签 (syncsafe 长度)
+    if data.len() >= 10 && &data[0..3] == b"ID3" {
+        let size = data[6..10]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 7) | (*b as usize & 0x7F));
+        pos = 10 + size + if data[5] & 0x10 != 0 { 10 } else { 0 };
+    }
+
+    let mut frames = Vec::new();
+    while pos + 4 <= data.len() {
+        match parse_mp3_header(&data[pos..pos + 4]) {
+            Some((len, duration)) if len > 4 && pos + len <= data.len() => {
+                frames.push(Mp3Frame { offset: pos, len, duration });
+                pos += len;
+            }
+            // 失去同步 (或遇到 ID3v1 / 垃圾数据) 时逐字节重新寻找帧头
+            _ => pos += 1,
+        }
+    }
+    frames
+}
+
+fn split_mp3(data: &[u8], max_bytes: usize) -> Result<Vec<AudioChunk>, String> {
+    let frames = parse_mp3_frames(data);
+    if frames.is_empty() {
+        return Err("无法解析 MP3 帧".to_string());
+    }
+
+    let mut chunks = Vec::new();
+    let mut offset_secs = 0.0;
+    let mut i = 0;
+    while i < frames.len() {
+        let (mut bytes, mut secs, mut j) = (0, 0.0, i);
+        while j < frames.len()
+            && (j == i || (bytes + frames[j].len <= max_bytes && secs + frames[j].duration <= MAX_CHUNK_SECS))
+        {
+            bytes += frames[j].len;
+            secs += frames[j].duration;
+            j += 1;
+        }
+        let start = frames[i].offset;
+        let end = frames[j - 1].offset + frames[j - 1].len;
+        chunks.push(AudioChunk {
+            data: data[start..end].to_vec(),
+            offset_secs,
+            duration_secs: Some(secs),
+        });
+        offset_secs += secs;
+        i = j;
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav_16bit_mono(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&sample_rate.to_le_bytes());
+        fmt.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        fmt.extend_from_slice(&2u16.to_le_bytes());
+        fmt.extend_from_slice(&16u16.to_le_bytes());
+        let info = WavInfo {
+            fmt_chunk: &fmt,
+            audio_format: 1,
+            channels: 1,
+            sample_rate,
+            block_align: 2,
+            bits_per_sample: 16,
+            data_offset: 0,
+            data_len: 0,
+        };
+        let pcm: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        build_wav(&info, &pcm)
+    }
+
+    #[test]
+    fn test_wav_header_rejects_inconsistent_frame_width() {
+        let mut wav = wav_16bit_mono(&[0i16; 100], 1000);
+        // 32 位采样却声明 2 字节帧宽: 之前会在 amplitude 中越界 panic
+        wav[34..36].copy_from_slice(&32u16.to_le_bytes());
+        assert!(parse_wav(&wav).is_err());
+        assert!(split_audio(&wav, "audio/wav", 64).is_err());
+
+        let mut wav = wav_16bit_mono(&[0i16; 100], 1000);
+        wav[34..36].copy_from_slice(&12u16.to_le_bytes());
+        assert!(parse_wav(&wav).is_err());
+    }
+
+    #[test]
+    fn test_wav_split_on_silence() {
+        // 1000 Hz 采样: 0.9s 声音 + 0.2s 静音 + 0.9s 声音
+        let rate = 1000;
+        let mut samples = vec![8000i16; 900];
+        samples.extend(vec![0i16; 200]);
+        samples.extend(vec![8000i16; 900]);
+        let wav = wav_16bit_mono(&samples, rate);
+        assert_eq!(audio_duration(&wav, "audio/wav"), Some(2.0));
+
+        // 每片最多约 1.2s
+        let chunks = split_audio(&wav, "audio/wav", 44 + 2400).unwrap();
+        assert_eq!(chunks.len(), 2);
+        let first_end = chunks[0].duration_secs.unwrap();
+        assert!(first_end > 0.9 && first_end < 1.1, "cut at {}", first_end);
+        assert!((chunks[1].offset_secs - first_end).abs() < 1e-9);
+        let total: f64 = chunks.iter().map(|c| c.duration_secs.unwrap()).sum();
+        assert!((total - 2.0).abs() < 1e-9);
+        // 每个切片都是独立可解析的 WAV
+        assert!(parse_wav(&chunks[1].data).is_ok());
+
+        // 未超限时原样返回
+        let single = split_audio(&wav, "audio/wav", wav.len()).unwrap();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].data, wav);
+    }
+
+    #[test]
+    fn test_mp3_split_on_frame_boundaries() {
+        // MPEG1 Layer III, 128 kbps, 44.1 kHz, 无 padding => 417 字节 / 帧
+        let mut frame = vec![0u8; 417];
+        frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+        let mut mp3 = b"ID3\x03\x00\x00\x00\x00\x00\x05hello".to_vec();
+        for _ in 0..100 {
+            mp3.extend_from_slice(&frame);
+        }
+
+        let frame_secs = 1152.0 / 44100.0;
+        let duration = audio_duration(&mp3, "audio/mp3").unwrap();
+        assert!((duration - 100.0 * frame_secs).abs() < 1e-9);
+
+        let chunks = split_audio(&mp3, "audio/mp3", 417 * 30).unwrap();
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0].data.len(), 417 * 30);
+        assert_eq!(&chunks[1].data[..2], &[0xFF, 0xFB]);
+        assert!((chunks[3].offset_secs - 90.0 * frame_secs).abs() < 1e-9);
+
+        assert!(split_audio(&mp3, "audio/ogg", 1024).is_err());
+    }
+}
@@ -1,6 +1,12 @@
 use base64::{engine::general_purpose, Engine as _};
 use std::path::Path;
 
+pub mod chunker;    // 大文件切分 (WAV 静音 / MP3 帧边界)
+pub mod transcript; // 转录结果拼接与格式渲染
+
+/// 单次 Inline Data 上传的大小上限
+pub const MAX_INLINE_SIZE: usize = 15 * 1024 * 1024; // 15MB
+
 pub struct AudioProcessor;
 
 impl AudioProcessor {
@@ -29,8 +35,7 @@ impl AudioProcessor {
 
     /// 判断文件是否超过大小限制
     pub fn exceeds_size_limit(size_bytes: usize) -> bool {
-        const MAX_SIZE: usize = 15 * 1024 * 1024; // 15MB
-        size_bytes > MAX_SIZE
+        size_bytes > MAX_INLINE_SIZE
     }
 }
 
@@ -0,0 +1,317 @@
+// 转录结果: 解析模型输出、按切片偏移拼接、渲染为 OpenAI 的各种 response_format
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    Text,
+    Srt,
+    Vtt,
+    VerboseJson,
+}
+
+impl ResponseFormat {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim() {
+            "" | "json" => Ok(Self::Json),
+            "text" => Ok(Self::Text),
+            "srt" => Ok(Self::Srt),
+            "vtt" => Ok(Self::Vtt),
+            "verbose_json" => Ok(Self::VerboseJson),
+            other => Err(format!(
+                "Unsupported response_format '{}': use json, text, srt, vtt or verbose_json",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    pub word: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcript {
+    pub language: Option<String>,
+    pub segments: Vec<Segment>,
+    pub words: Vec<Word>,
+}
+
+/// 解析时间戳: 秒数 (数字或字符串) 或 `MM:SS(.mmm)` / `HH:MM:SS(,mmm)`
+fn parse_timestamp(v: &Value) -> Option<f64> {
+    if let Some(n) = v.as_f64() {
+        return Some(n.max(0.0));
+    }
+    let s = v.as_str()?.trim().trim_end_matches('s').replace(',', ".");
+    s.split(':')
+        .try_fold(0.0, |acc, part| part.trim().parse::<f64>().ok().map(|n| acc * 60.0 + n))
+        .map(|n| n.max(0.0))
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    let t = text.trim();
+    let Some(rest) = t.strip_prefix("```") else {
+        return t;
+    };
+    let rest = rest.trim_start_matches("json").trim_start_matches("JSON");
+    rest.strip_suffix("```").unwrap_or(rest).trim()
+}
+
+/// 解析模型返回的 JSON 转录；不是预期结构时整段文本作为一个片段 (覆盖整个切片)
+pub fn parse_model_output(text: &str, chunk_duration: Option<f64>) -> Transcript {
+    let fallback = || Transcript {
+        language: None,
+        segments: vec![Segment {
+            start: 0.0,
+            end: chunk_duration.unwrap_or(0.0),
+            text: text.trim().to_string(),
+        }],
+        words: Vec::new(),
+    };
+
+    let Ok(value) = serde_json::from_str::<Value>(strip_code_fence(text)) else {
+        return if text.trim().is_empty() { Transcript::default() } else { fallback() };
+    };
+    let Some(raw_segments) = value.get("segments").and_then(|s| s.as_array()) else {
+        return match value.get("text").and_then(|t| t.as_str()) {
+            Some(t) => parse_model_output(t, chunk_duration),
+            None => fallback(),
+        };
+    };
+
+    let segments = raw_segments
+        .iter()
+        .filter_map(|s| {
+            let text = s.get("text")?.as_str()?.trim().to_string();
+            let start = s.get("start").and_then(parse_timestamp).unwrap_or(0.0);
+            let end = s.get("end").and_then(parse_timestamp).unwrap_or(start).max(start);
+            (!text.is_empty()).then_some(Segment { start, end, text })
+        })
+        .collect();
+    let words = value
+        .get("words")
+        .and_then(|w| w.as_array())
+        .map(|words| {
+            words
+                .iter()
+                .filter_map(|w| {
+                    let word = w.get("word")?.as_str()?.trim().to_string();
+                    let start = w.get("start").and_then(parse_timestamp).unwrap_or(0.0);
+                    let end = w.get("end").and_then(parse_timestamp).unwrap_or(start).max(start);
+                    Some(Word { word, start, end })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Transcript {
+        language: value
+            .get("language")
+            .and_then(|l| l.as_str())
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_lowercase()),
+        segments,
+        words,
+    }
+}
+
+/// 按切片偏移拼接多个转录结果，并把时间戳限制在切片范围内保证单调
+pub fn stitch(parts: Vec<(f64, Option<f64>, Transcript)>) -> Transcript {
+    let mut result = Transcript::default();
+    for (offset, duration, part) in parts {
+        let limit = duration.map(|d| offset + d).unwrap_or(f64::MAX);
+        let clamp = |t: f64| (offset + t).min(limit);
+        if result.language.is_none() {
+            result.language = part.language;
+        }
+        let floor = result.segments.last().map(|s| s.end).unwrap_or(0.0);
+        result.segments.extend(part.segments.into_iter().map(|s| {
+            let start = clamp(s.start).max(floor);
+            Segment {
+                start,
+                end: clamp(s.end).max(start),
+                text: s.text,
+            }
+        }));
+        result.words.extend(part.words.into_iter().map(|w| Word {
+            word: w.word,
+            start: clamp(w.start),
+            end: clamp(w.end),
+        }));
+    }
+    result
+}
+
+impl Transcript {
+    pub fn text(&self) -> String {
+        self.segments
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// `HH:MM:SS,mmm` (SRT) 或 `HH:MM:SS.mmm` (VTT)
+pub fn format_timestamp(secs: f64, separator: char) -> String {
+    let total_ms = (secs.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        total_ms / 3_600_000,
+        (total_ms / 60_000) % 60,
+        (total_ms / 1000) % 60,
+        separator,
+        total_ms % 1000
+    )
+}
+
+pub fn to_srt(transcript: &Transcript) -> String {
+    transcript
+        .segments
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            format!(
+                "{}\n{} --> {}\n{}\n\n",
+                i + 1,
+                format_timestamp(s.start, ','),
+                format_timestamp(s.end, ','),
+                s.text
+            )
+        })
+        .collect()
+}
+
+pub fn to_vtt(transcript: &Transcript) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for s in &transcript.segments {
+        out.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            format_timestamp(s.start, '.'),
+            format_timestamp(s.end, '.'),
+            s.text
+        ));
+    }
+    out
+}
+
+/// OpenAI verbose_json (task: "transcribe" | "translate")
+///
+/// Gemini 不提供 logprob 等统计值，相关字段填中性值以兼容 SDK 的类型定义。
+pub fn to_verbose_json(
+    transcript: &Transcript,
+    task: &str,
+    duration: Option<f64>,
+    include_segments: bool,
+    include_words: bool,
+) -> Value {
+    let duration = duration
+        .or_else(|| transcript.segments.last().map(|s| s.end))
+        .unwrap_or(0.0);
+    let mut out = json!({
+        "task": task,
+        "language": transcript.language.clone().unwrap_or_else(|| "unknown".to_string()),
+        "duration": duration,
+        "text": transcript.text(),
+    });
+    if include_segments {
+        out["segments"] = transcript
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(id, s)| {
+                json!({
+                    "id": id,
+                    "seek": 0,
+                    "start": s.start,
+                    "end": s.end,
+                    "text": s.text,
+                    "tokens": [],
+                    "temperature": 0.0,
+                    "avg_logprob": 0.0,
+                    "compression_ratio": 0.0,
+                    "no_speech_prob": 0.0,
+                })
+            })
+            .collect();
+    }
+    if include_words {
+        out["words"] = transcript
+            .words
+            .iter()
+            .map(|w| json!({ "word": w.word, "start": w.start, "end": w.end }))
+            .collect();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_stitch_chunks() {
+        let first = parse_model_output(
+            "```json\n{\"language\":\"EN\",\"segments\":[{\"start\":0,\"end\":\"00:04.5\",\"text\":\"Hello there.\"},{\"start\":\"4.5\",\"end\":99,\"text\":\"General Kenobi.\"}]}\n```",
+            Some(10.0),
+        );
+        assert_eq!(first.language.as_deref(), Some("en"));
+        assert_eq!(first.segments[0].end, 4.5);
+
+        let second = parse_model_output(
+            r#"{"segments":[{"start":"0:01","end":"0:03,250","text":"You are a bold one."}],"words":[{"word":"You","start":1,"end":1.2}]}"#,
+            Some(10.0),
+        );
+        // 非 JSON 输出退化为单个片段
+        let third = parse_model_output("plain transcript", Some(5.0));
+        assert_eq!(third.segments, vec![Segment { start: 0.0, end: 5.0, text: "plain transcript".into() }]);
+
+        let stitched = stitch(vec![
+            (0.0, Some(10.0), first),
+            (10.0, Some(10.0), second),
+            (20.0, Some(5.0), third),
+        ]);
+        assert_eq!(stitched.language.as_deref(), Some("en"));
+        // 超出切片时长的时间戳被截断
+        assert_eq!(stitched.segments[1].end, 10.0);
+        assert_eq!(stitched.segments[2].start, 11.0);
+        assert_eq!(stitched.segments[2].end, 13.25);
+        assert_eq!(stitched.segments[3].start, 20.0);
+        assert_eq!(stitched.words[0].start, 11.0);
+        assert_eq!(
+            stitched.text(),
+            "Hello there. General Kenobi. You are a bold one. plain transcript"
+        );
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let t = Transcript {
+            language: Some("en".into()),
+            segments: vec![Segment { start: 3661.5, end: 3662.0, text: "Hi".into() }],
+            words: vec![],
+        };
+        assert_eq!(to_srt(&t), "1\n01:01:01,500 --> 01:01:02,000\nHi\n\n");
+        assert_eq!(to_vtt(&t), "WEBVTT\n\n01:01:01.500 --> 01:01:02.000\nHi\n\n");
+
+        let v = to_verbose_json(&t, "translate", Some(3700.0), true, false);
+        assert_eq!(v["task"], "translate");
+        assert_eq!(v["duration"], 3700.0);
+        assert_eq!(v["segments"][0]["start"], 3661.5);
+        assert!(v.get("words").is_none());
+
+        assert_eq!(ResponseFormat::parse("").unwrap(), ResponseFormat::Json);
+        assert_eq!(ResponseFormat::parse("verbose_json").unwrap(), ResponseFormat::VerboseJson);
+        assert!(ResponseFormat::parse("xml").is_err());
+    }
+}
@@ -15,8 +15,12 @@ pub enum CliApp {
     Claude,
     Codex,
     Gemini,
+    Droid,
 }
 
+/// 由本程序管理的 Droid customModels 条目 id 前缀
+const DROID_MODEL_ID_PREFIX: &str = "custom:droidgravity-";
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct CliConfigFile {
     pub name: String,
@@ -29,6 +33,7 @@ impl CliApp {
             CliApp::Claude => "claude",
             CliApp::Codex => "codex",
             CliApp::Gemini => "gemini",
+            CliApp::Droid => "droid",
         }
     }
 
@@ -72,6 +77,10 @@ impl CliApp {
                     path: home.join(".gemini").join("config.json"),
                 },
             ],
+            CliApp::Droid => vec![CliConfigFile {
+                name: "settings.json".to_string(),
+                path: home.join(".factory").join("settings.json"),
+            }],
         }
     }
 
@@ -80,6 +89,8 @@ impl CliApp {
             CliApp::Claude => "https://api.anthropic.com",
             CliApp::Codex => "https://api.openai.com/v1",
             CliApp::Gemini => "https://generativelanguage.googleapis.com",
+            // Droid 没有可恢复的官方地址，恢复时只移除本程序写入的模型
+            CliApp::Droid => "https://api.factory.ai",
         }
     }
 }
@@ -271,14 +282,174 @@ pub fn get_sync_status(app: &CliApp, proxy_url: &str) -> (bool, bool, Option<Str
                     }
                 }
             }
+            CliApp::Droid => {
+                let json: Value = serde_json::from_str(&content).unwrap_or_default();
+                let managed: Vec<&str> = json
+                    .get("customModels")
+                    .and_then(|m| m.as_array())
+                    .map(|models| {
+                        models
+                            .iter()
+                            .filter(|m| is_managed_droid_model(m, Some(proxy_url)))
+                            .filter_map(|m| m.get("baseUrl").and_then(|u| u.as_str()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                current_base_url = managed.first().map(|u| u.to_string());
+                if managed.is_empty()
+                    || managed
+                        .iter()
+                        .any(|u| droid_proxy_root(u) != droid_proxy_root(proxy_url))
+                {
+                    all_synced = false;
+                }
+            }
         }
     }
 
     (all_synced, has_backup, current_base_url)
 }
 
+/// 是否为本程序写入的 Droid 模型
+///
+/// 以 id 前缀为准。同步时 (`proxy_url` 为 Some) 也替换手动合并 factory-droid-settings.json 后指向代理的条目；
+/// 恢复时 (`proxy_url` 为 None) 只移除带前缀的条目，用户自己的模型即使地址相同也保留。
+fn is_managed_droid_model(model: &Value, proxy_url: Option<&str>) -> bool {
+    let id_managed = model
+        .get("id")
+        .and_then(|v| v.as_str())
+        .is_some_and(|id| id.starts_with(DROID_MODEL_ID_PREFIX));
+    let url_managed = proxy_url.is_some_and(|proxy_url| {
+        !proxy_url.is_empty()
+            && model
+                .get("baseUrl")
+                .and_then(|v| v.as_str())
+                .is_some_and(|u| droid_proxy_root(u) == droid_proxy_root(proxy_url))
+    });
+    id_managed || url_managed
+}
+
+/// 去掉结尾的 `/` 与 `/v1`，用于比较不同 provider 写入的 baseUrl
+fn droid_proxy_root(url: &str) -> &str {
+    let url = url.trim_end_matches('/');
+    url.strip_suffix("/v1").unwrap_or(url)
+}
+
+/// 根据模型 ID 选择 Droid provider 及对应的 baseUrl
+///
+/// Claude / Kiro 走 `/v1/messages`，GPT / o 系列走 `/v1/responses`，Gemini 等其它模型走 `/v1/chat/completions`
+fn droid_provider(model: &str, proxy_url: &str) -> (&'static str, String) {
+    let root = droid_proxy_root(proxy_url);
+    let is_openai = model.starts_with("gpt-")
+        || ["o1", "o3", "o4"]
+            .iter()
+            .any(|p| model == *p || model.starts_with(&format!("{}-", p)));
+    if model.starts_with("claude-") || model == "auto" {
+        ("anthropic", root.to_string())
+    } else if is_openai {
+        ("openai", format!("{}/v1", root))
+    } else {
+        ("generic-chat-completion-api", format!("{}/v1", root))
+    }
+}
+
+/// 根据模型 ID 生成 Droid 中显示的名称
+///
+/// 相邻的短数字段合并为版本号，例如 claude-sonnet-4-5-thinking -> Claude Sonnet 4.5 Thinking，
+/// gemini-3-pro-high -> Gemini 3 Pro High
+fn droid_display_name(model: &str) -> String {
+    let is_version_part = |part: &str| part.len() <= 2 && part.chars().all(|c| c.is_ascii_digit());
+    let mut words: Vec<String> = Vec::new();
+    let mut prev_version = false;
+    for part in model.split('-').filter(|part| !part.is_empty()) {
+        let version = is_version_part(part);
+        if version && prev_version {
+            if let Some(last) = words.last_mut() {
+                last.push('.');
+                last.push_str(part);
+            }
+            continue;
+        }
+        prev_version = version;
+        let word = match part {
+            "gpt" => "GPT".to_string(),
+            _ => {
+                let mut chars = part.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            }
+        };
+        words.push(word);
+    }
+    words.join(" ")
+}
+
+fn droid_max_output_tokens(model: &str) -> u32 {
+    if model.starts_with("claude-") {
+        if model.contains("thinking") { 16384 } else { 8192 }
+    } else if model.starts_with("gemini-3-pro") || model.starts_with("gemini-2.5-pro") {
+        32768
+    } else if model.starts_with("gemini-") {
+        24576
+    } else {
+        32000
+    }
+}
+
+/// 将代理模型合并进 Droid settings.json 的 customModels
+///
+/// 只替换本程序管理的条目，用户自己的模型与其它设置保持不变。
+/// `proxy_url` 为 None 时表示恢复：不写入任何模型，仅移除带本程序 id 前缀的条目。
+pub fn merge_droid_models(settings: &mut Value, proxy_url: Option<&str>, api_key: &str, models: &[String]) {
+    if !settings.is_object() {
+        *settings = serde_json::json!({});
+    }
+    let obj = settings.as_object_mut().unwrap();
+    let mut custom_models: Vec<Value> = obj
+        .get("customModels")
+        .and_then(|m| m.as_array())
+        .cloned()
+        .unwrap_or_default();
+    custom_models.retain(|m| !is_managed_droid_model(m, proxy_url));
+
+    let mut next_index = custom_models
+        .iter()
+        .filter_map(|m| m.get("index").and_then(|i| i.as_u64()))
+        .max()
+        .map(|i| i + 1)
+        .unwrap_or(0);
+
+    let Some(proxy_url) = proxy_url else {
+        obj.insert("customModels".to_string(), Value::Array(custom_models));
+        return;
+    };
+
+    // 图像生成变体对 Droid 无意义，跳过
+    for model in models.iter().filter(|m| !m.contains("-image")) {
+        let (provider, base_url) = droid_provider(model, proxy_url);
+        custom_models.push(serde_json::json!({
+            "model": model,
+            "id": format!("{}{}", DROID_MODEL_ID_PREFIX, model),
+            "index": next_index,
+            "baseUrl": base_url,
+            "apiKey": api_key,
+            "displayName": droid_display_name(model),
+            "maxOutputTokens": droid_max_output_tokens(model),
+            "noImageSupport": false,
+            "provider": provider
+        }));
+        next_index += 1;
+    }
+
+    obj.insert("customModels".to_string(), Value::Array(custom_models));
+}
+
 /// 执行同步逻辑
-pub fn sync_config(app: &CliApp, proxy_url: &str, api_key: &str) -> Result<(), String> {
+///
+/// `models` 仅用于 Droid (customModels 列表)，其它 CLI 忽略该参数
+pub fn sync_config(app: &CliApp, proxy_url: &str, api_key: &str, models: &[String]) -> Result<(), String> {
     let files = app.config_files();
     
     for file in &files {
@@ -402,6 +573,13 @@ pub fn sync_config(app: &CliApp, proxy_url: &str, api_key: &str) -> Result<(), S
                     content = serde_json::to_string_pretty(&json).unwrap();
                 }
             }
+            CliApp::Droid => {
+                let mut json: Value = serde_json::from_str(&content).unwrap_or_else(|_| serde_json::json!({}));
+                // 恢复默认时不写入任何模型，只清理本程序写入的条目
+                let target = if proxy_url == app.default_url() { None } else { Some(proxy_url) };
+                merge_droid_models(&mut json, target, api_key, models);
+                content = serde_json::to_string_pretty(&json).unwrap();
+            }
         }
 
         // 使用临时文件原子写入
@@ -435,8 +613,13 @@ pub async fn get_cli_sync_status(app_type: CliApp, proxy_url: String) -> Result<
 }
 
 #[tauri::command]
-pub async fn execute_cli_sync(app_type: CliApp, proxy_url: String, api_key: String) -> Result<(), String> {
-    sync_config(&app_type, &proxy_url, &api_key)
+pub async fn execute_cli_sync(
+    app_type: CliApp,
+    proxy_url: String,
+    api_key: String,
+    models: Vec<String>,
+) -> Result<(), String> {
+    sync_config(&app_type, &proxy_url, &api_key, &models)
 }
 
 #[tauri::command]
@@ -464,7 +647,7 @@ pub async fn execute_cli_restore(app_type: CliApp) -> Result<(), String> {
     // 如果没有备份，则执行原来的逻辑：恢复为默认配置
     let default_url = app_type.default_url();
     // 恢复默认时清空 API Key，让用户重新授权或使用官方 Key
-    sync_config(&app_type, default_url, "")
+    sync_config(&app_type, default_url, "", &[])
 }
 
 #[tauri::command]
@@ -481,3 +664,68 @@ pub async fn get_cli_config_content(app_type: CliApp, file_name: Option<String>)
     }
     fs::read_to_string(&file.path).map_err(|e| format!("读取配置文件失败: {}", e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_droid_models_keeps_user_settings() {
+        let mut settings = json!({
+            "diffMode": "github",
+            "customModels": [
+                { "model": "my-local", "id": "custom:My-Local-0", "index": 0, "baseUrl": "http://localhost:11434", "provider": "generic-chat-completion-api" },
+                { "model": "gemini-3-flash", "id": "custom:Gemini-3-Flash-1", "index": 1, "baseUrl": "http://127.0.0.1:8045/", "provider": "anthropic" },
+                { "model": "old", "id": "custom:droidgravity-old", "index": 2, "baseUrl": "http://127.0.0.1:9000", "provider": "anthropic" }
+            ]
+        });
+        let models = vec![
+            "claude-sonnet-4-5-thinking".to_string(),
+            "gemini-3-pro-high".to_string(),
+            "gemini-3-pro-image-4k".to_string(),
+        ];
+
+        merge_droid_models(&mut settings, Some("http://127.0.0.1:8045"), "sk-test", &models);
+
+        assert_eq!(settings["diffMode"], "github");
+        let custom = settings["customModels"].as_array().unwrap();
+        assert_eq!(custom.len(), 3);
+        assert_eq!(custom[0]["id"], "custom:My-Local-0");
+        assert_eq!(custom[1]["id"], "custom:droidgravity-claude-sonnet-4-5-thinking");
+        assert_eq!(custom[1]["index"], 1);
+        assert_eq!(custom[1]["maxOutputTokens"], 16384);
+        assert_eq!(custom[1]["provider"], "anthropic");
+        assert_eq!(custom[1]["baseUrl"], "http://127.0.0.1:8045");
+        assert_eq!(custom[1]["displayName"], "Claude Sonnet 4.5 Thinking");
+        assert_eq!(custom[2]["displayName"], "Gemini 3 Pro High");
+        assert_eq!(custom[2]["provider"], "generic-chat-completion-api");
+        assert_eq!(custom[2]["baseUrl"], "http://127.0.0.1:8045/v1");
+        assert_eq!(custom[2]["apiKey"], "sk-test");
+    }
+
+    #[test]
+    fn test_merge_droid_models_empty_list_removes_managed() {
+        let mut settings = json!({
+            "customModels": [
+                { "model": "my-local", "id": "custom:My-Local-0", "index": 0, "baseUrl": "http://localhost:11434" },
+                { "model": "auto", "id": "custom:droidgravity-auto", "index": 1, "baseUrl": "http://127.0.0.1:8045" },
+                { "model": "factory-byok", "id": "custom:Factory-Byok-2", "index": 2, "baseUrl": "https://api.factory.ai" }
+            ]
+        });
+
+        merge_droid_models(&mut settings, None, "", &[]);
+
+        let custom = settings["customModels"].as_array().unwrap();
+        assert_eq!(custom.len(), 2);
+        assert_eq!(custom[0]["id"], "custom:My-Local-0");
+        assert_eq!(custom[1]["id"], "custom:Factory-Byok-2");
+    }
+
+    #[test]
+    fn test_droid_display_name_formats_versions() {
+        assert_eq!(droid_display_name("claude-opus-4-5-20251101"), "Claude Opus 4.5 20251101");
+        assert_eq!(droid_display_name("gemini-2.5-flash"), "Gemini 2.5 Flash");
+        assert_eq!(droid_display_name("gpt-5-1"), "GPT 5.1");
+    }
+}
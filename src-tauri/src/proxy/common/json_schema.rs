@@ -1,8 +1,312 @@
-use serde_json::Value;
+use serde_json::{json, Value};
 
-/// 别名，用于兼容旧代码
-pub fn fix_tool_call_args(_value: &mut serde_json::Value, _schema: &serde_json::Value) {
-    // 占位实现，实际逻辑可能需要更复杂的参数修复
+/// 工具名 → 原始 (未经 clean_json_schema 清洗) 参数 Schema
+pub type ToolSchemas = std::collections::HashMap<String, Value>;
+
+/// 按工具的原始 Schema 修正调用参数，并记录每一处修复
+///
+/// Gemini 只看到清洗后的 Schema，常见偏差:
+/// - 数字/布尔值以字符串返回 ("42", "true")
+/// - object/array 参数被序列化为 JSON 字符串
+/// - 单个值代替数组
+/// - enum 大小写不一致
+/// - 缺少带默认值 (default / 单值 enum / const) 的 required 字段
+pub fn fix_tool_call_args(value: &mut Value, schema: &Value) {
+    let repairs = coerce_tool_call_args(value, schema);
+    log_repairs(None, &repairs);
+}
+
+/// 按工具名查找 Schema 后修正参数 (响应映射使用)
+pub fn fix_tool_call_args_by_name(tool_name: &str, args: &mut Value, schemas: &ToolSchemas) {
+    if let Some(schema) = schemas.get(tool_name) {
+        let repairs = coerce_tool_call_args(args, schema);
+        log_repairs(Some(tool_name), &repairs);
+    }
+}
+
+/// 修正 Gemini 响应 (兼容 v1internal 的 response 包装) 中所有 functionCall.args
+pub fn fix_function_calls_in_response(response: &mut Value, schemas: &ToolSchemas) {
+    if schemas.is_empty() {
+        return;
+    }
+    let inner = if response.get("response").is_some() {
+        &mut response["response"]
+    } else {
+        response
+    };
+    let Some(candidates) = inner.get_mut("candidates").and_then(|c| c.as_array_mut()) else {
+        return;
+    };
+    for candidate in candidates {
+        let Some(parts) = candidate
+            .get_mut("content")
+            .and_then(|c| c.get_mut("parts"))
+            .and_then(|p| p.as_array_mut())
+        else {
+            continue;
+        };
+        for part in parts {
+            let Some(fc) = part.get_mut("functionCall").and_then(|f| f.as_object_mut()) else {
+                continue;
+            };
+            let Some(name) = fc.get("name").and_then(|n| n.as_str()).map(|n| n.to_string()) else {
+                continue;
+            };
+            if let Some(args) = fc.get_mut("args") {
+                fix_tool_call_args_by_name(&name, args, schemas);
+            }
+        }
+    }
+}
+
+/// 参数修正引擎，返回修复记录 (不打日志)
+pub fn coerce_tool_call_args(value: &mut Value, schema: &Value) -> Vec<String> {
+    let mut repairs = Vec::new();
+    coerce_node(value, schema, schema, "$", 0, &mut repairs);
+    repairs
+}
+
+fn log_repairs(tool_name: Option<&str>, repairs: &[String]) {
+    if repairs.is_empty() {
+        return;
+    }
+    tracing::info!(
+        "[Tool-Args] Repaired {} argument(s){}: {}",
+        repairs.len(),
+        tool_name.map(|n| format!(" for '{}'", n)).unwrap_or_default(),
+        repairs.join("; ")
+    );
+}
+
+fn coerce_node(
+    value: &mut Value,
+    schema: &Value,
+    root: &Value,
+    path: &str,
+    depth: usize,
+    repairs: &mut Vec<String>,
+) {
+    if depth > MAX_REF_DEPTH {
+        return;
+    }
+    let Value::Object(map) = schema else {
+        return;
+    };
+
+    if let Some(Value::String(ref_path)) = map.get("$ref") {
+        if let Some(target) = resolve_ref(root, ref_path) {
+            coerce_node(value, target, root, path, depth + 1, repairs);
+        }
+    }
+    if let Some(Value::Array(all_of)) = map.get("allOf") {
+        for sub in all_of {
+            coerce_node(value, sub, root, path, depth + 1, repairs);
+        }
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(branches)) = map.get(key) {
+            coerce_union(value, branches, root, path, depth, repairs);
+        }
+    }
+
+    if let Some(types) = declared_types(map) {
+        coerce_type(value, &types, path, repairs);
+    }
+    if let Some(Value::Array(allowed)) = map.get("enum") {
+        coerce_enum(value, allowed, path, repairs);
+    }
+
+    match value {
+        Value::Object(obj) => {
+            let properties = map.get("properties").and_then(|p| p.as_object());
+            if let Some(props) = properties {
+                for (key, prop_schema) in props {
+                    if let Some(child) = obj.get_mut(key) {
+                        let child_path = format!("{}.{}", path, key);
+                        coerce_node(child, prop_schema, root, &child_path, depth + 1, repairs);
+                    }
+                }
+            }
+            if let Some(extra @ Value::Object(_)) = map.get("additionalProperties") {
+                for (key, child) in obj.iter_mut() {
+                    if properties.is_some_and(|p| p.contains_key(key)) {
+                        continue;
+                    }
+                    let child_path = format!("{}.{}", path, key);
+                    coerce_node(child, extra, root, &child_path, depth + 1, repairs);
+                }
+            }
+            if let Some(Value::Array(required)) = map.get("required") {
+                for key in required.iter().filter_map(|k| k.as_str()) {
+                    if obj.contains_key(key) {
+                        continue;
+                    }
+                    let default = properties
+                        .and_then(|p| p.get(key))
+                        .and_then(|prop_schema| default_for(prop_schema, root, depth));
+                    if let Some(default) = default {
+                        repairs.push(format!("{}.{}: filled missing required field with {}", path, key, default));
+                        obj.insert(key.to_string(), default);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            let prefix = map.get("prefixItems").and_then(|p| p.as_array());
+            for (i, item) in items.iter_mut().enumerate() {
+                let item_schema = prefix.and_then(|p| p.get(i)).or_else(|| map.get("items"));
+                if let Some(item_schema) = item_schema {
+                    let child_path = format!("{}[{}]", path, i);
+                    coerce_node(item, item_schema, root, &child_path, depth + 1, repairs);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// anyOf/oneOf: 已匹配某个分支则不动，否则取第一个修正后能通过校验的分支
+fn coerce_union(
+    value: &mut Value,
+    branches: &[Value],
+    root: &Value,
+    path: &str,
+    depth: usize,
+    repairs: &mut Vec<String>,
+) {
+    if branches.iter().any(|b| is_valid(value, b, root, path, depth)) {
+        return;
+    }
+    for branch in branches {
+        let mut candidate = value.clone();
+        let mut branch_repairs = Vec::new();
+        coerce_node(&mut candidate, branch, root, path, depth + 1, &mut branch_repairs);
+        if !branch_repairs.is_empty() && is_valid(&candidate, branch, root, path, depth) {
+            *value = candidate;
+            repairs.extend(branch_repairs);
+            return;
+        }
+    }
+}
+
+fn declared_types(map: &serde_json::Map<String, Value>) -> Option<Vec<String>> {
+    match map.get("type")? {
+        Value::String(t) => Some(vec![t.to_lowercase()]),
+        Value::Array(types) => Some(
+            types
+                .iter()
+                .filter_map(|t| t.as_str())
+                .map(|t| t.to_lowercase())
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn coerce_type(value: &mut Value, types: &[String], path: &str, repairs: &mut Vec<String>) {
+    if types.is_empty() || types.iter().any(|t| type_matches(value, t)) {
+        return;
+    }
+    for target in types.iter().filter(|t| t.as_str() != "null") {
+        if let Some(converted) = convert_to_type(value, target) {
+            repairs.push(format!("{}: {} → {}", path, json_type_name(value), target));
+            *value = converted;
+            return;
+        }
+    }
+}
+
+fn convert_to_type(value: &Value, target: &str) -> Option<Value> {
+    match (target, value) {
+        ("integer", Value::String(s)) => {
+            let s = s.trim();
+            s.parse::<i64>().ok().map(|i| json!(i)).or_else(|| {
+                s.parse::<f64>()
+                    .ok()
+                    .filter(|f| f.fract() == 0.0 && f.is_finite())
+                    .map(|f| json!(f as i64))
+            })
+        }
+        ("number", Value::String(s)) => {
+            let s = s.trim();
+            s.parse::<i64>().ok().map(|i| json!(i)).or_else(|| {
+                s.parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+            })
+        }
+        ("boolean", Value::String(s)) => match s.trim().to_lowercase().as_str() {
+            "true" => Some(json!(true)),
+            "false" => Some(json!(false)),
+            _ => None,
+        },
+        ("boolean", Value::Number(n)) => match n.as_i64() {
+            Some(0) => Some(json!(false)),
+            Some(1) => Some(json!(true)),
+            _ => None,
+        },
+        ("string", Value::Number(_) | Value::Bool(_)) => Some(Value::String(value.to_string())),
+        ("string", Value::Object(_) | Value::Array(_)) => Some(Value::String(value.to_string())),
+        ("object", Value::String(s)) => serde_json::from_str::<Value>(s.trim())
+            .ok()
+            .filter(|v| v.is_object()),
+        ("array", Value::String(s)) => match serde_json::from_str::<Value>(s.trim()) {
+            Ok(parsed @ Value::Array(_)) => Some(parsed),
+            _ => Some(json!([value])),
+        },
+        ("array", Value::Null) => None,
+        ("array", _) => Some(json!([value])),
+        _ => None,
+    }
+}
+
+/// enum 值大小写/空白不一致时替换为 Schema 中的规范值
+fn coerce_enum(value: &mut Value, allowed: &[Value], path: &str, repairs: &mut Vec<String>) {
+    if allowed.contains(value) {
+        return;
+    }
+    let replacement = match &*value {
+        Value::String(s) => {
+            let needle = s.trim().to_lowercase();
+            allowed
+                .iter()
+                .find(|a| a.as_str().is_some_and(|a| a.to_lowercase() == needle))
+                .cloned()
+        }
+        Value::Number(_) | Value::Bool(_) => {
+            let as_string = value.to_string();
+            allowed.iter().find(|a| a.as_str() == Some(as_string.as_str())).cloned()
+        }
+        _ => None,
+    };
+    if let Some(replacement) = replacement {
+        repairs.push(format!("{}: enum {} → {}", path, value, replacement));
+        *value = replacement;
+    }
+}
+
+/// 缺失 required 字段的默认值: default > const > 单值 enum
+fn default_for(schema: &Value, root: &Value, depth: usize) -> Option<Value> {
+    if depth > MAX_REF_DEPTH {
+        return None;
+    }
+    let map = schema.as_object()?;
+    if let Some(Value::String(ref_path)) = map.get("$ref") {
+        if let Some(found) = resolve_ref(root, ref_path).and_then(|t| default_for(t, root, depth + 1)) {
+            return Some(found);
+        }
+    }
+    if let Some(default) = map.get("default") {
+        return Some(default.clone());
+    }
+    if let Some(constant) = map.get("const") {
+        return Some(constant.clone());
+    }
+    match map.get("enum") {
+        Some(Value::Array(values)) if values.len() == 1 => Some(values[0].clone()),
+        _ => None,
+    }
 }
 
 /// 递归清理 JSON Schema 以符合 Gemini 接口要求
@@ -292,6 +596,258 @@ fn extract_type_from_union(union_array: &Vec<Value>) -> Option<String> {
     None
 }
 
+/// 校验结果最多保留的错误条数 (避免修复提示词过长)
+const MAX_VALIDATION_ERRORS: usize = 10;
+/// $ref 解析的最大深度 (防止循环引用)
+const MAX_REF_DEPTH: usize = 32;
+
+/// 按原始 JSON Schema 校验实例 (用于 Structured Outputs 的响应校验)
+///
+/// 与 `clean_json_schema` 相反，这里使用的是未清洗的原始 Schema，支持常用子集:
+/// type / enum / const / properties / required / additionalProperties / items / prefixItems /
+/// 长度与数值约束 / pattern / anyOf / oneOf / allOf / not / 本地 $ref
+///
+/// 返回形如 `$.items[0].name: expected string` 的错误列表
+pub fn validate_json_schema(instance: &Value, schema: &Value) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    validate_node(instance, schema, schema, "$", 0, &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        errors.truncate(MAX_VALIDATION_ERRORS);
+        Err(errors)
+    }
+}
+
+fn validate_node(
+    instance: &Value,
+    schema: &Value,
+    root: &Value,
+    path: &str,
+    depth: usize,
+    errors: &mut Vec<String>,
+) {
+    if errors.len() >= MAX_VALIDATION_ERRORS {
+        return;
+    }
+
+    let map = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => {
+            errors.push(format!("{}: value is not allowed", path));
+            return;
+        }
+        Value::Object(map) => map,
+        _ => return,
+    };
+
+    if let Some(Value::String(ref_path)) = map.get("$ref") {
+        match resolve_ref(root, ref_path) {
+            Some(target) if depth < MAX_REF_DEPTH => {
+                validate_node(instance, target, root, path, depth + 1, errors);
+            }
+            Some(_) => errors.push(format!("{}: $ref depth limit exceeded ({})", path, ref_path)),
+            None => errors.push(format!("{}: unresolvable $ref {}", path, ref_path)),
+        }
+    }
+
+    if let Some(type_val) = map.get("type") {
+        let matches = match type_val {
+            Value::String(t) => type_matches(instance, t),
+            Value::Array(types) => types
+                .iter()
+                .filter_map(|t| t.as_str())
+                .any(|t| type_matches(instance, t)),
+            _ => true,
+        };
+        if !matches {
+            errors.push(format!("{}: expected {}, got {}", path, type_val, json_type_name(instance)));
+            // 类型不符时其余约束没有意义
+            return;
+        }
+    }
+
+    if let Some(Value::Array(allowed)) = map.get("enum") {
+        if !allowed.contains(instance) {
+            errors.push(format!("{}: {} is not one of {}", path, instance, Value::Array(allowed.clone())));
+        }
+    }
+    if let Some(expected) = map.get("const") {
+        if expected != instance {
+            errors.push(format!("{}: expected constant {}", path, expected));
+        }
+    }
+
+    match instance {
+        Value::Object(obj) => {
+            if let Some(Value::Array(required)) = map.get("required") {
+                for key in required.iter().filter_map(|k| k.as_str()) {
+                    if !obj.contains_key(key) {
+                        errors.push(format!("{}: missing required property \"{}\"", path, key));
+                    }
+                }
+            }
+            let properties = map.get("properties").and_then(|p| p.as_object());
+            for (key, value) in obj {
+                let child_path = format!("{}.{}", path, key);
+                match properties.and_then(|p| p.get(key)) {
+                    Some(prop_schema) => {
+                        validate_node(value, prop_schema, root, &child_path, depth, errors)
+                    }
+                    None => match map.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            errors.push(format!("{}: unexpected property \"{}\"", path, key))
+                        }
+                        Some(extra @ Value::Object(_)) => {
+                            validate_node(value, extra, root, &child_path, depth, errors)
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            let prefix = map.get("prefixItems").and_then(|p| p.as_array());
+            let prefix_len = prefix.map(|p| p.len()).unwrap_or(0);
+            for (i, item) in items.iter().enumerate() {
+                let child_path = format!("{}[{}]", path, i);
+                if let Some(item_schema) = prefix.and_then(|p| p.get(i)) {
+                    validate_node(item, item_schema, root, &child_path, depth, errors);
+                } else if i >= prefix_len {
+                    if let Some(item_schema) = map.get("items") {
+                        validate_node(item, item_schema, root, &child_path, depth, errors);
+                    }
+                }
+            }
+            if let Some(min) = map.get("minItems").and_then(|v| v.as_u64()) {
+                if (items.len() as u64) < min {
+                    errors.push(format!("{}: expected at least {} items, got {}", path, min, items.len()));
+                }
+            }
+            if let Some(max) = map.get("maxItems").and_then(|v| v.as_u64()) {
+                if (items.len() as u64) > max {
+                    errors.push(format!("{}: expected at most {} items, got {}", path, max, items.len()));
+                }
+            }
+        }
+        Value::String(text) => {
+            let len = text.chars().count() as u64;
+            if let Some(min) = map.get("minLength").and_then(|v| v.as_u64()) {
+                if len < min {
+                    errors.push(format!("{}: string shorter than {}", path, min));
+                }
+            }
+            if let Some(max) = map.get("maxLength").and_then(|v| v.as_u64()) {
+                if len > max {
+                    errors.push(format!("{}: string longer than {}", path, max));
+                }
+            }
+            if let Some(pattern) = map.get("pattern").and_then(|v| v.as_str()) {
+                // 无法编译的正则不作为校验失败处理
+                if let Ok(re) = regex::Regex::new(pattern) {
+                    if !re.is_match(text) {
+                        errors.push(format!("{}: does not match pattern {}", path, pattern));
+                    }
+                }
+            }
+        }
+        Value::Number(n) => {
+            let x = n.as_f64().unwrap_or_default();
+            let bound = |key: &str| map.get(key).and_then(|v| v.as_f64());
+            if let Some(min) = bound("minimum") {
+                if x < min {
+                    errors.push(format!("{}: {} is less than minimum {}", path, x, min));
+                }
+            }
+            if let Some(max) = bound("maximum") {
+                if x > max {
+                    errors.push(format!("{}: {} is greater than maximum {}", path, x, max));
+                }
+            }
+            if let Some(min) = bound("exclusiveMinimum") {
+                if x <= min {
+                    errors.push(format!("{}: {} must be greater than {}", path, x, min));
+                }
+            }
+            if let Some(max) = bound("exclusiveMaximum") {
+                if x >= max {
+                    errors.push(format!("{}: {} must be less than {}", path, x, max));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(Value::Array(all_of)) = map.get("allOf") {
+        for sub in all_of {
+            validate_node(instance, sub, root, path, depth, errors);
+        }
+    }
+    if let Some(Value::Array(any_of)) = map.get("anyOf") {
+        if !any_of.iter().any(|sub| is_valid(instance, sub, root, path, depth)) {
+            errors.push(format!("{}: does not match any schema in anyOf", path));
+        }
+    }
+    if let Some(Value::Array(one_of)) = map.get("oneOf") {
+        let matched = one_of
+            .iter()
+            .filter(|sub| is_valid(instance, sub, root, path, depth))
+            .count();
+        if matched != 1 {
+            errors.push(format!("{}: expected exactly one oneOf match, got {}", path, matched));
+        }
+    }
+    if let Some(not) = map.get("not") {
+        if is_valid(instance, not, root, path, depth) {
+            errors.push(format!("{}: must not match schema in not", path));
+        }
+    }
+}
+
+fn is_valid(instance: &Value, schema: &Value, root: &Value, path: &str, depth: usize) -> bool {
+    let mut errors = Vec::new();
+    validate_node(instance, schema, root, path, depth, &mut errors);
+    errors.is_empty()
+}
+
+/// 解析本地引用: `#`, `#/$defs/Name`, `#/definitions/Name` 或任意 JSON Pointer
+fn resolve_ref<'a>(root: &'a Value, ref_path: &str) -> Option<&'a Value> {
+    let pointer = ref_path.strip_prefix('#')?;
+    if pointer.is_empty() {
+        return Some(root);
+    }
+    root.pointer(pointer)
+}
+
+fn type_matches(instance: &Value, type_name: &str) -> bool {
+    match type_name.to_lowercase().as_str() {
+        "object" => instance.is_object(),
+        "array" => instance.is_array(),
+        "string" => instance.is_string(),
+        "boolean" => instance.is_boolean(),
+        "null" => instance.is_null(),
+        "number" => instance.is_number(),
+        "integer" => match instance {
+            Value::Number(n) => {
+                n.is_i64() || n.is_u64() || n.as_f64().map(|f| f.fract() == 0.0).unwrap_or(false)
+            }
+            _ => false,
+        },
+        _ => true,
+    }
+}
+
+fn json_type_name(instance: &Value) -> &'static str {
+    match instance {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -480,6 +1036,100 @@ mod tests {
         assert_eq!(schema["properties"]["value"]["type"], "integer");
     }
 
+    #[test]
+    fn test_fix_tool_call_args_coerces_types() {
+        let schema = json!({
+            "type": "object",
+            "$defs": {
+                "Mode": { "type": "string", "enum": ["fast", "Thorough"] }
+            },
+            "properties": {
+                "limit": { "type": "integer" },
+                "ratio": { "type": "number" },
+                "recursive": { "type": "boolean" },
+                "options": {
+                    "type": "object",
+                    "properties": { "depth": { "type": "integer" } }
+                },
+                "paths": { "type": "array", "items": { "type": "string" } },
+                "label": { "type": "string" },
+                "mode": { "$ref": "#/$defs/Mode" },
+                "timeout": { "anyOf": [{ "type": "integer" }, { "type": "null" }] },
+                "format": { "type": "string", "enum": ["json"], "default": "json" }
+            },
+            "required": ["limit", "format"]
+        });
+        let mut args = json!({
+            "limit": "42",
+            "ratio": "0.5",
+            "recursive": "TRUE",
+            "options": "{\"depth\": \"3\"}",
+            "paths": "src",
+            "label": 7,
+            "mode": "thorough",
+            "timeout": "30"
+        });
+
+        let repairs = coerce_tool_call_args(&mut args, &schema);
+
+        assert_eq!(args["limit"], json!(42));
+        assert_eq!(args["ratio"], json!(0.5));
+        assert_eq!(args["recursive"], json!(true));
+        assert_eq!(args["options"], json!({ "depth": 3 }));
+        assert_eq!(args["paths"], json!(["src"]));
+        assert_eq!(args["label"], json!("7"));
+        assert_eq!(args["mode"], json!("Thorough"));
+        assert_eq!(args["timeout"], json!(30));
+        assert_eq!(args["format"], json!("json"));
+        assert_eq!(repairs.len(), 10);
+        assert!(validate_json_schema(&args, &schema).is_ok());
+    }
+
+    #[test]
+    fn test_fix_tool_call_args_leaves_valid_args_untouched() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "command": { "type": "string" },
+                "paths": { "type": "array", "items": { "type": "string" } },
+                "value": { "anyOf": [{ "type": "string" }, { "type": "integer" }] }
+            }
+        });
+        let mut args = json!({ "command": "ls -la", "paths": ["a", "b"], "value": "12", "extra": "1" });
+        let original = args.clone();
+
+        assert!(coerce_tool_call_args(&mut args, &schema).is_empty());
+        assert_eq!(args, original);
+    }
+
+    #[test]
+    fn test_fix_function_calls_in_response() {
+        let mut schemas = ToolSchemas::new();
+        schemas.insert(
+            "read_file".to_string(),
+            json!({ "type": "object", "properties": { "offset": { "type": "integer" } } }),
+        );
+        let mut response = json!({
+            "response": {
+                "candidates": [{
+                    "content": {
+                        "parts": [
+                            { "text": "Reading" },
+                            { "functionCall": { "name": "read_file", "args": { "offset": "10" } } },
+                            { "functionCall": { "name": "unknown_tool", "args": { "offset": "10" } } }
+                        ]
+                    }
+                }]
+            }
+        });
+
+        fix_function_calls_in_response(&mut response, &schemas);
+
+        let parts = &response["response"]["candidates"][0]["content"]["parts"];
+        assert_eq!(parts[1]["functionCall"]["args"]["offset"], json!(10));
+        assert_eq!(parts[2]["functionCall"]["args"]["offset"], json!("10"));
+    }
+
     // [NEW TEST] 验证已有 type 不被覆盖
     #[test]
     fn test_existing_type_preserved() {
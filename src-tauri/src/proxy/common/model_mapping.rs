@@ -143,7 +143,7 @@ pub async fn get_all_dynamic_models(
 /// - `*-thinking` 匹配所有以 `-thinking` 结尾的模型
 /// - `claude-*-sonnet-*` 匹配 `claude-3-5-sonnet-20241022` 等 (multi-wildcard)
 /// - `*thinking*` 匹配包含 "thinking" 的模型
-fn wildcard_match(pattern: &str, text: &str) -> bool {
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
     if !pattern.contains('*') {
         return pattern == text;
     }
@@ -190,7 +190,7 @@ fn wildcard_match(pattern: &str, text: &str) -> bool {
 }
 
 /// Calculate pattern specificity (higher = more specific)
-fn pattern_specificity(pattern: &str) -> usize {
+pub(crate) fn pattern_specificity(pattern: &str) -> usize {
     let wildcard_count = pattern.matches('*').count();
     let char_count = pattern.chars().count();
     char_count.saturating_sub(wildcard_count)
@@ -0,0 +1,110 @@
+// Structured Outputs 公共逻辑
+// OpenAI response_format.json_schema / Claude output_config.format → Gemini responseSchema，
+// 并按原始 Schema 校验模型输出
+
+use super::json_schema::{clean_json_schema, validate_json_schema};
+use serde_json::{json, Value};
+
+/// 将 JSON Schema 注入 Gemini generationConfig
+///
+/// Gemini 只接受受限的 OpenAPI 子集，因此注入前先经过 `clean_json_schema`；
+/// 被清洗掉的约束 (pattern/minLength 等) 由 `validate_output` 用原始 Schema 兜底校验
+pub fn apply_response_schema(gen_config: &mut Value, schema: &Value) {
+    let mut cleaned = schema.clone();
+    clean_json_schema(&mut cleaned);
+    gen_config["responseMimeType"] = json!("application/json");
+    gen_config["responseSchema"] = cleaned;
+}
+
+/// 解析并校验模型输出，成功时返回解析后的 JSON
+pub fn validate_output(text: &str, schema: &Value) -> Result<Value, String> {
+    let body = strip_code_fence(text);
+    let value: Value = serde_json::from_str(body)
+        .map_err(|e| format!("response is not valid JSON: {}", e))?;
+    validate_json_schema(&value, schema).map_err(|errors| errors.join("; "))?;
+    Ok(value)
+}
+
+/// 修复重试时追加给模型的提示词
+pub fn repair_prompt(error: &str) -> String {
+    format!(
+        "[System Recovery] Your previous response did not match the required JSON schema: {}. \
+         Respond again with only the corrected JSON document, without markdown fences or commentary.",
+        error
+    )
+}
+
+/// 部分模型即使设置了 responseMimeType 仍会用 ```json 包裹输出
+fn strip_code_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let rest = rest.strip_prefix("json").unwrap_or(rest);
+    rest.strip_suffix("```").unwrap_or(rest).trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "$defs": {
+                "Tag": { "type": "string", "pattern": "^[a-z]+$" }
+            },
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "age": { "type": "integer", "minimum": 0 },
+                "role": { "enum": ["admin", "user"] },
+                "tags": { "type": "array", "items": { "$ref": "#/$defs/Tag" }, "maxItems": 3 },
+                "nickname": { "anyOf": [{ "type": "string" }, { "type": "null" }] }
+            },
+            "required": ["name", "age"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_apply_response_schema_cleans_schema() {
+        let mut gen_config = json!({ "temperature": 1.0 });
+        apply_response_schema(&mut gen_config, &person_schema());
+
+        assert_eq!(gen_config["responseMimeType"], "application/json");
+        let schema = &gen_config["responseSchema"];
+        assert!(schema.get("additionalProperties").is_none());
+        assert!(schema.get("$defs").is_none());
+        assert_eq!(schema["properties"]["tags"]["items"]["type"], "string");
+        assert_eq!(schema["properties"]["nickname"]["type"], "string");
+    }
+
+    #[test]
+    fn test_validate_output_accepts_valid_and_fenced_json() {
+        let schema = person_schema();
+        let ok = r#"{"name":"Ann","age":30,"role":"admin","tags":["a","b"],"nickname":null}"#;
+        assert!(validate_output(ok, &schema).is_ok());
+
+        let fenced = format!("```json\n{}\n```", ok);
+        assert_eq!(validate_output(&fenced, &schema).unwrap()["name"], "Ann");
+    }
+
+    #[test]
+    fn test_validate_output_reports_violations() {
+        let schema = person_schema();
+
+        let err = validate_output("not json", &schema).unwrap_err();
+        assert!(err.contains("not valid JSON"));
+
+        let err = validate_output(
+            r#"{"age":-1.5,"role":"root","tags":["OK"],"extra":1}"#,
+            &schema,
+        )
+        .unwrap_err();
+        assert!(err.contains("missing required property \"name\""));
+        assert!(err.contains("$.age: expected \"integer\""));
+        assert!(err.contains("$.role"));
+        assert!(err.contains("$.tags[0]: does not match pattern"));
+        assert!(err.contains("unexpected property \"extra\""));
+    }
+}
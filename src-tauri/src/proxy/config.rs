@@ -260,6 +260,64 @@ impl Default for SecurityMonitorConfig {
     }
 }
 
+/// 客户端 API Key (团队共享反代时为每个成员签发独立的 Key)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientApiKey {
+    /// 唯一标识 (用于统计归属与管理接口)
+    pub id: String,
+    /// 显示名称 (例如成员名或用途)
+    pub name: String,
+    /// 实际的 Key 值 (`sk-...`)
+    pub key: String,
+    /// 允许使用的模型列表，支持 `*` 通配符；为空表示不限制
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+    /// 每日 Token 预算 (输入 + 输出，按 UTC 日计算)；None 表示不限制
+    #[serde(default)]
+    pub daily_token_limit: Option<u64>,
+    /// 每日请求次数预算 (按 UTC 日计算)；None 表示不限制
+    #[serde(default)]
+    pub daily_request_limit: Option<u64>,
+    /// 过期时间 (Unix 秒)；None 表示永不过期
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// 是否已吊销
+    #[serde(default)]
+    pub revoked: bool,
+    /// 创建时间 (Unix 秒)
+    #[serde(default)]
+    pub created_at: i64,
+}
+
+impl ClientApiKey {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            key: format!("sk-{}", uuid::Uuid::new_v4().simple()),
+            allowed_models: Vec::new(),
+            daily_token_limit: None,
+            daily_request_limit: None,
+            expires_at: None,
+            revoked: false,
+            created_at: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.map(|t| now >= t).unwrap_or(false)
+    }
+
+    /// 检查模型是否在允许列表中 (空列表表示不限制)
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.allowed_models.is_empty()
+            || self
+                .allowed_models
+                .iter()
+                .any(|pattern| crate::proxy::common::model_mapping::wildcard_match(pattern, model))
+    }
+}
+
 /// 反代服务配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyConfig {
@@ -289,6 +347,19 @@ pub struct ProxyConfig {
     /// Web UI 管理后台密码 (可选，如未设置则使用 api_key)
     pub admin_password: Option<String>,
 
+    /// 客户端 API Key 列表 (每个 Key 独立的模型白名单、每日预算与有效期)
+    #[serde(default)]
+    pub client_keys: Vec<ClientApiKey>,
+
+    /// Prometheus 抓取 /metrics 专用令牌 (Bearer)；不接受 API Key 与客户端 Key
+    #[serde(default)]
+    pub metrics_token: Option<String>,
+
+    /// 受信任的反向代理 (IP 或 CIDR)；仅当 TCP 对端在此列表中时才采信
+    /// X-Forwarded-For / X-Real-IP (用于管理端登录限流)
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+
     /// 是否自动启动
     pub auto_start: bool,
 
@@ -312,10 +383,18 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub upstream_proxy: UpstreamProxyConfig,
 
+    /// 账号独立代理 (Account.individual_proxy) 的健康检查与故障回退
+    #[serde(default)]
+    pub account_proxy: AccountProxyConfig,
+
     /// z.ai provider configuration (Anthropic-compatible).
     #[serde(default)]
     pub zai: ZaiConfig,
 
+    /// 通用 MCP 网关 (暴露为 /mcp/<name>/mcp)
+    #[serde(default)]
+    pub mcp_gateway: crate::proxy::mcp_gateway::McpGatewayConfig,
+
     /// 自定义 User-Agent 请求头 (可选覆盖)
     #[serde(default)]
     pub user_agent_override: Option<String>,
@@ -324,6 +403,26 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub scheduling: crate::proxy::sticky_config::StickySessionConfig,
 
+    /// 账号路由策略 (按模型通配符选择)
+    #[serde(default)]
+    pub routing: crate::proxy::routing_policy::RoutingConfig,
+
+    /// 模型降级链 (整个账号池不可用时依次尝试，可跨提供商)
+    #[serde(default)]
+    pub model_fallback: crate::proxy::model_fallback::ModelFallbackConfig,
+
+    /// 模型路由规则 (优先于自定义映射，按 priority 评估)
+    #[serde(default)]
+    pub model_router: crate::proxy::model_router::ModelRouterConfig,
+
+    /// 确定性请求的响应缓存 (temperature = 0 或后台任务)
+    #[serde(default)]
+    pub response_cache: crate::proxy::response_cache::ResponseCacheConfig,
+
+    /// 监控日志的采集级别、脱敏与保留策略
+    #[serde(default)]
+    pub monitor_policy: crate::proxy::monitor_policy::MonitorPolicyConfig,
+
     /// 实验性功能配置
     #[serde(default)]
     pub experimental: ExperimentalConfig,
@@ -352,6 +451,59 @@ pub struct UpstreamProxyConfig {
     pub url: String,
 }
 
+/// 账号独立代理不可用时的回退方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountProxyFallback {
+    /// 改走全局上游代理 (未启用时直连)
+    UpstreamProxy,
+    /// 直连上游
+    Direct,
+    /// 不回退：该账号暂不参与调度，直到代理恢复 (默认，避免账号流量换出口 IP)
+    #[default]
+    SkipAccount,
+}
+
+/// 账号独立代理配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountProxyConfig {
+    /// 代理不可用时的回退方式
+    #[serde(default)]
+    pub fallback: AccountProxyFallback,
+    /// 连续连接失败多少次判定代理不可用
+    #[serde(default = "default_account_proxy_failure_threshold")]
+    pub failure_threshold: u32,
+    /// 后台健康检查间隔(秒)，0 表示关闭定时探测 (不可用的代理仍每 60 秒探测一次)
+    #[serde(default = "default_account_proxy_check_interval")]
+    pub health_check_interval_secs: u64,
+    /// 健康检查经代理访问的地址 (任意 HTTP 响应即视为代理可用)
+    #[serde(default = "default_account_proxy_check_url")]
+    pub health_check_url: String,
+}
+
+impl Default for AccountProxyConfig {
+    fn default() -> Self {
+        Self {
+            fallback: AccountProxyFallback::default(),
+            failure_threshold: default_account_proxy_failure_threshold(),
+            health_check_interval_secs: default_account_proxy_check_interval(),
+            health_check_url: default_account_proxy_check_url(),
+        }
+    }
+}
+
+fn default_account_proxy_failure_threshold() -> u32 {
+    3
+}
+
+fn default_account_proxy_check_interval() -> u64 {
+    60
+}
+
+fn default_account_proxy_check_url() -> String {
+    "https://www.gstatic.com/generate_204".to_string()
+}
+
 impl Default for ProxyConfig {
     fn default() -> Self {
         Self {
@@ -361,14 +513,24 @@ impl Default for ProxyConfig {
             port: 8045,
             api_key: format!("sk-{}", uuid::Uuid::new_v4().simple()),
             admin_password: None,
+            client_keys: Vec::new(),
+            metrics_token: None,
+            trusted_proxies: Vec::new(),
             auto_start: false,
             custom_mapping: std::collections::HashMap::new(),
             request_timeout: default_request_timeout(),
             enable_logging: true, // 默认开启，支持 token 统计功能
             debug_logging: DebugLoggingConfig::default(),
             upstream_proxy: UpstreamProxyConfig::default(),
+            account_proxy: AccountProxyConfig::default(),
             zai: ZaiConfig::default(),
+            mcp_gateway: crate::proxy::mcp_gateway::McpGatewayConfig::default(),
             scheduling: crate::proxy::sticky_config::StickySessionConfig::default(),
+            routing: crate::proxy::routing_policy::RoutingConfig::default(),
+            model_fallback: crate::proxy::model_fallback::ModelFallbackConfig::default(),
+            model_router: crate::proxy::model_router::ModelRouterConfig::default(),
+            response_cache: crate::proxy::response_cache::ResponseCacheConfig::default(),
+            monitor_policy: crate::proxy::monitor_policy::MonitorPolicyConfig::default(),
             experimental: ExperimentalConfig::default(),
             security_monitor: SecurityMonitorConfig::default(),
             preferred_account_id: None, // 默认使用轮询模式
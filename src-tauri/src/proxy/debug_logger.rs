@@ -1,3 +1,5 @@
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio::fs;
 use std::path::PathBuf;
@@ -5,6 +7,79 @@ use futures::StreamExt;
 
 use crate::proxy::config::DebugLoggingConfig;
 
+/// upstream_response 文件同时也是可回放的 cassette (见 docs/proxy/cassettes.md)
+/// v2: 录制的分块改为 base64 原始字节 (chunks_b64)，v1 的文本分块仍可回放
+pub const CASSETTE_VERSION: u32 = 2;
+
+/// 录制的上游响应 (保留原始分块边界，回放时按相同分块下发)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteUpstream {
+    #[serde(default = "default_cassette_status")]
+    pub status: u16,
+    #[serde(default = "default_cassette_content_type")]
+    pub content_type: String,
+    /// 原始字节分块 (base64)，录制时写入，多字节字符被切开或 gzip 等二进制内容也能逐字节还原
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks_b64: Vec<String>,
+    /// 文本分块 (v1 录制或手写精简的用例)；chunks_b64 非空时忽略
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<String>,
+}
+
+impl CassetteUpstream {
+    /// 按录制时的分块边界还原上游原始字节
+    #[allow(dead_code)] // Used in tests (replay harness)
+    pub fn chunk_bytes(&self) -> Result<Vec<bytes::Bytes>, String> {
+        if self.chunks_b64.is_empty() {
+            return Ok(self
+                .chunks
+                .iter()
+                .map(|c| bytes::Bytes::from(c.clone()))
+                .collect());
+        }
+        self.chunks_b64
+            .iter()
+            .map(|c| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(c)
+                    .map(bytes::Bytes::from)
+                    .map_err(|e| format!("invalid base64 chunk: {}", e))
+            })
+            .collect()
+    }
+}
+
+fn default_cassette_status() -> u16 {
+    200
+}
+
+fn default_cassette_content_type() -> String {
+    "text/event-stream".to_string()
+}
+
+/// 一次上游交互的录制: 客户端原始请求 + 上游原始字节流
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(dead_code)] // Used in tests (replay harness)
+pub struct Cassette {
+    #[serde(default)]
+    pub cassette_version: u32,
+    #[serde(default)]
+    pub trace_id: Option<String>,
+    /// protocol / original_model / mapped_model / method 等
+    #[serde(default)]
+    pub meta: Value,
+    /// 客户端原始请求体
+    pub request: Value,
+    pub upstream: CassetteUpstream,
+}
+
+impl Cassette {
+    #[allow(dead_code)] // Used in tests
+    pub fn protocol(&self) -> &str {
+        self.meta.get("protocol").and_then(|v| v.as_str()).unwrap_or("anthropic")
+    }
+}
+
 fn build_filename(prefix: &str, trace_id: Option<&str>) -> String {
     let ts = chrono::Utc::now().format("%Y%m%d_%H%M%S%.3f");
     let tid = trace_id.unwrap_or("unknown");
@@ -140,21 +215,39 @@ pub fn wrap_reqwest_stream_with_debug(
 
     let wrapped = async_stream::stream! {
         let mut collected: Vec<u8> = Vec::new();
+        let mut chunks_b64: Vec<String> = Vec::new();
         let mut inner = stream;
         while let Some(item) = inner.next().await {
             if let Ok(bytes) = &item {
                 collected.extend_from_slice(bytes);
+                chunks_b64.push(base64::engine::general_purpose::STANDARD.encode(bytes));
             }
             yield item;
         }
 
         let raw_text = String::from_utf8_lossy(&collected).to_string();
         let (thinking_content, response_content) = parse_sse_stream(&raw_text);
+
+        // [NEW] 录制为 cassette: 原始请求由 handler 放在 meta.request 中
+        let mut meta = meta;
+        let request = meta
+            .as_object_mut()
+            .and_then(|m| m.remove("request"))
+            .unwrap_or(Value::Null);
+        let status = meta.get("status").and_then(|v| v.as_u64()).unwrap_or(200) as u16;
         
         let mut payload = serde_json::json!({
             "kind": "upstream_response",
+            "cassette_version": CASSETTE_VERSION,
             "trace_id": trace_id,
             "meta": meta,
+            "request": request,
+            "upstream": CassetteUpstream {
+                status,
+                content_type: default_cassette_content_type(),
+                chunks_b64,
+                chunks: Vec::new(),
+            },
         });
         
         // 只有在有内容时才添加对应字段
@@ -1,27 +1,65 @@
 use axum::{
     extract::{Multipart, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
+use futures::StreamExt;
 use serde_json::{json, Value};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 use crate::proxy::{
-    audio::AudioProcessor,
+    audio::{
+        chunker::{split_audio, AudioChunk},
+        transcript::{self, parse_model_output, stitch, ResponseFormat, Transcript},
+        AudioProcessor, MAX_INLINE_SIZE,
+    },
+    handlers::common::should_rotate_account,
     server::AppState,
 };
 
-/// 处理音频转录请求 (OpenAI Whisper API 兼容)
-pub async fn handle_audio_transcription(
-    State(state): State<AppState>,
-    mut multipart: Multipart,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+/// 切片并行转录的最大并发数 (每个切片独立取号，分摊到不同账号)
+const MAX_PARALLEL_CHUNKS: usize = 4;
+
+const MAX_RETRY_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AudioTask {
+    Transcribe,
+    Translate,
+}
+
+impl AudioTask {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Transcribe => "transcribe",
+            Self::Translate => "translate",
+        }
+    }
+}
+
+/// OpenAI 音频接口的表单字段
+struct AudioForm {
+    audio: Vec<u8>,
+    filename: String,
+    model: String,
+    prompt: Option<String>,
+    language: Option<String>,
+    temperature: Option<f64>,
+    response_format: ResponseFormat,
+    granularities: Vec<String>,
+}
+
+async fn parse_form(mut multipart: Multipart) -> Result<AudioForm, (StatusCode, String)> {
     let mut audio_data: Option<Vec<u8>> = None;
     let mut filename: Option<String> = None;
     let mut model = "gemini-2.0-flash-exp".to_string();
-    let mut prompt = "Generate a transcript of the speech.".to_string();
+    let mut prompt = None;
+    let mut language = None;
+    let mut temperature = None;
+    let mut response_format = ResponseFormat::Json;
+    let mut granularities = Vec::new();
 
     // 1. 解析 multipart/form-data
     while let Some(field) = multipart.next_field().await.map_err(|e| {
@@ -40,54 +78,91 @@ pub async fn handle_audio_transcription(
                 model = field.text().await.unwrap_or(model);
             }
             "prompt" => {
-                prompt = field.text().await.unwrap_or(prompt);
+                prompt = field.text().await.ok().filter(|s| !s.trim().is_empty());
+            }
+            "language" => {
+                language = field.text().await.ok().filter(|s| !s.trim().is_empty());
+            }
+            "temperature" => {
+                let raw = field.text().await.unwrap_or_default();
+                temperature = Some(raw.trim().parse::<f64>().map_err(|_| {
+                    (StatusCode::BAD_REQUEST, format!("Invalid temperature: {}", raw))
+                })?);
+            }
+            "response_format" => {
+                let raw = field.text().await.unwrap_or_default();
+                response_format = ResponseFormat::parse(&raw).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+            }
+            "timestamp_granularities[]" | "timestamp_granularities" => {
+                granularities.push(field.text().await.unwrap_or_default().trim().to_string());
             }
             _ => {}
         }
     }
 
-    let audio_bytes = audio_data.ok_or((
-        StatusCode::BAD_REQUEST,
-        "缺少音频文件".to_string(),
-    ))?;
-
-    let file_name = filename.ok_or((
-        StatusCode::BAD_REQUEST,
-        "无法获取文件名".to_string(),
-    ))?;
-
-    info!(
-        "收到音频转录请求: 文件={}, 大小={} bytes, 模型={}",
-        file_name,
-        audio_bytes.len(),
-        model
-    );
-
-    // 2. 检测 MIME 类型
-    let mime_type = AudioProcessor::detect_mime_type(&file_name)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(AudioForm {
+        audio: audio_data.ok_or((StatusCode::BAD_REQUEST, "缺少音频文件".to_string()))?,
+        filename: filename.ok_or((StatusCode::BAD_REQUEST, "无法获取文件名".to_string()))?,
+        model,
+        prompt,
+        language,
+        temperature,
+        response_format,
+        granularities,
+    })
+}
 
-    // 3. 验证文件大小
-    if AudioProcessor::exceeds_size_limit(audio_bytes.len()) {
-        let size_mb = audio_bytes.len() as f64 / (1024.0 * 1024.0);
-        return Err((
-            StatusCode::PAYLOAD_TOO_LARGE,
-            format!(
-                "音频文件过大 ({:.1} MB)。最大支持 15 MB (约 16 分钟 MP3)。建议: 1) 压缩音频质量 2) 分段上传",
-                size_mb
-            ),
+/// 构造转录 / 翻译指令，要求模型输出带时间戳的 JSON
+fn build_instruction(task: AudioTask, form: &AudioForm, want_words: bool) -> String {
+    let mut instruction = match task {
+        AudioTask::Transcribe => "Transcribe the speech in this audio verbatim, in the language it is spoken.".to_string(),
+        AudioTask::Translate => "Translate the speech in this audio into English.".to_string(),
+    };
+    if let Some(language) = &form.language {
+        instruction.push_str(&format!(" The audio is in language '{}' (ISO-639-1).", language));
+    }
+    if let Some(prompt) = &form.prompt {
+        instruction.push_str(&format!(
+            " Use this context for vocabulary and spelling, do not transcribe it: \"{}\".",
+            prompt
         ));
     }
+    instruction.push_str(
+        " Respond with JSON only, shaped as {\"language\": \"<ISO-639-1 code of the spoken language>\", \
+         \"segments\": [{\"start\": <seconds>, \"end\": <seconds>, \"text\": \"...\"}]",
+    );
+    if want_words {
+        instruction.push_str(", \"words\": [{\"word\": \"...\", \"start\": <seconds>, \"end\": <seconds>}]");
+    }
+    instruction.push_str(
+        "}. Timestamps are seconds from the start of this audio clip. \
+         Split segments at sentence or pause boundaries, at most about 30 seconds each.",
+    );
+    instruction
+}
 
-    // 4. 使用 Inline Data 方式
-    debug!("使用 Inline Data 方式处理");
-    let base64_audio = AudioProcessor::encode_to_base64(&audio_bytes);
+/// 转录单个切片，按账号轮换重试；返回 (结果, 使用的账号)
+async fn transcribe_chunk(
+    state: &AppState,
+    model: &str,
+    mime_type: &str,
+    instruction: &str,
+    temperature: Option<f64>,
+    chunk: &AudioChunk,
+) -> Result<(Transcript, String), (StatusCode, String)> {
+    let token_manager = &state.token_manager;
+    let max_attempts = MAX_RETRY_ATTEMPTS.min(token_manager.len()).max(1);
+    let base64_audio = AudioProcessor::encode_to_base64(&chunk.data);
 
-    // 5. 构建 Gemini 请求
+    let mut generation_config = json!({ "responseMimeType": "application/json" });
+    if let Some(t) = temperature {
+        generation_config["temperature"] = json!(t);
+    }
     let gemini_request = json!({
         "contents": [{
+            "role": "user",
             "parts": [
-                {"text": prompt},
+                {"text": instruction},
                 {
                     "inlineData": {
                         "mimeType": mime_type,
@@ -95,41 +170,83 @@ pub async fn handle_audio_transcription(
                     }
                 }
             ]
-        }]
+        }],
+        "generationConfig": generation_config
     });
 
-    // 6. 获取 Token 和上游客户端
-    let token_manager = state.token_manager;
-    let (access_token, project_id, email, account_id, _wait_ms) = token_manager
-        .get_token("text", false, None, &model, None) // [FIX] Audio handler usually doesn't retry internally yet, passing None
-        .await
-        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))?;
-
-    info!("使用账号: {}", email);
-
-    // 7. 包装请求为 v1internal 格式
-    let wrapped_body = json!({
-        "project": project_id.clone(),
-        "requestId": format!("audio-{}", Uuid::new_v4()),
-        "request": gemini_request,
-        "model": model.clone(),
-        "userAgent": "antigravity",
-        "requestType": "text"
-    });
+    let mut failed_accounts = std::collections::HashSet::new();
+    let mut last_error = String::new();
+    for attempt in 0..max_attempts {
+        let (access_token, project_id, email, account_id, _wait_ms) = token_manager
+            .get_token("text", attempt > 0, None, model, Some(&failed_accounts))
+            .await
+            .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))?;
+        debug!(
+            "使用账号: {} (切片 @{:.1}s, {} bytes)",
+            email,
+            chunk.offset_secs,
+            chunk.data.len()
+        );
+
+        // 包装请求为 v1internal 格式
+        let wrapped_body = json!({
+            "project": project_id,
+            "requestId": format!("audio-{}", Uuid::new_v4()),
+            "request": gemini_request,
+            "model": model,
+            "userAgent": "antigravity",
+            "requestType": "text"
+        });
 
-    // 8. 发送请求到 Gemini
-    let upstream = state.upstream.clone();
-    let response = upstream
-        .call_v1_internal("generateContent", &access_token, wrapped_body, None)
-        .await
-        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("上游请求失败: {}", e)))?;
+        let response = match state
+            .upstream
+            .call_v1_internal(
+                "generateContent",
+                &access_token,
+                wrapped_body,
+                None,
+                token_manager.get_individual_proxy(&account_id).as_deref(),
+            )
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                last_error = format!("上游请求失败: {}", e);
+                continue;
+            }
+        };
+
+        let status = response.status();
+        if status.is_success() {
+            let result: Value = response
+                .json()
+                .await
+                .map_err(|e| (StatusCode::BAD_GATEWAY, format!("解析响应失败: {}", e)))?;
+
+            // 提取文本响应（解包 v1internal 响应）
+            let inner_response = result.get("response").unwrap_or(&result);
+            let text: String = inner_response
+                .get("candidates")
+                .and_then(|c| c.get(0))
+                .and_then(|c| c.get("content"))
+                .and_then(|c| c.get("parts"))
+                .and_then(|p| p.as_array())
+                .map(|parts| {
+                    parts
+                        .iter()
+                        .filter(|p| !p.get("thought").and_then(|t| t.as_bool()).unwrap_or(false))
+                        .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                        .collect()
+                })
+                .unwrap_or_default();
+            return Ok((parse_model_output(&text, chunk.duration_secs), email));
+        }
 
-    let status = response.status();
-    if !status.is_success() {
         let status_code = status.as_u16();
         let retry_after = response.headers().get("Retry-After").and_then(|h| h.to_str().ok()).map(|s| s.to_string());
         let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        
+        last_error = format!("Gemini API 错误: {}", error_text);
+
         // 标记限流状态
         if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 {
             token_manager
@@ -139,42 +256,133 @@ pub async fn handle_audio_transcription(
                     status_code,
                     retry_after.as_deref(),
                     &error_text,
-                    Some(&model),
+                    Some(model),
                 )
                 .await;
+            failed_accounts.insert(account_id);
         }
 
-        return Err((
-            StatusCode::BAD_GATEWAY,
-            format!("Gemini API 错误: {}", error_text),
-        ));
+        if !should_rotate_account(status_code) {
+            break;
+        }
+        warn!("[Audio] Upstream {} on {}, rotating account", status_code, email);
+    }
+
+    Err((StatusCode::BAD_GATEWAY, last_error))
+}
+
+fn render(form: &AudioForm, task: AudioTask, transcript: &Transcript, duration: Option<f64>) -> Response {
+    let plain = |body: String| ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body).into_response();
+    match form.response_format {
+        ResponseFormat::Json => Json(json!({ "text": transcript.text() })).into_response(),
+        ResponseFormat::Text => plain(transcript.text()),
+        ResponseFormat::Srt => plain(transcript::to_srt(transcript)),
+        ResponseFormat::Vtt => plain(transcript::to_vtt(transcript)),
+        ResponseFormat::VerboseJson => {
+            let include_words = form.granularities.iter().any(|g| g == "word");
+            let include_segments = !include_words || form.granularities.iter().any(|g| g == "segment");
+            Json(transcript::to_verbose_json(
+                transcript,
+                task.as_str(),
+                duration,
+                include_segments,
+                include_words,
+            ))
+            .into_response()
+        }
+    }
+}
+
+async fn handle_audio(
+    state: AppState,
+    multipart: Multipart,
+    task: AudioTask,
+) -> Result<Response, (StatusCode, String)> {
+    let form = parse_form(multipart).await?;
+
+    info!(
+        "收到音频{}请求: 文件={}, 大小={} bytes, 模型={}, 格式={:?}",
+        if task == AudioTask::Translate { "翻译" } else { "转录" },
+        form.filename,
+        form.audio.len(),
+        form.model,
+        form.response_format
+    );
+
+    // 2. 检测 MIME 类型
+    let mime_type = AudioProcessor::detect_mime_type(&form.filename)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    // 3. 超过单次上传限制时切分 (WAV 按静音、MP3 按帧边界)
+    let chunks = split_audio(&form.audio, &mime_type, MAX_INLINE_SIZE)
+        .map_err(|e| (StatusCode::PAYLOAD_TOO_LARGE, e))?;
+    if AudioProcessor::exceeds_size_limit(form.audio.len()) {
+        info!("音频超过 {} MB，已切分为 {} 段并行处理", MAX_INLINE_SIZE / 1024 / 1024, chunks.len());
+    }
+    let duration = chunks
+        .iter()
+        .map(|c| c.duration_secs)
+        .sum::<Option<f64>>();
+
+    // 4. 并行转录 (buffered 保持原顺序)
+    let want_words = form.granularities.iter().any(|g| g == "word");
+    let instruction = build_instruction(task, &form, want_words);
+    let tasks: Vec<_> = chunks
+        .iter()
+        .map(|chunk| {
+            transcribe_chunk(&state, &form.model, &mime_type, &instruction, form.temperature, chunk)
+        })
+        .collect();
+    let results: Vec<_> = futures::stream::iter(tasks)
+        .buffered(MAX_PARALLEL_CHUNKS)
+        .collect()
+        .await;
+
+    let mut parts = Vec::with_capacity(results.len());
+    let mut email = String::new();
+    for (chunk, result) in chunks.iter().zip(results) {
+        let (transcript, chunk_email) = result?;
+        if email.is_empty() {
+            email = chunk_email;
+        }
+        parts.push((chunk.offset_secs, chunk.duration_secs, transcript));
+    }
+
+    let mut transcript = stitch(parts);
+    // 已指定语言时以请求为准 (翻译任务的 language 表示源语言)
+    if let Some(language) = &form.language {
+        transcript.language = Some(language.to_lowercase());
     }
+    info!(
+        "音频{}完成: {} 段, {} 字符",
+        task.as_str(),
+        transcript.segments.len(),
+        transcript.text().len()
+    );
 
-    let result: Value = response
-        .json()
-        .await
-        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("解析响应失败: {}", e)))?;
-
-    // 9. 提取文本响应（解包 v1internal 响应）
-    let inner_response = result.get("response").unwrap_or(&result);
-    let text = inner_response
-        .get("candidates")
-        .and_then(|c| c.get(0))
-        .and_then(|c| c.get("content"))
-        .and_then(|c| c.get("parts"))
-        .and_then(|p| p.get(0))
-        .and_then(|p| p.get("text"))
-        .and_then(|t| t.as_str())
-        .unwrap_or("");
-
-    info!("音频转录完成，返回 {} 字符", text.len());
-
-    // 10. 返回标准格式响应
-    Ok((
-        StatusCode::OK,
-        [("X-Account-Email", email.as_str())],
-        Json(json!({
-            "text": text
-        }))
-    ).into_response())
+    // 5. 按 response_format 返回
+    let mut response = render(&form, task, &transcript, duration);
+    if let Ok(v) = email.parse() {
+        response.headers_mut().insert("X-Account-Email", v);
+    }
+    if let Ok(v) = form.model.parse() {
+        response.headers_mut().insert("X-Mapped-Model", v);
+    }
+    Ok(response)
+}
+
+/// 处理音频转录请求 (OpenAI Whisper API 兼容)
+pub async fn handle_audio_transcription(
+    State(state): State<AppState>,
+    multipart: Multipart,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    handle_audio(state, multipart, AudioTask::Transcribe).await
+}
+
+/// 处理音频翻译请求 (翻译为英文，OpenAI /v1/audio/translations 兼容)
+pub async fn handle_audio_translation(
+    State(state): State<AppState>,
+    multipart: Multipart,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    handle_audio(state, multipart, AudioTask::Translate).await
 }
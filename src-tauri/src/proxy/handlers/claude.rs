@@ -2,7 +2,7 @@
 
 use axum::{
     body::Body,
-    extract::{Json, State},
+    extract::{Extension, Json, State},
     http::{header, StatusCode},
     response::{IntoResponse, Response},
 };
@@ -20,6 +20,10 @@ use crate::proxy::mappers::claude::{
     models::{Message, MessageContent},
 };
 use crate::proxy::server::AppState;
+use crate::proxy::model_fallback::{self, FallbackApplied, FallbackTarget};
+use crate::proxy::model_router::{self, RequestFeatures};
+use crate::proxy::response_cache::{self, CacheContext, CacheProtocol};
+use crate::proxy::middleware::auth::ClientKeyIdentity;
 use crate::proxy::mappers::context_manager::ContextManager;
 use crate::proxy::mappers::estimation_calibrator::get_calibrator;
 use crate::proxy::debug_logger;
@@ -116,7 +120,54 @@ use super::common::{determine_retry_strategy, apply_retry_strategy, should_rotat
 pub async fn handle_messages(
     State(state): State<AppState>,
     headers: HeaderMap,
+    client_key: Option<Extension<ClientKeyIdentity>>,
     Json(body): Json<Value>,
+) -> Response {
+    let client_key = client_key.map(|Extension(key)| key);
+
+    // [NEW] 响应缓存: 确定性请求 (temperature = 0 / 后台任务) 命中时不再请求上游
+    let cache_ctx = response_cache_context(&body, client_key.as_ref());
+    if let Some(ctx) = &cache_ctx {
+        if let Some(hit) = ctx.lookup().await {
+            return hit;
+        }
+    }
+
+    // [NEW] 模型降级链: 处理流程记录实际发生的降级，在此统一写入响应头 (供客户端与监控日志读取)
+    let mut fallback: Option<FallbackApplied> = None;
+    let mut response = handle_messages_inner(state, headers, client_key, body, &mut fallback).await;
+    if let Some(applied) = fallback {
+        applied.apply_headers(&mut response);
+    }
+    match cache_ctx {
+        Some(ctx) => ctx.store(response).await,
+        None => response,
+    }
+}
+
+/// 请求可缓存时返回缓存上下文 (后台任务按 detect_background_task_type 识别)
+fn response_cache_context(body: &Value, client_key: Option<&ClientKeyIdentity>) -> Option<CacheContext> {
+    let cache = response_cache::cache();
+    if !cache.is_enabled() {
+        return None;
+    }
+    let request: ClaudeRequest = serde_json::from_value(body.clone()).ok()?;
+    cache.prepare(
+        CacheProtocol::Anthropic,
+        client_key.map(|k| k.id.as_str()),
+        &request.model,
+        body,
+        request.stream,
+        detect_background_task_type(&request).is_some(),
+    )
+}
+
+async fn handle_messages_inner(
+    state: AppState,
+    headers: HeaderMap,
+    client_key: Option<ClientKeyIdentity>,
+    body: Value,
+    fallback: &mut Option<FallbackApplied>,
 ) -> Response {
     // [FIX] 保存原始请求体的完整副本，用于日志记录
     // 这确保了即使结构体定义遗漏字段，日志也能完整记录所有参数
@@ -388,11 +439,23 @@ pub async fn handle_messages(
     // 3. 准备闭包
     let mut request_for_body = request.clone();
     let token_manager = state.token_manager.clone();
+    // [NEW] 原始工具 Schema，响应映射时据此修正 functionCall 参数类型
+    let tool_schemas = Arc::new(crate::proxy::mappers::claude::request::build_tool_schemas(&request));
     
     let pool_size = token_manager.len();
     // [FIX] Ensure max_attempts is at least 2 to allow for internal retries (e.g. stripping signatures)
     // even if the user has only 1 account.
-    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2);
+    // [NEW] Structured outputs: 保留原始 Schema 用于响应校验，并额外预留一次修复重试
+    let response_schema = request
+        .output_config
+        .as_ref()
+        .and_then(|c| c.json_schema())
+        .cloned();
+    let mut schema_repair_attempted = false;
+    // 修复重试沿用产出无效 JSON 的账号 (不触发轮换)
+    let mut repair_account: Option<String> = None;
+    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2)
+        + usize::from(response_schema.is_some());
 
     let mut last_error = String::new();
     let retried_without_thinking = false;
@@ -404,12 +467,106 @@ pub async fn handle_messages(
     // 传递给 token_manager 以防止重试时再次选中它们
     let mut failed_accounts = std::collections::HashSet::new();
 
+    // [NEW] 路由规则匹配所需的请求特征 (只有规则用到 token 条件时才预估 token)
+    let route_features = RequestFeatures::from_body(
+        "anthropic",
+        &request.model,
+        &original_body,
+        model_router::router().needs_prompt_tokens(),
+    )
+    .with_client_key(client_key.as_ref());
+
     for attempt in 0..max_attempts {
-        // 2. 模型路由解析
-        let mut mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        // 2. 模型路由解析 (路由规则 > 自定义映射 > 系统默认映射)
+        let mut mapped_model = model_router::resolve_model(
             &request_for_body.model,
             &*state.custom_mapping.read().await,
+            &route_features,
         );
+
+        // [NEW] Determine provider (quota_group) by model name
+        // Kiro models: auto, claude-sonnet-4, claude-haiku-4-5, deepseek-3, etc.
+        // All other models use Gemini provider
+        let mut quota_group = crate::proxy::common::model_mapping::determine_provider_by_model(&request_for_body.model);
+
+        // [NEW] 模型降级链: 主目标的账号池已全部不可用时，切换到链上第一个可用的目标 (可跨提供商)
+        // 每次尝试重新判断，上一次尝试的降级结果不沿用
+        *fallback = None;
+        let fallback_targets = token_manager.fallback_targets(&request_for_body.model);
+        if !fallback_targets.is_empty() {
+            let primary = if quota_group == "kiro" {
+                FallbackTarget::Kiro(request_for_body.model.clone())
+            } else {
+                FallbackTarget::Gemini(mapped_model.clone())
+            };
+            let picked = model_fallback::select_target(primary.clone(), fallback_targets, |t| {
+                model_fallback::is_target_available(&state, t, &failed_accounts)
+            })
+            .await;
+            if let Some((hop, target)) = picked.filter(|(hop, _)| *hop > 0) {
+                info!(
+                    "[{}] 模型降级: {} 的账号池不可用，切换到第 {} 跳 {}",
+                    trace_id,
+                    primary.label(),
+                    hop,
+                    target.label()
+                );
+                let mut applied = FallbackApplied {
+                    from: primary.label(),
+                    to: target.label(),
+                };
+                match target {
+                    FallbackTarget::Gemini(model) => {
+                        mapped_model = model;
+                        quota_group = "gemini";
+                        *fallback = Some(applied);
+                    }
+                    FallbackTarget::Kiro(model) => {
+                        *fallback = Some(applied);
+                        let mut kiro_request = request_for_body.clone();
+                        kiro_request.model = model;
+                        return crate::proxy::handlers::kiro::handle_kiro_messages(
+                            State(state.clone()),
+                            Json(kiro_request),
+                        )
+                        .await
+                        .into_response();
+                    }
+                    FallbackTarget::Zai(model) => {
+                        let mut zai_request = request_for_body.clone();
+                        if let Some(model) = model {
+                            zai_request.model = format!("zai:{}", model);
+                        }
+                        // 与 z.ai 直连路径一致: 重新应用 Anthropic Prompt Caching 标记
+                        crate::proxy::mappers::claude::apply_cache_control_for_anthropic(
+                            &mut zai_request.messages,
+                            &mut zai_request.system,
+                        );
+                        applied.to = format!(
+                            "zai:{}",
+                            crate::proxy::providers::zai_anthropic::map_model_for_zai(&zai_request.model, &zai)
+                        );
+                        *fallback = Some(applied);
+                        let message_count = zai_request.messages.len();
+                        return match serde_json::to_value(&zai_request) {
+                            Ok(new_body) => crate::proxy::providers::zai_anthropic::forward_anthropic_json(
+                                &state,
+                                axum::http::Method::POST,
+                                "/v1/messages",
+                                &headers,
+                                new_body,
+                                message_count,
+                            )
+                            .await,
+                            Err(e) => {
+                                tracing::error!("Failed to serialize fallback request for z.ai: {}", e);
+                                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                            }
+                        };
+                    }
+                }
+            }
+        }
         last_mapped_model = Some(mapped_model.clone());
         
         // 将 Claude 工具转为 Value 数组以便探测联网
@@ -438,19 +595,27 @@ pub async fn handle_messages(
         });
 
         let force_rotate_token = attempt > 0;
-        
-        // [NEW] Determine provider (quota_group) by model name
-        // Kiro models: auto, claude-sonnet-4, claude-haiku-4-5, deepseek-3, etc.
-        // All other models use Gemini provider
-        let quota_group = crate::proxy::common::model_mapping::determine_provider_by_model(&request_for_body.model);
-        
-        let (access_token, project_id, email, account_id, _wait_ms) = match token_manager.get_token(
-            quota_group,
-            force_rotate_token, 
-            session_id, 
-            &config.final_model,
-            Some(&failed_accounts) // [FIX] 传入黑名单
-        ).await {
+
+        // 结构化输出修复重试: 优先使用上一次的账号，失败时回退到正常调度
+        let pinned = match repair_account.take() {
+            Some(repair_email) => token_manager.get_token_by_email(&repair_email).await.ok(),
+            None => None,
+        };
+        let token_result = match pinned {
+            Some(t) => Ok(t),
+            None => {
+                token_manager
+                    .get_token(
+                        quota_group,
+                        force_rotate_token,
+                        session_id,
+                        &config.final_model,
+                        Some(&failed_accounts), // [FIX] 传入黑名单
+                    )
+                    .await
+            }
+        };
+        let (access_token, project_id, email, account_id, _wait_ms) = match token_result {
             Ok(t) => t,
             Err(e) => {
                 let safe_message = if e.contains("invalid_grant") {
@@ -551,15 +716,13 @@ pub async fn handle_messages(
                 2_000_000
             };
 
-            // 2. [ENHANCED] 使用校准器提高估算准确度 (PR #925)
-            let raw_estimated = ContextManager::estimate_token_usage(&request_with_mapped);
-            let calibrator = get_calibrator();
-            let mut estimated_usage = calibrator.calibrate(raw_estimated);
+            // 2. [ENHANCED] 离线分词器计数 + 按模型学习的校准残差 (PR #925)
+            let mut estimated_usage = ContextManager::estimate_token_usage(&request_with_mapped, &mapped_model);
             let mut usage_ratio = estimated_usage as f32 / context_limit as f32;
             
             info!(
-                "[{}] [ContextManager] Context pressure: {:.1}% (raw: {}, calibrated: {} / {}), Calibration factor: {:.2}",
-                trace_id, usage_ratio * 100.0, raw_estimated, estimated_usage, context_limit, calibrator.get_factor()
+                "[{}] [ContextManager] Context pressure: {:.1}% (calibrated: {} / {}), Calibration factor for {}: {:.2}",
+                trace_id, usage_ratio * 100.0, estimated_usage, context_limit, mapped_model, get_calibrator().get_factor(&mapped_model)
             );
 
             // ===== Layer 1: Tool Message Trimming (L1 threshold) =====
@@ -574,8 +737,7 @@ pub async fn handle_messages(
                     compression_applied = true;
                     
                     // Re-estimate after trimming (with calibration)
-                    let new_raw = ContextManager::estimate_token_usage(&request_with_mapped);
-                    let new_usage = calibrator.calibrate(new_raw);
+                    let new_usage = ContextManager::estimate_token_usage(&request_with_mapped, &mapped_model);
                     let new_ratio = new_usage as f32 / context_limit as f32;
                     
                     info!(
@@ -613,8 +775,7 @@ pub async fn handle_messages(
                     is_purified = true; // Still breaks cache, but preserves signatures
                     compression_applied = true;
                     
-                    let new_raw = ContextManager::estimate_token_usage(&request_with_mapped);
-                    let new_usage = calibrator.calibrate(new_raw);
+                    let new_usage = ContextManager::estimate_token_usage(&request_with_mapped, &mapped_model);
                     let new_ratio = new_usage as f32 / context_limit as f32;
                     
                     info!(
@@ -651,8 +812,7 @@ pub async fn handle_messages(
                         is_purified = false; // Fork doesn't break cache!
                         
                         // Re-estimate after fork (with calibration)
-                        let new_raw = ContextManager::estimate_token_usage(&request_with_mapped);
-                        let new_usage = calibrator.calibrate(new_raw);
+                        let new_usage = ContextManager::estimate_token_usage(&request_with_mapped, &mapped_model);
                         let new_ratio = new_usage as f32 / context_limit as f32;
                         
                         info!(
@@ -685,8 +845,9 @@ pub async fn handle_messages(
 
         // [FIX] Estimate AFTER purification to get accurate token count for calibrator learning
         // Only estimate for calibrator when content was not purified, to avoid skewed learning
+        // (raw input count only: the calibrator learns the residual against upstream promptTokenCount)
         let raw_estimated = if !is_purified {
-            ContextManager::estimate_token_usage(&request_with_mapped)
+            crate::proxy::tokenizer::count_claude_request(&request_with_mapped, &mapped_model).total()
         } else {
             0 // Don't record calibration data when content was purified
         };
@@ -753,9 +914,10 @@ pub async fn handle_messages(
             tracing::debug!("[{}] Added Beta Header: interleaved-thinking-2025-05-14", trace_id);
         }
 
-        // 5. 上游调用
+        // 5. 上游调用 (账号独立代理由代理池选择客户端)
+        let account_proxy = token_manager.get_individual_proxy(&account_id);
         let response = tokio::select! {
-            res = upstream.call_v1_internal_with_headers(method, &access_token, gemini_body, query, extra_headers.clone()) => match res {
+            res = upstream.call_v1_internal_with_headers(method, &access_token, gemini_body, query, extra_headers.clone(), account_proxy.as_deref()) => match res {
                 Ok(r) => r,
                 Err(e) => {
                     last_error = e.clone();
@@ -799,6 +961,7 @@ pub async fn handle_messages(
                     "request_type": config.request_type,
                     "attempt": attempt,
                     "status": status.as_u16(),
+                    "request": debug_logger::is_enabled(&debug_cfg).then(|| original_body.clone()),
                 });
                 let gemini_stream = debug_logger::wrap_reqwest_stream_with_debug(
                     Box::pin(response.bytes_stream()),
@@ -821,7 +984,9 @@ pub async fn handle_messages(
                     scaling_enabled,
                     context_limit,
                     Some(raw_estimated), // [FIX] Pass estimated tokens for calibrator learning
+                    Some(request_with_mapped.model.clone()), // [NEW] 校准器按映射后的模型学习
                     current_message_count, // [NEW v4.0.0] Pass message count for rewind detection
+                    tool_schemas.clone(),
                 );
 
                 let mut first_data_chunk = None;
@@ -926,6 +1091,40 @@ pub async fn handle_messages(
                             match collect_stream_to_json(combined_stream).await {
                                 Ok(full_response) => {
                                     info!("[{}] ✓ Stream collected and converted to JSON", trace_id);
+
+                                    // [NEW] Structured outputs: 按原始 Schema 校验，失败时自动修复重试一次
+                                    if let Some(schema) = &response_schema {
+                                        if let Err(e) = validate_structured_output(&full_response, schema) {
+                                            if !schema_repair_attempted {
+                                                tracing::warn!(
+                                                    "[{}] Structured output validation failed, retrying with repair prompt: {}",
+                                                    trace_id, e
+                                                );
+                                                schema_repair_attempted = true;
+                                                repair_account = Some(email.clone());
+                                                append_schema_repair_messages(&mut request_for_body, &full_response, &e);
+                                                last_error = format!("Structured output validation failed: {}", e);
+                                                continue;
+                                            }
+                                            error!("[{}] Structured output still invalid after repair: {}", trace_id, e);
+                                            return (
+                                                StatusCode::BAD_GATEWAY,
+                                                [
+                                                    ("X-Account-Email", email.as_str()),
+                                                    ("X-Mapped-Model", request_with_mapped.model.as_str()),
+                                                ],
+                                                Json(json!({
+                                                    "type": "error",
+                                                    "error": {
+                                                        "type": "api_error",
+                                                        "message": format!("Model output does not match output_config.format json_schema: {}", e)
+                                                    }
+                                                })),
+                                            )
+                                                .into_response();
+                                        }
+                                    }
+
                                     return Response::builder()
                                         .status(StatusCode::OK)
                                         .header(header::CONTENT_TYPE, "application/json")
@@ -1011,6 +1210,7 @@ pub async fn handle_messages(
                     s_id_owned,
                     request_with_mapped.model.clone(),
                     request_with_mapped.messages.len(), // [NEW v4.0.0] Pass message count for rewind detection
+                    tool_schemas.clone(),
                 ) {
                     Ok(r) => r,
                     Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("Transform error: {}", e)).into_response(),
@@ -1359,7 +1559,7 @@ pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoRespo
     }))
 }
 
-/// 计算 tokens (占位符)
+/// 计算 tokens (离线分词器 + 按模型校准)
 pub async fn handle_count_tokens(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -1380,11 +1580,36 @@ pub async fn handle_count_tokens(
         .await;
     }
 
-    Json(json!({
-        "input_tokens": 0,
-        "output_tokens": 0
-    }))
-    .into_response()
+    let request: ClaudeRequest = match serde_json::from_value(body) {
+        Ok(r) => r,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "type": "error",
+                    "error": {
+                        "type": "invalid_request_error",
+                        "message": format!("Invalid request body: {}", e)
+                    }
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    // 按映射后的上游模型计数，与实际请求使用同一套词表和校准残差
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &request.model,
+        &*state.custom_mapping.read().await,
+    );
+    let count = crate::proxy::tokenizer::count_claude_request(&request, &mapped_model);
+    let input_tokens = get_calibrator().calibrate(&mapped_model, count.total());
+    debug!(
+        "[count_tokens] {} -> {}: {} tokens (raw {:?})",
+        request.model, mapped_model, input_tokens, count
+    );
+
+    Json(json!({ "input_tokens": input_tokens })).into_response()
 }
 
 // 移除已失效的简单单元测试，后续将补全完整的集成测试
@@ -1465,6 +1690,50 @@ const PROBE_KEYWORDS: &[&str] = &[
     "test connection",
 ];
 
+/// 提取响应中的文本内容 (不含 thinking)
+fn response_text(response: &crate::proxy::mappers::claude::models::ClaudeResponse) -> String {
+    use crate::proxy::mappers::claude::models::ContentBlock;
+    response
+        .content
+        .iter()
+        .filter_map(|b| match b {
+            ContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 校验响应是否符合 output_config.format 的 json_schema
+fn validate_structured_output(
+    response: &crate::proxy::mappers::claude::models::ClaudeResponse,
+    schema: &Value,
+) -> Result<(), String> {
+    // 调用工具时没有 JSON 正文，不做校验
+    if response.stop_reason == "tool_use" {
+        return Ok(());
+    }
+    crate::proxy::common::structured_output::validate_output(&response_text(response), schema)
+        .map(|_| ())
+}
+
+/// 将无效输出与修复提示词追加到对话中，供下一次尝试使用
+fn append_schema_repair_messages(
+    request: &mut ClaudeRequest,
+    response: &crate::proxy::mappers::claude::models::ClaudeResponse,
+    error: &str,
+) {
+    request.messages.push(Message {
+        role: "assistant".to_string(),
+        content: MessageContent::String(response_text(response)),
+    });
+    request.messages.push(Message {
+        role: "user".to_string(),
+        content: MessageContent::String(
+            crate::proxy::common::structured_output::repair_prompt(error),
+        ),
+    });
+}
+
 /// 检测后台任务并返回任务类型
 fn detect_background_task_type(request: &ClaudeRequest) -> Option<BackgroundTaskType> {
     let last_user_msg = extract_last_user_message_for_detection(request)?;
@@ -1,8 +1,10 @@
 use tokio::time::{sleep, Duration};
 use tracing::{debug, info};
-use axum::{http::StatusCode, response::{IntoResponse, Response}, Json, extract::State};
+use axum::{http::StatusCode, response::{IntoResponse, Response}, Json, extract::{Extension, State}};
 use serde_json::{json, Value};
 use crate::proxy::server::AppState;
+use crate::proxy::middleware::auth::ClientKeyIdentity;
+use crate::proxy::model_router::{self, RequestFeatures};
 
 // ===== 统一重试与退避策略 =====
 
@@ -143,8 +145,13 @@ pub fn should_rotate_account(status_code: u16) -> bool {
 
 /// Detects model capabilities and configuration
 /// POST /v1/models/detect
+///
+/// 同时作为路由规则的 dry-run: body 可以是完整的请求 (按 `protocol` 字段解析，默认
+/// 有 contents 时为 gemini，否则为 openai)，`features` 可覆盖提取出的请求特征，
+/// `client_key` 可模拟其他客户端 Key (默认使用本次请求的 Key)。
 pub async fn handle_detect_model(
     State(state): State<AppState>,
+    client_key: Option<Extension<ClientKeyIdentity>>,
     Json(body): Json<Value>,
 ) -> Response {
     let model_name = body.get("model").and_then(|v| v.as_str()).unwrap_or("");
@@ -153,11 +160,36 @@ pub async fn handle_detect_model(
         return (StatusCode::BAD_REQUEST, "Missing 'model' field").into_response();
     }
 
-    // 1. Resolve mapping
-    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
-        model_name,
-        &*state.custom_mapping.read().await,
-    );
+    // 1. Resolve mapping (路由规则 > 自定义映射 > 系统默认映射)
+    let protocol = body
+        .get("protocol")
+        .and_then(|v| v.as_str())
+        .unwrap_or(if body.get("contents").is_some() { "gemini" } else { "openai" });
+    let mut features = RequestFeatures::from_body(protocol, model_name, &body, true)
+        .with_client_key(client_key.as_ref().map(|Extension(key)| key));
+    if let Some(key) = body.get("client_key").and_then(|v| v.as_str()) {
+        features.client_key_id = Some(key.to_string());
+        features.client_key_name = Some(key.to_string());
+    }
+    if let Some(overrides) = body.get("features").filter(|v| v.is_object()) {
+        let mut merged = serde_json::to_value(&features).unwrap_or_else(|_| json!({}));
+        if let (Some(base), Some(extra)) = (merged.as_object_mut(), overrides.as_object()) {
+            base.extend(extra.clone());
+        }
+        match serde_json::from_value(merged) {
+            Ok(f) => features = f,
+            Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid 'features': {}", e)).into_response(),
+        }
+    }
+
+    let (decision, trace) = model_router::router().explain(model_name, &features);
+    let mapped_model = match &decision {
+        Some(d) => d.target.clone(),
+        None => crate::proxy::common::model_mapping::resolve_model_route(
+            model_name,
+            &*state.custom_mapping.read().await,
+        ),
+    };
 
     // 2. Resolve capabilities
     let config = crate::proxy::mappers::common_utils::resolve_request_config(
@@ -176,6 +208,11 @@ pub async fn handle_detect_model(
         "features": {
             "has_web_search": config.inject_google_search,
             "is_image_gen": config.request_type == "image_gen"
+        },
+        "router": {
+            "matched_rule": decision,
+            "request_features": features,
+            "trace": trace
         }
     });
 
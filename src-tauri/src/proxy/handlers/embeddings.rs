@@ -0,0 +1,236 @@
+// Embeddings Handler
+// OpenAI /v1/embeddings 与 Gemini 原生 :embedContent / :batchEmbedContents 共用同一条上游链路
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use crate::proxy::handlers::common::should_rotate_account;
+use crate::proxy::mappers::openai::embeddings::{
+    build_embed_requests, build_openai_response, extract_vectors, resolve_embedding_model,
+    EmbeddingsRequest, MAX_BATCH_SIZE,
+};
+use crate::proxy::server::AppState;
+
+const MAX_RETRY_ATTEMPTS: usize = 3;
+
+/// 通过 TokenManager 轮换账号调用 v1internal batchEmbedContents
+///
+/// 超过 MAX_BATCH_SIZE 的输入按批拆分，按原顺序拼接返回的 embeddings。
+/// 返回 (embeddings, 最后使用的账号 email)。
+async fn call_batch_embed(
+    state: &AppState,
+    mapped_model: &str,
+    requests: Vec<Value>,
+) -> Result<(Vec<Value>, String), (StatusCode, String)> {
+    let token_manager = &state.token_manager;
+    let max_attempts = MAX_RETRY_ATTEMPTS.min(token_manager.len()).max(1);
+    let mut embeddings = Vec::with_capacity(requests.len());
+    let mut last_email = String::new();
+
+    for chunk in requests.chunks(MAX_BATCH_SIZE) {
+        let mut failed_accounts = std::collections::HashSet::new();
+        let mut last_error = String::new();
+        let mut done = false;
+
+        for attempt in 0..max_attempts {
+            let (access_token, project_id, email, account_id, _wait_ms) = token_manager
+                .get_token("text", attempt > 0, None, mapped_model, Some(&failed_accounts))
+                .await
+                .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, format!("Token error: {}", e)))?;
+            last_email = email.clone();
+            info!("✓ Using account: {} (embeddings, {} inputs)", email, chunk.len());
+
+            let wrapped_body = json!({
+                "project": project_id,
+                "requestId": format!("embed-{}", uuid::Uuid::new_v4()),
+                "request": { "requests": chunk },
+                "model": mapped_model,
+                "userAgent": "antigravity",
+                "requestType": "text"
+            });
+
+            let response = match state
+                .upstream
+                .call_v1_internal(
+                    "batchEmbedContents",
+                    &access_token,
+                    wrapped_body,
+                    None,
+                    token_manager.get_individual_proxy(&account_id).as_deref(),
+                )
+                .await
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    debug!("Embeddings request failed on attempt {}/{}: {}", attempt + 1, max_attempts, e);
+                    last_error = e;
+                    continue;
+                }
+            };
+
+            let status = response.status();
+            if status.is_success() {
+                let body: Value = response
+                    .json()
+                    .await
+                    .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
+                let inner = body.get("response").unwrap_or(&body);
+                let chunk_embeddings = inner
+                    .get("embeddings")
+                    .and_then(|v| v.as_array())
+                    .filter(|v| v.len() == chunk.len())
+                    .ok_or_else(|| {
+                        (
+                            StatusCode::BAD_GATEWAY,
+                            "Upstream returned an unexpected number of embeddings".to_string(),
+                        )
+                    })?;
+                embeddings.extend(chunk_embeddings.iter().cloned());
+                done = true;
+                break;
+            }
+
+            let status_code = status.as_u16();
+            let retry_after = response
+                .headers()
+                .get("Retry-After")
+                .and_then(|h| h.to_str().ok())
+                .map(|s| s.to_string());
+            let error_text = response.text().await.unwrap_or_else(|_| format!("HTTP {}", status_code));
+            last_error = format!("HTTP {}: {}", status_code, error_text);
+
+            if matches!(status_code, 429 | 500 | 503 | 529) {
+                token_manager
+                    .mark_rate_limited_async(
+                        &account_id,
+                        &email,
+                        status_code,
+                        retry_after.as_deref(),
+                        &error_text,
+                        Some(mapped_model),
+                    )
+                    .await;
+                failed_accounts.insert(account_id.clone());
+            }
+
+            if should_rotate_account(status_code) && attempt + 1 < max_attempts {
+                warn!("[Embeddings] Upstream {} on {}, rotating account", status_code, email);
+                continue;
+            }
+
+            return Err((status, error_text));
+        }
+
+        if !done {
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                format!("All accounts exhausted. Last error: {}", last_error),
+            ));
+        }
+    }
+
+    Ok((embeddings, last_email))
+}
+
+/// 估算 Gemini 嵌入请求的输入 token (上游不返回 usage)
+fn estimate_request_tokens(model: &str, requests: &[Value]) -> u32 {
+    let tokenizer = crate::proxy::tokenizer::for_model(model);
+    requests
+        .iter()
+        .filter_map(|r| r.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array()))
+        .flatten()
+        .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+        .map(|t| tokenizer.count_text(t))
+        .sum()
+}
+
+/// 处理 OpenAI /v1/embeddings
+pub async fn handle_embeddings(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> Result<Response, (StatusCode, String)> {
+    let request: EmbeddingsRequest = serde_json::from_value(body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid embeddings request: {}", e)))?;
+    let texts = request.texts().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let mapped_model = resolve_embedding_model(&request.model, &*state.custom_mapping.read().await);
+    info!(
+        "Received embeddings request: {} -> {} ({} inputs)",
+        request.model,
+        mapped_model,
+        texts.len()
+    );
+
+    let requests = build_embed_requests(&texts, &mapped_model, request.dimensions);
+    let prompt_tokens = estimate_request_tokens(&mapped_model, &requests);
+    let (embeddings, email) = call_batch_embed(&state, &mapped_model, requests).await?;
+
+    let vectors = extract_vectors(&json!({ "embeddings": embeddings }))
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+    let response = build_openai_response(&vectors, &request.model, request.wants_base64(), prompt_tokens);
+
+    Ok((
+        StatusCode::OK,
+        [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())],
+        Json(response),
+    )
+        .into_response())
+}
+
+/// 处理 Gemini 原生 :embedContent / :batchEmbedContents
+pub async fn handle_gemini_embed(
+    state: AppState,
+    model_name: &str,
+    method: &str,
+    body: Value,
+) -> Result<Response, (StatusCode, String)> {
+    let mapped_model = resolve_embedding_model(model_name, &*state.custom_mapping.read().await);
+    let model_ref = format!("models/{}", mapped_model);
+
+    // 统一成 batch 请求，每条都指向映射后的模型
+    let single = method == "embedContent";
+    let mut requests = if single {
+        vec![body]
+    } else {
+        body.get("requests")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .filter(|v| !v.is_empty())
+            .ok_or((StatusCode::BAD_REQUEST, "requests must be a non-empty array".to_string()))?
+    };
+    for req in requests.iter_mut() {
+        if req.get("content").is_none() {
+            return Err((StatusCode::BAD_REQUEST, "Each request needs content".to_string()));
+        }
+        req["model"] = json!(model_ref);
+    }
+
+    info!(
+        "Received Gemini embed request: {}:{} -> {} ({} inputs)",
+        model_name,
+        method,
+        mapped_model,
+        requests.len()
+    );
+
+    let prompt_tokens = estimate_request_tokens(&mapped_model, &requests);
+    let (mut embeddings, email) = call_batch_embed(&state, &mapped_model, requests).await?;
+
+    let usage = json!({ "promptTokenCount": prompt_tokens, "totalTokenCount": prompt_tokens });
+    let response = if single {
+        json!({ "embedding": embeddings.remove(0), "usageMetadata": usage })
+    } else {
+        json!({ "embeddings": embeddings, "usageMetadata": usage })
+    };
+
+    Ok((
+        StatusCode::OK,
+        [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())],
+        Json(response),
+    )
+        .into_response())
+}
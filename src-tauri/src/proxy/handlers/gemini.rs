@@ -1,34 +1,98 @@
 // Gemini Handler
-use axum::{extract::State, extract::{Json, Path}, http::StatusCode, response::IntoResponse};
+use axum::{extract::State, extract::{Extension, Json, Path}, http::StatusCode, response::{IntoResponse, Response}};
 use serde_json::{json, Value};
 use tracing::{debug, error, info};
 
-use crate::proxy::mappers::gemini::{wrap_request, unwrap_response};
+use crate::proxy::mappers::gemini::{wrap_request, unwrap_response, build_tool_schemas};
+use crate::proxy::mappers::estimation_calibrator;
+use crate::proxy::common::json_schema::fix_function_calls_in_response;
 use crate::proxy::server::AppState;
+use crate::proxy::model_router::{self, RequestFeatures};
+use crate::proxy::model_fallback::{self, FallbackApplied};
+use crate::proxy::response_cache::{self, CacheProtocol};
+use crate::proxy::middleware::auth::ClientKeyIdentity;
 use crate::proxy::session_manager::SessionManager;
 use crate::proxy::handlers::common::{determine_retry_strategy, apply_retry_strategy, should_rotate_account};
 use crate::proxy::debug_logger;
  
 const MAX_RETRY_ATTEMPTS: usize = 3;
  
+/// 解析路径中的 model:method
+fn split_model_action(model_action: &str) -> (String, String) {
+    match model_action.rsplit_once(':') {
+        Some((m, action)) => (m.to_string(), action.to_string()),
+        None => (model_action.to_string(), "generateContent".to_string()),
+    }
+}
+
 /// 处理 generateContent 和 streamGenerateContent
 /// 路径参数: model_name, method (e.g. "gemini-pro", "generateContent")
 pub async fn handle_generate(
     State(state): State<AppState>,
     Path(model_action): Path<String>,
-    Json(mut body): Json<Value>  // 改为 mut 以支持修复提示词注入
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // 解析 model:method
-    let (model_name, method) = if let Some((m, action)) = model_action.rsplit_once(':') {
-        (m.to_string(), action.to_string())
+    client_key: Option<Extension<ClientKeyIdentity>>,
+    Json(body): Json<Value>,
+) -> Result<Response, (StatusCode, String)> {
+    let client_key = client_key.map(|Extension(key)| key);
+
+    // [NEW] 响应缓存: 仅 generationConfig.temperature = 0 的确定性请求
+    let (model_name, method) = split_model_action(&model_action);
+    let cache_ctx = if method == "generateContent" || method == "streamGenerateContent" {
+        response_cache::cache().prepare(
+            CacheProtocol::Gemini,
+            client_key.as_ref().map(|k| k.id.as_str()),
+            &model_name,
+            &body,
+            method == "streamGenerateContent",
+            false,
+        )
     } else {
-        (model_action, "generateContent".to_string())
+        None
     };
+    if let Some(ctx) = &cache_ctx {
+        if let Some(hit) = ctx.lookup().await {
+            return Ok(hit);
+        }
+    }
+
+    // [NEW] 模型降级链: 处理流程记录实际发生的降级，在此统一写入响应头
+    let mut fallback: Option<FallbackApplied> = None;
+    let mut response = handle_generate_inner(state, model_action, client_key, body, &mut fallback)
+        .await?
+        .into_response();
+    if let Some(applied) = fallback {
+        applied.apply_headers(&mut response);
+    }
+    Ok(match cache_ctx {
+        Some(ctx) => ctx.store(response).await,
+        None => response,
+    })
+}
+
+async fn handle_generate_inner(
+    state: AppState,
+    model_action: String,
+    client_key: Option<ClientKeyIdentity>,
+    mut body: Value, // 改为 mut 以支持修复提示词注入
+    fallback: &mut Option<FallbackApplied>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    // 解析 model:method
+    let (model_name, method) = split_model_action(&model_action);
 
     crate::modules::logger::log_info(&format!("Received Gemini request: {}/{}", model_name, method));
     let trace_id = format!("req_{}", chrono::Utc::now().timestamp_subsec_millis());
     let debug_cfg = state.debug_logging.read().await.clone();
 
+    // [NEW] 嵌入方法走独立的 batchEmbedContents 链路
+    if method == "embedContent" || method == "batchEmbedContents" {
+        return crate::proxy::handlers::embeddings::handle_gemini_embed(state, &model_name, &method, body).await;
+    }
+
+    // [NEW] models/{model}:countTokens 与 /countTokens 路由共用离线计数
+    if method == "countTokens" {
+        return Ok(Json(count_tokens(&state, &model_name, &body).await).into_response());
+    }
+
     // 1. 验证方法
     if method != "generateContent" && method != "streamGenerateContent" {
         return Err((StatusCode::BAD_REQUEST, format!("Unsupported method: {}", method)));
@@ -64,13 +128,36 @@ pub async fn handle_generate(
     
     // [FIX] 严格排除列表
     let mut failed_accounts = std::collections::HashSet::new();
+    // [NEW] 原始工具 Schema，响应中的 functionCall 参数据此修正类型
+    let tool_schemas = std::sync::Arc::new(build_tool_schemas(&body));
+    // [NEW] 路由规则匹配所需的请求特征 (只有规则用到 token 条件时才预估 token)
+    let route_features = RequestFeatures::from_body(
+        "gemini",
+        &model_name,
+        &body,
+        model_router::router().needs_prompt_tokens(),
+    )
+    .with_client_key(client_key.as_ref());
 
     for attempt in 0..max_attempts {
-        // 3. 模型路由解析
-        let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        // 3. 模型路由解析 (路由规则 > 自定义映射 > 系统默认映射)
+        let primary_model = model_router::resolve_model(
             &model_name,
             &*state.custom_mapping.read().await,
+            &route_features,
         );
+        // [NEW] 模型降级链: 按已失败的账号重新判断 (Gemini 原生协议只在 Antigravity 账号池内降级)
+        *fallback = model_fallback::select_gemini_fallback(
+            &token_manager,
+            &model_name,
+            &primary_model,
+            &failed_accounts,
+        )
+        .await;
+        let mapped_model = fallback
+            .as_ref()
+            .map(|applied| applied.to.clone())
+            .unwrap_or(primary_model);
         // 提取 tools 列表以进行联网探测 (Gemini 风格可能是嵌套的)
         let tools_val: Option<Vec<Value>> = body.get("tools").and_then(|t| t.as_array()).map(|arr| {
             let mut flattened = Vec::new();
@@ -150,12 +237,21 @@ pub async fn handle_generate(
             debug_logger::write_debug_payload(&debug_cfg, Some(&trace_id), "v1internal_request", &payload).await;
         }
 
+        // 离线估算实际发往上游的请求，用于校准器学习
+        let calibration_estimate = crate::proxy::tokenizer::count_gemini_request(&wrapped_body, &mapped_model).total();
+
         // 5. 上游调用
         let query_string = if is_stream { Some("alt=sse") } else { None };
         let upstream_method = if is_stream { "streamGenerateContent" } else { "generateContent" };
 
         let response = match upstream
-            .call_v1_internal(upstream_method, &access_token, wrapped_body, query_string)
+            .call_v1_internal(
+                upstream_method,
+                &access_token,
+                wrapped_body,
+                query_string,
+                token_manager.get_individual_proxy(&_account_id).as_deref(),
+            )
             .await {
                 Ok(r) => r,
                 Err(e) => {
@@ -182,9 +278,15 @@ pub async fn handle_generate(
                     "request_type": config.request_type,
                     "attempt": attempt,
                     "status": status.as_u16(),
+                    "method": method,
+                    "request": debug_logger::is_enabled(&debug_cfg).then(|| body.clone()),
                 });
                 let mut response_stream = debug_logger::wrap_reqwest_stream_with_debug(
-                    Box::pin(response.bytes_stream()),
+                    estimation_calibrator::record_from_stream(
+                        Box::pin(response.bytes_stream()),
+                        mapped_model.clone(),
+                        calibration_estimate,
+                    ),
                     debug_cfg.clone(),
                     trace_id.clone(),
                     "upstream_response",
@@ -228,6 +330,7 @@ pub async fn handle_generate(
                 }
 
                 let s_id_for_stream = s_id.clone();
+                let tool_schemas_for_stream = tool_schemas.clone();
                 let stream = async_stream::stream! {
                     let mut first_data = first_chunk;
                     loop {
@@ -264,6 +367,8 @@ pub async fn handle_generate(
                                     
                                     match serde_json::from_str::<Value>(json_part) {
                                         Ok(mut json) => {
+                                            fix_function_calls_in_response(&mut json, &tool_schemas_for_stream);
+
                                             // [FIX #765] Extract thoughtSignature from stream
                                             let inner_val = if json.get("response").is_some() {
                                                 json.get("response")
@@ -347,6 +452,10 @@ pub async fn handle_generate(
                 .await
                 .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
 
+            if let Some(actual) = estimation_calibrator::prompt_token_count(&gemini_resp) {
+                estimation_calibrator::get_calibrator().record(&mapped_model, calibration_estimate, actual);
+            }
+
             // [FIX #765] Extract thoughtSignature from non-streaming response
             let inner_val = if gemini_resp.get("response").is_some() {
                 gemini_resp.get("response")
@@ -370,7 +479,8 @@ pub async fn handle_generate(
                 }
             }
 
-            let unwrapped = unwrap_response(&gemini_resp);
+            let mut unwrapped = unwrap_response(&gemini_resp);
+            fix_function_calls_in_response(&mut unwrapped, &tool_schemas);
             return Ok((StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())], Json(unwrapped)).into_response());
         }
 
@@ -509,10 +619,19 @@ pub async fn handle_get_model(Path(model_name): Path<String>) -> impl IntoRespon
     }))
 }
 
-pub async fn handle_count_tokens(State(state): State<AppState>, Path(_model_name): Path<String>, Json(_body): Json<Value>) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let model_group = "gemini";
-    let (_access_token, _project_id, _, _acc_id, _wait_ms) = state.token_manager.get_token(model_group, false, None, "gemini", None).await
-        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, format!("Token error: {}", e)))?;
-    
-    Ok(Json(json!({"totalTokens": 0})))
+pub async fn handle_count_tokens(State(state): State<AppState>, Path(model_name): Path<String>, Json(body): Json<Value>) -> Result<impl IntoResponse, (StatusCode, String)> {
+    Ok(Json(count_tokens(&state, &model_name, &body).await))
+}
+
+/// 离线计数 (分词器 + 按模型校准)，不占用账号配额
+async fn count_tokens(state: &AppState, model_name: &str, body: &Value) -> Value {
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        model_name,
+        &*state.custom_mapping.read().await,
+    );
+    let count = crate::proxy::tokenizer::count_gemini_request(body, &mapped_model);
+    let total = estimation_calibrator::get_calibrator()
+        .calibrate(&mapped_model, count.total());
+    debug!("[countTokens] {} -> {}: {} tokens (raw {:?})", model_name, mapped_model, total, count);
+    json!({ "totalTokens": total })
 }
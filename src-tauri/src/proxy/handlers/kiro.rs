@@ -8,7 +8,6 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use bytes::Bytes;
 use serde_json::json;
 use tracing::{debug, error, info};
 
@@ -17,14 +16,15 @@ use crate::proxy::{
     mappers::{
         claude::models::ClaudeRequest,
         kiro::{
-            convert_claude_to_kiro, convert_event_to_claude_chunk, convert_kiro_to_claude,
-            parse_event_stream, collect_stream_to_text,
+            build_content_blocks, collect_stream, convert_claude_to_kiro, convert_kiro_to_claude,
+            uses_native_tools, KiroSseConverter,
         },
     },
     server::AppState,
 };
 
 const MAX_RETRY_ATTEMPTS: usize = 3;
+const KIRO_TIMEOUT_SECS: u64 = 600;
 const KIRO_API_ENDPOINT: &str = "https://q.us-east-1.amazonaws.com/generateAssistantResponse";
 const MACHINE_ID: &str = "82f653a1e60a7c25cf6152578375f4e011db8e8015d712b91f2cf3ec8e9b8ed8";
 
@@ -56,6 +56,8 @@ pub async fn handle_kiro_messages(
     
     // Определяем streaming mode
     let is_stream = claude_req.stream;
+    // С клиентскими tools Kiro отвечает нативными toolUseEvent, XML команды разбирать не нужно
+    let native_tools = uses_native_tools(&claude_req);
     
     for attempt in 0..max_attempts {
         let force_rotate = attempt > 0;
@@ -108,28 +110,20 @@ pub async fn handle_kiro_messages(
         // Сохраняем conversation_id для следующих попыток
         conversation_id = Some(kiro_req.conversation_state.conversation_id.clone());
         
-        // Получаем individual proxy если настроен
+        // Получаем individual proxy если настроен (клиент из общего пула, с fallback)
         let individual_proxy = token_manager.get_individual_proxy(&account_id);
-        
-        // Строим HTTP client с proxy
-        let client = if let Some(proxy_url) = individual_proxy {
-            debug!("Using individual proxy for account {}: {}", email, proxy_url);
-            match reqwest::Proxy::all(&proxy_url) {
-                Ok(proxy) => match reqwest::Client::builder().proxy(proxy).build() {
-                    Ok(c) => c,
-                    Err(e) => {
-                        error!("Failed to build client with proxy {}: {}", proxy_url, e);
-                        reqwest::Client::new()
-                    }
-                },
-                Err(e) => {
-                    error!("Invalid proxy URL {}: {}", proxy_url, e);
-                    reqwest::Client::new()
-                }
+        let routed = match crate::proxy::upstream::proxy_pool::pool()
+            .client_for(individual_proxy.as_deref(), KIRO_TIMEOUT_SECS)
+        {
+            Ok(r) => r,
+            Err(e) => {
+                last_error = e;
+                error!("{}", last_error);
+                failed_accounts.insert(account_id);
+                continue;
             }
-        } else {
-            reqwest::Client::new()
         };
+        let client = routed.client.clone().unwrap_or_else(reqwest::Client::new);
         
         // Генерируем invocation_id
         let invocation_id = uuid::Uuid::new_v4().to_string();
@@ -166,13 +160,14 @@ pub async fn handle_kiro_messages(
         );
         
         // Отправляем запрос
-        let response = match client
+        let response = client
             .post(KIRO_API_ENDPOINT)
             .headers(headers)
             .json(&kiro_req)
             .send()
-            .await
-        {
+            .await;
+        routed.observe(&response);
+        let response = match response {
             Ok(r) => r,
             Err(e) => {
                 last_error = format!("Kiro request failed: {}", e);
@@ -187,15 +182,22 @@ pub async fn handle_kiro_messages(
         if status.is_success() {
             info!("Kiro request successful: {}", email);
             
-            // [AUTO-CONVERSION] Всегда собираем stream и парсим команды
-            // Это нужно для правильной конвертации команд в tool_use blocks
-            if is_stream {
-                // Client wants streaming - собираем stream, парсим команды, и отправляем как streaming
+            if is_stream && native_tools {
+                // Нативные tools - конвертируем Event Stream в Claude SSE на лету
+                return Ok(handle_native_streaming(response, claude_req.model.clone(), email));
+            } else if is_stream {
+                // [AUTO-CONVERSION] Без tools собираем stream и парсим XML команды,
+                // это нужно для правильной конвертации команд в tool_use blocks
                 return handle_streaming_with_commands(response, claude_req.model.clone(), email).await;
             } else {
                 // Non-streaming mode - собираем stream и конвертируем в Claude JSON
-                return handle_non_streaming_response(response, claude_req.model.clone(), email)
-                    .await;
+                return handle_non_streaming_response(
+                    response,
+                    claude_req.model.clone(),
+                    email,
+                    !native_tools,
+                )
+                .await;
             }
         } else {
             let status_code = status.as_u16();
@@ -255,6 +257,52 @@ pub async fn handle_kiro_messages(
     ))
 }
 
+/// Обрабатывает streaming response с нативными tools: каждый chunk сразу конвертируется в Claude SSE
+fn handle_native_streaming(response: reqwest::Response, model: String, email: String) -> Response {
+    use futures::StreamExt;
+    
+    let mut upstream = response.bytes_stream();
+    let mut converter = KiroSseConverter::new(model.clone());
+    
+    let stream = async_stream::stream! {
+        while let Some(chunk) = upstream.next().await {
+            let events = match chunk {
+                Ok(chunk) => converter.feed(&chunk),
+                Err(e) => Err(e.to_string()),
+            };
+            match events {
+                Ok(events) => {
+                    for event in events {
+                        yield Ok::<_, std::io::Error>(event);
+                    }
+                }
+                Err(e) => {
+                    error!("Kiro stream error: {}", e);
+                    let error_event = json!({
+                        "type": "error",
+                        "error": { "type": "api_error", "message": e }
+                    });
+                    yield Ok(bytes::Bytes::from(format!("event: error\ndata: {}\n\n", error_event)));
+                    return;
+                }
+            }
+        }
+        for event in converter.finish() {
+            yield Ok(event);
+        }
+    };
+    
+    Response::builder()
+        .header("Content-Type", "text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .header("Connection", "keep-alive")
+        .header("X-Accel-Buffering", "no")
+        .header("X-Account-Email", &email)
+        .header("X-Mapped-Model", &model)
+        .body(Body::from_stream(stream))
+        .unwrap()
+}
+
 /// Обрабатывает streaming response с парсингом команд
 /// Собирает весь stream, парсит команды и отправляет правильные SSE события
 async fn handle_streaming_with_commands(
@@ -266,9 +314,9 @@ async fn handle_streaming_with_commands(
     
     let stream = response.bytes_stream().map(|r| r.map_err(|e| e.to_string()));
     
-    // Собираем весь stream в текст
-    let full_text = match collect_stream_to_text(stream).await {
-        Ok(text) => text,
+    // Собираем весь stream
+    let collected = match collect_stream(stream).await {
+        Ok(collected) => collected,
         Err(e) => {
             error!("Failed to collect stream: {}", e);
             return Err((
@@ -279,8 +327,7 @@ async fn handle_streaming_with_commands(
     };
     
     // Парсим команды из текста
-    use crate::proxy::mappers::kiro::command_parser::parse_commands_from_text;
-    let content_blocks = parse_commands_from_text(&full_text);
+    let content_blocks = build_content_blocks(collected, true);
     
     // Генерируем ID для сообщения
     let message_id = format!("msg_{}", uuid::Uuid::new_v4().simple());
@@ -432,14 +479,15 @@ async fn handle_non_streaming_response(
     response: reqwest::Response,
     model: String,
     email: String,
+    parse_commands: bool,
 ) -> Result<Response, (StatusCode, String)> {
     use futures::StreamExt;
     
     let stream = response.bytes_stream().map(|r| r.map_err(|e| e.to_string()));
     
-    // Собираем весь stream в текст
-    let full_text = match collect_stream_to_text(stream).await {
-        Ok(text) => text,
+    // Собираем весь stream
+    let collected = match collect_stream(stream).await {
+        Ok(collected) => collected,
         Err(e) => {
             error!("Failed to collect stream: {}", e);
             return Err((
@@ -450,7 +498,7 @@ async fn handle_non_streaming_response(
     };
     
     // Конвертируем в Claude формат
-    let claude_response = convert_kiro_to_claude(full_text, model.clone(), None);
+    let claude_response = convert_kiro_to_claude(collected, model.clone(), None, parse_commands);
     
     Ok(Response::builder()
         .status(StatusCode::OK)
@@ -1,7 +1,7 @@
 use axum::{
     body::{to_bytes, Body},
-    extract::State,
-    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
     response::{IntoResponse, Response},
 };
 use bytes::Bytes;
@@ -10,6 +10,9 @@ use serde_json::{json, Value};
 use tokio::time::Duration;
 use tokio_stream::wrappers::IntervalStream;
 
+use crate::proxy::mcp_gateway::{
+    McpServerConfig, McpTransport, SessionUpstream, StdioProcess, MAX_STDIO_SESSIONS_PER_SERVER,
+};
 use crate::proxy::server::AppState;
 
 fn build_client(
@@ -395,3 +398,420 @@ pub async fn handle_zai_mcp_server(
         _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
     }
 }
+
+// ===== 通用 MCP 网关 (/mcp/:name/mcp) =====
+
+/// SSE 长连接 (GET) 不受普通请求超时限制
+const GATEWAY_STREAM_TIMEOUT_SECS: u64 = 24 * 3600;
+
+async fn send_gateway_http(
+    state: &AppState,
+    server: &McpServerConfig,
+    method: Method,
+    incoming_headers: &HeaderMap,
+    upstream_session: Option<&str>,
+    body: Bytes,
+    timeout_secs: u64,
+) -> Result<reqwest::Response, Response> {
+    let upstream_proxy = state.upstream_proxy.read().await.clone();
+    let client = build_client(upstream_proxy, timeout_secs)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e).into_response())?;
+
+    let mut headers = copy_passthrough_headers(incoming_headers);
+    for name in ["mcp-protocol-version", "last-event-id"] {
+        if let Some(v) = incoming_headers.get(name) {
+            headers.insert(name, v.clone());
+        }
+    }
+    for (k, v) in &server.headers {
+        if let (Ok(k), Ok(v)) = (HeaderName::from_bytes(k.as_bytes()), HeaderValue::from_str(v)) {
+            headers.insert(k, v);
+        }
+    }
+    if let Some(v) = upstream_session.and_then(|s| HeaderValue::from_str(s).ok()) {
+        headers.insert("mcp-session-id", v);
+    }
+
+    client
+        .request(method, &server.url)
+        .headers(headers)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_GATEWAY,
+                format!("Upstream request failed: {}", e),
+            )
+                .into_response()
+        })
+}
+
+fn upstream_status(resp: &reqwest::Response) -> StatusCode {
+    StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY)
+}
+
+/// 原样流式转发上游响应
+fn stream_gateway_response(resp: reqwest::Response) -> Response {
+    let mut out = Response::builder().status(upstream_status(&resp));
+    if let Some(ct) = resp.headers().get(header::CONTENT_TYPE) {
+        out = out.header(header::CONTENT_TYPE, ct.clone());
+    }
+
+    let stream = resp.bytes_stream().map(|chunk| match chunk {
+        Ok(b) => Ok::<Bytes, std::io::Error>(b),
+        Err(e) => Ok(Bytes::from(format!("Upstream stream error: {}", e))),
+    });
+
+    out.body(Body::from_stream(stream)).unwrap_or_else(|_| {
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
+    })
+}
+
+/// 缓冲 tools/list 响应 (JSON 或 SSE) 并移除不允许的工具
+async fn filtered_gateway_response(server: &McpServerConfig, resp: reqwest::Response) -> Response {
+    let status = upstream_status(&resp);
+    let content_type = resp.headers().get(header::CONTENT_TYPE).cloned();
+    let bytes = match resp.bytes().await {
+        Ok(b) => b,
+        Err(e) => {
+            return (
+                StatusCode::BAD_GATEWAY,
+                format!("Upstream stream error: {}", e),
+            )
+                .into_response();
+        }
+    };
+
+    let is_sse = content_type
+        .as_ref()
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    let body = if is_sse {
+        Bytes::from(server.filter_sse_payload(&String::from_utf8_lossy(&bytes)))
+    } else {
+        match serde_json::from_slice::<Value>(&bytes) {
+            Ok(mut v) => {
+                server.filter_tools_list(&mut v);
+                Bytes::from(v.to_string())
+            }
+            Err(_) => bytes,
+        }
+    };
+
+    let mut out = Response::builder().status(status);
+    if let Some(ct) = content_type {
+        out = out.header(header::CONTENT_TYPE, ct);
+    }
+    out.body(Body::from(body)).unwrap_or_else(|_| {
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
+    })
+}
+
+fn with_session_header(mut resp: Response, session_id: Option<String>) -> Response {
+    if let Some(v) = session_id.and_then(|s| HeaderValue::from_str(&s).ok()) {
+        resp.headers_mut().insert("mcp-session-id", v);
+    }
+    resp
+}
+
+async fn gateway_http_post(
+    state: &AppState,
+    server: &McpServerConfig,
+    headers: &HeaderMap,
+    body: Bytes,
+    session: Option<(String, SessionUpstream)>,
+    filter_tools: bool,
+) -> Response {
+    let upstream_session = match &session {
+        Some((_, SessionUpstream::Http { session_id })) => session_id.clone(),
+        _ => None,
+    };
+    let resp = match send_gateway_http(
+        state,
+        server,
+        Method::POST,
+        headers,
+        upstream_session.as_deref(),
+        body,
+        state.request_timeout,
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(resp) => return resp,
+    };
+
+    let status = upstream_status(&resp);
+    let new_session = match &session {
+        // initialize 成功后创建网关会话，记住上游分配的会话 ID
+        None if status.is_success() => {
+            let upstream_sid = resp
+                .headers()
+                .get("mcp-session-id")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string());
+            Some(
+                state
+                    .mcp_gateway
+                    .create_session(&server.name, SessionUpstream::Http { session_id: upstream_sid }, None)
+                    .await,
+            )
+        }
+        // 上游会话已失效，同步删除网关会话让客户端重新 initialize
+        Some((sid, _)) if status == StatusCode::NOT_FOUND => {
+            state.mcp_gateway.remove_session(&server.name, sid).await;
+            None
+        }
+        _ => None,
+    };
+
+    let resp = if filter_tools && status.is_success() {
+        filtered_gateway_response(server, resp).await
+    } else {
+        stream_gateway_response(resp)
+    };
+    with_session_header(resp, new_session)
+}
+
+async fn gateway_stdio_post(
+    state: &AppState,
+    server: &McpServerConfig,
+    request_json: &Value,
+    session: Option<(String, SessionUpstream)>,
+    filter_tools: bool,
+) -> Response {
+    let id = request_json.get("id").cloned().unwrap_or(Value::Null);
+    let (process, existing_sid, slot) = match session {
+        Some((sid, SessionUpstream::Stdio(process))) => (process, Some(sid), None),
+        Some(_) => return StatusCode::NOT_FOUND.into_response(),
+        None => {
+            // 先预留名额再启动进程；启动或初始化失败时名额随 slot 释放
+            let Some(slot) = state.mcp_gateway.reserve_stdio_slot(&server.name).await else {
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    axum::Json(jsonrpc_error(
+                        id,
+                        -32000,
+                        format!(
+                            "Too many sessions for this MCP server (max {})",
+                            MAX_STDIO_SESSIONS_PER_SERVER
+                        ),
+                    )),
+                )
+                    .into_response();
+            };
+            match StdioProcess::spawn(server) {
+                Ok(p) => (std::sync::Arc::new(tokio::sync::Mutex::new(p)), None, Some(slot)),
+                Err(e) => {
+                    return (StatusCode::BAD_GATEWAY, axum::Json(jsonrpc_error(id, -32000, e)))
+                        .into_response();
+                }
+            }
+        }
+    };
+
+    let result = process
+        .lock()
+        .await
+        .send(request_json, Duration::from_secs(state.request_timeout))
+        .await;
+
+    match result {
+        Ok(Some(mut response)) => {
+            if filter_tools {
+                server.filter_tools_list(&mut response);
+            }
+            let new_session = match existing_sid {
+                Some(_) => None,
+                None => Some(
+                    state
+                        .mcp_gateway
+                        .create_session(&server.name, SessionUpstream::Stdio(process), slot)
+                        .await,
+                ),
+            };
+            with_session_header(
+                (StatusCode::OK, axum::Json(response)).into_response(),
+                new_session,
+            )
+        }
+        Ok(None) => StatusCode::ACCEPTED.into_response(),
+        Err(e) => {
+            tracing::warn!("[MCP Gateway] stdio server '{}' failed: {}", server.name, e);
+            // 进程异常的会话直接丢弃 (进程随之终止)，客户端下次请求会收到 404 并重新 initialize
+            if let Some(sid) = existing_sid {
+                state.mcp_gateway.remove_session(&server.name, &sid).await;
+            }
+            (StatusCode::BAD_GATEWAY, axum::Json(jsonrpc_error(id, -32000, e))).into_response()
+        }
+    }
+}
+
+async fn gateway_post(
+    state: &AppState,
+    server: &McpServerConfig,
+    headers: HeaderMap,
+    collected: Bytes,
+    request_json: Value,
+) -> Response {
+    if let Some((id, tool)) = server.denied_tool_call(&request_json) {
+        tracing::warn!("[MCP Gateway] Blocked tool call {}/{}", server.name, tool);
+        return (
+            StatusCode::OK,
+            axum::Json(jsonrpc_error(
+                id,
+                -32602,
+                format!("Tool '{}' is not allowed by the MCP gateway", tool),
+            )),
+        )
+            .into_response();
+    }
+
+    let messages = crate::proxy::mcp_gateway::messages(&request_json);
+    let initialize = messages.iter().any(|m| is_initialize_request(m));
+    let filter_tools = server.has_tool_filters()
+        && messages
+            .iter()
+            .any(|m| m.get("method").and_then(|v| v.as_str()) == Some("tools/list"));
+
+    let session = if initialize {
+        None
+    } else {
+        let Some(session_id) = mcp_session_id(&headers) else {
+            return (
+                StatusCode::BAD_REQUEST,
+                axum::Json(jsonrpc_error(Value::Null, -32000, "Bad Request: missing Mcp-Session-Id")),
+            )
+                .into_response();
+        };
+        match state.mcp_gateway.session_upstream(&server.name, &session_id).await {
+            Some(upstream) => Some((session_id, upstream)),
+            None => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    axum::Json(jsonrpc_error(Value::Null, -32001, "Session not found")),
+                )
+                    .into_response();
+            }
+        }
+    };
+
+    match server.transport {
+        McpTransport::Http => {
+            gateway_http_post(state, server, &headers, collected, session, filter_tools).await
+        }
+        McpTransport::Stdio => {
+            gateway_stdio_post(state, server, &request_json, session, filter_tools).await
+        }
+    }
+}
+
+async fn gateway_get(state: &AppState, server: &McpServerConfig, headers: HeaderMap) -> Response {
+    let Some(session_id) = mcp_session_id(&headers) else {
+        return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id").into_response();
+    };
+    let Some(SessionUpstream::Http { session_id: upstream_sid }) =
+        state.mcp_gateway.session_upstream(&server.name, &session_id).await
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    match send_gateway_http(
+        state,
+        server,
+        Method::GET,
+        &headers,
+        upstream_sid.as_deref(),
+        Bytes::new(),
+        GATEWAY_STREAM_TIMEOUT_SECS,
+    )
+    .await
+    {
+        Ok(resp) => stream_gateway_response(resp),
+        Err(resp) => resp,
+    }
+}
+
+async fn gateway_delete(state: &AppState, server: &McpServerConfig, headers: HeaderMap) -> Response {
+    let Some(session_id) = mcp_session_id(&headers) else {
+        return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id").into_response();
+    };
+    let Some(upstream) = state.mcp_gateway.remove_session(&server.name, &session_id).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    // 通知上游结束会话，失败不影响网关侧清理 (stdio 进程随会话释放而终止)
+    if let SessionUpstream::Http { session_id: Some(upstream_sid) } = upstream {
+        let _ = send_gateway_http(
+            state,
+            server,
+            Method::DELETE,
+            &headers,
+            Some(&upstream_sid),
+            Bytes::new(),
+            state.request_timeout,
+        )
+        .await;
+    }
+    StatusCode::OK.into_response()
+}
+
+/// 通用 MCP 网关入口，按名称转发到配置的上游服务器
+pub async fn handle_mcp_gateway(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    method: Method,
+    body: Body,
+) -> Response {
+    let Some(server) = state.mcp_gateway.server(&name).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let (mut resp, label) = match (&method, server.transport) {
+        (&Method::POST, _) => {
+            let collected = match to_bytes(body, 100 * 1024 * 1024).await {
+                Ok(b) => b,
+                Err(e) => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        format!("Failed to read request body: {}", e),
+                    )
+                        .into_response();
+                }
+            };
+            let request_json: Value = match serde_json::from_slice(&collected) {
+                Ok(v) => v,
+                Err(e) => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        axum::Json(jsonrpc_error(Value::Null, -32700, format!("Parse error: {}", e))),
+                    )
+                        .into_response();
+                }
+            };
+            let label = crate::proxy::mcp_gateway::log_label(&server.name, &request_json);
+            (
+                gateway_post(&state, &server, headers, collected, request_json).await,
+                label,
+            )
+        }
+        (&Method::GET, McpTransport::Http) => (
+            gateway_get(&state, &server, headers).await,
+            format!("{}/get", server.name),
+        ),
+        (&Method::DELETE, _) => (
+            gateway_delete(&state, &server, headers).await,
+            format!("{}/delete", server.name),
+        ),
+        // stdio 服务器没有服务端推送流
+        _ => return StatusCode::METHOD_NOT_ALLOWED.into_response(),
+    };
+
+    // 监控日志以 "<server>/<tool|method>" 作为模型列展示
+    if let Ok(v) = HeaderValue::from_str(&label) {
+        resp.headers_mut().insert("X-Mapped-Model", v);
+    }
+    resp
+}
@@ -8,5 +8,7 @@ pub mod kiro;
 pub mod mcp;
 pub mod common;
 pub mod audio;
+pub mod embeddings;
+pub mod responses;
 pub mod warmup;
 
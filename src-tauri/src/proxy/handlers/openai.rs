@@ -1,6 +1,7 @@
 // OpenAI Handler
 use axum::{
-    extract::Json, extract::State, http::StatusCode, response::IntoResponse, response::Response,
+    extract::Extension, extract::Json, extract::State, http::StatusCode, response::IntoResponse,
+    response::Response,
 };
 use base64::Engine as _;
 use bytes::Bytes;
@@ -11,9 +12,15 @@ use tracing::{debug, error, info}; // Import Engine trait for encode method
 use crate::proxy::mappers::openai::{
     transform_openai_request, transform_openai_response, OpenAIRequest,
 };
+use crate::proxy::mappers::openai::request::build_tool_schemas;
 // use crate::proxy::upstream::client::UpstreamClient; // 通过 state 获取
 use crate::proxy::server::AppState;
+use crate::proxy::model_fallback::{self, FallbackApplied};
+use crate::proxy::model_router::{self, RequestFeatures};
+use crate::proxy::response_cache::{self, CacheProtocol};
+use crate::proxy::middleware::auth::ClientKeyIdentity;
 use crate::proxy::debug_logger;
+use crate::proxy::mappers::estimation_calibrator;
 
 const MAX_RETRY_ATTEMPTS: usize = 3;
 use super::common::{
@@ -25,7 +32,45 @@ use tokio_util::sync::CancellationToken;
 
 pub async fn handle_chat_completions(
     State(state): State<AppState>,
-    Json(mut body): Json<Value>,
+    client_key: Option<Extension<ClientKeyIdentity>>,
+    Json(body): Json<Value>,
+) -> Result<Response, (StatusCode, String)> {
+    let client_key = client_key.map(|Extension(key)| key);
+
+    // [NEW] 响应缓存: 仅 temperature = 0 的确定性请求
+    let cache_ctx = response_cache::cache().prepare(
+        CacheProtocol::OpenAI,
+        client_key.as_ref().map(|k| k.id.as_str()),
+        body.get("model").and_then(|m| m.as_str()).unwrap_or(""),
+        &body,
+        body.get("stream").and_then(|s| s.as_bool()).unwrap_or(false),
+        false,
+    );
+    if let Some(ctx) = &cache_ctx {
+        if let Some(hit) = ctx.lookup().await {
+            return Ok(hit);
+        }
+    }
+
+    // [NEW] 模型降级链: 处理流程记录实际发生的降级，在此统一写入响应头
+    let mut fallback: Option<FallbackApplied> = None;
+    let mut response = handle_chat_completions_inner(state, client_key, body, &mut fallback)
+        .await?
+        .into_response();
+    if let Some(applied) = fallback {
+        applied.apply_headers(&mut response);
+    }
+    Ok(match cache_ctx {
+        Some(ctx) => ctx.store(response).await,
+        None => response,
+    })
+}
+
+async fn handle_chat_completions_inner(
+    state: AppState,
+    client_key: Option<ClientKeyIdentity>,
+    mut body: Value,
+    fallback: &mut Option<FallbackApplied>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // [FIX] 保存原始请求体的完整副本，用于日志记录
     // 这确保了即使结构体定义遗漏字段，日志也能完整记录所有参数
@@ -80,6 +125,16 @@ pub async fn handle_chat_completions(
         }
     }
 
+    // [NEW] 路由规则匹配所需的请求特征 (只有规则用到 token 条件时才预估 token)
+    let request_model = body.get("model").and_then(|m| m.as_str()).unwrap_or_default().to_string();
+    let route_features = RequestFeatures::from_body(
+        "openai",
+        &request_model,
+        &body,
+        model_router::router().needs_prompt_tokens(),
+    )
+    .with_client_key(client_key.as_ref());
+
     let mut openai_req: OpenAIRequest = serde_json::from_value(body)
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
 
@@ -118,26 +173,55 @@ pub async fn handle_chat_completions(
         debug_logger::write_debug_payload(&debug_cfg, Some(&trace_id), "original_request", &original_payload).await;
     }
 
+    // [NEW] Structured Outputs: 保留原始 Schema 用于响应校验
+    let response_schema = openai_req
+        .response_format
+        .as_ref()
+        .and_then(|f| f.schema())
+        .cloned();
+    let mut schema_repair_attempted = false;
+    // 修复重试沿用产出无效 JSON 的账号 (不触发轮换)
+    let mut repair_account: Option<String> = None;
+
     // 1. 获取 UpstreamClient (Clone handle)
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
     let pool_size = token_manager.len();
     // [FIX] Ensure max_attempts is at least 2 to allow for internal retries
-    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2);
+    // Structured Outputs 额外预留一次修复重试，不占用账号轮换次数
+    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2)
+        + usize::from(response_schema.is_some());
 
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
 
     // 2. 模型路由解析 (移到循环外以支持在所有路径返回 X-Mapped-Model)
-    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+    let primary_model = model_router::resolve_model(
         &openai_req.model,
         &*state.custom_mapping.read().await,
+        &route_features,
     );
+    let mut mapped_model = primary_model.clone();
     
     // [FIX] 严格排除列表
     let mut failed_accounts = std::collections::HashSet::new();
+    // [NEW] 原始工具 Schema，响应映射时据此修正 functionCall 参数类型
+    let tool_schemas = std::sync::Arc::new(build_tool_schemas(&openai_req));
 
     for attempt in 0..max_attempts {
+        // [NEW] 模型降级链: 每次尝试都按已失败的账号重新判断 (OpenAI 协议只在 Antigravity 账号池内降级)
+        *fallback = model_fallback::select_gemini_fallback(
+            &token_manager,
+            &openai_req.model,
+            &primary_model,
+            &failed_accounts,
+        )
+        .await;
+        mapped_model = fallback
+            .as_ref()
+            .map(|applied| applied.to.clone())
+            .unwrap_or_else(|| primary_model.clone());
+
         // 将 OpenAI 工具转为 Value 数组以便探测联网
         let tools_val: Option<Vec<Value>> = openai_req
             .tools
@@ -162,17 +246,26 @@ pub async fn handle_chat_completions(
         });
 
         // 4. 获取 Token (使用准确的 request_type)
-        // 关键：在重试尝试 (attempt > 0) 时强制轮换账号
-        let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
-            .get_token(
-                &config.request_type,
-                attempt > 0,
-                Some(&session_id),
-                &mapped_model,
-                Some(&failed_accounts), // [FIX] 传入黑名单
-            )
-            .await
-        {
+        // 关键：在重试尝试 (attempt > 0) 时强制轮换账号；结构化输出修复重试优先沿用上一次的账号
+        let pinned = match repair_account.take() {
+            Some(repair_email) => token_manager.get_token_by_email(&repair_email).await.ok(),
+            None => None,
+        };
+        let token_result = match pinned {
+            Some(t) => Ok(t),
+            None => {
+                token_manager
+                    .get_token(
+                        &config.request_type,
+                        attempt > 0,
+                        Some(&session_id),
+                        &mapped_model,
+                        Some(&failed_accounts), // [FIX] 传入黑名单
+                    )
+                    .await
+            }
+        };
+        let (access_token, project_id, email, account_id, _wait_ms) = match token_result {
             Ok(t) => t,
             Err(e) => {
                 // [FIX] Attach headers to error response for logging visibility
@@ -191,6 +284,8 @@ pub async fn handle_chat_completions(
 
         // 4. 转换请求
         let gemini_body = transform_openai_request(&openai_req, &project_id, &mapped_model);
+        // 离线估算实际发往上游的请求，用于校准器学习
+        let calibration_estimate = crate::proxy::tokenizer::count_gemini_request(&gemini_body, &mapped_model).total();
 
         if debug_logger::is_enabled(&debug_cfg) {
             let payload = json!({
@@ -230,8 +325,9 @@ pub async fn handle_chat_completions(
         };
         let query_string = if actual_stream { Some("alt=sse") } else { None };
 
+        let account_proxy = token_manager.get_individual_proxy(&account_id);
         let response: reqwest::Response = tokio::select! {
-            res = upstream.call_v1_internal(method, &access_token, gemini_body, query_string) => match res {
+            res = upstream.call_v1_internal(method, &access_token, gemini_body, query_string, account_proxy.as_deref()) => match res {
                 Ok(r) => r,
                 Err(e) => {
                     last_error = e.clone();
@@ -276,9 +372,14 @@ pub async fn handle_chat_completions(
                     "request_type": config.request_type,
                     "attempt": attempt,
                     "status": status.as_u16(),
+                    "request": debug_logger::is_enabled(&debug_cfg).then(|| original_body.clone()),
                 });
                 let gemini_stream = debug_logger::wrap_reqwest_stream_with_debug(
-                    Box::pin(response.bytes_stream()),
+                    estimation_calibrator::record_from_stream(
+                        Box::pin(response.bytes_stream()),
+                        mapped_model.clone(),
+                        calibration_estimate,
+                    ),
                     debug_cfg.clone(),
                     trace_id.clone(),
                     "upstream_response",
@@ -288,7 +389,7 @@ pub async fn handle_chat_completions(
                 // [P1 FIX] Enhanced Peek logic to handle heartbeats and slow start
                 // Pre-read until we find meaningful content, skip heartbeats
                 let mut openai_stream =
-                    create_openai_sse_stream(gemini_stream, openai_req.model.clone());
+                    create_openai_sse_stream(gemini_stream, openai_req.model.clone(), tool_schemas.clone());
 
                 let mut first_data_chunk = None;
                 let mut retry_this_account = false;
@@ -363,6 +464,7 @@ pub async fn handle_chat_completions(
 
                 if client_wants_stream {
                     // 客户端请求流式，返回 SSE
+                    // Structured Outputs 在流式模式下只下发 responseSchema，无法事后校验与重试
                     let body = Body::from_stream(combined_stream);
                     return Ok(Response::builder()
                         .header("Content-Type", "text/event-stream")
@@ -382,6 +484,40 @@ pub async fn handle_chat_completions(
                     match collect_stream_to_json(Box::pin(combined_stream)).await {
                         Ok(full_response) => {
                             info!("[{}] ✓ Stream collected and converted to JSON", trace_id);
+
+                            // [NEW] Structured Outputs: 按原始 Schema 校验，失败时自动修复重试一次
+                            if let Some(schema) = &response_schema {
+                                if let Err(e) = validate_structured_output(&full_response, schema) {
+                                    if !schema_repair_attempted {
+                                        tracing::warn!(
+                                            "[{}] Structured output validation failed, retrying with repair prompt: {}",
+                                            trace_id, e
+                                        );
+                                        schema_repair_attempted = true;
+                                        repair_account = Some(email.clone());
+                                        append_schema_repair_messages(&mut openai_req, &full_response, &e);
+                                        last_error = format!("Structured output validation failed: {}", e);
+                                        continue;
+                                    }
+                                    error!("[{}] Structured output still invalid after repair: {}", trace_id, e);
+                                    return Ok((
+                                        StatusCode::BAD_GATEWAY,
+                                        [
+                                            ("X-Account-Email", email.as_str()),
+                                            ("X-Mapped-Model", mapped_model.as_str()),
+                                        ],
+                                        Json(json!({
+                                            "error": {
+                                                "message": format!("Model output does not match response_format json_schema: {}", e),
+                                                "type": "server_error",
+                                                "code": "json_schema_validation_failed"
+                                            }
+                                        })),
+                                    )
+                                        .into_response());
+                                }
+                            }
+
                             return Ok((
                                 StatusCode::OK,
                                 [
@@ -409,7 +545,11 @@ pub async fn handle_chat_completions(
                 .await
                 .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
 
-            let openai_response = transform_openai_response(&gemini_resp);
+            if let Some(actual) = estimation_calibrator::prompt_token_count(&gemini_resp) {
+                estimation_calibrator::get_calibrator().record(&mapped_model, calibration_estimate, actual);
+            }
+
+            let openai_response = transform_openai_response(&gemini_resp, &tool_schemas);
             return Ok((
                 StatusCode::OK,
                 [
@@ -646,6 +786,77 @@ pub async fn handle_chat_completions(
     }
 }
 
+/// 提取 choice 的文本内容 (Structured Outputs 只关心 content，不含 reasoning)
+fn choice_text(message: &crate::proxy::mappers::openai::OpenAIMessage) -> String {
+    use crate::proxy::mappers::openai::{OpenAIContent, OpenAIContentBlock};
+    match &message.content {
+        Some(OpenAIContent::String(s)) => s.clone(),
+        Some(OpenAIContent::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| match b {
+                OpenAIContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// 校验所有 choices 是否符合 response_format.json_schema
+fn validate_structured_output(
+    response: &crate::proxy::mappers::openai::OpenAIResponse,
+    schema: &Value,
+) -> Result<(), String> {
+    use crate::proxy::common::structured_output::validate_output;
+    for choice in &response.choices {
+        // 调用工具时没有 JSON 正文，不做校验
+        if choice.message.tool_calls.is_some() {
+            continue;
+        }
+        validate_output(&choice_text(&choice.message), schema).map_err(|e| {
+            if response.choices.len() > 1 {
+                format!("choice {}: {}", choice.index, e)
+            } else {
+                e
+            }
+        })?;
+    }
+    Ok(())
+}
+
+/// 将无效输出与修复提示词追加到对话中，供下一次尝试使用
+fn append_schema_repair_messages(
+    openai_req: &mut OpenAIRequest,
+    response: &crate::proxy::mappers::openai::OpenAIResponse,
+    error: &str,
+) {
+    use crate::proxy::mappers::openai::{OpenAIContent, OpenAIMessage};
+    let previous = response
+        .choices
+        .first()
+        .map(|c| choice_text(&c.message))
+        .unwrap_or_default();
+
+    openai_req.messages.push(OpenAIMessage {
+        role: "assistant".to_string(),
+        content: Some(OpenAIContent::String(previous)),
+        reasoning_content: None,
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    });
+    openai_req.messages.push(OpenAIMessage {
+        role: "user".to_string(),
+        content: Some(OpenAIContent::String(
+            crate::proxy::common::structured_output::repair_prompt(error),
+        )),
+        reasoning_content: None,
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    });
+}
+
 /// 处理 Legacy Completions API (/v1/completions)
 /// 将 Prompt 转换为 Chat Message 格式，复用 handle_chat_completions
 pub async fn handle_completions(
@@ -683,208 +894,9 @@ pub async fn handle_completions(
             debug!("[Factory Droid] Converted string input to user message");
         } else if let Some(input_items) = body.get("input").and_then(|v| v.as_array()) {
             // Input is an array - process normally
-            let mut call_id_to_name = std::collections::HashMap::new();
-
-            // Pass 1: Build Call ID to Name Map
-            for item in input_items {
-                let item_type = item.get("type").and_then(|v| v.as_str()).unwrap_or("");
-                match item_type {
-                    "function_call" | "local_shell_call" | "web_search_call" => {
-                        let call_id = item
-                            .get("call_id")
-                            .and_then(|v| v.as_str())
-                            .or_else(|| item.get("id").and_then(|v| v.as_str()))
-                            .unwrap_or("unknown");
-
-                        let name = if item_type == "local_shell_call" {
-                            "shell"
-                        } else if item_type == "web_search_call" {
-                            "google_search"
-                        } else {
-                            item.get("name")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("unknown")
-                        };
-
-                        call_id_to_name.insert(call_id.to_string(), name.to_string());
-                        tracing::debug!("Mapped call_id {} to name {}", call_id, name);
-                    }
-                    _ => {}
-                }
-            }
-
-            // Pass 2: Map Input Items to Messages
-            for item in input_items {
-                let item_type = item.get("type").and_then(|v| v.as_str()).unwrap_or("");
-                match item_type {
-                    "message" => {
-                        let role = item.get("role").and_then(|v| v.as_str()).unwrap_or("user");
-                        let content = item.get("content").and_then(|v| v.as_array());
-                        let mut text_parts = Vec::new();
-                        let mut image_parts: Vec<Value> = Vec::new();
-
-                        if let Some(parts) = content {
-                            for part in parts {
-                                // 处理文本块
-                                if let Some(text) = part.get("text").and_then(|v| v.as_str()) {
-                                    text_parts.push(text.to_string());
-                                }
-                                // [NEW] 处理图像块 (Codex input_image 格式)
-                                else if part.get("type").and_then(|v| v.as_str())
-                                    == Some("input_image")
-                                {
-                                    if let Some(image_url) =
-                                        part.get("image_url").and_then(|v| v.as_str())
-                                    {
-                                        image_parts.push(json!({
-                                            "type": "image_url",
-                                            "image_url": { "url": image_url }
-                                        }));
-                                        debug!("[Codex] Found input_image: {}", image_url);
-                                    }
-                                }
-                                // [NEW] 兼容标准 OpenAI image_url 格式
-                                else if part.get("type").and_then(|v| v.as_str())
-                                    == Some("image_url")
-                                {
-                                    if let Some(url_obj) = part.get("image_url") {
-                                        image_parts.push(json!({
-                                            "type": "image_url",
-                                            "image_url": url_obj.clone()
-                                        }));
-                                    }
-                                }
-                            }
-                        }
-
-                        // 构造消息内容：如果有图像则使用数组格式
-                        if image_parts.is_empty() {
-                            messages.push(json!({
-                                "role": role,
-                                "content": text_parts.join("\n")
-                            }));
-                        } else {
-                            let mut content_blocks: Vec<Value> = Vec::new();
-                            if !text_parts.is_empty() {
-                                content_blocks.push(json!({
-                                    "type": "text",
-                                    "text": text_parts.join("\n")
-                                }));
-                            }
-                            content_blocks.extend(image_parts);
-                            messages.push(json!({
-                                "role": role,
-                                "content": content_blocks
-                            }));
-                        }
-                    }
-                    "function_call" | "local_shell_call" | "web_search_call" => {
-                        let mut name = item
-                            .get("name")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("unknown");
-                        let mut args_str = item
-                            .get("arguments")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("{}")
-                            .to_string();
-                        let call_id = item
-                            .get("call_id")
-                            .and_then(|v| v.as_str())
-                            .or_else(|| item.get("id").and_then(|v| v.as_str()))
-                            .unwrap_or("unknown");
-
-                        // Handle native shell calls
-                        if item_type == "local_shell_call" {
-                            name = "shell";
-                            if let Some(action) = item.get("action") {
-                                if let Some(exec) = action.get("exec") {
-                                    // Map to ShellCommandToolCallParams (string command) or ShellToolCallParams (array command)
-                                    // Most LLMs prefer a single string for shell
-                                    let mut args_obj = serde_json::Map::new();
-                                    if let Some(cmd) = exec.get("command") {
-                                        // CRITICAL FIX: The 'shell' tool schema defines 'command' as an ARRAY of strings.
-                                        // We MUST pass it as an array, not a joined string, otherwise Gemini rejects with 400 INVALID_ARGUMENT.
-                                        let cmd_val = if cmd.is_string() {
-                                            json!([cmd]) // Wrap in array
-                                        } else {
-                                            cmd.clone() // Assume already array
-                                        };
-                                        args_obj.insert("command".to_string(), cmd_val);
-                                    }
-                                    if let Some(wd) =
-                                        exec.get("working_directory").or(exec.get("workdir"))
-                                    {
-                                        args_obj.insert("workdir".to_string(), wd.clone());
-                                    }
-                                    args_str = serde_json::to_string(&args_obj)
-                                        .unwrap_or("{}".to_string());
-                                }
-                            }
-                        } else if item_type == "web_search_call" {
-                            name = "google_search";
-                            if let Some(action) = item.get("action") {
-                                let mut args_obj = serde_json::Map::new();
-                                if let Some(q) = action.get("query") {
-                                    args_obj.insert("query".to_string(), q.clone());
-                                }
-                                args_str =
-                                    serde_json::to_string(&args_obj).unwrap_or("{}".to_string());
-                            }
-                        }
-
-                        messages.push(json!({
-                            "role": "assistant",
-                            "tool_calls": [
-                                {
-                                    "id": call_id,
-                                    "type": "function",
-                                    "function": {
-                                        "name": name,
-                                        "arguments": args_str
-                                    }
-                                }
-                            ]
-                        }));
-                    }
-                    "function_call_output" | "custom_tool_call_output" => {
-                        let call_id = item
-                            .get("call_id")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("unknown");
-                        let output = item.get("output");
-                        let output_str = if let Some(o) = output {
-                            if o.is_string() {
-                                o.as_str().unwrap().to_string()
-                            } else if let Some(content) = o.get("content").and_then(|v| v.as_str())
-                            {
-                                content.to_string()
-                            } else {
-                                o.to_string()
-                            }
-                        } else {
-                            "".to_string()
-                        };
-
-                        let name = call_id_to_name.get(call_id).cloned().unwrap_or_else(|| {
-                            // Fallback: if unknown and we see function_call_output, it's likely "shell" in this context
-                            tracing::warn!(
-                                "Unknown tool name for call_id {}, defaulting to 'shell'",
-                                call_id
-                            );
-                            "shell".to_string()
-                        });
-
-                        messages.push(json!({
-                            "role": "tool",
-                            "tool_call_id": call_id,
-                            "name": name,
-                            "content": output_str
-                        }));
-                    }
-                    _ => {}
-                }
-            }
+            messages.extend(
+                crate::proxy::mappers::openai::responses::input_items_to_messages(input_items),
+            );
         }
 
         if let Some(obj) = body.as_object_mut() {
@@ -1048,6 +1060,8 @@ pub async fn handle_completions(
     
     // [FIX] 严格排除列表
     let mut failed_accounts = std::collections::HashSet::new();
+    // [NEW] 原始工具 Schema，响应映射时据此修正 functionCall 参数类型
+    let tool_schemas = std::sync::Arc::new(build_tool_schemas(&openai_req));
 
     for attempt in 0..max_attempts {
         // 3. 模型配置解析
@@ -1098,6 +1112,8 @@ pub async fn handle_completions(
         info!("✓ Using account: {} (type: {})", email, config.request_type);
 
         let gemini_body = transform_openai_request(&openai_req, &project_id, &mapped_model);
+        // 离线估算实际发往上游的请求，用于校准器学习
+        let calibration_estimate = crate::proxy::tokenizer::count_gemini_request(&gemini_body, &mapped_model).total();
 
         // [New] 打印转换后的报文 (Gemini Body) 供调试 (Codex 路径) ———— 缩减为 simple debug
         debug!(
@@ -1121,7 +1137,13 @@ pub async fn handle_completions(
         let query_string = if list_response { Some("alt=sse") } else { None };
 
         let response = match upstream
-            .call_v1_internal(method, &access_token, gemini_body, query_string)
+            .call_v1_internal(
+                method,
+                &access_token,
+                gemini_body,
+                query_string,
+                token_manager.get_individual_proxy(&_account_id).as_deref(),
+            )
             .await
         {
             Ok(r) => r,
@@ -1147,7 +1169,11 @@ pub async fn handle_completions(
                 use axum::response::Response;
                 use futures::StreamExt;
 
-                let gemini_stream = response.bytes_stream();
+                let gemini_stream = estimation_calibrator::record_from_stream(
+                    Box::pin(response.bytes_stream()),
+                    mapped_model.clone(),
+                    calibration_estimate,
+                );
 
                 // DECISION: Which stream to create?
                 // If client wants stream: give them what they asked (Legacy/Codex SSE).
@@ -1157,10 +1183,10 @@ pub async fn handle_completions(
                 if client_wants_stream {
                     let mut openai_stream = if is_codex_style {
                         use crate::proxy::mappers::openai::streaming::create_codex_sse_stream;
-                        create_codex_sse_stream(Box::pin(gemini_stream), openai_req.model.clone())
+                        create_codex_sse_stream(gemini_stream, openai_req.model.clone(), tool_schemas.clone())
                     } else {
                         use crate::proxy::mappers::openai::streaming::create_legacy_sse_stream;
-                        create_legacy_sse_stream(Box::pin(gemini_stream), openai_req.model.clone())
+                        create_legacy_sse_stream(gemini_stream, openai_req.model.clone())
                     };
 
                     // [P1 FIX] Enhanced Peek logic (Reused from above/standard)
@@ -1235,7 +1261,7 @@ pub async fn handle_completions(
                     // Note: We use create_openai_sse_stream regardless of is_codex_style here,
                     // because we just want the content aggregation which chat stream does well.
                     let mut openai_stream =
-                        create_openai_sse_stream(Box::pin(gemini_stream), openai_req.model.clone());
+                        create_openai_sse_stream(gemini_stream, openai_req.model.clone(), tool_schemas.clone());
 
                     // Peek Logic (Repeated for safety/correctness on this stream type)
                     let mut first_data_chunk = None;
@@ -1350,7 +1376,11 @@ pub async fn handle_completions(
                 }
             };
 
-            let chat_resp = transform_openai_response(&gemini_resp);
+            if let Some(actual) = estimation_calibrator::prompt_token_count(&gemini_resp) {
+                estimation_calibrator::get_calibrator().record(&mapped_model, calibration_estimate, actual);
+            }
+
+            let chat_resp = transform_openai_response(&gemini_resp, &tool_schemas);
 
             // Map Chat Response -> Legacy Completions Response
             let choices = chat_resp.choices.iter().map(|c| {
@@ -1566,6 +1596,7 @@ pub async fn handle_images_generations(
     };
 
     info!("✓ Using account: {} for image generation", email);
+    let account_proxy = token_manager.get_individual_proxy(&_account_id);
 
     // 5. 并发发送请求 (解决 candidateCount > 1 不支持的问题)
     let mut tasks = Vec::new();
@@ -1573,6 +1604,7 @@ pub async fn handle_images_generations(
     for _ in 0..n {
         let upstream = upstream.clone();
         let access_token = access_token.clone();
+        let account_proxy = account_proxy.clone();
         let project_id = project_id.clone();
         let final_prompt = final_prompt.clone();
         let image_config = image_config.clone(); // 使用解析后的完整配置
@@ -1607,7 +1639,7 @@ pub async fn handle_images_generations(
             });
 
             match upstream
-                .call_v1_internal("generateContent", &access_token, gemini_body, None)
+                .call_v1_internal("generateContent", &access_token, gemini_body, None, account_proxy.as_deref())
                 .await
             {
                 Ok(response) => {
@@ -1933,15 +1965,17 @@ pub async fn handle_images_edits(
     });
 
     // 5. Execute Requests (Parallel for n > 1)
+    let account_proxy = token_manager.get_individual_proxy(&_account_id);
     let mut tasks = Vec::new();
     for _ in 0..n {
         let upstream = upstream.clone();
         let access_token = access_token.clone();
+        let account_proxy = account_proxy.clone();
         let body = gemini_body.clone();
 
         tasks.push(tokio::spawn(async move {
             match upstream
-                .call_v1_internal("generateContent", &access_token, body, None)
+                .call_v1_internal("generateContent", &access_token, body, None, account_proxy.as_deref())
                 .await
             {
                 Ok(response) => {
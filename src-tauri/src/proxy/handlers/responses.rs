@@ -0,0 +1,505 @@
+// Responses API Handler
+// POST /v1/responses、GET / DELETE /v1/responses/{id}、GET /v1/responses/{id}/input_items
+//
+// 响应在本地 SQLite 中存储 (store=false 除外)，previous_response_id 由代理侧展开为完整历史。
+use axum::{
+    body::Body,
+    extract::{Extension, Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+use super::common::{apply_retry_strategy, determine_retry_strategy};
+use crate::modules::responses_db::{self, StoredResponse};
+use crate::proxy::common::json_schema::{fix_function_calls_in_response, ToolSchemas};
+use crate::proxy::mappers::estimation_calibrator;
+use crate::proxy::mappers::openai::request::build_tool_schemas;
+use crate::proxy::mappers::openai::responses::{
+    build_chat_request, format_sse_event, new_response_id, normalize_input, response_skeleton,
+    ResponsesStreamState,
+};
+use crate::proxy::mappers::openai::{transform_openai_request, OpenAIRequest};
+use crate::proxy::middleware::auth::ClientKeyIdentity;
+use crate::proxy::server::AppState;
+use crate::proxy::session_manager::SessionManager;
+
+const MAX_RETRY_ATTEMPTS: usize = 3;
+
+type ChunkStream = Pin<Box<dyn Stream<Item = Result<Value, String>> + Send>>;
+
+/// OpenAI 风格的错误响应
+fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    let error_type = if status.is_client_error() {
+        "invalid_request_error"
+    } else {
+        "server_error"
+    };
+    (
+        status,
+        Json(json!({
+            "error": { "message": message.into(), "type": error_type, "param": null, "code": null }
+        })),
+    )
+        .into_response()
+}
+
+fn db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(responses_db::get_responses_db_path(&data_dir))
+}
+
+async fn run_db<T, F>(f: F) -> Result<T, Response>
+where
+    T: Send + 'static,
+    F: FnOnce(PathBuf) -> Result<T, String> + Send + 'static,
+{
+    let path = db_path().map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    match tokio::task::spawn_blocking(move || f(path)).await {
+        Ok(Ok(v)) => Ok(v),
+        Ok(Err(e)) => Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("Response store error: {}", e))),
+        Err(e) => Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("Response store error: {}", e))),
+    }
+}
+
+fn not_found(id: &str) -> Response {
+    error_response(StatusCode::NOT_FOUND, format!("Response with id '{}' not found.", id))
+}
+
+/// Gemini SSE 字节流 → 解析后的分片 (剥离 v1internal 的 response 包装并修正函数参数类型)
+fn gemini_chunk_stream(
+    mut upstream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+    tool_schemas: Arc<ToolSchemas>,
+) -> ChunkStream {
+    Box::pin(async_stream::stream! {
+        let mut buffer = BytesMut::new();
+        while let Some(item) = upstream.next().await {
+            let bytes = match item {
+                Ok(b) => b,
+                Err(e) => {
+                    yield Err(format!("Upstream stream error: {}", e));
+                    return;
+                }
+            };
+            buffer.extend_from_slice(&bytes);
+            while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                let line_raw = buffer.split_to(pos + 1);
+                let Ok(line) = std::str::from_utf8(&line_raw) else { continue };
+                let Some(data) = line.trim().strip_prefix("data:") else { continue };
+                let data = data.trim();
+                if data.is_empty() || data == "[DONE]" {
+                    continue;
+                }
+                let Ok(mut json) = serde_json::from_str::<Value>(data) else {
+                    debug!("[Responses] Skipping unparsable upstream line");
+                    continue;
+                };
+                if let Some(err) = json.get("error") {
+                    let message = err.get("message").and_then(|m| m.as_str()).unwrap_or("upstream error");
+                    yield Err(message.to_string());
+                    return;
+                }
+                let mut chunk = json.get_mut("response").map(|v| v.take()).unwrap_or(json);
+                fix_function_calls_in_response(&mut chunk, &tool_schemas);
+                yield Ok(chunk);
+            }
+        }
+    })
+}
+
+/// 轮换账号调用 streamGenerateContent，直到拿到第一个有效分片
+///
+/// 返回 (分片流, 账号 email)。首包之前的错误 (HTTP 错误、空流、超时) 都会换号重试。
+async fn open_upstream(
+    state: &AppState,
+    openai_req: &OpenAIRequest,
+    mapped_model: &str,
+    tool_schemas: Arc<ToolSchemas>,
+) -> Result<(ChunkStream, String), Response> {
+    let token_manager = &state.token_manager;
+    let max_attempts = MAX_RETRY_ATTEMPTS.min(token_manager.len().saturating_add(1)).max(2);
+    let trace_id = format!("resp_{}", chrono::Utc::now().timestamp_subsec_millis());
+    let session_id = SessionManager::extract_openai_session_id(openai_req);
+    let tools_val: Option<Vec<Value>> = openai_req.tools.clone();
+    let config = crate::proxy::mappers::common_utils::resolve_request_config(
+        &openai_req.model,
+        mapped_model,
+        &tools_val,
+        None,
+        None,
+    );
+
+    let mut failed_accounts = std::collections::HashSet::new();
+    let mut last_error = String::new();
+
+    for attempt in 0..max_attempts {
+        let (access_token, project_id, email, account_id, _wait_ms) = token_manager
+            .get_token(
+                &config.request_type,
+                attempt > 0,
+                Some(session_id.as_str()),
+                mapped_model,
+                Some(&failed_accounts),
+            )
+            .await
+            .map_err(|e| error_response(StatusCode::SERVICE_UNAVAILABLE, format!("Token error: {}", e)))?;
+        info!("✓ Using account: {} (responses, type: {})", email, config.request_type);
+
+        let gemini_body = transform_openai_request(openai_req, &project_id, mapped_model);
+        let calibration_estimate = crate::proxy::tokenizer::count_gemini_request(&gemini_body, mapped_model).total();
+        let response = match state
+            .upstream
+            .call_v1_internal(
+                "streamGenerateContent",
+                &access_token,
+                gemini_body,
+                Some("alt=sse"),
+                token_manager.get_individual_proxy(&account_id).as_deref(),
+            )
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                debug!("Responses request failed on attempt {}/{}: {}", attempt + 1, max_attempts, e);
+                last_error = e;
+                continue;
+            }
+        };
+
+        let status = response.status();
+        if status.is_success() {
+            let upstream_stream = estimation_calibrator::record_from_stream(
+                Box::pin(response.bytes_stream()),
+                mapped_model.to_string(),
+                calibration_estimate,
+            );
+            let mut chunks = gemini_chunk_stream(upstream_stream, tool_schemas.clone());
+            match tokio::time::timeout(std::time::Duration::from_secs(60), chunks.next()).await {
+                Ok(Some(Ok(first))) => {
+                    token_manager.mark_account_success(&email);
+                    let stream = futures::stream::once(async move { Ok(first) }).chain(chunks);
+                    return Ok((Box::pin(stream), email));
+                }
+                Ok(Some(Err(e))) => last_error = e,
+                Ok(None) => last_error = "Empty response stream".to_string(),
+                Err(_) => last_error = "Timeout waiting for first data".to_string(),
+            }
+            warn!("[Responses] {} on {}, rotating account", last_error, email);
+            failed_accounts.insert(account_id);
+            continue;
+        }
+
+        let status_code = status.as_u16();
+        let retry_after = response
+            .headers()
+            .get("Retry-After")
+            .and_then(|h| h.to_str().ok())
+            .map(|s| s.to_string());
+        let error_text = response.text().await.unwrap_or_else(|_| format!("HTTP {}", status_code));
+        last_error = format!("HTTP {}: {}", status_code, error_text);
+
+        if matches!(status_code, 429 | 500 | 503 | 529) {
+            token_manager
+                .mark_rate_limited_async(
+                    &account_id,
+                    &email,
+                    status_code,
+                    retry_after.as_deref(),
+                    &error_text,
+                    Some(mapped_model),
+                )
+                .await;
+            failed_accounts.insert(account_id.clone());
+        }
+
+        let strategy = determine_retry_strategy(status_code, &error_text, false);
+        if !apply_retry_strategy(strategy, attempt, max_attempts, status_code, &trace_id).await {
+            return Err(error_response(status, error_text));
+        }
+    }
+
+    Err(error_response(
+        StatusCode::TOO_MANY_REQUESTS,
+        format!("All accounts exhausted. Last error: {}", last_error),
+    ))
+}
+
+/// 待持久化的本轮请求信息 (store=false 时为 None)
+struct PendingStore {
+    model: String,
+    previous_response_id: Option<String>,
+    owner: Option<String>,
+    input_items: Vec<Value>,
+}
+
+impl PendingStore {
+    async fn save(self, response: &Value) {
+        let stored = StoredResponse {
+            id: response["id"].as_str().unwrap_or_default().to_string(),
+            created_at: response["created_at"].as_i64().unwrap_or_default(),
+            model: self.model,
+            previous_response_id: self.previous_response_id,
+            owner: self.owner,
+            input_items: self.input_items,
+            response: response.clone(),
+        };
+        let result = tokio::task::spawn_blocking(move || {
+            responses_db::save_response(&db_path()?, &stored)
+        })
+        .await;
+        if let Ok(Err(e)) | Err(e) = result.map_err(|e| e.to_string()) {
+            warn!("[Responses] Failed to store response: {}", e);
+        }
+    }
+}
+
+/// 处理 POST /v1/responses
+pub async fn handle_create_response(
+    State(state): State<AppState>,
+    client_key: Option<Extension<ClientKeyIdentity>>,
+    Json(body): Json<Value>,
+) -> Response {
+    // 存储的响应只对创建它的客户端 Key 可见
+    let owner = client_key.map(|Extension(key)| key.id);
+    let previous_response_id = body
+        .get("previous_response_id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    let store = body.get("store").and_then(|v| v.as_bool()).unwrap_or(true);
+
+    // 1. 展开 previous_response_id 历史
+    let history = match &previous_response_id {
+        Some(prev) => {
+            let prev_id = prev.clone();
+            let prev_owner = owner.clone();
+            match run_db(move |path| {
+                responses_db::load_conversation(&path, &prev_id, prev_owner.as_deref())
+            })
+            .await
+            {
+                Ok(Some(items)) => items,
+                Ok(None) => {
+                    return error_response(
+                        StatusCode::NOT_FOUND,
+                        format!("Previous response with id '{}' not found.", prev),
+                    )
+                }
+                Err(resp) => return resp,
+            }
+        }
+        None => Vec::new(),
+    };
+
+    // 2. 转换为 Chat 请求
+    let input_items = normalize_input(body.get("input"));
+    let openai_req = match build_chat_request(&body, &history, &input_items) {
+        Ok(r) => r,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &openai_req.model,
+        &*state.custom_mapping.read().await,
+    );
+    info!(
+        "Received responses request: {} -> {} ({} history items, {} new items, stream: {})",
+        openai_req.model,
+        mapped_model,
+        history.len(),
+        input_items.len(),
+        openai_req.stream
+    );
+    let tool_schemas = Arc::new(build_tool_schemas(&openai_req));
+
+    // 3. 上游调用 (始终流式，非流式请求在本地聚合)
+    let (mut chunks, email) = match open_upstream(&state, &openai_req, &mapped_model, tool_schemas).await {
+        Ok(v) => v,
+        Err(mut resp) => {
+            if let Ok(v) = mapped_model.parse() {
+                resp.headers_mut().insert("X-Mapped-Model", v);
+            }
+            return resp;
+        }
+    };
+
+    let skeleton = response_skeleton(&new_response_id(), &body, chrono::Utc::now().timestamp());
+    let mut machine = ResponsesStreamState::new(skeleton, &body);
+    let pending = store.then(|| PendingStore {
+        model: openai_req.model.clone(),
+        previous_response_id,
+        owner,
+        input_items,
+    });
+
+    if openai_req.stream {
+        let stream = async_stream::stream! {
+            for ev in machine.start() {
+                yield Ok::<Bytes, String>(Bytes::from(format_sse_event(&ev)));
+            }
+
+            let mut heartbeat = tokio::time::interval(std::time::Duration::from_secs(15));
+            heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            heartbeat.tick().await;
+
+            let mut failure = None;
+            loop {
+                tokio::select! {
+                    _ = heartbeat.tick() => {
+                        yield Ok(Bytes::from(": ping\n\n"));
+                    }
+                    item = chunks.next() => match item {
+                        Some(Ok(chunk)) => {
+                            for ev in machine.process_chunk(&chunk) {
+                                yield Ok(Bytes::from(format_sse_event(&ev)));
+                            }
+                        }
+                        Some(Err(e)) => {
+                            failure = Some(e);
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+            }
+
+            let events = match failure {
+                Some(e) => machine.fail(&e),
+                None => machine.finish(),
+            };
+            for ev in events {
+                yield Ok(Bytes::from(format_sse_event(&ev)));
+            }
+            if let Some(pending) = pending {
+                pending.save(machine.response()).await;
+            }
+        };
+
+        return Response::builder()
+            .header("Content-Type", "text/event-stream")
+            .header("Cache-Control", "no-cache")
+            .header("Connection", "keep-alive")
+            .header("X-Account-Email", &email)
+            .header("X-Mapped-Model", &mapped_model)
+            .body(Body::from_stream(stream))
+            .unwrap()
+            .into_response();
+    }
+
+    // 非流式: 聚合后返回完整 response 对象
+    machine.start();
+    let mut failure = None;
+    while let Some(item) = chunks.next().await {
+        match item {
+            Ok(chunk) => {
+                machine.process_chunk(&chunk);
+            }
+            Err(e) => {
+                failure = Some(e);
+                break;
+            }
+        }
+    }
+    if let Some(e) = failure {
+        machine.fail(&e);
+        return error_response(StatusCode::BAD_GATEWAY, format!("Stream collection error: {}", e));
+    }
+    machine.finish();
+    if let Some(pending) = pending {
+        pending.save(machine.response()).await;
+    }
+
+    (
+        StatusCode::OK,
+        [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())],
+        Json(machine.response().clone()),
+    )
+        .into_response()
+}
+
+/// 处理 GET /v1/responses/{id}
+pub async fn handle_get_response(
+    client_key: Option<Extension<ClientKeyIdentity>>,
+    Path(id): Path<String>,
+) -> Response {
+    let owner = client_key.map(|Extension(key)| key.id);
+    let lookup = id.clone();
+    match run_db(move |path| responses_db::get_response(&path, &lookup, owner.as_deref())).await {
+        Ok(Some(stored)) => Json(stored.response).into_response(),
+        Ok(None) => not_found(&id),
+        Err(resp) => resp,
+    }
+}
+
+/// 处理 DELETE /v1/responses/{id}
+pub async fn handle_delete_response(
+    client_key: Option<Extension<ClientKeyIdentity>>,
+    Path(id): Path<String>,
+) -> Response {
+    let owner = client_key.map(|Extension(key)| key.id);
+    let lookup = id.clone();
+    match run_db(move |path| responses_db::delete_response(&path, &lookup, owner.as_deref())).await {
+        Ok(true) => Json(json!({ "id": id, "object": "response", "deleted": true })).into_response(),
+        Ok(false) => not_found(&id),
+        Err(resp) => resp,
+    }
+}
+
+/// 处理 GET /v1/responses/{id}/input_items (本轮及之前所有轮次的输入项，不含模型输出)
+pub async fn handle_list_input_items(
+    client_key: Option<Extension<ClientKeyIdentity>>,
+    Path(id): Path<String>,
+) -> Response {
+    let owner = client_key.map(|Extension(key)| key.id);
+    let lookup = id.clone();
+    let chain = run_db(move |path| {
+        let mut items = Vec::new();
+        let mut next = Some(lookup);
+        while let Some(current) = next {
+            if items.len() >= responses_db::MAX_CHAIN_DEPTH {
+                return Err(format!(
+                    "Conversation chain exceeds {} responses",
+                    responses_db::MAX_CHAIN_DEPTH
+                ));
+            }
+            let Some(stored) = responses_db::get_response(&path, &current, owner.as_deref())? else {
+                break;
+            };
+            items.push(stored.input_items);
+            next = stored.previous_response_id;
+        }
+        Ok(items)
+    })
+    .await;
+
+    match chain {
+        Ok(chain) if chain.is_empty() => not_found(&id),
+        Ok(chain) => {
+            // OpenAI 默认按时间倒序返回
+            let data: Vec<Value> = chain
+                .into_iter()
+                .flat_map(|items| items.into_iter().rev())
+                .enumerate()
+                .map(|(i, mut item)| {
+                    if item.get("id").is_none() {
+                        item["id"] = json!(format!("{}_item_{}", id, i));
+                    }
+                    item
+                })
+                .collect();
+            Json(json!({
+                "object": "list",
+                "data": data,
+                "first_id": data.first().and_then(|i| i.get("id")).cloned(),
+                "last_id": data.last().and_then(|i| i.get("id")).cloned(),
+                "has_more": false
+            }))
+            .into_response()
+        }
+        Err(resp) => resp,
+    }
+}
@@ -168,16 +168,22 @@ pub async fn handle_warmup(
         ("streamGenerateContent", Some("alt=sse"))
     };
 
+    // [NEW] 预热同样走账号独立代理
+    let account_proxy = state
+        .token_manager
+        .get_account_id_by_email(&req.email)
+        .and_then(|id| state.token_manager.get_individual_proxy(&id));
+
     let mut result = state
         .upstream
-        .call_v1_internal(method, &access_token, body.clone(), query)
+        .call_v1_internal(method, &access_token, body.clone(), query, account_proxy.as_deref())
         .await;
 
     // 如果流式请求失败，尝试非流式请求
     if result.is_err() && !prefer_non_stream {
         result = state
             .upstream
-            .call_v1_internal("generateContent", &access_token, body, None)
+            .call_v1_internal("generateContent", &access_token, body, None, account_proxy.as_deref())
             .await;
     }
 
@@ -151,6 +151,12 @@ where
                                     current_signature = Some(sig.to_string());
                                 }
                             }
+                            // [FIX] 流式转换器在 thinking 块结束前以 signature_delta 发送签名
+                            "signature_delta" => {
+                                if let Some(sig) = delta.get("signature").and_then(|v| v.as_str()) {
+                                    current_signature = Some(sig.to_string());
+                                }
+                            }
                             "input_json_delta" => {
                                 if let Some(partial_json) = delta.get("partial_json").and_then(|v| v.as_str()) {
                                     current_tool_input.push_str(partial_json);
@@ -18,7 +18,9 @@ pub use collector::collect_stream_to_json;
 
 use bytes::Bytes;
 use futures::Stream;
+use crate::proxy::common::json_schema::ToolSchemas;
 use std::pin::Pin;
+use std::sync::Arc;
 
 /// 创建从 Gemini SSE 流到 Claude SSE 流的转换
 pub fn create_claude_sse_stream(
@@ -29,7 +31,9 @@ pub fn create_claude_sse_stream(
     scaling_enabled: bool, // [NEW] Flag for context usage scaling
     context_limit: u32,
     estimated_prompt_tokens: Option<u32>, // [FIX] Estimated tokens for calibrator learning
+    calibration_model: Option<String>, // [NEW] 估算所用模型，校准器按模型学习
     message_count: usize, // [NEW v4.0.0] Message count for rewind detection
+    tool_schemas: Arc<ToolSchemas>, // [NEW] 原始工具 Schema，用于修正 functionCall 参数
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> {
     use async_stream::stream;
     use bytes::BytesMut;
@@ -42,6 +46,8 @@ pub fn create_claude_sse_stream(
         state.scaling_enabled = scaling_enabled; // Set scaling enabled flag
         state.context_limit = context_limit;
         state.estimated_prompt_tokens = estimated_prompt_tokens; // [FIX] Pass estimated tokens
+        state.calibration_model = calibration_model;
+        state.tool_schemas = tool_schemas;
         let mut buffer = BytesMut::new();
 
         loop {
@@ -478,7 +484,9 @@ mod tests {
             false,
             1_000,
             None,
+            None,
             1, // message_count
+            Arc::new(ToolSchemas::new()),
         );
 
         // 3. 收集输出
@@ -222,12 +222,35 @@ pub struct Metadata {
 }
 
 /// Output Configuration (Claude API v2.0.67+)
-/// Controls effort level for model reasoning
+/// Controls effort level for model reasoning and structured outputs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutputConfig {
     /// Effort level: "high", "medium", "low"
     #[serde(skip_serializing_if = "Option::is_none")]
     pub effort: Option<String>,
+    /// Structured outputs: {"type": "json_schema", "schema": {...}}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<OutputFormat>,
+}
+
+impl OutputConfig {
+    /// type = "json_schema" 时返回用户提供的原始 Schema
+    pub fn json_schema(&self) -> Option<&serde_json::Value> {
+        let format = self.format.as_ref()?;
+        if format.type_ != "json_schema" {
+            return None;
+        }
+        format.schema.as_ref()
+    }
+}
+
+/// Structured outputs format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputFormat {
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
 }
 
 /// Claude API 响应
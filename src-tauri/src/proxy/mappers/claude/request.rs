@@ -408,6 +408,19 @@ fn reorder_gemini_parts(parts: &mut Vec<Value>) {
     parts.extend(tool_parts);
 }
 
+/// 工具名 → 原始 input_schema (请求参数修正与响应映射共用)
+pub fn build_tool_schemas(claude_req: &ClaudeRequest) -> HashMap<String, Value> {
+    let mut tool_name_to_schema = HashMap::new();
+    if let Some(tools) = &claude_req.tools {
+        for tool in tools {
+            if let (Some(name), Some(schema)) = (&tool.name, &tool.input_schema) {
+                tool_name_to_schema.insert(name.clone(), schema.clone());
+            }
+        }
+    }
+    tool_name_to_schema
+}
+
 pub fn transform_claude_request_in(
     claude_req: &ClaudeRequest,
     project_id: &str,
@@ -463,14 +476,7 @@ pub fn transform_claude_request_in(
         .unwrap_or(false);
 
     // [New] 预先构建工具名称到原始 Schema 的映射，用于后续参数类型修正
-    let mut tool_name_to_schema = HashMap::new();
-    if let Some(tools) = &claude_req.tools {
-        for tool in tools {
-            if let (Some(name), Some(schema)) = (&tool.name, &tool.input_schema) {
-                tool_name_to_schema.insert(name.clone(), schema.clone());
-            }
-        }
-    }
+    let tool_name_to_schema = build_tool_schemas(claude_req);
 
     // 1. System Instruction (注入动态身份防护 & MCP XML 协议)
     let system_instruction = build_system_instruction(&claude_req.system, &claude_req.model, has_mcp_tools);
@@ -662,6 +668,7 @@ pub fn transform_claude_request_in(
             if let Some(gen_obj) = gen_config.as_object_mut() {
                 gen_obj.remove("thinkingConfig");
                 gen_obj.remove("responseMimeType");
+                gen_obj.remove("responseSchema");
                 gen_obj.remove("responseModalities");
                 gen_obj.insert("imageConfig".to_string(), image_config);
             }
@@ -1724,6 +1731,11 @@ fn build_generation_config(
         config["topK"] = json!(top_k);
     }
 
+    // Structured outputs: output_config.format (json_schema) → responseSchema
+    if let Some(schema) = claude_req.output_config.as_ref().and_then(|c| c.json_schema()) {
+        crate::proxy::common::structured_output::apply_response_schema(&mut config, schema);
+    }
+
     // Effort level mapping (Claude API v2.0.67+)
     // Maps Claude's output_config.effort to Gemini's effortLevel
     if let Some(output_config) = &claude_req.output_config {
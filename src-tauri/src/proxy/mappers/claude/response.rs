@@ -3,7 +3,9 @@
 
 use super::models::*;
 use super::utils::to_claude_usage;
+use crate::proxy::common::json_schema::{fix_tool_call_args_by_name, ToolSchemas};
 use serde_json::json;
+use std::sync::Arc;
 
 /// Known parameter remappings for Gemini → Claude compatibility
 /// [FIX] Gemini sometimes uses different parameter names than specified in tool schema
@@ -158,6 +160,7 @@ pub struct NonStreamingProcessor {
     pub session_id: Option<String>,
     pub model_name: String,
     pub message_count: usize, // [NEW v4.0.0] Message count for rewind detection
+    pub tool_schemas: Arc<ToolSchemas>, // [NEW] 原始工具 Schema，用于修正参数类型
 }
 
 impl NonStreamingProcessor {
@@ -174,6 +177,7 @@ impl NonStreamingProcessor {
             session_id,
             model_name,
             message_count,
+            tool_schemas: Arc::new(ToolSchemas::new()),
         }
     }
 
@@ -297,6 +301,7 @@ impl NonStreamingProcessor {
             // [FIX] Remap args for Gemini → Claude compatibility
             let mut args = fc.args.clone().unwrap_or(serde_json::json!({}));
             remap_function_call_args(&tool_name, &mut args);
+            fix_tool_call_args_by_name(&tool_name, &mut args, &self.tool_schemas);
 
             let mut tool_use = ContentBlock::ToolUse {
                 id: tool_id,
@@ -545,8 +550,10 @@ pub fn transform_response(
     session_id: Option<String>,
     model_name: String,
     message_count: usize, // [NEW v4.0.0] Message count for rewind detection
+    tool_schemas: Arc<ToolSchemas>,
 ) -> Result<ClaudeResponse, String> {
     let mut processor = NonStreamingProcessor::new(session_id, model_name, message_count);
+    processor.tool_schemas = tool_schemas;
     Ok(processor.process(gemini_response, scaling_enabled, context_limit))
 }
 
@@ -590,6 +597,7 @@ mod tests {
             None,
             "gemini-2.5-flash".to_string(),
             1,
+            Arc::new(ToolSchemas::new()),
         );
         assert!(result.is_ok());
 
@@ -647,6 +655,7 @@ mod tests {
             None,
             "gemini-2.5-flash".to_string(),
             1,
+            Arc::new(ToolSchemas::new()),
         );
         assert!(result.is_ok());
 
@@ -6,7 +6,9 @@ use super::utils::to_claude_usage;
 use crate::proxy::mappers::estimation_calibrator::get_calibrator;
 // use crate::proxy::mappers::signature_store::store_thought_signature; // Deprecated
 use crate::proxy::SignatureCache;
+use crate::proxy::common::json_schema::{fix_tool_call_args_by_name, ToolSchemas};
 use bytes::Bytes;
+use std::sync::Arc;
 use serde_json::{json, Value};
 
 /// Known parameter remappings for Gemini → Claude compatibility
@@ -226,10 +228,14 @@ pub struct StreamingState {
     pub in_mcp_xml: bool,
     // [FIX] Estimated prompt tokens for calibrator learning
     pub estimated_prompt_tokens: Option<u32>,
+    // [NEW] 估算所用的模型 (映射后)，校准器按模型学习残差
+    pub calibration_model: Option<String>,
     // [FIX #859] Post-thinking interruption tracking
     pub has_thinking: bool,
     pub has_content: bool,
     pub message_count: usize, // [NEW v4.0.0] Message count for rewind detection
+    // [NEW] 原始工具 Schema，用于修正 functionCall 参数类型
+    pub tool_schemas: Arc<ToolSchemas>,
 }
 
 impl StreamingState {
@@ -254,9 +260,11 @@ impl StreamingState {
             mcp_xml_buffer: String::new(),
             in_mcp_xml: false,
             estimated_prompt_tokens: None,
+            calibration_model: None,
             has_thinking: false,
             has_content: false,
             message_count: 0,
+            tool_schemas: Arc::new(ToolSchemas::new()),
         }
     }
 
@@ -480,10 +488,16 @@ impl StreamingState {
                 if let (Some(estimated), Some(actual)) =
                     (self.estimated_prompt_tokens, u.prompt_token_count)
                 {
+                    let model = self
+                        .calibration_model
+                        .as_deref()
+                        .or(self.model_name.as_deref())
+                        .unwrap_or_default();
                     if estimated > 0 && actual > 0 {
-                        get_calibrator().record(estimated, actual);
+                        get_calibrator().record(model, estimated, actual);
                         tracing::debug!(
-                            "[Calibrator] Recorded: estimated={}, actual={}, ratio={:.2}x",
+                            "[Calibrator] Recorded: model={}, estimated={}, actual={}, ratio={:.2}x",
+                            model,
                             estimated,
                             actual,
                             actual as f64 / estimated as f64
@@ -1006,6 +1020,7 @@ impl<'a> PartProcessor<'a> {
                 final_tool_name = "Grep".to_string();
             }
             remap_function_call_args(&final_tool_name, &mut remapped_args);
+            fix_tool_call_args_by_name(&final_tool_name, &mut remapped_args, &self.state.tool_schemas);
 
             let json_str =
                 serde_json::to_string(&remapped_args).unwrap_or_else(|_| "{}".to_string());
@@ -3,39 +3,11 @@
 //! Responsible for estimating token usage and purifying context (stripping thinking blocks)
 //! to prevent "Prompt is too long" errors and avoid invalid signatures.
 
-use super::claude::models::{ClaudeRequest, ContentBlock, Message, MessageContent, SystemPrompt};
+use super::claude::models::{ClaudeRequest, ContentBlock, Message, MessageContent};
+use super::estimation_calibrator::get_calibrator;
+use crate::proxy::tokenizer::count_claude_request;
 use tracing::{debug, info};
 
-/// Helper to estimate tokens from text with multi-language awareness
-///
-/// Improved estimation algorithm:
-/// - ASCII/English: ~4 characters per token
-/// - Unicode/CJK: ~1.5 characters per token (Chinese, Japanese, Korean are tokenized differently)
-/// - Adds 15% safety margin to prevent underestimation
-fn estimate_tokens_from_str(s: &str) -> u32 {
-    if s.is_empty() {
-        return 0;
-    }
-
-    let mut ascii_chars = 0u32;
-    let mut unicode_chars = 0u32;
-
-    for c in s.chars() {
-        if c.is_ascii() {
-            ascii_chars += 1;
-        } else {
-            unicode_chars += 1;
-        }
-    }
-
-    // ASCII: ~4 chars/token, Unicode/CJK: ~1.5 chars/token
-    let ascii_tokens = (ascii_chars as f32 / 4.0).ceil() as u32;
-    let unicode_tokens = (unicode_chars as f32 / 1.5).ceil() as u32;
-
-    // Add 15% safety margin to account for tokenizer variations
-    ((ascii_tokens + unicode_tokens) as f32 * 1.15).ceil() as u32
-}
-
 /// Strategy for context purification
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PurificationStrategy {
@@ -101,97 +73,20 @@ impl ContextManager {
 }
 
 impl ContextManager {
-    /// Estimate token usage for a Claude Request
+    /// Estimate token usage for a Claude Request against the target model
     ///
-    /// This is a lightweight estimation, not a precise count.
-    /// It iterates through all messages and blocks to sum up estimated tokens.
-    pub fn estimate_token_usage(request: &ClaudeRequest) -> u32 {
-        let mut total = 0;
-
-        // System prompt
-        if let Some(sys) = &request.system {
-            match sys {
-                SystemPrompt::Text(s) => total += estimate_tokens_from_str(s),
-                SystemPrompt::Blocks(blocks) => {
-                    for block in blocks {
-                        total += estimate_tokens_from_str(&block.text);
-                    }
-                }
-            }
-        }
-
-        // Messages
-        for msg in &request.messages {
-            // Message overhead
-            total += 4;
-
-            match &msg.content {
-                MessageContent::String(s) => {
-                    total += estimate_tokens_from_str(s);
-                }
-                MessageContent::Array(blocks) => {
-                    for block in blocks {
-                        match block {
-                            ContentBlock::Text { text, .. } => {
-                                total += estimate_tokens_from_str(text);
-                            }
-                            ContentBlock::Thinking { thinking, .. } => {
-                                total += estimate_tokens_from_str(thinking);
-                                // Signature overhead
-                                total += 100;
-                            }
-                            ContentBlock::RedactedThinking { data } => {
-                                total += estimate_tokens_from_str(data);
-                            }
-                            ContentBlock::ToolUse { name, input, .. } => {
-                                total += 20; // Function call overhead
-                                total += estimate_tokens_from_str(name);
-                                if let Ok(json_str) = serde_json::to_string(input) {
-                                    total += estimate_tokens_from_str(&json_str);
-                                }
-                            }
-                            ContentBlock::ToolResult { content, .. } => {
-                                total += 10; // Result overhead
-                                             // content is serde_json::Value
-                                if let Some(s) = content.as_str() {
-                                    total += estimate_tokens_from_str(s);
-                                } else if let Some(arr) = content.as_array() {
-                                    for item in arr {
-                                        if let Some(text) =
-                                            item.get("text").and_then(|t| t.as_str())
-                                        {
-                                            total += estimate_tokens_from_str(text);
-                                        }
-                                    }
-                                } else {
-                                    // Fallback for objects or other types
-                                    if let Ok(s) = serde_json::to_string(content) {
-                                        total += estimate_tokens_from_str(&s);
-                                    }
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-            }
-        }
-
-        // Tools definition overhead (rough estimate)
-        if let Some(tools) = &request.tools {
-            for tool in tools {
-                if let Ok(json_str) = serde_json::to_string(tool) {
-                    total += estimate_tokens_from_str(&json_str);
-                }
-            }
-        }
+    /// Input tokens are estimated with the offline tokenizer of the model's family
+    /// (text, tool schemas, images, thinking) and corrected by the model's learned
+    /// calibration residual. The thinking budget is reserved on top, since it
+    /// consumes context without being part of the input.
+    pub fn estimate_token_usage(request: &ClaudeRequest, model: &str) -> u32 {
+        let input = count_claude_request(request, model).total();
+        let mut total = get_calibrator().calibrate(model, input);
 
         // Thinking budget overhead if enabled
-        if let Some(thinking) = &request.thinking {
-            if let Some(budget) = thinking.budget_tokens {
-                // Reserve budget in estimation
-                total += budget;
-            }
+        if let Some(budget) = request.thinking.as_ref().and_then(|t| t.budget_tokens) {
+            // Reserve budget in estimation
+            total += budget;
         }
 
         total
@@ -469,7 +364,7 @@ mod tests {
             content: MessageContent::String("Hello World".into()),
         }];
 
-        let tokens = ContextManager::estimate_token_usage(&req);
+        let tokens = ContextManager::estimate_token_usage(&req, &req.model);
         assert!(tokens > 0);
         assert!(tokens < 50);
     }
@@ -3,100 +3,209 @@
 //! Learns from historical request/response pairs to improve token estimation accuracy.
 //! Uses actual token counts from Google API responses to calibrate future estimates.
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use futures::StreamExt;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::pin::Pin;
 use std::sync::RwLock;
 use tracing::info;
 
-/// Estimation Calibrator - learns estimation error from historical requests
+use crate::proxy::tokenizer::ModelFamily;
+
+/// Factor used before any samples exist. The offline tokenizer is already
+/// model-aware, so we start neutral instead of the old 2.0 fudge factor.
+const DEFAULT_FACTOR: f32 = 1.0;
+
+/// Update the factor every N samples
+const UPDATE_INTERVAL: u64 = 5;
+
+/// Weight of the newest window in the exponential moving average
+const EMA_ALPHA: f32 = 0.4;
+
+/// Residual between offline estimates and upstream counts
+#[derive(Debug, Clone)]
+struct Residual {
+    /// Estimated tokens in the current window (reset after each update)
+    window_estimated: u64,
+    /// Actual tokens in the current window (from upstream usage)
+    window_actual: u64,
+    /// Total sample count
+    sample_count: u64,
+    /// Current calibration factor (estimated * factor ≈ actual)
+    factor: f32,
+}
+
+impl Residual {
+    fn new() -> Self {
+        Self {
+            window_estimated: 0,
+            window_actual: 0,
+            sample_count: 0,
+            factor: DEFAULT_FACTOR,
+        }
+    }
+
+    /// Whether the factor has been learned from real data at least once
+    fn is_trained(&self) -> bool {
+        self.sample_count >= UPDATE_INTERVAL
+    }
+}
+
+/// Estimation Calibrator - learns per-model estimation residuals from historical requests
 ///
 /// This module tracks the ratio between estimated tokens (before request) and
-/// actual tokens (from Google API response) to improve future estimations.
+/// actual tokens (from Google API response) for each model, plus an aggregate per
+/// model family. Models without enough samples of their own fall back to the
+/// family residual, then to the neutral default.
 pub struct EstimationCalibrator {
-    /// Cumulative estimated tokens
-    total_estimated: AtomicU64,
-    /// Cumulative actual tokens (from Google API)
-    total_actual: AtomicU64,
-    /// Sample count
-    sample_count: AtomicU64,
-    /// Current calibration factor (estimated * factor ≈ actual)
-    calibration_factor: RwLock<f32>,
+    /// Residuals keyed by normalized model name and by family (`family:<name>`)
+    residuals: RwLock<HashMap<String, Residual>>,
+}
+
+/// Normalize a model name into a residual key
+fn model_key(model: &str) -> String {
+    model.trim_start_matches("models/").to_lowercase()
+}
+
+fn family_key(model: &str) -> String {
+    format!("family:{}", ModelFamily::from_model(model).as_str())
 }
 
 impl EstimationCalibrator {
     /// Create a new calibrator with default settings
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
-            total_estimated: AtomicU64::new(0),
-            total_actual: AtomicU64::new(0),
-            sample_count: AtomicU64::new(0),
-            // Initial assumption: estimates are 2.0x lower than actual
-            // This is conservative and will be adjusted based on real data
-            calibration_factor: RwLock::new(2.0),
+            residuals: RwLock::new(HashMap::new()),
         }
     }
 
-    /// Record a request's estimated vs actual token counts
+    /// Record a request's estimated vs actual token counts for a model
     ///
-    /// Call this after receiving a response from Google API with actual token usage.
-    pub fn record(&self, estimated: u32, actual: u32) {
+    /// Call this after receiving a response with actual upstream prompt token usage.
+    pub fn record(&self, model: &str, estimated: u32, actual: u32) {
         if estimated == 0 || actual == 0 {
             return;
         }
 
-        self.total_estimated
-            .fetch_add(estimated as u64, Ordering::Relaxed);
-        self.total_actual
-            .fetch_add(actual as u64, Ordering::Relaxed);
-        let count = self.sample_count.fetch_add(1, Ordering::Relaxed) + 1;
+        let Ok(mut residuals) = self.residuals.write() else {
+            return;
+        };
+        for key in [model_key(model), family_key(model)] {
+            let residual = residuals.entry(key.clone()).or_insert_with(Residual::new);
+            residual.window_estimated += estimated as u64;
+            residual.window_actual += actual as u64;
+            residual.sample_count += 1;
 
-        // Update calibration factor every 5 requests
-        if count % 5 == 0 {
-            self.update_calibration();
+            // Update calibration factor every 5 requests
+            if residual.sample_count % UPDATE_INTERVAL == 0 {
+                Self::update_calibration(&key, residual);
+            }
         }
     }
 
-    /// Update the calibration factor based on accumulated data
-    fn update_calibration(&self) {
-        let estimated = self.total_estimated.load(Ordering::Relaxed) as f64;
-        let actual = self.total_actual.load(Ordering::Relaxed) as f64;
+    /// Fold the current window's ratio into the factor and start a new window
+    fn update_calibration(key: &str, residual: &mut Residual) {
+        let estimated = residual.window_estimated as f64;
+        let actual = residual.window_actual as f64;
+        residual.window_estimated = 0;
+        residual.window_actual = 0;
 
         if estimated > 0.0 {
             let new_factor = (actual / estimated) as f32;
-            // Clamp to reasonable range [0.8, 4.0]
-            // - Below 0.8 means we're overestimating (rare)
-            // - Above 4.0 means severe underestimation
-            let clamped = new_factor.clamp(0.8, 4.0);
-
-            if let Ok(mut factor) = self.calibration_factor.write() {
-                // Exponential moving average: 60% old + 40% new
-                // This provides stability while still adapting to changes
-                let old = *factor;
-                *factor = old * 0.6 + clamped * 0.4;
-
-                info!(
-                    "[Calibrator] Updated factor: {:.2} -> {:.2} (raw: {:.2}, samples: {})",
-                    old,
-                    *factor,
-                    new_factor,
-                    self.sample_count.load(Ordering::Relaxed)
-                );
-            }
+            // Clamp to reasonable range [0.5, 3.0]
+            // The estimator may now overestimate as well as underestimate
+            let clamped = new_factor.clamp(0.5, 3.0);
+
+            // Exponential moving average over windows: 60% old + 40% new
+            // This provides stability while still adapting to changes
+            // (the first window is adopted directly)
+            let old = residual.factor;
+            residual.factor = if residual.sample_count == UPDATE_INTERVAL {
+                clamped
+            } else {
+                old * (1.0 - EMA_ALPHA) + clamped * EMA_ALPHA
+            };
+
+            info!(
+                "[Calibrator] Updated factor for {}: {:.2} -> {:.2} (raw: {:.2}, samples: {})",
+                key, old, residual.factor, new_factor, residual.sample_count
+            );
         }
     }
 
     /// Get a calibrated estimate from a raw estimate
     ///
-    /// Multiplies the raw estimate by the current calibration factor.
-    pub fn calibrate(&self, estimated: u32) -> u32 {
-        let factor = self.calibration_factor.read().map(|f| *f).unwrap_or(2.0);
+    /// Multiplies the raw estimate by the model's current calibration factor.
+    pub fn calibrate(&self, model: &str, estimated: u32) -> u32 {
+        (estimated as f32 * self.get_factor(model)).ceil() as u32
+    }
 
-        (estimated as f32 * factor).ceil() as u32
+    /// Get the current calibration factor for a model
+    ///
+    /// Uses the model's own residual once trained, otherwise its family's.
+    pub fn get_factor(&self, model: &str) -> f32 {
+        let Ok(residuals) = self.residuals.read() else {
+            return DEFAULT_FACTOR;
+        };
+        [model_key(model), family_key(model)]
+            .iter()
+            .filter_map(|key| residuals.get(key))
+            .find(|r| r.is_trained())
+            .map(|r| r.factor)
+            .unwrap_or(DEFAULT_FACTOR)
     }
+}
 
-    /// Get the current calibration factor
-    pub fn get_factor(&self) -> f32 {
-        self.calibration_factor.read().map(|f| *f).unwrap_or(2.0)
+/// Extract `usageMetadata.promptTokenCount` from a Gemini response (plain or v1internal-wrapped)
+pub fn prompt_token_count(response: &Value) -> Option<u32> {
+    response
+        .get("response")
+        .unwrap_or(response)
+        .get("usageMetadata")?
+        .get("promptTokenCount")?
+        .as_u64()
+        .map(|n| n as u32)
+}
+
+type UpstreamStream = Pin<Box<dyn futures::Stream<Item = Result<bytes::Bytes, reqwest::Error>> + Send>>;
+
+/// Wrap an upstream Gemini SSE stream and record a calibration sample for `model`
+/// from the last `promptTokenCount` seen once the stream ends.
+pub fn record_from_stream(stream: UpstreamStream, model: String, estimated: u32) -> UpstreamStream {
+    if estimated == 0 {
+        return stream;
     }
+
+    let wrapped = async_stream::stream! {
+        let mut inner = stream;
+        let mut pending: Vec<u8> = Vec::new();
+        let mut actual = None;
+        while let Some(item) = inner.next().await {
+            if let Ok(bytes) = &item {
+                pending.extend_from_slice(bytes);
+                while let Some(pos) = pending.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=pos).collect();
+                    let Some(data) = std::str::from_utf8(&line).ok().and_then(|l| l.trim().strip_prefix("data:")) else {
+                        continue;
+                    };
+                    if !data.contains("promptTokenCount") {
+                        continue;
+                    }
+                    if let Some(count) = serde_json::from_str::<Value>(data.trim()).ok().as_ref().and_then(prompt_token_count) {
+                        actual = Some(count);
+                    }
+                }
+            }
+            yield item;
+        }
+
+        if let Some(actual) = actual {
+            get_calibrator().record(&model, estimated, actual);
+            tracing::debug!("[Calibrator] Recorded: model={}, estimated={}, actual={}", model, estimated, actual);
+        }
+    };
+
+    Box::pin(wrapped)
 }
 
 impl Default for EstimationCalibrator {
@@ -123,27 +232,74 @@ mod tests {
     fn test_calibrator_basic() {
         let calibrator = EstimationCalibrator::new();
 
-        // Initial factor should be 2.0
-        assert!((calibrator.get_factor() - 2.0).abs() < 0.01);
+        // Initial factor should be neutral
+        assert!((calibrator.get_factor("gemini-3-flash") - 1.0).abs() < 0.01);
 
         // Record some samples where actual is 3x estimated
         for _ in 0..10 {
-            calibrator.record(100, 300);
+            calibrator.record("gemini-3-flash", 100, 300);
         }
 
         // Factor should have moved towards 3.0
-        let factor = calibrator.get_factor();
+        let factor = calibrator.get_factor("gemini-3-flash");
         assert!(factor > 2.0);
-        assert!(factor < 3.5);
+        assert!(factor <= 3.0);
+
+        // Untrained models of the same family use the family residual,
+        // other families are unaffected
+        assert!((calibrator.get_factor("models/gemini-3-pro-high") - factor).abs() < 0.01);
+        assert!((calibrator.get_factor("claude-sonnet-4-5") - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_per_model_residuals() {
+        let calibrator = EstimationCalibrator::new();
+
+        for _ in 0..5 {
+            calibrator.record("gemini-3-flash", 100, 120);
+            calibrator.record("gemini-3-pro-high", 100, 80);
+        }
+
+        // Each model learns its own residual instead of sharing one factor
+        assert!((calibrator.get_factor("gemini-3-flash") - 1.2).abs() < 0.01);
+        assert!((calibrator.get_factor("gemini-3-pro-high") - 0.8).abs() < 0.01);
+        // The family residual aggregates both
+        let family = calibrator.get_factor("gemini-2.5-flash");
+        assert!(family > 0.95 && family < 1.1);
+    }
+
+    #[test]
+    fn test_factor_tracks_recent_windows() {
+        let calibrator = EstimationCalibrator::new();
+
+        for _ in 0..5 {
+            calibrator.record("gemini-3-flash", 100, 200);
+        }
+        assert!((calibrator.get_factor("gemini-3-flash") - 2.0).abs() < 0.01);
+
+        // The next window only sees its own samples: 2.0 * 0.6 + 1.0 * 0.4
+        for _ in 0..5 {
+            calibrator.record("gemini-3-flash", 100, 100);
+        }
+        assert!((calibrator.get_factor("gemini-3-flash") - 1.6).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_prompt_token_count() {
+        let wrapped = serde_json::json!({ "response": { "usageMetadata": { "promptTokenCount": 42 } } });
+        let plain = serde_json::json!({ "usageMetadata": { "promptTokenCount": 7 } });
+        assert_eq!(prompt_token_count(&wrapped), Some(42));
+        assert_eq!(prompt_token_count(&plain), Some(7));
+        assert_eq!(prompt_token_count(&serde_json::json!({ "candidates": [] })), None);
     }
 
     #[test]
     fn test_calibrate() {
         let calibrator = EstimationCalibrator::new();
 
-        // With default factor of 2.0, 100 should become 200
-        let calibrated = calibrator.calibrate(100);
-        assert_eq!(calibrated, 200);
+        // With the default factor of 1.0, 100 stays 100
+        let calibrated = calibrator.calibrate("claude-opus-4-5", 100);
+        assert_eq!(calibrated, 100);
     }
 
     #[test]
@@ -151,9 +307,9 @@ mod tests {
         let calibrator = EstimationCalibrator::new();
 
         // Recording zeros should not affect anything
-        calibrator.record(0, 100);
-        calibrator.record(100, 0);
+        calibrator.record("gemini-3-flash", 0, 100);
+        calibrator.record("gemini-3-flash", 100, 0);
 
-        assert_eq!(calibrator.sample_count.load(Ordering::Relaxed), 0);
+        assert!(calibrator.residuals.read().unwrap().is_empty());
     }
 }
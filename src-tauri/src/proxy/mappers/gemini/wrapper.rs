@@ -1,4 +1,5 @@
 // Gemini v1internal 包装/解包
+use crate::proxy::common::json_schema::ToolSchemas;
 use serde_json::{json, Value};
 
 /// 包装请求体为 v1internal 格式
@@ -236,6 +237,25 @@ pub fn unwrap_response(response: &Value) -> Value {
     response.get("response").unwrap_or(response).clone()
 }
 
+/// 工具名 → 原始参数 Schema (functionDeclarations[].parameters / parametersJsonSchema)
+pub fn build_tool_schemas(body: &Value) -> ToolSchemas {
+    let mut schemas = ToolSchemas::new();
+    let Some(tools) = body.get("tools").and_then(|t| t.as_array()) else {
+        return schemas;
+    };
+    for decl in tools
+        .iter()
+        .filter_map(|t| t.get("functionDeclarations").and_then(|d| d.as_array()))
+        .flatten()
+    {
+        let schema = decl.get("parameters").or_else(|| decl.get("parametersJsonSchema"));
+        if let (Some(name), Some(schema)) = (decl.get("name").and_then(|n| n.as_str()), schema) {
+            schemas.insert(name.to_string(), schema.clone());
+        }
+    }
+    schemas
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -43,7 +43,9 @@ pub struct UserInputMessage {
 #[serde(rename_all = "camelCase")]
 pub struct UserInputMessageContext {
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub tools: Vec<Value>, // Tool specifications (optional)
+    pub tools: Vec<Value>, // [{ toolSpecification: { name, description, inputSchema: { json } } }]
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub tool_results: Vec<Value>, // [{ toolUseId, status, content: [{ text }] }]
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,7 +62,7 @@ pub struct HistoryMessage {
 pub struct AssistantResponseMessage {
     pub content: String,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub tool_uses: Vec<Value>,
+    pub tool_uses: Vec<Value>, // [{ toolUseId, name, input }]
 }
 
 /// AWS Event Stream Event Types
@@ -69,7 +71,20 @@ pub struct AssistantResponseMessage {
 pub enum KiroEvent {
     #[serde(rename = "assistantResponseEvent")]
     AssistantResponse { content: String },
-    
+
+    /// Нативный вызов инструмента. `input` приходит фрагментами JSON-строки,
+    /// последний фрагмент помечен `stop: true`
+    #[serde(rename = "toolUseEvent")]
+    ToolUse {
+        name: String,
+        #[serde(rename = "toolUseId")]
+        tool_use_id: String,
+        #[serde(default)]
+        input: Option<Value>,
+        #[serde(default)]
+        stop: Option<bool>,
+    },
+
     #[serde(rename = "meteringEvent")]
     Metering {
         unit: String,
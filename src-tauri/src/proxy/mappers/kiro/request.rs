@@ -1,7 +1,10 @@
 // Kiro Request конвертация (Anthropic → Kiro)
 
 use super::models::*;
-use crate::proxy::mappers::claude::models::{ClaudeRequest, Message as ClaudeMessage};
+use crate::proxy::mappers::claude::models::{
+    ClaudeRequest, ContentBlock, Message as ClaudeMessage, MessageContent, Tool,
+};
+use serde_json::{json, Value};
 use uuid::Uuid;
 
 /// Конвертирует Anthropic Claude запрос в Kiro формат
@@ -21,6 +24,19 @@ pub fn convert_claude_to_kiro(
     
     // Строим историю из предыдущих сообщений
     let history = build_history(&claude_req.messages);
+
+    // Инструменты и результаты их вызовов передаются через userInputMessageContext
+    let tools = claude_req
+        .tools
+        .as_deref()
+        .map(build_tool_specifications)
+        .unwrap_or_default();
+    let tool_results = claude_req
+        .messages
+        .last()
+        .filter(|m| m.role == "user")
+        .map(extract_tool_results)
+        .unwrap_or_default();
     
     KiroRequest {
         conversation_state: ConversationState {
@@ -34,7 +50,7 @@ pub fn convert_claude_to_kiro(
                     content: current_content,
                     model_id,
                     origin: "AI_EDITOR".to_string(),
-                    user_input_message_context: None, // Tools пока не поддерживаем
+                    user_input_message_context: build_context(tools, tool_results),
                 },
             },
         },
@@ -42,6 +58,14 @@ pub fn convert_claude_to_kiro(
     }
 }
 
+/// Передаёт ли запрос клиентские tools (тогда Kiro отвечает нативными toolUseEvent)
+pub fn uses_native_tools(claude_req: &ClaudeRequest) -> bool {
+    claude_req
+        .tools
+        .as_ref()
+        .is_some_and(|tools| tools.iter().any(|t| t.input_schema.is_some()))
+}
+
 /// Извлекает model_id из имени модели
 fn extract_model_id(model: &str) -> String {
     // Маппинг моделей Factory Droid → Kiro API (проверено тестами):
@@ -139,7 +163,10 @@ fn build_history(messages: &[ClaudeMessage]) -> Vec<HistoryMessage> {
                     content: extract_text_from_message(msg),
                     model_id: extract_model_id("auto"), // Используем auto для истории
                     origin: "AI_EDITOR".to_string(),
-                    user_input_message_context: None,
+                    user_input_message_context: build_context(
+                        Vec::new(),
+                        extract_tool_results(msg),
+                    ),
                 }),
                 assistant_response_message: None,
             },
@@ -147,7 +174,7 @@ fn build_history(messages: &[ClaudeMessage]) -> Vec<HistoryMessage> {
                 user_input_message: None,
                 assistant_response_message: Some(AssistantResponseMessage {
                     content: extract_text_from_message(msg),
-                    tool_uses: Vec::new(), // Tools пока не поддерживаем
+                    tool_uses: extract_tool_uses(msg),
                 }),
             },
             _ => continue,
@@ -159,17 +186,110 @@ fn build_history(messages: &[ClaudeMessage]) -> Vec<HistoryMessage> {
     history
 }
 
+/// Собирает userInputMessageContext, если есть что передавать
+fn build_context(tools: Vec<Value>, tool_results: Vec<Value>) -> Option<UserInputMessageContext> {
+    if tools.is_empty() && tool_results.is_empty() {
+        return None;
+    }
+    Some(UserInputMessageContext { tools, tool_results })
+}
+
+/// Конвертирует Claude tools в Kiro toolSpecification
+///
+/// Серверные инструменты (web_search и т.п.) не имеют input_schema и Kiro их не поддерживает
+fn build_tool_specifications(tools: &[Tool]) -> Vec<Value> {
+    tools
+        .iter()
+        .filter_map(|tool| {
+            let schema = match &tool.input_schema {
+                Some(schema) => schema.clone(),
+                None => {
+                    tracing::debug!("[Kiro] Skipping server tool without input_schema: {}", tool.get_name());
+                    return None;
+                }
+            };
+            let name = tool.get_name();
+            // Kiro отклоняет пустое описание инструмента
+            let description = tool
+                .description
+                .clone()
+                .filter(|d| !d.trim().is_empty())
+                .unwrap_or_else(|| name.clone());
+
+            Some(json!({
+                "toolSpecification": {
+                    "name": name,
+                    "description": description,
+                    "inputSchema": { "json": schema }
+                }
+            }))
+        })
+        .collect()
+}
+
+/// Извлекает tool_result блоки из user сообщения в формате Kiro toolResults
+fn extract_tool_results(msg: &ClaudeMessage) -> Vec<Value> {
+    let MessageContent::Array(blocks) = &msg.content else {
+        return Vec::new();
+    };
+
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::ToolResult { tool_use_id, content, is_error } => Some(json!({
+                "toolUseId": tool_use_id,
+                "status": if is_error.unwrap_or(false) { "error" } else { "success" },
+                "content": [{ "text": tool_result_text(content) }]
+            })),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Извлекает tool_use блоки из assistant сообщения в формате Kiro toolUses
+fn extract_tool_uses(msg: &ClaudeMessage) -> Vec<Value> {
+    let MessageContent::Array(blocks) = &msg.content else {
+        return Vec::new();
+    };
+
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::ToolUse { id, name, input, .. } => Some(json!({
+                "toolUseId": id,
+                "name": name,
+                "input": input
+            })),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Содержимое tool_result может быть строкой или массивом блоков - Kiro принимает только текст
+fn tool_result_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|b| match b {
+                Value::String(text) => Some(text.clone()),
+                _ => b.get("text").and_then(|t| t.as_str()).map(|t| t.to_string()),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
 /// Извлекает текст из Claude сообщения
 fn extract_text_from_message(msg: &ClaudeMessage) -> String {
-    use crate::proxy::mappers::claude::models::MessageContent;
-    
     match &msg.content {
         MessageContent::String(text) => text.clone(),
         MessageContent::Array(blocks) => {
             blocks
                 .iter()
                 .filter_map(|block| {
-                    use crate::proxy::mappers::claude::models::ContentBlock;
                     match block {
                         ContentBlock::Text { text, .. } => Some(text.clone()),
                         ContentBlock::Thinking { thinking, .. } => {
@@ -188,6 +308,135 @@ fn extract_text_from_message(msg: &ClaudeMessage) -> String {
 mod tests {
     use super::*;
     
+    fn tool_conversation() -> ClaudeRequest {
+        serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "stream": true,
+            "tools": [
+                {
+                    "name": "Edit",
+                    "description": "Edit a file",
+                    "input_schema": {
+                        "type": "object",
+                        "properties": { "file_path": { "type": "string" } },
+                        "required": ["file_path"]
+                    }
+                },
+                { "name": "mcp__fs__stat", "input_schema": { "type": "object" } },
+                { "type": "web_search_20250305", "name": "web_search" }
+            ],
+            "messages": [
+                { "role": "user", "content": "Fix the typo in main.rs" },
+                {
+                    "role": "assistant",
+                    "content": [
+                        { "type": "text", "text": "Reading the file." },
+                        { "type": "tool_use", "id": "tooluse_1", "name": "Read", "input": { "file_path": "main.rs" } }
+                    ]
+                },
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "tool_result", "tool_use_id": "tooluse_1", "content": [{ "type": "text", "text": "fn mian() {}" }] }
+                    ]
+                },
+                {
+                    "role": "assistant",
+                    "content": [
+                        { "type": "tool_use", "id": "tooluse_2", "name": "Edit", "input": { "file_path": "main.rs" } }
+                    ]
+                },
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "tool_result", "tool_use_id": "tooluse_2", "content": "permission denied", "is_error": true }
+                    ]
+                }
+            ]
+        }))
+        .unwrap()
+    }
+    
+    #[test]
+    fn test_tools_mapped_to_user_input_context() {
+        let req = tool_conversation();
+        assert!(uses_native_tools(&req));
+        
+        let kiro_req = convert_claude_to_kiro(&req, "arn:test", None);
+        let context = kiro_req
+            .conversation_state
+            .current_message
+            .user_input_message
+            .user_input_message_context
+            .expect("context with tools");
+        
+        // web_search без input_schema пропускается
+        assert_eq!(context.tools.len(), 2);
+        let spec = &context.tools[0]["toolSpecification"];
+        assert_eq!(spec["name"], "Edit");
+        assert_eq!(spec["description"], "Edit a file");
+        assert_eq!(spec["inputSchema"]["json"]["required"][0], "file_path");
+        // Пустое описание заменяется именем инструмента
+        assert_eq!(context.tools[1]["toolSpecification"]["description"], "mcp__fs__stat");
+        
+        // tool_result последнего user сообщения уходит в currentMessage
+        assert_eq!(context.tool_results.len(), 1);
+        assert_eq!(context.tool_results[0]["toolUseId"], "tooluse_2");
+        assert_eq!(context.tool_results[0]["status"], "error");
+        assert_eq!(context.tool_results[0]["content"][0]["text"], "permission denied");
+    }
+    
+    #[test]
+    fn test_tool_history_mapping() {
+        let kiro_req = convert_claude_to_kiro(&tool_conversation(), "arn:test", None);
+        let history = &kiro_req.conversation_state.history;
+        assert_eq!(history.len(), 4);
+        
+        let assistant = history[1].assistant_response_message.as_ref().unwrap();
+        assert_eq!(assistant.content, "Reading the file.");
+        assert_eq!(assistant.tool_uses, vec![json!({
+            "toolUseId": "tooluse_1",
+            "name": "Read",
+            "input": { "file_path": "main.rs" }
+        })]);
+        
+        let results = &history[2]
+            .user_input_message
+            .as_ref()
+            .unwrap()
+            .user_input_message_context
+            .as_ref()
+            .unwrap()
+            .tool_results;
+        assert_eq!(results[0]["toolUseId"], "tooluse_1");
+        assert_eq!(results[0]["status"], "success");
+        assert_eq!(results[0]["content"][0]["text"], "fn mian() {}");
+        
+        // Tools передаются только в currentMessage
+        let serialized = serde_json::to_value(&kiro_req).unwrap();
+        assert!(serialized["conversationState"]["history"][0]["userInputMessage"]
+            .get("userInputMessageContext")
+            .is_none());
+    }
+    
+    #[test]
+    fn test_no_tools_keeps_context_empty() {
+        let req: ClaudeRequest = serde_json::from_value(json!({
+            "model": "auto",
+            "messages": [{ "role": "user", "content": "hi" }]
+        }))
+        .unwrap();
+        assert!(!uses_native_tools(&req));
+        
+        let kiro_req = convert_claude_to_kiro(&req, "arn:test", None);
+        assert!(kiro_req
+            .conversation_state
+            .current_message
+            .user_input_message
+            .user_input_message_context
+            .is_none());
+    }
+    
     #[test]
     fn test_extract_model_id() {
         // Claude модели: заменяем последний дефис на точку
@@ -1,19 +1,26 @@
 // Kiro Response конвертация (Kiro → Anthropic Claude)
 // Non-streaming response
 
-use crate::proxy::mappers::claude::models::{ClaudeResponse, Usage};
+use crate::proxy::mappers::claude::models::{ClaudeResponse, ContentBlock, Usage};
 use super::command_parser::parse_commands_from_text;
+use super::streaming::KiroCollected;
 
 /// Конвертирует собранный Kiro response в Claude формат
+///
+/// `parse_commands` включает разбор XML команд из текста - нужен только когда клиент
+/// не передал tools и Kiro отвечает встроенными командами вместо toolUseEvent
 pub fn convert_kiro_to_claude(
-    content: String,
+    collected: KiroCollected,
     model: String,
     usage: Option<(u32, u32)>, // (input_tokens, output_tokens)
+    parse_commands: bool,
 ) -> ClaudeResponse {
     let (input_tokens, output_tokens) = usage.unwrap_or((0, 0));
     
-    // Парсим команды из текста и конвертируем в content blocks
-    let content_blocks = parse_commands_from_text(&content);
+    let content_blocks = build_content_blocks(collected, parse_commands);
+    let has_tool_use = content_blocks
+        .iter()
+        .any(|b| matches!(b, ContentBlock::ToolUse { .. }));
     
     ClaudeResponse {
         id: format!("msg_{}", uuid::Uuid::new_v4()),
@@ -21,7 +28,7 @@ pub fn convert_kiro_to_claude(
         role: "assistant".to_string(),
         content: content_blocks,
         model,
-        stop_reason: "end_turn".to_string(),
+        stop_reason: if has_tool_use { "tool_use" } else { "end_turn" }.to_string(),
         stop_sequence: None,
         usage: Usage {
             input_tokens,
@@ -32,3 +39,78 @@ pub fn convert_kiro_to_claude(
         },
     }
 }
+
+/// Строит Claude content blocks: текст (опционально с разбором XML команд) + нативные tool_use
+pub fn build_content_blocks(collected: KiroCollected, parse_commands: bool) -> Vec<ContentBlock> {
+    let mut blocks = if parse_commands {
+        parse_commands_from_text(&collected.text)
+    } else if !collected.text.is_empty() || collected.tool_uses.is_empty() {
+        vec![ContentBlock::Text {
+            text: collected.text,
+            cache_control: None,
+        }]
+    } else {
+        Vec::new()
+    };
+    
+    // parse_commands_from_text возвращает пустой text блок для пустого ответа
+    if !collected.tool_uses.is_empty() {
+        blocks.retain(|b| !matches!(b, ContentBlock::Text { text, .. } if text.trim().is_empty()));
+    }
+    
+    for tool_use in collected.tool_uses {
+        blocks.push(ContentBlock::ToolUse {
+            input: tool_use.input(),
+            id: tool_use.id,
+            name: tool_use.name,
+            signature: None,
+            cache_control: None,
+        });
+    }
+    
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::mappers::kiro::streaming::KiroToolUse;
+    
+    fn collected_with_tool(text: &str) -> KiroCollected {
+        KiroCollected {
+            text: text.to_string(),
+            tool_uses: vec![KiroToolUse {
+                id: "tooluse_1".to_string(),
+                name: "Bash".to_string(),
+                input_json: r#"{"command":"ls"}"#.to_string(),
+            }],
+        }
+    }
+    
+    #[test]
+    fn test_native_tool_use_sets_stop_reason() {
+        let resp = convert_kiro_to_claude(collected_with_tool("Listing."), "auto".to_string(), None, false);
+        assert_eq!(resp.stop_reason, "tool_use");
+        assert_eq!(resp.content.len(), 2);
+        match &resp.content[1] {
+            ContentBlock::ToolUse { id, name, input, .. } => {
+                assert_eq!(id, "tooluse_1");
+                assert_eq!(name, "Bash");
+                assert_eq!(input["command"], "ls");
+            }
+            other => panic!("Expected tool_use, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_native_tools_skip_xml_command_parsing() {
+        let text = "<ls><path>.</path></ls>";
+        let blocks = build_content_blocks(collected_with_tool(text), false);
+        assert_eq!(blocks.len(), 2);
+        assert!(matches!(&blocks[0], ContentBlock::Text { text: t, .. } if t == text));
+        
+        // Пустой текст не порождает лишний text блок
+        let blocks = build_content_blocks(collected_with_tool(""), true);
+        assert_eq!(blocks.len(), 1);
+    }
+}
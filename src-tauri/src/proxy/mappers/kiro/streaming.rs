@@ -15,9 +15,13 @@ use byteorder::{BigEndian, ReadBytesExt};
 /// - headers_length bytes: headers
 /// - payload bytes: payload
 /// - 4 bytes: message_crc (big-endian uint32)
-pub fn parse_event_stream(data: &[u8]) -> Result<Vec<EventStreamMessage>, String> {
+///
+/// Возвращает все полные сообщения в начале буфера и количество прочитанных байт
+/// (неполный хвост остаётся для следующего chunk, см. `EventStreamDecoder`)
+pub fn parse_event_stream(data: &[u8]) -> Result<(Vec<EventStreamMessage>, usize), String> {
     let mut messages = Vec::new();
     let mut cursor = Cursor::new(data);
+    let mut consumed = 0usize;
     
     while cursor.position() < data.len() as u64 {
         // Проверяем что осталось минимум 12 байт для prelude
@@ -33,6 +37,14 @@ pub fn parse_event_stream(data: &[u8]) -> Result<Vec<EventStreamMessage>, String
         let _prelude_crc = cursor.read_u32::<BigEndian>()
             .map_err(|e| format!("Failed to read prelude_crc: {}", e))?;
         
+        // prelude (12) + message_crc (4) - меньше быть не может
+        if total_length < 16 || headers_length > total_length - 16 {
+            return Err(format!(
+                "Malformed event stream frame: total_length={}, headers_length={}",
+                total_length, headers_length
+            ));
+        }
+        
         // Проверяем что есть достаточно данных
         let remaining = data.len() as u64 - cursor.position();
         let needed = (total_length as u64) - 12;
@@ -79,10 +91,105 @@ pub fn parse_event_stream(data: &[u8]) -> Result<Vec<EventStreamMessage>, String
         // Пропускаем message CRC
         cursor.set_position(payload_end + 4);
         
+        consumed = cursor.position() as usize;
         messages.push(EventStreamMessage { headers, payload });
     }
     
-    Ok(messages)
+    Ok((messages, consumed))
+}
+
+/// Инкрементальный декодер AWS Event Stream
+///
+/// HTTP chunk не обязан совпадать с границами сообщений, поэтому неполный хвост буферизуется
+#[derive(Debug, Default)]
+pub struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавляет chunk и возвращает все сообщения, которые удалось собрать целиком
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<EventStreamMessage>, String> {
+        self.buffer.extend_from_slice(chunk);
+        let (messages, consumed) = parse_event_stream(&self.buffer)?;
+        self.buffer.drain(..consumed);
+        Ok(messages)
+    }
+}
+
+/// Нативный вызов инструмента, собранный из toolUseEvent фрагментов
+#[derive(Debug, Clone, PartialEq)]
+pub struct KiroToolUse {
+    pub id: String,
+    pub name: String,
+    /// Сырой JSON аргументов (склеенные фрагменты `input`)
+    pub input_json: String,
+}
+
+impl KiroToolUse {
+    /// Аргументы как JSON объект; пустой или битый input превращается в `{}`
+    pub fn input(&self) -> Value {
+        if self.input_json.trim().is_empty() {
+            return json!({});
+        }
+        match serde_json::from_str::<Value>(&self.input_json) {
+            Ok(v) if v.is_object() => v,
+            Ok(v) => {
+                tracing::warn!("[Kiro] Tool {} input is not an object: {}", self.name, v);
+                json!({})
+            }
+            Err(e) => {
+                tracing::warn!("[Kiro] Failed to parse tool {} input: {} ({})", self.name, e, self.input_json);
+                json!({})
+            }
+        }
+    }
+}
+
+/// Результат сбора всего stream (non-streaming mode)
+#[derive(Debug, Clone, Default)]
+pub struct KiroCollected {
+    pub text: String,
+    pub tool_uses: Vec<KiroToolUse>,
+}
+
+/// Один фрагмент toolUseEvent
+#[derive(Debug, Clone)]
+struct ToolUseFragment {
+    id: String,
+    name: String,
+    input: String,
+    stop: bool,
+}
+
+fn parse_tool_use_fragment(payload: &[u8]) -> Option<ToolUseFragment> {
+    let payload_json: Value = serde_json::from_slice(payload).ok()?;
+    let id = payload_json.get("toolUseId")?.as_str()?.to_string();
+    let name = payload_json
+        .get("name")
+        .and_then(|n| n.as_str())
+        .unwrap_or_default()
+        .to_string();
+    // Обычно input приходит строкой-фрагментом, но иногда сразу целым объектом
+    let input = match payload_json.get("input") {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    };
+    let stop = payload_json.get("stop").and_then(|s| s.as_bool()).unwrap_or(false);
+
+    Some(ToolUseFragment { id, name, input, stop })
+}
+
+fn is_event(msg: &EventStreamMessage) -> bool {
+    msg.headers.get(":message-type").map(|t| t.as_str()) == Some("event")
+}
+
+fn event_type(msg: &EventStreamMessage) -> Option<&str> {
+    msg.headers.get(":event-type").map(|t| t.as_str())
 }
 
 /// Конвертирует Kiro event в Claude SSE chunk
@@ -137,32 +244,456 @@ pub fn convert_event_to_claude_chunk(msg: &EventStreamMessage) -> Option<Bytes>
     }
 }
 
-/// Собирает stream в полный текст (для non-streaming mode)
-pub async fn collect_stream_to_text(
+/// Собирает stream в текст и нативные вызовы инструментов (для non-streaming mode)
+pub async fn collect_stream(
     mut stream: impl futures::Stream<Item = Result<Bytes, String>> + Unpin,
-) -> Result<String, String> {
+) -> Result<KiroCollected, String> {
     use futures::StreamExt;
     
-    let mut full_text = String::new();
+    let mut decoder = EventStreamDecoder::new();
+    let mut collected = KiroCollected::default();
     
     while let Some(chunk_result) = stream.next().await {
         let chunk = chunk_result?;
         
-        // Парсим AWS Event Stream
-        let messages = parse_event_stream(&chunk)?;
-        
-        for msg in messages {
-            if let Some(event_type) = msg.headers.get(":event-type") {
-                if event_type == "assistantResponseEvent" {
+        for msg in decoder.push(&chunk)? {
+            if !is_event(&msg) {
+                if let Some(error) = exception_message(&msg) {
+                    return Err(error);
+                }
+                continue;
+            }
+            match event_type(&msg) {
+                Some("assistantResponseEvent") => {
                     if let Ok(payload_json) = serde_json::from_slice::<Value>(&msg.payload) {
                         if let Some(content) = payload_json.get("content").and_then(|c| c.as_str()) {
-                            full_text.push_str(content);
+                            collected.text.push_str(content);
+                        }
+                    }
+                }
+                Some("toolUseEvent") => {
+                    let Some(fragment) = parse_tool_use_fragment(&msg.payload) else {
+                        continue;
+                    };
+                    match collected.tool_uses.iter_mut().find(|t| t.id == fragment.id) {
+                        Some(tool_use) => {
+                            if tool_use.name.is_empty() {
+                                tool_use.name = fragment.name;
+                            }
+                            tool_use.input_json.push_str(&fragment.input);
                         }
+                        None => collected.tool_uses.push(KiroToolUse {
+                            id: fragment.id,
+                            name: fragment.name,
+                            input_json: fragment.input,
+                        }),
                     }
                 }
+                _ => {}
+            }
+        }
+    }
+    
+    Ok(collected)
+}
+
+/// Текст ошибки из exception/error сообщения Event Stream
+fn exception_message(msg: &EventStreamMessage) -> Option<String> {
+    let message_type = msg.headers.get(":message-type")?;
+    if message_type != "exception" && message_type != "error" {
+        return None;
+    }
+    let kind = msg
+        .headers
+        .get(":exception-type")
+        .or_else(|| msg.headers.get(":error-code"))
+        .cloned()
+        .unwrap_or_else(|| message_type.clone());
+    Some(format!("Kiro stream {}: {}", kind, String::from_utf8_lossy(&msg.payload)))
+}
+
+fn sse_event(event: &str, data: &Value) -> Bytes {
+    Bytes::from(format!("event: {}\ndata: {}\n\n", event, data))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum OpenBlock {
+    Text,
+    ToolUse(String),
+}
+
+/// Потоковый конвертер Kiro Event Stream → Claude SSE
+///
+/// Текст отдаётся как text блоки, toolUseEvent - как tool_use блоки с input_json_delta.
+/// Индексы content блоков растут по мере открытия новых блоков
+pub struct KiroSseConverter {
+    decoder: EventStreamDecoder,
+    model: String,
+    message_id: String,
+    started: bool,
+    next_index: usize,
+    open_block: Option<OpenBlock>,
+    finished_tool_ids: std::collections::HashSet<String>,
+    has_tool_use: bool,
+}
+
+impl KiroSseConverter {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            decoder: EventStreamDecoder::new(),
+            model: model.into(),
+            message_id: format!("msg_{}", uuid::Uuid::new_v4().simple()),
+            started: false,
+            next_index: 0,
+            open_block: None,
+            finished_tool_ids: std::collections::HashSet::new(),
+            has_tool_use: false,
+        }
+    }
+
+    /// Обрабатывает очередной HTTP chunk и возвращает готовые SSE события
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<Bytes>, String> {
+        let mut out = Vec::new();
+        for msg in self.decoder.push(chunk)? {
+            self.handle_message(&msg, &mut out);
+        }
+        Ok(out)
+    }
+
+    /// Закрывает открытый блок и завершает сообщение
+    pub fn finish(&mut self) -> Vec<Bytes> {
+        let mut out = Vec::new();
+        self.ensure_started(&mut out);
+        self.close_block(&mut out);
+
+        let stop_reason = if self.has_tool_use { "tool_use" } else { "end_turn" };
+        out.push(sse_event("message_delta", &json!({
+            "type": "message_delta",
+            "delta": {
+                "stop_reason": stop_reason,
+                "stop_sequence": null
+            },
+            "usage": {
+                "output_tokens": 0
+            }
+        })));
+        out.push(sse_event("message_stop", &json!({ "type": "message_stop" })));
+        out
+    }
+
+    fn handle_message(&mut self, msg: &EventStreamMessage, out: &mut Vec<Bytes>) {
+        self.ensure_started(out);
+
+        if !is_event(msg) {
+            if let Some(error) = exception_message(msg) {
+                tracing::error!("[Kiro] {}", error);
+                out.push(sse_event("error", &json!({
+                    "type": "error",
+                    "error": { "type": "api_error", "message": error }
+                })));
+            }
+            return;
+        }
+
+        match event_type(msg) {
+            Some("assistantResponseEvent") => {
+                let Ok(payload_json) = serde_json::from_slice::<Value>(&msg.payload) else {
+                    return;
+                };
+                let Some(content) = payload_json.get("content").and_then(|c| c.as_str()) else {
+                    return;
+                };
+                if content.is_empty() {
+                    return;
+                }
+                if self.open_block != Some(OpenBlock::Text) {
+                    self.close_block(out);
+                    out.push(sse_event("content_block_start", &json!({
+                        "type": "content_block_start",
+                        "index": self.next_index,
+                        "content_block": { "type": "text", "text": "" }
+                    })));
+                    self.open_block = Some(OpenBlock::Text);
+                }
+                out.push(sse_event("content_block_delta", &json!({
+                    "type": "content_block_delta",
+                    "index": self.next_index,
+                    "delta": { "type": "text_delta", "text": content }
+                })));
+            }
+            Some("toolUseEvent") => {
+                let Some(fragment) = parse_tool_use_fragment(&msg.payload) else {
+                    return;
+                };
+                // Повторные события после stop игнорируем
+                if self.finished_tool_ids.contains(&fragment.id) {
+                    return;
+                }
+                if self.open_block != Some(OpenBlock::ToolUse(fragment.id.clone())) {
+                    self.close_block(out);
+                    out.push(sse_event("content_block_start", &json!({
+                        "type": "content_block_start",
+                        "index": self.next_index,
+                        "content_block": {
+                            "type": "tool_use",
+                            "id": fragment.id,
+                            "name": fragment.name,
+                            "input": {}
+                        }
+                    })));
+                    self.open_block = Some(OpenBlock::ToolUse(fragment.id.clone()));
+                    self.has_tool_use = true;
+                }
+                if !fragment.input.is_empty() {
+                    out.push(sse_event("content_block_delta", &json!({
+                        "type": "content_block_delta",
+                        "index": self.next_index,
+                        "delta": { "type": "input_json_delta", "partial_json": fragment.input }
+                    })));
+                }
+                if fragment.stop {
+                    self.close_block(out);
+                    self.finished_tool_ids.insert(fragment.id);
+                }
             }
+            Some("meteringEvent") | Some("contextUsageEvent") => {
+                // Только логирование, клиенту не отправляем
+                let _ = convert_event_to_claude_chunk(msg);
+            }
+            _ => {}
+        }
+    }
+
+    fn ensure_started(&mut self, out: &mut Vec<Bytes>) {
+        if self.started {
+            return;
         }
+        self.started = true;
+        out.push(sse_event("message_start", &json!({
+            "type": "message_start",
+            "message": {
+                "id": self.message_id,
+                "type": "message",
+                "role": "assistant",
+                "content": [],
+                "model": self.model,
+                "stop_reason": null,
+                "stop_sequence": null,
+                "usage": {
+                    "input_tokens": 0,
+                    "output_tokens": 0
+                }
+            }
+        })));
     }
+
+    fn close_block(&mut self, out: &mut Vec<Bytes>) {
+        if let Some(block) = self.open_block.take() {
+            if let OpenBlock::ToolUse(id) = block {
+                self.finished_tool_ids.insert(id);
+            }
+            out.push(sse_event("content_block_stop", &json!({
+                "type": "content_block_stop",
+                "index": self.next_index
+            })));
+            self.next_index += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
     
-    Ok(full_text)
+    /// Кодирует одно сообщение AWS Event Stream (string headers, CRC не проверяется парсером)
+    fn encode_frame(headers: &[(&str, &str)], payload: &str) -> Vec<u8> {
+        let mut header_bytes = Vec::new();
+        for (name, value) in headers {
+            header_bytes.push(name.len() as u8);
+            header_bytes.extend_from_slice(name.as_bytes());
+            header_bytes.push(7); // string
+            header_bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            header_bytes.extend_from_slice(value.as_bytes());
+        }
+        let total_length = 12 + header_bytes.len() + payload.len() + 4;
+        
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&(total_length as u32).to_be_bytes());
+        frame.extend_from_slice(&(header_bytes.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0; 4]); // prelude_crc
+        frame.extend_from_slice(&header_bytes);
+        frame.extend_from_slice(payload.as_bytes());
+        frame.extend_from_slice(&[0; 4]); // message_crc
+        frame
+    }
+    
+    fn event(event_type: &str, payload: Value) -> Vec<u8> {
+        encode_frame(
+            &[
+                (":event-type", event_type),
+                (":content-type", "application/json"),
+                (":message-type", "event"),
+            ],
+            &payload.to_string(),
+        )
+    }
+    
+    /// Типичный ответ Kiro: текст, затем toolUseEvent с input по фрагментам и metering
+    fn tool_use_fixture() -> Vec<u8> {
+        [
+            event("assistantResponseEvent", json!({ "content": "Let me fix " })),
+            event("assistantResponseEvent", json!({ "content": "that." })),
+            event("toolUseEvent", json!({ "name": "Edit", "toolUseId": "tooluse_a1", "input": "" })),
+            event("toolUseEvent", json!({ "name": "Edit", "toolUseId": "tooluse_a1", "input": "{\"file_path\": \"src/ma" })),
+            event("toolUseEvent", json!({ "name": "Edit", "toolUseId": "tooluse_a1", "input": "in.rs\", \"old\": \"mian\"}" })),
+            event("toolUseEvent", json!({ "name": "Edit", "toolUseId": "tooluse_a1", "stop": true })),
+            event("toolUseEvent", json!({ "name": "mcp__git__status", "toolUseId": "tooluse_b2", "input": "{}" })),
+            event("toolUseEvent", json!({ "name": "mcp__git__status", "toolUseId": "tooluse_b2", "stop": true })),
+            event("meteringEvent", json!({ "unit": "credit", "unitPlural": "credits", "usage": 0.12 })),
+            event("contextUsageEvent", json!({ "contextUsagePercentage": 3.5 })),
+        ]
+        .concat()
+    }
+    
+    /// Разбирает SSE вывод в список (event, data)
+    fn parse_sse(chunks: &[Bytes]) -> Vec<(String, Value)> {
+        let text: String = chunks.iter().map(|c| String::from_utf8_lossy(c).to_string()).collect();
+        text.split("\n\n")
+            .filter(|e| !e.is_empty())
+            .map(|e| {
+                let mut lines = e.lines();
+                let name = lines.next().unwrap().trim_start_matches("event: ").to_string();
+                let data = serde_json::from_str(lines.next().unwrap().trim_start_matches("data: ")).unwrap();
+                (name, data)
+            })
+            .collect()
+    }
+    
+    #[test]
+    fn test_parse_event_stream_keeps_partial_tail() {
+        let data = tool_use_fixture();
+        let (messages, consumed) = parse_event_stream(&data[..data.len() - 5]).unwrap();
+        assert_eq!(messages.len(), 9);
+        assert!(consumed < data.len());
+        assert_eq!(messages[0].headers.get(":event-type").unwrap(), "assistantResponseEvent");
+        
+        let (messages, consumed) = parse_event_stream(&data).unwrap();
+        assert_eq!(messages.len(), 10);
+        assert_eq!(consumed, data.len());
+    }
+    
+    #[test]
+    fn test_decoder_handles_frames_split_across_chunks() {
+        let data = tool_use_fixture();
+        let mut decoder = EventStreamDecoder::new();
+        let mut total = 0;
+        // Режем на куски по 7 байт - границы не совпадают с сообщениями
+        for chunk in data.chunks(7) {
+            total += decoder.push(chunk).unwrap().len();
+        }
+        assert_eq!(total, 10);
+    }
+    
+    #[test]
+    fn test_malformed_frame_is_error() {
+        let mut frame = encode_frame(&[], "");
+        frame[..4].copy_from_slice(&8u32.to_be_bytes());
+        assert!(parse_event_stream(&frame).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_collect_stream_merges_tool_use_fragments() {
+        let data = tool_use_fixture();
+        let chunks: Vec<Result<Bytes, String>> = data
+            .chunks(13)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        
+        let collected = collect_stream(futures::stream::iter(chunks)).await.unwrap();
+        assert_eq!(collected.text, "Let me fix that.");
+        assert_eq!(collected.tool_uses.len(), 2);
+        assert_eq!(collected.tool_uses[0].id, "tooluse_a1");
+        assert_eq!(collected.tool_uses[0].name, "Edit");
+        assert_eq!(
+            collected.tool_uses[0].input(),
+            json!({ "file_path": "src/main.rs", "old": "mian" })
+        );
+        assert_eq!(collected.tool_uses[1].name, "mcp__git__status");
+        assert_eq!(collected.tool_uses[1].input(), json!({}));
+    }
+    
+    #[tokio::test]
+    async fn test_collect_stream_reports_exception() {
+        let data = encode_frame(
+            &[
+                (":exception-type", "ThrottlingException"),
+                (":content-type", "application/json"),
+                (":message-type", "exception"),
+            ],
+            r#"{"message":"Too many requests"}"#,
+        );
+        let chunks = vec![Ok::<_, String>(Bytes::from(data))];
+        let err = collect_stream(futures::stream::iter(chunks)).await.unwrap_err();
+        assert!(err.contains("ThrottlingException"));
+    }
+    
+    #[test]
+    fn test_converter_emits_claude_tool_use_sse() {
+        let data = tool_use_fixture();
+        let mut converter = KiroSseConverter::new("claude-sonnet-4-5");
+        let mut out = Vec::new();
+        for chunk in data.chunks(11) {
+            out.extend(converter.feed(chunk).unwrap());
+        }
+        out.extend(converter.finish());
+        
+        let events = parse_sse(&out);
+        let names: Vec<&str> = events.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "message_start",
+                "content_block_start", "content_block_delta", "content_block_delta", "content_block_stop",
+                "content_block_start", "content_block_delta", "content_block_delta", "content_block_stop",
+                "content_block_start", "content_block_delta", "content_block_stop",
+                "message_delta",
+                "message_stop",
+            ]
+        );
+        
+        assert_eq!(events[0].1["message"]["model"], "claude-sonnet-4-5");
+        assert_eq!(events[2].1["delta"]["text"], "Let me fix ");
+        
+        // Первый tool_use блок: index 1, input собирается из input_json_delta
+        let start = &events[5].1;
+        assert_eq!(start["index"], 1);
+        assert_eq!(start["content_block"]["type"], "tool_use");
+        assert_eq!(start["content_block"]["id"], "tooluse_a1");
+        assert_eq!(start["content_block"]["name"], "Edit");
+        let partial: String = events[6..8]
+            .iter()
+            .map(|(_, d)| d["delta"]["partial_json"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            serde_json::from_str::<Value>(&partial).unwrap(),
+            json!({ "file_path": "src/main.rs", "old": "mian" })
+        );
+        assert_eq!(events[8].1["index"], 1);
+        
+        assert_eq!(events[9].1["index"], 2);
+        assert_eq!(events[9].1["content_block"]["name"], "mcp__git__status");
+        assert_eq!(events[12].1["delta"]["stop_reason"], "tool_use");
+    }
+    
+    #[test]
+    fn test_converter_text_only_ends_turn() {
+        let data = event("assistantResponseEvent", json!({ "content": "Hello" }));
+        let mut converter = KiroSseConverter::new("auto");
+        let mut out = converter.feed(&data).unwrap();
+        out.extend(converter.finish());
+        
+        let events = parse_sse(&out);
+        assert_eq!(events.len(), 6);
+        assert_eq!(events[4].1["delta"]["stop_reason"], "end_turn");
+    }
 }
@@ -0,0 +1,210 @@
+// OpenAI Embeddings ↔ Gemini batchEmbedContents 转换
+use base64::Engine as _;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// 未配置映射时使用的 Gemini 嵌入模型
+pub const DEFAULT_EMBEDDING_MODEL: &str = "gemini-embedding-001";
+
+/// 单次 batchEmbedContents 的最大条数 (Gemini 上限 100)
+pub const MAX_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Single(String),
+    Batch(Vec<String>),
+    /// token 数组 (Gemini 不支持，解析后直接拒绝)
+    #[allow(dead_code)]
+    Tokens(Vec<i64>),
+    #[allow(dead_code)]
+    TokenBatch(Vec<Vec<i64>>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingsRequest {
+    pub model: String,
+    pub input: EmbeddingInput,
+    #[serde(default)]
+    pub dimensions: Option<u32>,
+    /// "float" (默认) | "base64"
+    #[serde(default)]
+    pub encoding_format: Option<String>,
+    #[serde(default)]
+    #[allow(dead_code)]
+    pub user: Option<String>,
+}
+
+impl EmbeddingsRequest {
+    /// 展开为文本列表
+    pub fn texts(&self) -> Result<Vec<String>, String> {
+        let texts = match &self.input {
+            EmbeddingInput::Single(s) => vec![s.clone()],
+            EmbeddingInput::Batch(v) => v.clone(),
+            EmbeddingInput::Tokens(_) | EmbeddingInput::TokenBatch(_) => {
+                return Err("Token array input is not supported, send text instead".to_string())
+            }
+        };
+        if texts.is_empty() {
+            return Err("input must not be empty".to_string());
+        }
+        if texts.iter().any(|t| t.is_empty()) {
+            return Err("input must not contain empty strings".to_string());
+        }
+        Ok(texts)
+    }
+
+    pub fn wants_base64(&self) -> bool {
+        self.encoding_format.as_deref() == Some("base64")
+    }
+}
+
+fn is_gemini_embedding_model(model: &str) -> bool {
+    model.starts_with("gemini-embedding") || model.starts_with("text-embedding-00") || model.starts_with("embedding-")
+}
+
+/// OpenAI / 通用模型名 -> Gemini 嵌入模型
+///
+/// 自定义映射 (精确 / 通配符) 指向嵌入模型时优先；已是 Gemini 嵌入模型时原样使用，其余落到默认模型。
+/// 映射到对话模型的规则 (例如 `*` 兜底) 会被忽略，否则 text-embedding-* 会被发给 chat 模型。
+pub fn resolve_embedding_model(
+    requested: &str,
+    custom_mapping: &std::collections::HashMap<String, String>,
+) -> String {
+    let requested = requested.trim_start_matches("models/");
+    let mapped = crate::proxy::common::model_mapping::resolve_model_route(requested, custom_mapping);
+    let mapped = mapped.trim_start_matches("models/");
+    if is_gemini_embedding_model(mapped) {
+        return mapped.to_string();
+    }
+    if is_gemini_embedding_model(requested) {
+        return requested.to_string();
+    }
+    DEFAULT_EMBEDDING_MODEL.to_string()
+}
+
+/// 构建 batchEmbedContents 的 requests 数组
+pub fn build_embed_requests(texts: &[String], model: &str, dimensions: Option<u32>) -> Vec<Value> {
+    texts
+        .iter()
+        .map(|text| {
+            let mut req = json!({
+                "model": format!("models/{}", model),
+                "content": { "parts": [{ "text": text }] },
+            });
+            if let Some(d) = dimensions {
+                req["outputDimensionality"] = json!(d);
+            }
+            req
+        })
+        .collect()
+}
+
+/// 从 batchEmbedContents 响应中提取向量
+pub fn extract_vectors(response: &Value) -> Result<Vec<Vec<f32>>, String> {
+    let inner = response.get("response").unwrap_or(response);
+    let embeddings = inner
+        .get("embeddings")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| "Upstream response has no embeddings".to_string())?;
+
+    embeddings
+        .iter()
+        .map(|e| {
+            e.get("values")
+                .and_then(|v| v.as_array())
+                .map(|values| values.iter().filter_map(|x| x.as_f64()).map(|x| x as f32).collect())
+                .ok_or_else(|| "Embedding without values".to_string())
+        })
+        .collect()
+}
+
+/// 向量编码为 base64 (小端 float32，与 OpenAI 一致)
+pub fn encode_base64(vector: &[f32]) -> String {
+    let bytes: Vec<u8> = vector.iter().flat_map(|f| f.to_le_bytes()).collect();
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// 构建 OpenAI embeddings 响应
+pub fn build_openai_response(vectors: &[Vec<f32>], model: &str, base64: bool, prompt_tokens: u32) -> Value {
+    let data: Vec<Value> = vectors
+        .iter()
+        .enumerate()
+        .map(|(index, vector)| {
+            let embedding = if base64 {
+                json!(encode_base64(vector))
+            } else {
+                json!(vector)
+            };
+            json!({
+                "object": "embedding",
+                "index": index,
+                "embedding": embedding,
+            })
+        })
+        .collect();
+
+    json!({
+        "object": "list",
+        "data": data,
+        "model": model,
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "total_tokens": prompt_tokens,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_request_parsing_and_model_resolution() {
+        let req: EmbeddingsRequest = serde_json::from_value(json!({
+            "model": "text-embedding-3-small",
+            "input": ["a", "b"],
+            "dimensions": 256,
+            "encoding_format": "base64"
+        }))
+        .unwrap();
+        assert_eq!(req.texts().unwrap(), vec!["a", "b"]);
+        assert!(req.wants_base64());
+
+        let tokens: EmbeddingsRequest =
+            serde_json::from_value(json!({"model": "x", "input": [1, 2, 3]})).unwrap();
+        assert!(tokens.texts().is_err());
+
+        let mut custom = HashMap::new();
+        assert_eq!(resolve_embedding_model("text-embedding-3-small", &custom), DEFAULT_EMBEDDING_MODEL);
+        assert_eq!(resolve_embedding_model("models/text-embedding-004", &custom), "text-embedding-004");
+        custom.insert("text-embedding-3-large".to_string(), "gemini-embedding-exp".to_string());
+        assert_eq!(resolve_embedding_model("text-embedding-3-large", &custom), "gemini-embedding-exp");
+        // 兜底到对话模型的通配符不影响嵌入
+        custom.insert("*".to_string(), "gemini-3-flash".to_string());
+        assert_eq!(resolve_embedding_model("text-embedding-3-small", &custom), DEFAULT_EMBEDDING_MODEL);
+
+        let requests = build_embed_requests(&req.texts().unwrap(), DEFAULT_EMBEDDING_MODEL, req.dimensions);
+        assert_eq!(requests[1]["model"], "models/gemini-embedding-001");
+        assert_eq!(requests[1]["outputDimensionality"], 256);
+    }
+
+    #[test]
+    fn test_openai_response_float_and_base64() {
+        let upstream = json!({"response": {"embeddings": [{"values": [1.0, -0.5]}, {"values": [0.25]}]}});
+        let vectors = extract_vectors(&upstream).unwrap();
+        assert_eq!(vectors, vec![vec![1.0, -0.5], vec![0.25]]);
+
+        let resp = build_openai_response(&vectors, "text-embedding-3-small", false, 7);
+        assert_eq!(resp["data"][0]["embedding"], json!([1.0, -0.5]));
+        assert_eq!(resp["data"][1]["index"], 1);
+        assert_eq!(resp["usage"]["prompt_tokens"], 7);
+
+        let resp = build_openai_response(&vectors, "text-embedding-3-small", true, 7);
+        let encoded = resp["data"][0]["embedding"].as_str().unwrap();
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded).unwrap();
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(f32::from_le_bytes(bytes[4..8].try_into().unwrap()), -0.5);
+    }
+}
@@ -6,6 +6,8 @@ pub mod request;
 pub mod response;
 pub mod streaming;
 pub mod collector; // [NEW]
+pub mod embeddings; // [NEW] OpenAI embeddings ↔ Gemini batchEmbedContents
+pub mod responses; // [NEW] Responses API (input items / response.* 事件流)
 
 pub use models::*;
 pub use request::*;
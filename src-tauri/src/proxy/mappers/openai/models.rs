@@ -53,7 +53,32 @@ pub struct ThinkingConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponseFormat {
-    pub r#type: String,
+    pub r#type: String, // "text" | "json_object" | "json_schema"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<JsonSchemaFormat>,
+}
+
+impl ResponseFormat {
+    /// type = "json_schema" 时返回用户提供的原始 Schema
+    pub fn schema(&self) -> Option<&Value> {
+        if self.r#type != "json_schema" {
+            return None;
+        }
+        self.json_schema.as_ref()?.schema.as_ref()
+    }
+}
+
+/// Structured Outputs: response_format.json_schema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub schema: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
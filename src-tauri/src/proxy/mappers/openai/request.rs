@@ -1,8 +1,33 @@
 // OpenAI → Gemini 请求转换
 use super::models::*;
 use super::streaming::get_thought_signature;
+use crate::proxy::common::json_schema::ToolSchemas;
 use serde_json::{json, Value};
 
+/// 工具名 → 原始 parameters Schema (请求参数修正与响应映射共用)
+pub fn build_tool_schemas(request: &OpenAIRequest) -> ToolSchemas {
+    let mut tool_name_to_schema = ToolSchemas::new();
+    if let Some(tools) = &request.tools {
+        for tool in tools {
+            if let (Some(name), Some(params)) = (
+                tool.get("function")
+                    .and_then(|f| f.get("name"))
+                    .and_then(|v| v.as_str()),
+                tool.get("function").and_then(|f| f.get("parameters")),
+            ) {
+                tool_name_to_schema.insert(name.to_string(), params.clone());
+            } else if let (Some(name), Some(params)) = (
+                tool.get("name").and_then(|v| v.as_str()),
+                tool.get("parameters"),
+            ) {
+                // 处理某些客户端可能透传的精简格式
+                tool_name_to_schema.insert(name.to_string(), params.clone());
+            }
+        }
+    }
+    tool_name_to_schema
+}
+
 pub fn transform_openai_request(
     request: &OpenAIRequest,
     project_id: &str,
@@ -135,25 +160,7 @@ pub fn transform_openai_request(
     }
 
     // [New] 预先构建工具名称到原始 Schema 的映射，用于后续参数类型修正
-    let mut tool_name_to_schema = std::collections::HashMap::new();
-    if let Some(tools) = &request.tools {
-        for tool in tools {
-            if let (Some(name), Some(params)) = (
-                tool.get("function")
-                    .and_then(|f| f.get("name"))
-                    .and_then(|v| v.as_str()),
-                tool.get("function").and_then(|f| f.get("parameters")),
-            ) {
-                tool_name_to_schema.insert(name.to_string(), params.clone());
-            } else if let (Some(name), Some(params)) = (
-                tool.get("name").and_then(|v| v.as_str()),
-                tool.get("parameters"),
-            ) {
-                // 处理某些客户端可能透传的精简格式
-                tool_name_to_schema.insert(name.to_string(), params.clone());
-            }
-        }
-    }
+    let tool_name_to_schema = build_tool_schemas(request);
 
     // 2. 构建 Gemini contents (过滤掉 system/developer 指令)
     let contents: Vec<Value> = request
@@ -448,6 +455,9 @@ pub fn transform_openai_request(
     if let Some(fmt) = &request.response_format {
         if fmt.r#type == "json_object" {
             gen_config["responseMimeType"] = json!("application/json");
+        } else if let Some(schema) = fmt.schema() {
+            // [NEW] Structured Outputs: json_schema → responseSchema
+            crate::proxy::common::structured_output::apply_response_schema(&mut gen_config, schema);
         }
     }
 
@@ -599,6 +609,7 @@ pub fn transform_openai_request(
             if let Some(gen_obj) = gen_config.as_object_mut() {
                 gen_obj.remove("thinkingConfig");
                 gen_obj.remove("responseMimeType");
+                gen_obj.remove("responseSchema");
                 gen_obj.remove("responseModalities");
                 gen_obj.insert("imageConfig".to_string(), image_config);
             }
@@ -643,6 +654,38 @@ fn enforce_uppercase_types(value: &mut Value) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_response_format_json_schema() {
+        let req: OpenAIRequest = serde_json::from_value(json!({
+            "model": "gemini-2.5-flash",
+            "messages": [{ "role": "user", "content": "Give me a person" }],
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": {
+                    "name": "person",
+                    "strict": true,
+                    "schema": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string", "minLength": 1 },
+                            "age": { "type": ["integer", "null"] }
+                        },
+                        "required": ["name"],
+                        "additionalProperties": false
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        assert!(req.response_format.as_ref().unwrap().schema().is_some());
+
+        let result = transform_openai_request(&req, "test-v", "gemini-2.5-flash");
+        let gen_config = &result["request"]["generationConfig"];
+        assert_eq!(gen_config["responseMimeType"], "application/json");
+        assert_eq!(gen_config["responseSchema"]["properties"]["age"]["type"], "integer");
+        assert!(gen_config["responseSchema"].get("additionalProperties").is_none());
+    }
+
     #[test]
     fn test_transform_openai_request_multimodal() {
         let req = OpenAIRequest {
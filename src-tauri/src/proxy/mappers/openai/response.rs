@@ -1,8 +1,9 @@
 // OpenAI 协议响应转换模块
 use super::models::*;
+use crate::proxy::common::json_schema::{fix_tool_call_args_by_name, ToolSchemas};
 use serde_json::Value;
 
-pub fn transform_openai_response(gemini_response: &Value) -> OpenAIResponse {
+pub fn transform_openai_response(gemini_response: &Value, tool_schemas: &ToolSchemas) -> OpenAIResponse {
     // 解包 response 字段
     let raw = gemini_response.get("response").unwrap_or(gemini_response);
 
@@ -51,10 +52,15 @@ pub fn transform_openai_response(gemini_response: &Value) -> OpenAIResponse {
                     // 工具调用部分
                     if let Some(fc) = part.get("functionCall") {
                         let name = fc.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
-                        let args = fc
-                            .get("args")
-                            .map(|v| v.to_string())
-                            .unwrap_or_else(|| "{}".to_string());
+                        // [NEW] 按原始 Schema 修正参数类型
+                        let args = match fc.get("args") {
+                            Some(raw_args) => {
+                                let mut fixed = raw_args.clone();
+                                fix_tool_call_args_by_name(name, &mut fixed, tool_schemas);
+                                fixed.to_string()
+                            }
+                            None => "{}".to_string(),
+                        };
                         let id = fc
                             .get("id")
                             .and_then(|v| v.as_str())
@@ -231,7 +237,7 @@ mod tests {
             "responseId": "resp_123"
         });
 
-        let result = transform_openai_response(&gemini_resp);
+        let result = transform_openai_response(&gemini_resp, &ToolSchemas::new());
         assert_eq!(result.object, "chat.completion");
         let content = match result.choices[0].message.content.as_ref().unwrap() {
             OpenAIContent::String(s) => s,
@@ -258,7 +264,7 @@ mod tests {
             "responseId": "resp_123"
         });
 
-        let result = transform_openai_response(&gemini_resp);
+        let result = transform_openai_response(&gemini_resp, &ToolSchemas::new());
 
         assert!(result.usage.is_some());
         let usage = result.usage.unwrap();
@@ -280,7 +286,7 @@ mod tests {
             "responseId": "resp_123"
         });
 
-        let result = transform_openai_response(&gemini_resp);
+        let result = transform_openai_response(&gemini_resp, &ToolSchemas::new());
         assert!(result.usage.is_none());
     }
 }
@@ -0,0 +1,988 @@
+// OpenAI Responses API ↔ Gemini 转换
+// 请求: input items (+ previous_response_id 历史) → Chat messages → 复用 transform_openai_request
+// 响应: Gemini SSE 分片 → response.* 事件流 + 最终 response 对象
+use super::models::*;
+use super::streaming::store_thought_signature;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+/// 当客户端声明 `{"type": "local_shell"}` 工具时注入的函数定义，
+/// 参数形状与历史中 local_shell_call → shell 的映射一致
+fn local_shell_function() -> Value {
+    json!({
+        "type": "function",
+        "name": "shell",
+        "description": "Runs a shell command and returns its output.",
+        "parameters": {
+            "type": "object",
+            "properties": {
+                "command": { "type": "array", "items": { "type": "string" } },
+                "workdir": { "type": "string" },
+                "timeout_ms": { "type": "number" }
+            },
+            "required": ["command"]
+        }
+    })
+}
+
+fn new_item_id(prefix: &str) -> String {
+    format!("{}_{}", prefix, uuid::Uuid::new_v4().simple())
+}
+
+pub fn new_response_id() -> String {
+    new_item_id("resp")
+}
+
+/// `input` 规范化为 item 数组: 字符串视为一条用户消息，省略 type 的 `{role, content}` 视为 message
+pub fn normalize_input(input: Option<&Value>) -> Vec<Value> {
+    match input {
+        Some(Value::String(s)) => vec![json!({
+            "type": "message",
+            "role": "user",
+            "content": [{ "type": "input_text", "text": s }]
+        })],
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                let mut item = item.clone();
+                if item.get("type").is_none() && item.get("role").is_some() {
+                    item["type"] = json!("message");
+                }
+                item
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// function_call_output 的 output 可能是字符串、`{content}` 或内容块数组
+fn tool_output_text(output: Option<&Value>) -> String {
+    match output {
+        Some(Value::String(s)) => s.clone(),
+        Some(o) => {
+            if let Some(content) = o.get("content").and_then(|v| v.as_str()) {
+                content.to_string()
+            } else if let Some(parts) = o.as_array() {
+                parts
+                    .iter()
+                    .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else {
+                o.to_string()
+            }
+        }
+        None => String::new(),
+    }
+}
+
+/// 追加消息；assistant 消息带上尚未消费的 reasoning 摘要
+fn push_message(messages: &mut Vec<Value>, mut msg: Value, pending_reasoning: &mut Option<String>) {
+    if msg.get("role").and_then(|r| r.as_str()) == Some("assistant") {
+        if let Some(reasoning) = pending_reasoning.take() {
+            msg["reasoning_content"] = json!(reasoning);
+        }
+    }
+    messages.push(msg);
+}
+
+/// Responses input items → Chat Completions messages
+///
+/// 同时服务 `/v1/responses` 与 `/v1/completions` 的 Codex / Factory Droid 兼容路径。
+/// reasoning item 的摘要挂到紧随其后的 assistant 消息的 reasoning_content 上，
+/// encrypted_content (thoughtSignature) 写回全局签名存储。
+pub fn input_items_to_messages(input_items: &[Value]) -> Vec<Value> {
+    let mut messages: Vec<Value> = Vec::new();
+    let mut call_id_to_name = HashMap::new();
+
+    // Pass 1: Build Call ID to Name Map
+    for item in input_items {
+        let item_type = item.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        match item_type {
+            "function_call" | "local_shell_call" | "web_search_call" => {
+                let call_id = item
+                    .get("call_id")
+                    .and_then(|v| v.as_str())
+                    .or_else(|| item.get("id").and_then(|v| v.as_str()))
+                    .unwrap_or("unknown");
+
+                let name = if item_type == "local_shell_call" {
+                    "shell"
+                } else if item_type == "web_search_call" {
+                    "google_search"
+                } else {
+                    item.get("name").and_then(|v| v.as_str()).unwrap_or("unknown")
+                };
+
+                call_id_to_name.insert(call_id.to_string(), name.to_string());
+                tracing::debug!("Mapped call_id {} to name {}", call_id, name);
+            }
+            _ => {}
+        }
+    }
+
+    // Pass 2: Map Input Items to Messages
+    let mut pending_reasoning: Option<String> = None;
+
+    for item in input_items {
+        let item_type = item.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        match item_type {
+            "message" => {
+                let role = match item.get("role").and_then(|v| v.as_str()).unwrap_or("user") {
+                    // Responses 的 developer 角色等价于 system
+                    "developer" => "system",
+                    other => other,
+                };
+                let mut text_parts = Vec::new();
+                let mut image_parts: Vec<Value> = Vec::new();
+
+                match item.get("content") {
+                    Some(Value::String(s)) => text_parts.push(s.clone()),
+                    Some(Value::Array(parts)) => {
+                        for part in parts {
+                            // 处理文本块 (input_text / output_text)
+                            if let Some(text) = part.get("text").and_then(|v| v.as_str()) {
+                                text_parts.push(text.to_string());
+                            }
+                            // [NEW] 处理图像块 (Codex input_image 格式)
+                            else if part.get("type").and_then(|v| v.as_str()) == Some("input_image") {
+                                if let Some(image_url) = part.get("image_url").and_then(|v| v.as_str()) {
+                                    image_parts.push(json!({
+                                        "type": "image_url",
+                                        "image_url": { "url": image_url }
+                                    }));
+                                    tracing::debug!("[Codex] Found input_image: {}", image_url);
+                                }
+                            }
+                            // [NEW] 兼容标准 OpenAI image_url 格式
+                            else if part.get("type").and_then(|v| v.as_str()) == Some("image_url") {
+                                if let Some(url_obj) = part.get("image_url") {
+                                    image_parts.push(json!({
+                                        "type": "image_url",
+                                        "image_url": url_obj.clone()
+                                    }));
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+
+                // 构造消息内容：如果有图像则使用数组格式
+                let msg = if image_parts.is_empty() {
+                    json!({ "role": role, "content": text_parts.join("\n") })
+                } else {
+                    let mut content_blocks: Vec<Value> = Vec::new();
+                    if !text_parts.is_empty() {
+                        content_blocks.push(json!({ "type": "text", "text": text_parts.join("\n") }));
+                    }
+                    content_blocks.extend(image_parts);
+                    json!({ "role": role, "content": content_blocks })
+                };
+                push_message(&mut messages, msg, &mut pending_reasoning);
+            }
+            "reasoning" => {
+                let summary = item
+                    .get("summary")
+                    .and_then(|s| s.as_array())
+                    .map(|parts| {
+                        parts
+                            .iter()
+                            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                            .collect::<Vec<_>>()
+                            .join("\n\n")
+                    })
+                    .unwrap_or_default();
+                if !summary.is_empty() {
+                    pending_reasoning = Some(summary);
+                }
+                if let Some(sig) = item.get("encrypted_content").and_then(|v| v.as_str()) {
+                    store_thought_signature(sig);
+                }
+            }
+            "function_call" | "local_shell_call" | "web_search_call" => {
+                let mut name = item.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
+                let mut args_str = item
+                    .get("arguments")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("{}")
+                    .to_string();
+                let call_id = item
+                    .get("call_id")
+                    .and_then(|v| v.as_str())
+                    .or_else(|| item.get("id").and_then(|v| v.as_str()))
+                    .unwrap_or("unknown");
+
+                // Handle native shell calls
+                if item_type == "local_shell_call" {
+                    name = "shell";
+                    if let Some(exec) = item.get("action").and_then(|a| a.get("exec").or(Some(a))) {
+                        let mut args_obj = serde_json::Map::new();
+                        if let Some(cmd) = exec.get("command") {
+                            // CRITICAL FIX: The 'shell' tool schema defines 'command' as an ARRAY of strings.
+                            // We MUST pass it as an array, not a joined string, otherwise Gemini rejects with 400 INVALID_ARGUMENT.
+                            let cmd_val = if cmd.is_string() { json!([cmd]) } else { cmd.clone() };
+                            args_obj.insert("command".to_string(), cmd_val);
+                        }
+                        if let Some(wd) = exec.get("working_directory").or(exec.get("workdir")) {
+                            args_obj.insert("workdir".to_string(), wd.clone());
+                        }
+                        if !args_obj.is_empty() {
+                            args_str = serde_json::to_string(&args_obj).unwrap_or("{}".to_string());
+                        }
+                    }
+                } else if item_type == "web_search_call" {
+                    name = "google_search";
+                    if let Some(action) = item.get("action") {
+                        let mut args_obj = serde_json::Map::new();
+                        if let Some(q) = action.get("query") {
+                            args_obj.insert("query".to_string(), q.clone());
+                        }
+                        args_str = serde_json::to_string(&args_obj).unwrap_or("{}".to_string());
+                    }
+                }
+
+                let msg = json!({
+                    "role": "assistant",
+                    "tool_calls": [{
+                        "id": call_id,
+                        "type": "function",
+                        "function": { "name": name, "arguments": args_str }
+                    }]
+                });
+                push_message(&mut messages, msg, &mut pending_reasoning);
+            }
+            "function_call_output" | "custom_tool_call_output" | "local_shell_call_output" => {
+                let call_id = item.get("call_id").and_then(|v| v.as_str()).unwrap_or("unknown");
+                let output_str = tool_output_text(item.get("output"));
+
+                let name = call_id_to_name.get(call_id).cloned().unwrap_or_else(|| {
+                    // Fallback: if unknown and we see function_call_output, it's likely "shell" in this context
+                    tracing::warn!("Unknown tool name for call_id {}, defaulting to 'shell'", call_id);
+                    "shell".to_string()
+                });
+
+                messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": call_id,
+                    "name": name,
+                    "content": output_str
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    messages
+}
+
+/// reasoning.effort → thinking 预算 (none / minimal 关闭思考)
+fn thinking_from_reasoning(reasoning: Option<&Value>) -> Option<ThinkingConfig> {
+    let effort = reasoning?.get("effort")?.as_str()?;
+    let budget = match effort {
+        "none" | "minimal" => return None,
+        "low" => 2048,
+        "high" => 24576,
+        _ => 8192,
+    };
+    Some(ThinkingConfig {
+        thinking_type: Some("enabled".to_string()),
+        budget_tokens: Some(budget),
+    })
+}
+
+/// text.format → Chat response_format
+fn response_format_from_text(text: Option<&Value>) -> Option<ResponseFormat> {
+    let format = text?.get("format")?;
+    match format.get("type")?.as_str()? {
+        "json_schema" => Some(ResponseFormat {
+            r#type: "json_schema".to_string(),
+            json_schema: Some(JsonSchemaFormat {
+                name: format.get("name").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                description: format
+                    .get("description")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string()),
+                schema: format.get("schema").cloned(),
+                strict: format.get("strict").and_then(|v| v.as_bool()),
+            }),
+        }),
+        "json_object" => Some(ResponseFormat {
+            r#type: "json_object".to_string(),
+            json_schema: None,
+        }),
+        _ => None,
+    }
+}
+
+/// 客户端是否声明了内置 local_shell 工具
+pub fn declares_local_shell(body: &Value) -> bool {
+    body.get("tools")
+        .and_then(|t| t.as_array())
+        .map(|tools| {
+            tools
+                .iter()
+                .any(|t| t.get("type").and_then(|v| v.as_str()) == Some("local_shell"))
+        })
+        .unwrap_or(false)
+}
+
+/// 将 Responses 请求 (历史 + 本轮输入) 转为 OpenAIRequest
+///
+/// instructions 不随 previous_response_id 继承，只作用于本轮请求 (与 OpenAI 行为一致)。
+pub fn build_chat_request(
+    body: &Value,
+    history: &[Value],
+    input_items: &[Value],
+) -> Result<OpenAIRequest, String> {
+    let model = body
+        .get("model")
+        .and_then(|v| v.as_str())
+        .filter(|m| !m.is_empty())
+        .ok_or("model is required")?;
+
+    let mut messages = Vec::new();
+    if let Some(instructions) = body.get("instructions").and_then(|v| v.as_str()) {
+        if !instructions.is_empty() {
+            messages.push(json!({ "role": "system", "content": instructions }));
+        }
+    }
+    let all_items: Vec<Value> = history.iter().chain(input_items.iter()).cloned().collect();
+    messages.extend(input_items_to_messages(&all_items));
+    let mut messages: Vec<OpenAIMessage> = serde_json::from_value(Value::Array(messages))
+        .map_err(|e| format!("Invalid input: {}", e))?;
+    if messages.is_empty() {
+        messages.push(OpenAIMessage {
+            role: "user".to_string(),
+            content: Some(OpenAIContent::String(" ".to_string())),
+            reasoning_content: None,
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        });
+    }
+
+    let tools = body.get("tools").and_then(|t| t.as_array()).map(|tools| {
+        tools
+            .iter()
+            .map(|t| {
+                if t.get("type").and_then(|v| v.as_str()) == Some("local_shell") {
+                    local_shell_function()
+                } else {
+                    t.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+    });
+
+    Ok(OpenAIRequest {
+        model: model.to_string(),
+        messages,
+        prompt: None,
+        stream: body.get("stream").and_then(|v| v.as_bool()).unwrap_or(false),
+        n: None,
+        max_tokens: body
+            .get("max_output_tokens")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32),
+        temperature: body.get("temperature").and_then(|v| v.as_f64()).map(|v| v as f32),
+        top_p: body.get("top_p").and_then(|v| v.as_f64()).map(|v| v as f32),
+        stop: None,
+        response_format: response_format_from_text(body.get("text")),
+        tools,
+        tool_choice: body.get("tool_choice").cloned(),
+        parallel_tool_calls: body.get("parallel_tool_calls").and_then(|v| v.as_bool()),
+        instructions: None,
+        input: None,
+        size: None,
+        quality: None,
+        person_generation: None,
+        thinking: thinking_from_reasoning(body.get("reasoning")),
+    })
+}
+
+/// 构造 response 对象骨架，回显请求参数
+pub fn response_skeleton(id: &str, body: &Value, created_at: i64) -> Value {
+    let echo = |key: &str, default: Value| body.get(key).cloned().unwrap_or(default);
+    json!({
+        "id": id,
+        "object": "response",
+        "created_at": created_at,
+        "status": "in_progress",
+        "error": null,
+        "incomplete_details": null,
+        "instructions": echo("instructions", Value::Null),
+        "max_output_tokens": echo("max_output_tokens", Value::Null),
+        "model": echo("model", Value::Null),
+        "output": [],
+        "parallel_tool_calls": echo("parallel_tool_calls", json!(true)),
+        "previous_response_id": echo("previous_response_id", Value::Null),
+        "reasoning": echo("reasoning", json!({ "effort": null, "summary": null })),
+        "store": echo("store", json!(true)),
+        "temperature": echo("temperature", json!(1.0)),
+        "text": echo("text", json!({ "format": { "type": "text" } })),
+        "tool_choice": echo("tool_choice", json!("auto")),
+        "tools": echo("tools", json!([])),
+        "top_p": echo("top_p", json!(1.0)),
+        "truncation": echo("truncation", json!("disabled")),
+        "usage": null,
+        "user": echo("user", Value::Null),
+        "metadata": echo("metadata", json!({})),
+    })
+}
+
+/// Gemini usageMetadata → Responses usage
+fn usage_from_metadata(u: &Value) -> Value {
+    let get = |k: &str| u.get(k).and_then(|v| v.as_u64()).unwrap_or(0);
+    let input = get("promptTokenCount");
+    let reasoning = get("thoughtsTokenCount");
+    let output = get("candidatesTokenCount") + reasoning;
+    let total = get("totalTokenCount").max(input + output);
+    json!({
+        "input_tokens": input,
+        "input_tokens_details": { "cached_tokens": get("cachedContentTokenCount") },
+        "output_tokens": output,
+        "output_tokens_details": { "reasoning_tokens": reasoning },
+        "total_tokens": total
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OpenKind {
+    Reasoning,
+    Message,
+}
+
+/// 正在流式输出的 reasoning / message item
+struct OpenItem {
+    kind: OpenKind,
+    id: String,
+    output_index: usize,
+    text: String,
+}
+
+/// Gemini 流 → Responses 事件的状态机
+///
+/// 每个方法返回需要发送的事件 (已带 type 与 sequence_number)；非流式请求忽略事件，
+/// 只取最终的 `response()`。
+pub struct ResponsesStreamState {
+    response: Value,
+    output: Vec<Value>,
+    open: Option<OpenItem>,
+    seq: u64,
+    emitted_calls: HashSet<String>,
+    usage: Option<Value>,
+    finish_reason: Option<String>,
+    signature: Option<String>,
+    local_shell: bool,
+    include_encrypted_reasoning: bool,
+}
+
+impl ResponsesStreamState {
+    pub fn new(skeleton: Value, body: &Value) -> Self {
+        let include_encrypted_reasoning = body
+            .get("include")
+            .and_then(|v| v.as_array())
+            .map(|inc| inc.iter().any(|v| v.as_str() == Some("reasoning.encrypted_content")))
+            .unwrap_or(false);
+        Self {
+            response: skeleton,
+            output: Vec::new(),
+            open: None,
+            seq: 0,
+            emitted_calls: HashSet::new(),
+            usage: None,
+            finish_reason: None,
+            signature: None,
+            local_shell: declares_local_shell(body),
+            include_encrypted_reasoning,
+        }
+    }
+
+    pub fn response(&self) -> &Value {
+        &self.response
+    }
+
+    fn event(&mut self, event_type: &str, mut fields: Value) -> Value {
+        fields["type"] = json!(event_type);
+        fields["sequence_number"] = json!(self.seq);
+        self.seq += 1;
+        fields
+    }
+
+    /// response.created + response.in_progress
+    pub fn start(&mut self) -> Vec<Value> {
+        let snapshot = self.response.clone();
+        vec![
+            self.event("response.created", json!({ "response": snapshot.clone() })),
+            self.event("response.in_progress", json!({ "response": snapshot })),
+        ]
+    }
+
+    fn open_item(&mut self, kind: OpenKind, events: &mut Vec<Value>) {
+        if self.open.as_ref().map(|o| o.kind) == Some(kind) {
+            return;
+        }
+        self.close_open(events);
+
+        let output_index = self.output.len();
+        let (id, item, part_event, part) = match kind {
+            OpenKind::Reasoning => {
+                let id = new_item_id("rs");
+                let item = json!({ "id": id, "type": "reasoning", "summary": [] });
+                let part = json!({
+                    "item_id": id,
+                    "output_index": output_index,
+                    "summary_index": 0,
+                    "part": { "type": "summary_text", "text": "" }
+                });
+                (id, item, "response.reasoning_summary_part.added", part)
+            }
+            OpenKind::Message => {
+                let id = new_item_id("msg");
+                let item = json!({
+                    "id": id,
+                    "type": "message",
+                    "status": "in_progress",
+                    "role": "assistant",
+                    "content": []
+                });
+                let part = json!({
+                    "item_id": id,
+                    "output_index": output_index,
+                    "content_index": 0,
+                    "part": { "type": "output_text", "text": "", "annotations": [] }
+                });
+                (id, item, "response.content_part.added", part)
+            }
+        };
+        events.push(self.event(
+            "response.output_item.added",
+            json!({ "output_index": output_index, "item": item }),
+        ));
+        events.push(self.event(part_event, part));
+
+        self.open = Some(OpenItem {
+            kind,
+            id,
+            output_index,
+            text: String::new(),
+        });
+    }
+
+    fn close_open(&mut self, events: &mut Vec<Value>) {
+        let Some(open) = self.open.take() else {
+            return;
+        };
+        let item = match open.kind {
+            OpenKind::Reasoning => {
+                let part = json!({ "type": "summary_text", "text": open.text });
+                events.push(self.event(
+                    "response.reasoning_summary_text.done",
+                    json!({
+                        "item_id": open.id,
+                        "output_index": open.output_index,
+                        "summary_index": 0,
+                        "text": open.text
+                    }),
+                ));
+                events.push(self.event(
+                    "response.reasoning_summary_part.done",
+                    json!({
+                        "item_id": open.id,
+                        "output_index": open.output_index,
+                        "summary_index": 0,
+                        "part": part
+                    }),
+                ));
+                let mut item = json!({ "id": open.id, "type": "reasoning", "summary": [part] });
+                if self.include_encrypted_reasoning {
+                    if let Some(sig) = &self.signature {
+                        item["encrypted_content"] = json!(sig);
+                    }
+                }
+                item
+            }
+            OpenKind::Message => {
+                let part = json!({ "type": "output_text", "text": open.text, "annotations": [] });
+                events.push(self.event(
+                    "response.output_text.done",
+                    json!({
+                        "item_id": open.id,
+                        "output_index": open.output_index,
+                        "content_index": 0,
+                        "text": open.text
+                    }),
+                ));
+                events.push(self.event(
+                    "response.content_part.done",
+                    json!({
+                        "item_id": open.id,
+                        "output_index": open.output_index,
+                        "content_index": 0,
+                        "part": part
+                    }),
+                ));
+                json!({
+                    "id": open.id,
+                    "type": "message",
+                    "status": "completed",
+                    "role": "assistant",
+                    "content": [part]
+                })
+            }
+        };
+        events.push(self.event(
+            "response.output_item.done",
+            json!({ "output_index": open.output_index, "item": item }),
+        ));
+        self.output.push(item);
+    }
+
+    fn push_text(&mut self, kind: OpenKind, text: &str, events: &mut Vec<Value>) {
+        if text.is_empty() {
+            return;
+        }
+        self.open_item(kind, events);
+        let open = self.open.as_mut().expect("item opened above");
+        open.text.push_str(text);
+        let (id, output_index) = (open.id.clone(), open.output_index);
+        let ev = match kind {
+            OpenKind::Reasoning => self.event(
+                "response.reasoning_summary_text.delta",
+                json!({ "item_id": id, "output_index": output_index, "summary_index": 0, "delta": text }),
+            ),
+            OpenKind::Message => self.event(
+                "response.output_text.delta",
+                json!({ "item_id": id, "output_index": output_index, "content_index": 0, "delta": text }),
+            ),
+        };
+        events.push(ev);
+    }
+
+    fn push_function_call(&mut self, call: &Value, events: &mut Vec<Value>) {
+        // 上游可能在多个 chunk 中重复同一调用: 有 id 时按 call_id 去重，否则按完整内容去重
+        let call_key = match call.get("id").and_then(|v| v.as_str()) {
+            Some(id) => format!("id:{}", id),
+            None => serde_json::to_string(call).unwrap_or_default(),
+        };
+        if !self.emitted_calls.insert(call_key) {
+            return;
+        }
+        self.close_open(events);
+
+        let name = call.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
+        let args = call.get("args").cloned().unwrap_or_else(|| json!({}));
+        let call_id = call
+            .get("id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| new_item_id("call"));
+        let output_index = self.output.len();
+
+        if self.local_shell && (name == "shell" || name == "local_shell") {
+            let command = match args.get("command") {
+                Some(Value::String(s)) => json!([s]),
+                Some(c) => c.clone(),
+                None => json!([]),
+            };
+            let mut action = json!({ "type": "exec", "command": command, "env": {} });
+            if let Some(wd) = args.get("workdir").or(args.get("working_directory")) {
+                action["working_directory"] = wd.clone();
+            }
+            if let Some(t) = args.get("timeout_ms") {
+                action["timeout_ms"] = t.clone();
+            }
+            let item = json!({
+                "id": new_item_id("lsh"),
+                "type": "local_shell_call",
+                "status": "completed",
+                "call_id": call_id,
+                "action": action
+            });
+            let mut added = item.clone();
+            added["status"] = json!("in_progress");
+            events.push(self.event(
+                "response.output_item.added",
+                json!({ "output_index": output_index, "item": added }),
+            ));
+            events.push(self.event(
+                "response.output_item.done",
+                json!({ "output_index": output_index, "item": item }),
+            ));
+            self.output.push(item);
+            return;
+        }
+
+        let id = new_item_id("fc");
+        let arguments = args.to_string();
+        events.push(self.event(
+            "response.output_item.added",
+            json!({
+                "output_index": output_index,
+                "item": {
+                    "id": id,
+                    "type": "function_call",
+                    "status": "in_progress",
+                    "call_id": call_id,
+                    "name": name,
+                    "arguments": ""
+                }
+            }),
+        ));
+        // Gemini 一次性给出完整参数，作为单个 delta 发送
+        events.push(self.event(
+            "response.function_call_arguments.delta",
+            json!({ "item_id": id, "output_index": output_index, "delta": arguments }),
+        ));
+        events.push(self.event(
+            "response.function_call_arguments.done",
+            json!({ "item_id": id, "output_index": output_index, "arguments": arguments }),
+        ));
+        let item = json!({
+            "id": id,
+            "type": "function_call",
+            "status": "completed",
+            "call_id": call_id,
+            "name": name,
+            "arguments": arguments
+        });
+        events.push(self.event(
+            "response.output_item.done",
+            json!({ "output_index": output_index, "item": item }),
+        ));
+        self.output.push(item);
+    }
+
+    /// 处理一个 Gemini 分片 (已剥离 v1internal 的 `response` 包装)
+    pub fn process_chunk(&mut self, chunk: &Value) -> Vec<Value> {
+        let mut events = Vec::new();
+
+        if let Some(u) = chunk.get("usageMetadata") {
+            self.usage = Some(usage_from_metadata(u));
+        }
+        let Some(candidate) = chunk
+            .get("candidates")
+            .and_then(|c| c.as_array())
+            .and_then(|c| c.first())
+        else {
+            return events;
+        };
+        if let Some(reason) = candidate.get("finishReason").and_then(|r| r.as_str()) {
+            self.finish_reason = Some(reason.to_string());
+        }
+
+        let parts = candidate
+            .get("content")
+            .and_then(|c| c.get("parts"))
+            .and_then(|p| p.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for part in &parts {
+            // 捕获 thoughtSignature
+            if let Some(sig) = part
+                .get("thoughtSignature")
+                .or(part.get("thought_signature"))
+                .and_then(|s| s.as_str())
+            {
+                store_thought_signature(sig);
+                if self.signature.as_ref().map(|s| sig.len() > s.len()).unwrap_or(true) {
+                    self.signature = Some(sig.to_string());
+                }
+            }
+
+            let is_thought = part.get("thought").and_then(|t| t.as_bool()).unwrap_or(false);
+            if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                let kind = if is_thought { OpenKind::Reasoning } else { OpenKind::Message };
+                self.push_text(kind, text, &mut events);
+            }
+            if let Some(call) = part.get("functionCall") {
+                self.push_function_call(call, &mut events);
+            }
+        }
+        events
+    }
+
+    fn finalize(&mut self, status: &str, events: &mut Vec<Value>) {
+        self.close_open(events);
+        self.response["status"] = json!(status);
+        self.response["output"] = json!(self.output);
+        self.response["usage"] = self.usage.clone().unwrap_or(Value::Null);
+    }
+
+    /// 上游流结束: response.completed，或因长度 / 安全截断时 response.incomplete
+    pub fn finish(&mut self) -> Vec<Value> {
+        let mut events = Vec::new();
+        let incomplete_reason = match self.finish_reason.as_deref() {
+            Some("MAX_TOKENS") => Some("max_output_tokens"),
+            Some("SAFETY") | Some("RECITATION") | Some("PROHIBITED_CONTENT") | Some("BLOCKLIST")
+            | Some("SPII") => Some("content_filter"),
+            _ => None,
+        };
+        match incomplete_reason {
+            Some(reason) => {
+                self.finalize("incomplete", &mut events);
+                self.response["incomplete_details"] = json!({ "reason": reason });
+                let snapshot = self.response.clone();
+                events.push(self.event("response.incomplete", json!({ "response": snapshot })));
+            }
+            None => {
+                self.finalize("completed", &mut events);
+                let snapshot = self.response.clone();
+                events.push(self.event("response.completed", json!({ "response": snapshot })));
+            }
+        }
+        events
+    }
+
+    /// 流中途出错: response.failed
+    pub fn fail(&mut self, message: &str) -> Vec<Value> {
+        let mut events = Vec::new();
+        self.finalize("failed", &mut events);
+        self.response["error"] = json!({ "code": "server_error", "message": message });
+        let snapshot = self.response.clone();
+        events.push(self.event("response.failed", json!({ "response": snapshot })));
+        events
+    }
+}
+
+/// 事件序列化为 SSE (带 event: 行)
+pub fn format_sse_event(event: &Value) -> String {
+    format!(
+        "event: {}\ndata: {}\n\n",
+        event.get("type").and_then(|t| t.as_str()).unwrap_or("message"),
+        event
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_chat_request_with_history() {
+        let body = json!({
+            "model": "gemini-3-flash",
+            "instructions": "Be brief.",
+            "max_output_tokens": 512,
+            "reasoning": { "effort": "high", "summary": "auto" },
+            "text": { "format": { "type": "json_schema", "name": "answer", "schema": { "type": "object" } } },
+            "tools": [{ "type": "local_shell" }, { "type": "function", "name": "lookup", "parameters": { "type": "object" } }]
+        });
+        let history = vec![
+            json!({ "role": "user", "content": "list files" }),
+            json!({ "type": "reasoning", "id": "rs_1", "summary": [{ "type": "summary_text", "text": "Use ls." }] }),
+            json!({ "type": "local_shell_call", "call_id": "call_1", "action": { "type": "exec", "command": ["ls"] } }),
+        ];
+        let input = normalize_input(Some(&json!([
+            { "type": "local_shell_call_output", "call_id": "call_1", "output": "a.txt" }
+        ])));
+        let req = build_chat_request(&body, &normalize_input(Some(&json!(history))), &input).unwrap();
+
+        assert_eq!(req.messages.len(), 4);
+        assert_eq!(req.messages[0].role, "system");
+        let call_msg = &req.messages[2];
+        assert_eq!(call_msg.reasoning_content.as_deref(), Some("Use ls."));
+        let call = &call_msg.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(call.function.name, "shell");
+        assert_eq!(call.function.arguments, r#"{"command":["ls"]}"#);
+        assert_eq!(req.messages[3].role, "tool");
+        assert_eq!(req.messages[3].name.as_deref(), Some("shell"));
+
+        assert_eq!(req.max_tokens, Some(512));
+        assert_eq!(req.thinking.as_ref().unwrap().budget_tokens, Some(24576));
+        assert!(req.response_format.as_ref().unwrap().schema().is_some());
+        // local_shell 内置工具替换为 shell 函数声明
+        assert_eq!(req.tools.as_ref().unwrap()[0]["name"], "shell");
+        assert!(build_chat_request(&json!({}), &[], &[]).is_err());
+    }
+
+    #[test]
+    fn test_stream_state_events() {
+        let body = json!({ "model": "gemini-3-pro", "include": ["reasoning.encrypted_content"] });
+        let mut state = ResponsesStreamState::new(response_skeleton("resp_1", &body, 1), &body);
+        let mut events = state.start();
+        events.extend(state.process_chunk(&json!({
+            "candidates": [{ "content": { "parts": [
+                { "text": "Thinking", "thought": true, "thoughtSignature": "sig-abc" },
+                { "text": "Hel" }
+            ]}}]
+        })));
+        events.extend(state.process_chunk(&json!({
+            "candidates": [{ "content": { "parts": [
+                { "text": "lo" },
+                { "functionCall": { "name": "lookup", "args": { "q": 1 } } }
+            ]}, "finishReason": "STOP" }],
+            "usageMetadata": { "promptTokenCount": 10, "candidatesTokenCount": 5, "thoughtsTokenCount": 3, "totalTokenCount": 18 }
+        })));
+        events.extend(state.finish());
+
+        let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(
+            types,
+            vec![
+                "response.created",
+                "response.in_progress",
+                "response.output_item.added",
+                "response.reasoning_summary_part.added",
+                "response.reasoning_summary_text.delta",
+                "response.reasoning_summary_text.done",
+                "response.reasoning_summary_part.done",
+                "response.output_item.done",
+                "response.output_item.added",
+                "response.content_part.added",
+                "response.output_text.delta",
+                "response.output_text.delta",
+                "response.output_text.done",
+                "response.content_part.done",
+                "response.output_item.done",
+                "response.output_item.added",
+                "response.function_call_arguments.delta",
+                "response.function_call_arguments.done",
+                "response.output_item.done",
+                "response.completed",
+            ]
+        );
+        let seqs: Vec<u64> = events.iter().map(|e| e["sequence_number"].as_u64().unwrap()).collect();
+        assert_eq!(seqs, (0..events.len() as u64).collect::<Vec<_>>());
+
+        let resp = state.response();
+        assert_eq!(resp["status"], "completed");
+        assert_eq!(resp["output"][0]["summary"][0]["text"], "Thinking");
+        assert_eq!(resp["output"][0]["encrypted_content"], "sig-abc");
+        assert_eq!(resp["output"][1]["content"][0]["text"], "Hello");
+        assert_eq!(resp["output"][2]["arguments"], r#"{"q":1}"#);
+        assert_eq!(resp["usage"]["output_tokens"], 8);
+        assert_eq!(resp["usage"]["output_tokens_details"]["reasoning_tokens"], 3);
+
+        // 输出项可作为下一轮的历史重新映射
+        let messages = input_items_to_messages(resp["output"].as_array().unwrap());
+        assert_eq!(messages[0]["reasoning_content"], "Thinking");
+        assert_eq!(messages[1]["tool_calls"][0]["function"]["name"], "lookup");
+
+        let mut truncated = ResponsesStreamState::new(response_skeleton("resp_2", &body, 1), &body);
+        truncated.process_chunk(&json!({ "candidates": [{ "content": { "parts": [{ "text": "x" }] }, "finishReason": "MAX_TOKENS" }] }));
+        let last = truncated.finish().pop().unwrap();
+        assert_eq!(last["type"], "response.incomplete");
+        assert_eq!(last["response"]["incomplete_details"]["reason"], "max_output_tokens");
+    }
+
+    #[test]
+    fn test_function_call_dedup_by_call_id() {
+        let body = json!({ "model": "gemini-3-pro" });
+        let mut state = ResponsesStreamState::new(response_skeleton("resp_1", &body, 1), &body);
+        state.process_chunk(&json!({ "candidates": [{ "content": { "parts": [
+            { "functionCall": { "id": "call_1", "name": "lookup", "args": { "q": 1 } } }
+        ]}}]}));
+        // 同一 call_id 再次出现 (例如附带了签名) 不应产生第二个输出项
+        state.process_chunk(&json!({ "candidates": [{ "content": { "parts": [
+            { "functionCall": { "id": "call_1", "name": "lookup", "args": { "q": 1 } }, "thoughtSignature": "sig" },
+            { "functionCall": { "id": "call_1", "name": "lookup", "args": { "q": 1, "page": 2 } } }
+        ]}, "finishReason": "STOP" }]}));
+        state.finish();
+
+        let output = state.response()["output"].as_array().unwrap();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0]["call_id"], "call_1");
+    }
+}
@@ -1,11 +1,12 @@
 // OpenAI 流式转换
+use crate::proxy::common::json_schema::{fix_function_calls_in_response, ToolSchemas};
 use bytes::{Bytes, BytesMut};
 use chrono::Utc;
 use futures::{Stream, StreamExt};
 use rand::Rng;
 use serde_json::{json, Value};
 use std::pin::Pin;
-use std::sync::{Mutex, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock};
 use tracing::debug;
 use uuid::Uuid;
 
@@ -88,6 +89,7 @@ fn extract_usage_metadata(u: &Value) -> Option<super::models::OpenAIUsage> {
 pub fn create_openai_sse_stream(
     mut gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
     model: String,
+    tool_schemas: Arc<ToolSchemas>,
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> {
     let mut buffer = BytesMut::new();
 
@@ -132,11 +134,12 @@ pub fn create_openai_sse_stream(
                                     tracing::debug!("Gemini SSE Chunk: {}", json_part);
 
                                     // Handle v1internal wrapper if present
-                                    let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) {
+                                    let mut actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) {
                                         inner
                                     } else {
                                         json
                                     };
+                                    fix_function_calls_in_response(&mut actual_data, &tool_schemas);
 
                                     // Capture usageMetadata if present
                                     if let Some(u) = actual_data.get("usageMetadata") {
@@ -576,6 +579,7 @@ pub fn create_legacy_sse_stream(
 pub fn create_codex_sse_stream(
     mut gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
     _model: String,
+    tool_schemas: Arc<ToolSchemas>,
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> {
     let mut buffer = BytesMut::new();
 
@@ -632,7 +636,8 @@ pub fn create_codex_sse_stream(
                                     if json_part == "[DONE]" { continue; }
 
                                     if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
-                                        let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
+                                        let mut actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
+                                        fix_function_calls_in_response(&mut actual_data, &tool_schemas);
 
                                         // Capture usageMetadata if present
                                         if let Some(u) = actual_data.get("usageMetadata") {
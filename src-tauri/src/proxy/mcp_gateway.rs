@@ -0,0 +1,670 @@
+//! MCP Gateway
+//! 将多个上游 MCP 服务器 (HTTP streamable / 本地 stdio 命令) 统一暴露在 `/mcp/<name>/mcp`，
+//! 负责会话映射、鉴权头注入与工具白/黑名单，让团队共享同一份审核过的工具目录
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{Mutex, OwnedSemaphorePermit, RwLock, Semaphore};
+use tracing::{debug, info};
+
+use crate::proxy::common::model_mapping::wildcard_match;
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// 会话空闲超时 (超时后 stdio 进程被回收，客户端需重新 initialize)
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// 每个 stdio 服务器最多同时存在的会话 (每个会话独占一个进程)
+pub const MAX_STDIO_SESSIONS_PER_SERVER: usize = 32;
+
+/// 内置 z.ai MCP 路由占用的名称，网关服务器不能重名
+const RESERVED_NAMES: [&str; 3] = ["web_search_prime", "web_reader", "zai-mcp-server"];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum McpTransport {
+    /// Streamable HTTP (POST / GET / DELETE 同一个 URL)
+    #[default]
+    Http,
+    /// 由管理器启动的本地命令，JSON-RPC 按行走 stdin / stdout
+    Stdio,
+}
+
+/// 单个上游 MCP 服务器
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct McpServerConfig {
+    /// 路由名，暴露为 `/mcp/<name>/mcp`
+    pub name: String,
+    pub enabled: bool,
+    pub transport: McpTransport,
+    /// HTTP: 上游 MCP 端点
+    pub url: String,
+    /// HTTP: 附加到每个上游请求的头 (例如 Authorization)
+    pub headers: HashMap<String, String>,
+    /// stdio: 启动命令、参数、环境变量与工作目录
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub cwd: Option<String>,
+    /// 工具白名单 (支持 `*` 通配符，为空表示全部允许)
+    pub allow_tools: Vec<String>,
+    /// 工具黑名单 (优先于白名单)
+    pub deny_tools: Vec<String>,
+}
+
+impl Default for McpServerConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            enabled: true,
+            transport: McpTransport::Http,
+            url: String::new(),
+            headers: HashMap::new(),
+            command: String::new(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            cwd: None,
+            allow_tools: Vec::new(),
+            deny_tools: Vec::new(),
+        }
+    }
+}
+
+/// MCP 网关配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct McpGatewayConfig {
+    pub enabled: bool,
+    pub servers: Vec<McpServerConfig>,
+}
+
+impl McpGatewayConfig {
+    /// 保存前校验: 名称合法且唯一、必填字段齐全
+    pub fn validate(&self) -> Result<(), String> {
+        let mut seen = std::collections::HashSet::new();
+        for server in &self.servers {
+            let name = server.name.as_str();
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "Invalid MCP server name '{}': use letters, digits, '-' or '_'",
+                    name
+                ));
+            }
+            if RESERVED_NAMES.contains(&name) {
+                return Err(format!("MCP server name '{}' is reserved by the built-in z.ai MCP routes", name));
+            }
+            if !seen.insert(name) {
+                return Err(format!("Duplicate MCP server name '{}'", name));
+            }
+            match server.transport {
+                McpTransport::Http if server.url.trim().is_empty() => {
+                    return Err(format!("MCP server '{}' needs a url", name));
+                }
+                McpTransport::Stdio if server.command.trim().is_empty() => {
+                    return Err(format!("MCP server '{}' needs a command", name));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 单条或批量 JSON-RPC 消息统一展开
+pub fn messages(payload: &Value) -> Vec<&Value> {
+    match payload {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    }
+}
+
+fn message_method(message: &Value) -> Option<&str> {
+    message.get("method").and_then(|m| m.as_str())
+}
+
+/// 需要等待响应的请求 id (通知和客户端回包返回 None)
+fn request_id(message: &Value) -> Option<&Value> {
+    message_method(message)?;
+    message.get("id").filter(|id| !id.is_null())
+}
+
+/// 监控日志里展示的标签: `<server>/<tool>` (tools/call) 或 `<server>/<method>`
+pub fn log_label(server: &str, payload: &Value) -> String {
+    let Some(first) = messages(payload).into_iter().next() else {
+        return server.to_string();
+    };
+    match message_method(first) {
+        Some("tools/call") => {
+            let tool = first
+                .get("params")
+                .and_then(|p| p.get("name"))
+                .and_then(|n| n.as_str())
+                .unwrap_or("unknown");
+            format!("{}/{}", server, tool)
+        }
+        Some(method) => format!("{}/{}", server, method),
+        None => server.to_string(),
+    }
+}
+
+impl McpServerConfig {
+    pub fn has_tool_filters(&self) -> bool {
+        !self.allow_tools.is_empty() || !self.deny_tools.is_empty()
+    }
+
+    pub fn is_tool_allowed(&self, tool: &str) -> bool {
+        if self.deny_tools.iter().any(|p| wildcard_match(p, tool)) {
+            return false;
+        }
+        self.allow_tools.is_empty() || self.allow_tools.iter().any(|p| wildcard_match(p, tool))
+    }
+
+    /// 找出请求中第一个被拒绝的 tools/call，返回 (请求 id, 工具名)
+    pub fn denied_tool_call(&self, payload: &Value) -> Option<(Value, String)> {
+        messages(payload).into_iter().find_map(|m| {
+            if message_method(m) != Some("tools/call") {
+                return None;
+            }
+            let tool = m
+                .get("params")
+                .and_then(|p| p.get("name"))
+                .and_then(|n| n.as_str())
+                .unwrap_or_default();
+            (!self.is_tool_allowed(tool))
+                .then(|| (m.get("id").cloned().unwrap_or(Value::Null), tool.to_string()))
+        })
+    }
+
+    /// 从 tools/list 响应中移除不允许的工具，返回移除数量
+    pub fn filter_tools_list(&self, payload: &mut Value) -> usize {
+        let mut removed = 0;
+        let items: Vec<&mut Value> = match payload {
+            Value::Array(items) => items.iter_mut().collect(),
+            other => vec![other],
+        };
+        for message in items {
+            let Some(tools) = message
+                .get_mut("result")
+                .and_then(|r| r.get_mut("tools"))
+                .and_then(|t| t.as_array_mut())
+            else {
+                continue;
+            };
+            let before = tools.len();
+            tools.retain(|t| {
+                t.get("name")
+                    .and_then(|n| n.as_str())
+                    .map(|name| self.is_tool_allowed(name))
+                    .unwrap_or(false)
+            });
+            removed += before - tools.len();
+        }
+        removed
+    }
+
+    /// 对 SSE 响应体逐个 `data:` 行执行 tools/list 过滤
+    pub fn filter_sse_payload(&self, payload: &str) -> String {
+        payload
+            .split('\n')
+            .map(|line| {
+                let Some(data) = line.strip_prefix("data:") else {
+                    return line.to_string();
+                };
+                match serde_json::from_str::<Value>(data.trim()) {
+                    Ok(mut v) => {
+                        if self.filter_tools_list(&mut v) > 0 {
+                            format!("data: {}", v)
+                        } else {
+                            line.to_string()
+                        }
+                    }
+                    Err(_) => line.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// 由网关启动的 stdio MCP 服务器进程 (每个会话一个)
+pub struct StdioProcess {
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+impl StdioProcess {
+    pub fn spawn(server: &McpServerConfig) -> Result<Self, String> {
+        // Windows 下 npx 等命令是 .cmd 脚本，需要经过 cmd 启动
+        #[cfg(target_os = "windows")]
+        let mut cmd = {
+            let mut c = Command::new("cmd");
+            c.arg("/C").arg(&server.command);
+            c.creation_flags(CREATE_NO_WINDOW);
+            c
+        };
+        #[cfg(not(target_os = "windows"))]
+        let mut cmd = Command::new(&server.command);
+
+        cmd.args(&server.args)
+            .envs(&server.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(cwd) = server.cwd.as_deref().filter(|c| !c.is_empty()) {
+            cmd.current_dir(cwd);
+        }
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("Failed to start MCP server '{}': {}", server.name, e))?;
+        let stdin = child.stdin.take().ok_or("Failed to open MCP server stdin")?;
+        let stdout = child.stdout.take().ok_or("Failed to open MCP server stdout")?;
+
+        if let Some(stderr) = child.stderr.take() {
+            let name = server.name.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    debug!("[MCP:{}] {}", name, line);
+                }
+            });
+        }
+
+        info!("[MCP Gateway] Started stdio server '{}' ({})", server.name, server.command);
+        Ok(Self {
+            _child: child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+        })
+    }
+
+    async fn write_message(&mut self, message: &Value) -> Result<(), String> {
+        let mut line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+        line.push('\n');
+        self.stdin
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write to MCP server: {}", e))?;
+        self.stdin
+            .flush()
+            .await
+            .map_err(|e| format!("Failed to write to MCP server: {}", e))
+    }
+
+    async fn read_response(&mut self, id: &Value) -> Result<Value, String> {
+        loop {
+            let line = self
+                .stdout
+                .next_line()
+                .await
+                .map_err(|e| format!("Failed to read from MCP server: {}", e))?
+                .ok_or("MCP server process exited")?;
+            let Ok(message) = serde_json::from_str::<Value>(line.trim()) else {
+                debug!("[MCP Gateway] Ignoring non JSON-RPC output: {}", line);
+                continue;
+            };
+            if message_method(&message).is_some() {
+                // 服务器主动发起的请求 (sampling / roots 等) 网关无法代答，直接回错误避免其阻塞
+                if let Some(server_request_id) = request_id(&message) {
+                    let reply = json!({
+                        "jsonrpc": "2.0",
+                        "error": { "code": -32601, "message": "Not supported by the MCP gateway" },
+                        "id": server_request_id,
+                    });
+                    self.write_message(&reply).await?;
+                }
+                continue;
+            }
+            if message.get("id") == Some(id) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// 发送单条或批量消息，收集请求的响应 (全是通知时返回 None)
+    pub async fn send(&mut self, payload: &Value, timeout: Duration) -> Result<Option<Value>, String> {
+        let mut responses = Vec::new();
+        for message in messages(payload) {
+            self.write_message(message).await?;
+            if let Some(id) = request_id(message) {
+                let response = tokio::time::timeout(timeout, self.read_response(id))
+                    .await
+                    .map_err(|_| "MCP server did not respond in time".to_string())??;
+                responses.push(response);
+            }
+        }
+        Ok(match payload {
+            Value::Array(_) if !responses.is_empty() => Some(Value::Array(responses)),
+            _ => responses.pop(),
+        })
+    }
+}
+
+/// 会话对应的上游连接
+#[derive(Clone)]
+pub enum SessionUpstream {
+    /// 上游分配的 mcp-session-id (无状态服务器为 None)
+    Http { session_id: Option<String> },
+    Stdio(Arc<Mutex<StdioProcess>>),
+}
+
+/// stdio 会话名额: 启动进程前预留，随会话移除 (或启动失败) 释放
+pub struct StdioSlot(#[allow(dead_code)] OwnedSemaphorePermit);
+
+struct GatewaySession {
+    server: String,
+    upstream: SessionUpstream,
+    last_active: Instant,
+    _slot: Option<StdioSlot>,
+}
+
+/// 供管理界面展示的服务器状态
+#[derive(Debug, Clone, Serialize)]
+pub struct McpServerStatus {
+    pub name: String,
+    pub transport: McpTransport,
+    pub enabled: bool,
+    pub active_sessions: usize,
+}
+
+/// 网关运行时状态: 配置 + 网关会话 ID -> 上游连接
+#[derive(Default)]
+pub struct McpGateway {
+    config: RwLock<McpGatewayConfig>,
+    sessions: Mutex<HashMap<String, GatewaySession>>,
+    /// 每个 stdio 服务器的会话名额 (MAX_STDIO_SESSIONS_PER_SERVER 个许可)
+    stdio_slots: std::sync::Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl McpGateway {
+    pub fn new(config: McpGatewayConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            sessions: Mutex::new(HashMap::new()),
+            stdio_slots: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 热更新配置，已删除 / 已禁用 / 配置变化的服务器的会话一并关闭
+    pub async fn update_config(&self, config: McpGatewayConfig) {
+        let mut current = self.config.write().await;
+        let unchanged: Vec<String> = config
+            .servers
+            .iter()
+            .filter(|s| config.enabled && s.enabled && current.servers.contains(s))
+            .map(|s| s.name.clone())
+            .collect();
+        self.sessions
+            .lock()
+            .await
+            .retain(|_, s| unchanged.contains(&s.server));
+        *current = config;
+    }
+
+    /// 按名称查找已启用的服务器
+    pub async fn server(&self, name: &str) -> Option<McpServerConfig> {
+        let config = self.config.read().await;
+        if !config.enabled {
+            return None;
+        }
+        config
+            .servers
+            .iter()
+            .find(|s| s.name == name && s.enabled)
+            .cloned()
+    }
+
+    /// 为新的 stdio 会话预留名额 (在启动进程之前调用)，已满时返回 None
+    ///
+    /// 名额是信号量许可，并发的 initialize 不会同时通过检查而超出上限
+    pub async fn reserve_stdio_slot(&self, server: &str) -> Option<StdioSlot> {
+        // 先回收过期会话，释放其占用的名额
+        self.sessions
+            .lock()
+            .await
+            .retain(|_, s| s.last_active.elapsed() < SESSION_IDLE_TIMEOUT);
+        let semaphore = self
+            .stdio_slots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(server.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(MAX_STDIO_SESSIONS_PER_SERVER)))
+            .clone();
+        semaphore.try_acquire_owned().ok().map(StdioSlot)
+    }
+
+    pub async fn create_session(
+        &self,
+        server: &str,
+        upstream: SessionUpstream,
+        slot: Option<StdioSlot>,
+    ) -> String {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let mut sessions = self.sessions.lock().await;
+        sessions.retain(|_, s| s.last_active.elapsed() < SESSION_IDLE_TIMEOUT);
+        sessions.insert(
+            session_id.clone(),
+            GatewaySession {
+                server: server.to_string(),
+                upstream,
+                last_active: Instant::now(),
+                _slot: slot,
+            },
+        );
+        session_id
+    }
+
+    /// 查找会话 (必须属于该服务器且未过期)，并刷新活跃时间
+    pub async fn session_upstream(&self, server: &str, session_id: &str) -> Option<SessionUpstream> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(session_id).filter(|s| s.server == server)?;
+        if session.last_active.elapsed() >= SESSION_IDLE_TIMEOUT {
+            sessions.remove(session_id);
+            return None;
+        }
+        session.last_active = Instant::now();
+        Some(session.upstream.clone())
+    }
+
+    /// 移除会话 (stdio 进程在最后一个引用释放时被终止)
+    pub async fn remove_session(&self, server: &str, session_id: &str) -> Option<SessionUpstream> {
+        let mut sessions = self.sessions.lock().await;
+        if sessions.get(session_id).map(|s| s.server.as_str()) != Some(server) {
+            return None;
+        }
+        sessions.remove(session_id).map(|s| s.upstream)
+    }
+
+    pub async fn session_count(&self, server: &str) -> usize {
+        let sessions = self.sessions.lock().await;
+        sessions
+            .values()
+            .filter(|s| s.server == server && s.last_active.elapsed() < SESSION_IDLE_TIMEOUT)
+            .count()
+    }
+
+    /// 关闭所有会话 (反代服务停止时调用)
+    pub async fn close_all(&self) {
+        let mut sessions = self.sessions.lock().await;
+        if !sessions.is_empty() {
+            info!("[MCP Gateway] Closing {} session(s)", sessions.len());
+        }
+        sessions.clear();
+    }
+
+    pub async fn status(&self) -> Vec<McpServerStatus> {
+        let config = self.config.read().await.clone();
+        let mut result = Vec::with_capacity(config.servers.len());
+        for server in config.servers {
+            result.push(McpServerStatus {
+                active_sessions: self.session_count(&server.name).await,
+                enabled: config.enabled && server.enabled,
+                transport: server.transport,
+                name: server.name,
+            });
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(allow: &[&str], deny: &[&str]) -> McpServerConfig {
+        McpServerConfig {
+            name: "github".to_string(),
+            url: "https://example.com/mcp".to_string(),
+            allow_tools: allow.iter().map(|s| s.to_string()).collect(),
+            deny_tools: deny.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tool_allow_deny_filtering() {
+        let s = server(&["get_*", "search_code"], &["get_secret*"]);
+        assert!(s.is_tool_allowed("get_issue"));
+        assert!(s.is_tool_allowed("search_code"));
+        assert!(!s.is_tool_allowed("get_secret_value"));
+        assert!(!s.is_tool_allowed("delete_repo"));
+
+        let mut list = json!({
+            "jsonrpc": "2.0", "id": 2,
+            "result": { "tools": [
+                {"name": "get_issue"}, {"name": "delete_repo"}, {"name": "get_secret_value"}, {"name": "search_code"}
+            ]}
+        });
+        assert_eq!(s.filter_tools_list(&mut list), 2);
+        let names: Vec<&str> = list["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["get_issue", "search_code"]);
+
+        let sse = format!("event: message\ndata: {}\n\n", json!({"id": 1, "result": {"tools": [{"name": "delete_repo"}]}}));
+        let filtered = s.filter_sse_payload(&sse);
+        assert!(filtered.starts_with("event: message\ndata: "));
+        assert!(!filtered.contains("delete_repo"));
+
+        let batch = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {"name": "get_issue"}},
+            {"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {"name": "delete_repo"}}
+        ]);
+        assert_eq!(s.denied_tool_call(&batch), Some((json!(2), "delete_repo".to_string())));
+        assert_eq!(log_label("github", &batch), "github/get_issue");
+        assert_eq!(log_label("github", &json!({"method": "initialize", "id": 0})), "github/initialize");
+
+        // 无过滤规则时全部放行
+        assert!(server(&[], &[]).is_tool_allowed("anything"));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let mut config = McpGatewayConfig {
+            enabled: true,
+            servers: vec![server(&[], &[])],
+        };
+        assert!(config.validate().is_ok());
+
+        config.servers.push(server(&[], &[]));
+        assert!(config.validate().unwrap_err().contains("Duplicate"));
+
+        config.servers[1].name = "web_reader".to_string();
+        assert!(config.validate().unwrap_err().contains("reserved"));
+
+        config.servers[1].name = "local fs".to_string();
+        assert!(config.validate().is_err());
+
+        config.servers[1] = McpServerConfig {
+            name: "fs".to_string(),
+            transport: McpTransport::Stdio,
+            ..Default::default()
+        };
+        assert!(config.validate().unwrap_err().contains("command"));
+
+        let parsed: McpGatewayConfig = serde_json::from_value(json!({
+            "enabled": true,
+            "servers": [{"name": "fs", "transport": "stdio", "command": "npx", "args": ["-y", "@modelcontextprotocol/server-filesystem"]}]
+        }))
+        .unwrap();
+        assert!(parsed.servers[0].enabled);
+        assert_eq!(parsed.servers[0].transport, McpTransport::Stdio);
+        assert!(parsed.validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn stdio_slots_are_capped_per_server() {
+        let gateway = McpGateway::default();
+        let mut slots = Vec::new();
+        for _ in 0..MAX_STDIO_SESSIONS_PER_SERVER {
+            slots.push(gateway.reserve_stdio_slot("echo").await.expect("slot available"));
+        }
+        assert!(gateway.reserve_stdio_slot("echo").await.is_none());
+        assert!(gateway.reserve_stdio_slot("other").await.is_some());
+
+        slots.pop();
+        assert!(gateway.reserve_stdio_slot("echo").await.is_some());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stdio_session_roundtrip() {
+        // 用 shell 模拟一个按行回显 JSON-RPC 结果的 stdio 服务器
+        let config = McpServerConfig {
+            name: "echo".to_string(),
+            transport: McpTransport::Stdio,
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                r#"while read line; do case "$line" in *'"id":1'*) echo '{"jsonrpc":"2.0","id":1,"result":{"tools":[{"name":"a"},{"name":"b"}]}}';; esac; done"#.to_string(),
+            ],
+            deny_tools: vec!["b".to_string()],
+            ..Default::default()
+        };
+        let gateway = McpGateway::new(McpGatewayConfig { enabled: true, servers: vec![config.clone()] });
+
+        let process = Arc::new(Mutex::new(StdioProcess::spawn(&config).unwrap()));
+        let slot = gateway.reserve_stdio_slot("echo").await;
+        assert!(slot.is_some());
+        let sid = gateway.create_session("echo", SessionUpstream::Stdio(process), slot).await;
+        assert!(gateway.session_upstream("other", &sid).await.is_none());
+        let Some(SessionUpstream::Stdio(process)) = gateway.session_upstream("echo", &sid).await else {
+            panic!("expected stdio session");
+        };
+
+        let mut proc = process.lock().await;
+        let notification = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        assert!(proc.send(&notification, Duration::from_secs(5)).await.unwrap().is_none());
+        let mut resp = proc
+            .send(&json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}), Duration::from_secs(5))
+            .await
+            .unwrap()
+            .unwrap();
+        drop(proc);
+        assert_eq!(config.filter_tools_list(&mut resp), 1);
+        assert_eq!(resp["result"]["tools"], json!([{"name": "a"}]));
+
+        // 配置变化后旧会话失效
+        let mut changed = config.clone();
+        changed.deny_tools.clear();
+        gateway.update_config(McpGatewayConfig { enabled: true, servers: vec![changed] }).await;
+        assert_eq!(gateway.session_count("echo").await, 0);
+    }
+}
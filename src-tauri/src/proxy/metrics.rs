@@ -0,0 +1,449 @@
+// Prometheus / OpenMetrics 指标
+//
+// 请求级指标 (请求数、延迟、Token) 由 monitor 中间件在每次请求后写入 ProxyMetrics；
+// 账号池状态 (限流、健康分、熔断阶段、配额) 在抓取时从 TokenManager 实时快照。
+use dashmap::DashMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 延迟直方图桶 (秒)，覆盖快速补全到长时间思考/图像生成
+const LATENCY_BUCKETS: [f64; 12] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0];
+
+/// 无法确定时使用的标签值
+const UNKNOWN: &str = "unknown";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RequestKey {
+    protocol: String,
+    model: String,
+    status: u16,
+    account: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TokenKey {
+    model: String,
+    account: String,
+    kind: &'static str,
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_ms: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, duration_ms: u64) {
+        let seconds = duration_ms as f64 / 1000.0;
+        for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+            if seconds <= *bound {
+                self.buckets[i].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_ms.fetch_add(duration_ms, Ordering::Relaxed);
+    }
+}
+
+/// 单次请求的指标观测值
+#[derive(Debug, Clone, Default)]
+pub struct RequestObservation {
+    pub protocol: Option<String>,
+    pub model: Option<String>,
+    pub status: u16,
+    pub account: Option<String>,
+    pub duration_ms: u64,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    pub cached_tokens: Option<u32>,
+    pub reasoning_tokens: Option<u32>,
+    /// 响应缓存: "HIT" / "MISS"，不可缓存的请求为 None
+    pub cache_status: Option<String>,
+}
+
+/// 请求级指标累加器 (进程生命周期内单调递增)
+#[derive(Default)]
+pub struct ProxyMetrics {
+    requests: DashMap<RequestKey, u64>,
+    latency: DashMap<(String, String), Histogram>,
+    tokens: DashMap<TokenKey, u64>,
+    /// key: (协议, "hit" / "miss")
+    response_cache: DashMap<(String, String), u64>,
+}
+
+impl ProxyMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, obs: &RequestObservation) {
+        let protocol = obs.protocol.clone().unwrap_or_else(|| UNKNOWN.to_string());
+        let model = obs.model.clone().unwrap_or_else(|| UNKNOWN.to_string());
+        let account = obs.account.clone().unwrap_or_else(|| UNKNOWN.to_string());
+
+        *self
+            .requests
+            .entry(RequestKey {
+                protocol: protocol.clone(),
+                model: model.clone(),
+                status: obs.status,
+                account: account.clone(),
+            })
+            .or_insert(0) += 1;
+
+        self.latency
+            .entry((protocol.clone(), model.clone()))
+            .or_default()
+            .observe(obs.duration_ms);
+
+        if let Some(cache_status) = &obs.cache_status {
+            *self
+                .response_cache
+                .entry((protocol, cache_status.to_ascii_lowercase()))
+                .or_insert(0) += 1;
+            // 缓存命中没有消耗上游 Token
+            if cache_status.eq_ignore_ascii_case("HIT") {
+                return;
+            }
+        }
+
+        let token_kinds = [
+            ("input", obs.input_tokens),
+            ("output", obs.output_tokens),
+            ("cached", obs.cached_tokens),
+            ("reasoning", obs.reasoning_tokens),
+        ];
+        for (kind, value) in token_kinds {
+            if let Some(v) = value.filter(|v| *v > 0) {
+                *self
+                    .tokens
+                    .entry(TokenKey {
+                        model: model.clone(),
+                        account: account.clone(),
+                        kind,
+                    })
+                    .or_insert(0) += v as u64;
+            }
+        }
+    }
+}
+
+/// 按请求路径推断协议
+pub fn protocol_from_path(path: &str) -> &'static str {
+    let path = path.split('?').next().unwrap_or(path);
+    if path.starts_with("/kiro/") {
+        "kiro"
+    } else if path.starts_with("/v1beta/") {
+        "gemini"
+    } else if path.starts_with("/v1/messages") {
+        "anthropic"
+    } else if path.starts_with("/mcp/") {
+        "mcp"
+    } else if path.starts_with("/v1/") {
+        "openai"
+    } else {
+        "other"
+    }
+}
+
+/// 单个限流记录的快照
+#[derive(Debug, Clone)]
+pub struct RateLimitSnapshot {
+    pub model: Option<String>,
+    pub reason: &'static str,
+    pub remaining_seconds: u64,
+}
+
+/// 单个账号在抓取时刻的状态
+#[derive(Debug, Clone)]
+pub struct AccountSnapshot {
+    pub account_id: String,
+    pub email: String,
+    pub provider: String,
+    pub tier: Option<String>,
+    pub health_score: f32,
+    pub validation_blocked: bool,
+    pub consecutive_failures: u32,
+    pub circuit_breaker_step: usize,
+    pub rate_limits: Vec<RateLimitSnapshot>,
+    /// (模型名, 剩余百分比)
+    pub quota: Vec<(String, i32)>,
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let inner: Vec<String> = pairs
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+        .collect();
+    format!("{{{}}}", inner.join(","))
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// 渲染 Prometheus text exposition format (0.0.4)
+pub fn render(metrics: &ProxyMetrics, accounts: &[AccountSnapshot]) -> String {
+    let mut out = String::new();
+
+    // ===== 请求 =====
+    header(&mut out, "droidgravity_requests_total", "counter", "Proxy requests by protocol, model, HTTP status and upstream account.");
+    let mut requests: Vec<_> = metrics.requests.iter().map(|e| (e.key().clone(), *e.value())).collect();
+    requests.sort_by(|a, b| {
+        (&a.0.protocol, &a.0.model, a.0.status, &a.0.account).cmp(&(&b.0.protocol, &b.0.model, b.0.status, &b.0.account))
+    });
+    for (key, count) in requests {
+        let status = key.status.to_string();
+        let _ = writeln!(
+            out,
+            "droidgravity_requests_total{} {}",
+            labels(&[("protocol", &key.protocol), ("model", &key.model), ("status", &status), ("account", &key.account)]),
+            count
+        );
+    }
+
+    header(&mut out, "droidgravity_request_duration_seconds", "histogram", "Time until the proxy produced the response head (first byte for streams).");
+    let mut latency_keys: Vec<(String, String)> = metrics.latency.iter().map(|e| e.key().clone()).collect();
+    latency_keys.sort();
+    for key in latency_keys {
+        let Some(hist) = metrics.latency.get(&key) else { continue };
+        let (protocol, model) = (&key.0, &key.1);
+        for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+            let le = bound.to_string();
+            let _ = writeln!(
+                out,
+                "droidgravity_request_duration_seconds_bucket{} {}",
+                labels(&[("protocol", protocol), ("model", model), ("le", &le)]),
+                hist.buckets[i].load(Ordering::Relaxed)
+            );
+        }
+        let count = hist.count.load(Ordering::Relaxed);
+        let base = labels(&[("protocol", protocol), ("model", model)]);
+        let _ = writeln!(
+            out,
+            "droidgravity_request_duration_seconds_bucket{} {}",
+            labels(&[("protocol", protocol), ("model", model), ("le", "+Inf")]),
+            count
+        );
+        let _ = writeln!(
+            out,
+            "droidgravity_request_duration_seconds_sum{} {}",
+            base,
+            hist.sum_ms.load(Ordering::Relaxed) as f64 / 1000.0
+        );
+        let _ = writeln!(out, "droidgravity_request_duration_seconds_count{} {}", base, count);
+    }
+
+    header(&mut out, "droidgravity_tokens_total", "counter", "Tokens reported by upstream usage metadata.");
+    let mut tokens: Vec<_> = metrics.tokens.iter().map(|e| (e.key().clone(), *e.value())).collect();
+    tokens.sort_by(|a, b| (&a.0.model, &a.0.account, a.0.kind).cmp(&(&b.0.model, &b.0.account, b.0.kind)));
+    for (key, count) in tokens {
+        let _ = writeln!(
+            out,
+            "droidgravity_tokens_total{} {}",
+            labels(&[("model", &key.model), ("account", &key.account), ("type", key.kind)]),
+            count
+        );
+    }
+
+    header(&mut out, "droidgravity_response_cache_total", "counter", "Cacheable requests by protocol and response cache result.");
+    let mut cache: Vec<_> = metrics.response_cache.iter().map(|e| (e.key().clone(), *e.value())).collect();
+    cache.sort();
+    for ((protocol, result), count) in cache {
+        let _ = writeln!(
+            out,
+            "droidgravity_response_cache_total{} {}",
+            labels(&[("protocol", &protocol), ("result", &result)]),
+            count
+        );
+    }
+
+    // ===== 账号池 =====
+    header(&mut out, "droidgravity_pool_accounts", "gauge", "Accounts loaded into the proxy pool.");
+    let _ = writeln!(out, "droidgravity_pool_accounts {}", accounts.len());
+
+    header(&mut out, "droidgravity_pool_available_accounts", "gauge", "Accounts without an account-level lockout or validation block.");
+    let available = accounts.iter().filter(|a| is_available(a)).count();
+    let _ = writeln!(out, "droidgravity_pool_available_accounts {}", available);
+
+    header(&mut out, "droidgravity_account_up", "gauge", "1 if the account can currently be selected for any model.");
+    for a in accounts {
+        let tier = a.tier.as_deref().unwrap_or(UNKNOWN);
+        let _ = writeln!(
+            out,
+            "droidgravity_account_up{} {}",
+            labels(&[("account", &a.email), ("provider", &a.provider), ("tier", tier)]),
+            u8::from(is_available(a))
+        );
+    }
+
+    header(&mut out, "droidgravity_account_health_score", "gauge", "Account health score (0-1) used for scheduling.");
+    for a in accounts {
+        let _ = writeln!(out, "droidgravity_account_health_score{} {}", labels(&[("account", &a.email)]), a.health_score);
+    }
+
+    header(&mut out, "droidgravity_account_validation_blocked", "gauge", "1 if the account is temporarily blocked by VALIDATION_REQUIRED.");
+    for a in accounts {
+        let _ = writeln!(
+            out,
+            "droidgravity_account_validation_blocked{} {}",
+            labels(&[("account", &a.email)]),
+            u8::from(a.validation_blocked)
+        );
+    }
+
+    header(&mut out, "droidgravity_account_consecutive_failures", "gauge", "Consecutive upstream failures driving the circuit breaker backoff.");
+    for a in accounts {
+        let _ = writeln!(
+            out,
+            "droidgravity_account_consecutive_failures{} {}",
+            labels(&[("account", &a.email)]),
+            a.consecutive_failures
+        );
+    }
+
+    header(&mut out, "droidgravity_account_circuit_breaker_step", "gauge", "Current index into the configured backoff steps (0 = not tripped).");
+    for a in accounts {
+        let _ = writeln!(
+            out,
+            "droidgravity_account_circuit_breaker_step{} {}",
+            labels(&[("account", &a.email)]),
+            a.circuit_breaker_step
+        );
+    }
+
+    header(&mut out, "droidgravity_account_rate_limit_remaining_seconds", "gauge", "Seconds until an active lockout expires (model=\"\" for account-level).");
+    for a in accounts {
+        for limit in &a.rate_limits {
+            let _ = writeln!(
+                out,
+                "droidgravity_account_rate_limit_remaining_seconds{} {}",
+                labels(&[
+                    ("account", &a.email),
+                    ("model", limit.model.as_deref().unwrap_or("")),
+                    ("reason", limit.reason),
+                ]),
+                limit.remaining_seconds
+            );
+        }
+    }
+
+    header(&mut out, "droidgravity_account_quota_remaining_percent", "gauge", "Remaining quota per model from the last quota refresh.");
+    for a in accounts {
+        for (model, percentage) in &a.quota {
+            let _ = writeln!(
+                out,
+                "droidgravity_account_quota_remaining_percent{} {}",
+                labels(&[("account", &a.email), ("model", model)]),
+                percentage
+            );
+        }
+    }
+
+    out
+}
+
+fn is_available(account: &AccountSnapshot) -> bool {
+    !account.validation_blocked && !account.rate_limits.iter().any(|l| l.model.is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_request_and_account_series() {
+        let metrics = ProxyMetrics::new();
+        metrics.record(&RequestObservation {
+            protocol: Some("anthropic".to_string()),
+            model: Some("claude-sonnet-4-5".to_string()),
+            status: 200,
+            account: Some("a@example.com".to_string()),
+            duration_ms: 1_500,
+            input_tokens: Some(100),
+            output_tokens: Some(20),
+            ..Default::default()
+        });
+        metrics.record(&RequestObservation {
+            protocol: Some("anthropic".to_string()),
+            model: Some("claude-sonnet-4-5".to_string()),
+            status: 200,
+            account: Some("a@example.com".to_string()),
+            duration_ms: 200,
+            input_tokens: Some(50),
+            ..Default::default()
+        });
+        // 缓存命中只计入请求数与缓存指标，不计 Token
+        metrics.record(&RequestObservation {
+            protocol: Some("openai".to_string()),
+            model: Some("gemini-3-flash".to_string()),
+            status: 200,
+            duration_ms: 5,
+            input_tokens: Some(1_000),
+            cache_status: Some("HIT".to_string()),
+            ..Default::default()
+        });
+
+        let accounts = vec![AccountSnapshot {
+            account_id: "acc1".to_string(),
+            email: "a@example.com".to_string(),
+            provider: "gemini".to_string(),
+            tier: Some("PRO".to_string()),
+            health_score: 0.5,
+            validation_blocked: false,
+            consecutive_failures: 2,
+            circuit_breaker_step: 2,
+            rate_limits: vec![RateLimitSnapshot {
+                model: Some("gemini-3-pro-high".to_string()),
+                reason: "quota_exhausted",
+                remaining_seconds: 120,
+            }],
+            quota: vec![("gemini-3-flash".to_string(), 42)],
+        }];
+
+        let text = render(&metrics, &accounts);
+
+        assert!(text.contains(
+            "droidgravity_requests_total{protocol=\"anthropic\",model=\"claude-sonnet-4-5\",status=\"200\",account=\"a@example.com\"} 2"
+        ));
+        assert!(text.contains(
+            "droidgravity_request_duration_seconds_bucket{protocol=\"anthropic\",model=\"claude-sonnet-4-5\",le=\"0.25\"} 1"
+        ));
+        assert!(text.contains(
+            "droidgravity_request_duration_seconds_bucket{protocol=\"anthropic\",model=\"claude-sonnet-4-5\",le=\"+Inf\"} 2"
+        ));
+        assert!(text.contains("droidgravity_request_duration_seconds_sum{protocol=\"anthropic\",model=\"claude-sonnet-4-5\"} 1.7"));
+        assert!(text.contains(
+            "droidgravity_tokens_total{model=\"claude-sonnet-4-5\",account=\"a@example.com\",type=\"input\"} 150"
+        ));
+        assert!(!text.contains("droidgravity_tokens_total{model=\"gemini-3-flash\""));
+        assert!(text.contains("droidgravity_response_cache_total{protocol=\"openai\",result=\"hit\"} 1"));
+        // 仅有模型级限流时账号仍视为可用
+        assert!(text.contains("droidgravity_account_up{account=\"a@example.com\",provider=\"gemini\",tier=\"PRO\"} 1"));
+        assert!(text.contains(
+            "droidgravity_account_rate_limit_remaining_seconds{account=\"a@example.com\",model=\"gemini-3-pro-high\",reason=\"quota_exhausted\"} 120"
+        ));
+        assert!(text.contains(
+            "droidgravity_account_quota_remaining_percent{account=\"a@example.com\",model=\"gemini-3-flash\"} 42"
+        ));
+    }
+
+    #[test]
+    fn test_protocol_from_path_and_escaping() {
+        assert_eq!(protocol_from_path("/v1/messages?beta=true"), "anthropic");
+        assert_eq!(protocol_from_path("/v1/chat/completions"), "openai");
+        assert_eq!(protocol_from_path("/v1beta/models/gemini-3-flash:generateContent"), "gemini");
+        assert_eq!(protocol_from_path("/kiro/v1/messages"), "kiro");
+        assert_eq!(labels(&[("model", "a\"b\\c")]), "{model=\"a\\\"b\\\\c\"}");
+    }
+}
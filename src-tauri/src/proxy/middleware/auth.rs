@@ -1,7 +1,8 @@
 // API Key 认证中间件
 use axum::{
+    body::Body,
     extract::State,
-    extract::Request,
+    extract::{MatchedPath, OriginalUri, Request},
     http::{header, StatusCode},
     middleware::Next,
     response::Response,
@@ -9,8 +10,173 @@ use axum::{
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::proxy::admin_auth;
+use crate::proxy::config::ClientApiKey;
+use crate::proxy::middleware::ip_filter::peer_ip_from_parts;
 use crate::proxy::{ProxyAuthMode, ProxySecurityConfig};
 
+const MAX_MODEL_PROBE_BODY_SIZE: usize = 100 * 1024 * 1024; // 与 monitor 中间件保持一致
+
+/// 通过认证的客户端 API Key 身份
+/// 写入 Request extensions 供路由规则匹配，写入 Response extensions 供 monitor 中间件做用量归属
+#[derive(Debug, Clone)]
+pub struct ClientKeyIdentity {
+    pub id: String,
+    pub name: String,
+}
+
+/// 从请求头中提取 API Key (Authorization: Bearer / x-api-key)
+fn extract_api_key(request: &Request) -> Option<String> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer ").or(Some(s)))
+        .or_else(|| {
+            request
+                .headers()
+                .get("x-api-key")
+                .and_then(|h| h.to_str().ok())
+        })
+        .map(|s| s.to_string())
+}
+
+/// 从 URL 路径、JSON body 或 multipart 表单 (音频转录) 中提取请求的模型名
+fn extract_request_model(path: &str, body: &[u8]) -> Option<String> {
+    if let Some(rest) = path.split("/v1beta/models/").nth(1) {
+        return rest.split(':').next().map(|s| s.to_string());
+    }
+    if let Ok(v) = serde_json::from_slice::<serde_json::Value>(body) {
+        return v.get("model").and_then(|m| m.as_str()).map(|s| s.to_string());
+    }
+    // multipart: Content-Disposition: form-data; name="model"\r\n\r\n<value>\r\n
+    let text = String::from_utf8_lossy(body);
+    let start = text.find("name=\"model\"")?;
+    let value = text[start..].split("\r\n\r\n").nth(1)?;
+    value
+        .split("\r\n")
+        .next()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// 请求体或路径中必然携带模型的推理路由
+/// 白名单 Key 在这些路由上无法识别模型时拒绝，其余路由 (模型列表、MCP 等) 不受影响
+fn route_requires_model(path: &str) -> bool {
+    matches!(
+        path,
+        "/v1/chat/completions"
+            | "/v1/completions"
+            | "/v1/responses"
+            | "/v1/images/generations"
+            | "/v1/images/edits"
+            | "/v1/embeddings"
+            | "/v1/audio/transcriptions"
+            | "/v1/audio/translations"
+            | "/v1/messages"
+            | "/v1/messages/count_tokens"
+            | "/kiro/v1/messages"
+            | "/v1/models/detect"
+    ) || path.starts_with("/v1beta/models/")
+}
+
+/// 校验客户端 API Key 的状态、模型白名单与每日预算，通过后转发请求
+async fn handle_client_key_request(
+    client_key: ClientApiKey,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if client_key.revoked {
+        tracing::warn!("[Auth] Rejected revoked client key: {}", client_key.name);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    if client_key.is_expired(chrono::Utc::now().timestamp()) {
+        tracing::warn!("[Auth] Rejected expired client key: {}", client_key.name);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    // 模型白名单检查 (需要读取 body 中的 model 字段)
+    let mut request = if client_key.allowed_models.is_empty() {
+        request
+    } else {
+        let path = request.uri().path().to_string();
+        let (parts, body) = request.into_parts();
+        let bytes = axum::body::to_bytes(body, MAX_MODEL_PROBE_BODY_SIZE)
+            .await
+            .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE)?;
+        match extract_request_model(&path, &bytes) {
+            Some(model) if !client_key.allows_model(&model) => {
+                tracing::warn!(
+                    "[Auth] Client key {} is not allowed to use model {}",
+                    client_key.name,
+                    model
+                );
+                return Err(StatusCode::FORBIDDEN);
+            }
+            // 推理路由上无法确定模型的请求不能绕过白名单
+            None if route_requires_model(&path) => {
+                tracing::warn!(
+                    "[Auth] Client key {} has a model allow-list but the request model is unknown: {}",
+                    client_key.name,
+                    path
+                );
+                return Err(StatusCode::FORBIDDEN);
+            }
+            _ => {}
+        }
+        Request::from_parts(parts, Body::from(bytes))
+    };
+
+    // 每日预算: 检查与计数在同一条 SQL 中原子完成，并发请求不会同时占用最后一个名额
+    let mut recorded = false;
+    if client_key.daily_token_limit.is_some() || client_key.daily_request_limit.is_some() {
+        let key_id = client_key.id.clone();
+        let key_name = client_key.name.clone();
+        let (request_limit, token_limit) = (client_key.daily_request_limit, client_key.daily_token_limit);
+        let reserved = tokio::task::spawn_blocking(move || {
+            crate::modules::token_stats::try_reserve_client_key_request(
+                &key_id,
+                &key_name,
+                request_limit,
+                token_limit,
+            )
+        })
+        .await;
+        match reserved {
+            Ok(Ok(true)) => recorded = true,
+            Ok(Ok(false)) => {
+                tracing::warn!("[Auth] Client key {} exceeded daily budget", client_key.name);
+                return Err(StatusCode::TOO_MANY_REQUESTS);
+            }
+            Ok(Err(e)) => tracing::error!("[Auth] Failed to reserve client key usage: {}", e),
+            Err(e) => tracing::error!("[Auth] Client key usage task failed: {}", e),
+        }
+    }
+
+    request.extensions_mut().insert(ClientKeyIdentity {
+        id: client_key.id.clone(),
+        name: client_key.name.clone(),
+    });
+
+    let mut response = next.run(request).await;
+
+    if !recorded {
+        let key_id = client_key.id.clone();
+        let key_name = client_key.name.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = crate::modules::token_stats::record_client_key_request(&key_id, &key_name) {
+                tracing::debug!("Failed to record client key request: {}", e);
+            }
+        });
+    }
+
+    response.extensions_mut().insert(ClientKeyIdentity {
+        id: client_key.id,
+        name: client_key.name,
+    });
+    Ok(response)
+}
+
 /// API Key 认证中间件
 pub async fn auth_middleware(
     State(security): State<Arc<RwLock<ProxySecurityConfig>>>,
@@ -44,37 +210,90 @@ pub async fn auth_middleware(
     }
     
     // 从 header 中提取 API key
-    let api_key = request
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|h| h.to_str().ok())
-        .and_then(|s| s.strip_prefix("Bearer ").or(Some(s)))
-        .or_else(|| {
-            request
-                .headers()
-                .get("x-api-key")
-                .and_then(|h| h.to_str().ok())
-        });
+    let api_key = extract_api_key(&request);
 
-    if security.api_key.is_empty() {
+    if security.api_key.is_empty() && security.client_keys.is_empty() {
         tracing::error!("Proxy auth is enabled but api_key is empty; denying request");
         return Err(StatusCode::UNAUTHORIZED);
     }
 
+    let Some(api_key) = api_key else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
     // Constant-time compare is unnecessary here, but keep strict equality and avoid leaking values.
-    let authorized = api_key.map(|k| k == security.api_key).unwrap_or(false);
+    if !security.api_key.is_empty() && api_key == security.api_key {
+        return Ok(next.run(request).await);
+    }
+
+    // 客户端 API Key (团队成员独立 Key)
+    if let Some(client_key) = security.find_client_key(&api_key).cloned() {
+        return handle_client_key_request(client_key, request, next).await;
+    }
+
+    Err(StatusCode::UNAUTHORIZED)
+}
+
+/// /metrics 抓取认证中间件
+///
+/// 只接受 `proxy.metrics_token` (Bearer)，API Key 与客户端 Key 均无效；
+/// 反代认证关闭 (auth_mode 解析为 Off) 时与其他路由一样开放。
+pub async fn metrics_auth_middleware(
+    State(security): State<Arc<RwLock<ProxySecurityConfig>>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let security = security.read().await.clone();
+    if matches!(security.effective_auth_mode(), ProxyAuthMode::Off) {
+        return Ok(next.run(request).await);
+    }
 
-    if authorized {
+    let Some(expected) = security.metrics_token.as_deref().filter(|t| !t.is_empty()) else {
+        tracing::warn!("[Auth] /metrics requested but proxy.metrics_token is not configured");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .unwrap_or("");
+    if admin_auth::constant_time_eq(expected.as_bytes(), provided.trim().as_bytes()) {
         Ok(next.run(request).await)
     } else {
         Err(StatusCode::UNAUTHORIZED)
     }
 }
 
-/// 管理端 API 认证中间件 (强制 API Key)
+/// 从 Authorization: Bearer 或会话 Cookie 中提取管理会话令牌
+/// 返回 (令牌, 是否来自 Cookie)
+fn extract_admin_session_token(request: &Request) -> Option<(String, bool)> {
+    if let Some(token) = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+    {
+        return Some((token.trim().to_string(), false));
+    }
+    request
+        .headers()
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|s| s.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == admin_auth::SESSION_COOKIE)
+        .map(|(_, value)| (value.to_string(), true))
+}
+
+/// 管理端 API 认证中间件
+///
+/// 只接受管理员登录签发的会话 (客户端 API Key 无管理权限)；
+/// Cookie 会话的写操作需校验 CSRF 令牌；所有写操作记录审计日志。
 pub async fn admin_auth_middleware(
     State(security): State<Arc<RwLock<ProxySecurityConfig>>>,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
     let method = request.method().clone();
@@ -90,41 +309,101 @@ pub async fn admin_auth_middleware(
         return Ok(next.run(request).await);
     }
 
-    let security = security.read().await.clone();
+    // 登录接口自身无需会话 (处理器内有失败限流)
+    if path == "/auth/login" {
+        return Ok(next.run(request).await);
+    }
 
-    // 管理端始终强制要求 API Key
-    let api_key = request
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|h| h.to_str().ok())
-        .and_then(|s| s.strip_prefix("Bearer ").or(Some(s)))
-        .or_else(|| {
-            request
-                .headers()
-                .get("x-api-key")
-                .and_then(|h| h.to_str().ok())
-        });
+    let now = chrono::Utc::now().timestamp();
+    let (token, from_cookie) = extract_admin_session_token(&request).ok_or(StatusCode::UNAUTHORIZED)?;
+    let session = admin_auth::sessions()
+        .validate(&token, now)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
 
-    if security.api_key.is_empty() {
-        return Err(StatusCode::UNAUTHORIZED);
+    let mutating = !matches!(
+        method,
+        axum::http::Method::GET | axum::http::Method::HEAD
+    );
+    if mutating && from_cookie {
+        let provided = request
+            .headers()
+            .get(admin_auth::CSRF_HEADER)
+            .and_then(|h| h.to_str().ok());
+        if !admin_auth::csrf_matches(&session, provided) {
+            tracing::warn!("[AdminAuth] CSRF token mismatch: {} {}", method, path);
+            return Err(StatusCode::FORBIDDEN);
+        }
     }
 
-    let authorized = api_key.map(|k| k == security.api_key).unwrap_or(false);
+    // 审计字段: 路由模板 (便于聚合) + 实际路径
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string());
+    let target = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|u| u.path().to_string())
+        .unwrap_or_else(|| path.clone());
+    // 审计 IP 取 TCP 对端，转发头仅在对端是受信任代理时采信
+    let trusted_proxies = security.read().await.trusted_proxies.clone();
+    let client_ip = peer_ip_from_parts(request.headers(), request.extensions(), &trusted_proxies);
+    let session_id = session.id.clone();
 
-    if authorized {
-        Ok(next.run(request).await)
-    } else {
-        Err(StatusCode::UNAUTHORIZED)
+    request
+        .extensions_mut()
+        .insert(admin_auth::AuthenticatedAdmin { token, session });
+    let response = next.run(request).await;
+
+    if mutating {
+        let action = format!("{} {}", method, route.unwrap_or_else(|| target.clone()));
+        admin_auth::audit(
+            &session_id,
+            &action,
+            Some(target),
+            response.status().as_u16(),
+            client_ip,
+        );
     }
+
+    Ok(response)
 }
 
 #[cfg(test)]
 mod tests {
-    // 移除未使用的 use super::*;
+    use super::*;
 
     #[test]
     fn test_auth_placeholder() {
         // Placeholder test
         assert!(true);
     }
+
+    #[test]
+    fn test_extract_request_model() {
+        assert_eq!(
+            extract_request_model("/v1beta/models/gemini-3-pro-high:generateContent", b""),
+            Some("gemini-3-pro-high".to_string())
+        );
+        assert_eq!(
+            extract_request_model("/v1/messages", br#"{"model":"claude-sonnet-4-5"}"#),
+            Some("claude-sonnet-4-5".to_string())
+        );
+        assert_eq!(extract_request_model("/v1/messages", b"not json"), None);
+        assert_eq!(extract_request_model("/v1/messages", br#"{"messages":[]}"#), None);
+        let multipart = b"--x\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.wav\"\r\n\r\nRIFF\r\n--x\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\nwhisper-1\r\n--x--\r\n";
+        assert_eq!(
+            extract_request_model("/v1/audio/transcriptions", multipart),
+            Some("whisper-1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_route_requires_model() {
+        assert!(route_requires_model("/v1/chat/completions"));
+        assert!(route_requires_model("/v1/messages"));
+        assert!(route_requires_model("/v1beta/models/gemini-2.5-flash:generateContent"));
+        assert!(!route_requires_model("/v1/models"));
+        assert!(!route_requires_model("/mcp/web_reader/mcp"));
+    }
 }
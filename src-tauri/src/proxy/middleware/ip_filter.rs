@@ -144,16 +144,22 @@ pub async fn ip_filter_middleware(
 
 /// 从请求中提取客户端 IP
 fn extract_client_ip(request: &Request) -> Option<String> {
+    client_ip_from_parts(request.headers(), request.extensions())
+}
+
+/// 从请求头与扩展中提取客户端 IP (供无法拿到完整 Request 的处理器复用)
+pub(crate) fn client_ip_from_parts(
+    headers: &axum::http::HeaderMap,
+    extensions: &axum::http::Extensions,
+) -> Option<String> {
     // 1. 优先从 X-Forwarded-For 提取 (取第一个 IP)
-    request
-        .headers()
+    headers
         .get("x-forwarded-for")
         .and_then(|v| v.to_str().ok())
         .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
         .or_else(|| {
             // 2. 备选从 X-Real-IP 提取
-            request
-                .headers()
+            headers
                 .get("x-real-ip")
                 .and_then(|v| v.to_str().ok())
                 .map(|s| s.to_string())
@@ -161,13 +167,77 @@ fn extract_client_ip(request: &Request) -> Option<String> {
         .or_else(|| {
             // 3. 最后尝试从 ConnectInfo 获取 (TCP 连接 IP)
             // 这可以解决本地开发/测试时没有代理头导致 IP 获取失败的问题
-            request
-                .extensions()
+            extensions
                 .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
                 .map(|info| info.0.ip().to_string())
         })
 }
 
+/// 取 TCP 对端地址作为客户端 IP；仅当对端是受信任代理时才采信转发头
+///
+/// X-Forwarded-For 从右向左取第一个不属于受信任代理的地址 (左侧可由客户端伪造)
+pub(crate) fn peer_ip_from_parts(
+    headers: &axum::http::HeaderMap,
+    extensions: &axum::http::Extensions,
+    trusted_proxies: &[String],
+) -> Option<String> {
+    let peer = extensions
+        .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
+        .map(|info| info.0.ip())?;
+    if !is_trusted_proxy(peer, trusted_proxies) {
+        return Some(peer.to_string());
+    }
+
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| {
+            s.split(',')
+                .rev()
+                .filter_map(|part| part.trim().parse::<std::net::IpAddr>().ok())
+                .find(|ip| !is_trusted_proxy(*ip, trusted_proxies))
+        })
+        .or_else(|| {
+            headers
+                .get("x-real-ip")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|s| s.trim().parse().ok())
+        });
+    Some(forwarded.unwrap_or(peer).to_string())
+}
+
+/// IP 是否命中受信任代理列表 (单个 IP 或 CIDR，支持 IPv4 / IPv6)
+fn is_trusted_proxy(ip: std::net::IpAddr, trusted_proxies: &[String]) -> bool {
+    use std::net::IpAddr;
+
+    trusted_proxies.iter().any(|entry| {
+        let entry = entry.trim();
+        let (network, prefix) = match entry.split_once('/') {
+            Some((net, prefix)) => match prefix.parse::<u32>() {
+                Ok(p) => (net, Some(p)),
+                Err(_) => return false,
+            },
+            None => (entry, None),
+        };
+        let Ok(network) = network.parse::<IpAddr>() else {
+            return false;
+        };
+        match (ip, network) {
+            (IpAddr::V4(ip), IpAddr::V4(net)) => {
+                let prefix = prefix.unwrap_or(32).min(32);
+                let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+                u32::from(ip) & mask == u32::from(net) & mask
+            }
+            (IpAddr::V6(ip), IpAddr::V6(net)) => {
+                let prefix = prefix.unwrap_or(128).min(128);
+                let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+                u128::from(ip) & mask == u128::from(net) & mask
+            }
+            _ => false,
+        }
+    })
+}
+
 /// 创建被封禁的响应
 fn create_blocked_response(ip: &str, message: &str) -> Response {
     let body = serde_json::json!({
@@ -186,3 +256,41 @@ fn create_blocked_response(ip: &str, message: &str) -> Response {
     )
         .into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::ConnectInfo;
+    use axum::http::{Extensions, HeaderMap};
+
+    fn parts(peer: &str, xff: Option<&str>) -> (HeaderMap, Extensions) {
+        let mut headers = HeaderMap::new();
+        if let Some(xff) = xff {
+            headers.insert("x-forwarded-for", xff.parse().unwrap());
+        }
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo::<std::net::SocketAddr>(peer.parse().unwrap()));
+        (headers, extensions)
+    }
+
+    #[test]
+    fn forwarded_headers_only_trusted_from_configured_proxies() {
+        let trusted = vec!["127.0.0.1".to_string(), "10.0.0.0/8".to_string()];
+
+        // 非受信任对端: 忽略伪造的转发头
+        let (h, e) = parts("203.0.113.9:5000", Some("1.2.3.4"));
+        assert_eq!(peer_ip_from_parts(&h, &e, &trusted).as_deref(), Some("203.0.113.9"));
+
+        // 受信任代理链: 取最右侧的非代理地址，客户端在左侧伪造的值无效
+        let (h, e) = parts("127.0.0.1:5000", Some("1.2.3.4, 198.51.100.7, 10.1.2.3"));
+        assert_eq!(peer_ip_from_parts(&h, &e, &trusted).as_deref(), Some("198.51.100.7"));
+
+        // 受信任代理但没有转发头: 回退到对端
+        let (h, e) = parts("127.0.0.1:5000", None);
+        assert_eq!(peer_ip_from_parts(&h, &e, &trusted).as_deref(), Some("127.0.0.1"));
+
+        // 未配置任何受信任代理
+        let (h, e) = parts("127.0.0.1:5000", Some("1.2.3.4"));
+        assert_eq!(peer_ip_from_parts(&h, &e, &[]).as_deref(), Some("127.0.0.1"));
+    }
+}
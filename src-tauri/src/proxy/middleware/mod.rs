@@ -7,7 +7,7 @@ pub mod monitor;
 pub mod ip_filter;
 pub mod service_status;
 
-pub use auth::{auth_middleware, admin_auth_middleware};
+pub use auth::{auth_middleware, admin_auth_middleware, metrics_auth_middleware};
 pub use cors::cors_layer;
 pub use ip_filter::ip_filter_middleware;
 pub use monitor::monitor_middleware;
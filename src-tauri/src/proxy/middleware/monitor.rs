@@ -7,6 +7,8 @@ use axum::{
 use std::time::Instant;
 use crate::proxy::server::AppState;
 use crate::proxy::monitor::ProxyRequestLog;
+use crate::proxy::metrics::{protocol_from_path, RequestObservation};
+use crate::proxy::monitor_policy::CaptureLevel;
 use serde_json::Value;
 use futures::StreamExt;
 
@@ -18,10 +20,6 @@ pub async fn monitor_middleware(
     request: Request,
     next: Next,
 ) -> Response {
-    if !state.monitor.is_enabled() {
-        return next.run(request).await;
-    }
-
     let start = Instant::now();
     let method = request.method().to_string();
     let uri = request.uri().to_string();
@@ -38,9 +36,16 @@ pub async fn monitor_middleware(
     } else {
         None
     };
+    let protocol = protocol_from_path(request.uri().path()).to_string();
 
+    let monitor_enabled = state.monitor.is_enabled();
+    // [NEW] 采集级别为 metadata 的协议不保留请求/响应体 (请求体仍会缓冲以提取模型名)
+    let keep_bodies = monitor_enabled
+        && crate::proxy::monitor_policy::policy().level_for(&protocol) != CaptureLevel::Metadata;
+
+    // 监控关闭时不缓冲请求体
     let request_body_str;
-    let request = if method == "POST" {
+    let request = if monitor_enabled && method == "POST" {
         let (parts, body) = request.into_parts();
         match axum::body::to_bytes(body, MAX_REQUEST_LOG_SIZE).await {
             Ok(bytes) => {
@@ -49,7 +54,9 @@ pub async fn monitor_middleware(
                         v.get("model").and_then(|m| m.as_str()).map(|s| s.to_string())
                     );
                 }
-                request_body_str = if let Ok(s) = std::str::from_utf8(&bytes) {
+                request_body_str = if !keep_bodies {
+                    None
+                } else if let Ok(s) = std::str::from_utf8(&bytes) {
                     Some(s.to_string())
                 } else {
                     Some("[Binary Request Data]".to_string())
@@ -67,7 +74,35 @@ pub async fn monitor_middleware(
     };
     
     let response = next.run(request).await;
-    
+
+    // 客户端 API Key 身份 (由 auth 中间件写入)
+    let client_key = response
+        .extensions()
+        .get::<crate::proxy::middleware::auth::ClientKeyIdentity>()
+        .cloned();
+
+    // [NEW] 监控关闭时仍记录指标，但只使用响应头可得的信息 (不缓冲 body，因此没有 Token 计数)
+    // 带客户端 Key 的请求除外: 每日 Token 预算依赖 log_request 记录的用量，需继续提取 usage
+    if !monitor_enabled && client_key.is_none() {
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string())
+        };
+        state.monitor.metrics.record(&RequestObservation {
+            protocol: Some(protocol),
+            model: header("X-Mapped-Model").or(model),
+            status: response.status().as_u16(),
+            account: header("X-Account-Email"),
+            duration_ms: start.elapsed().as_millis() as u64,
+            cache_status: header(crate::proxy::response_cache::CACHE_HEADER),
+            ..Default::default()
+        });
+        return response;
+    }
+
     let duration = start.elapsed().as_millis() as u64;
     let status = response.status().as_u16();
     
@@ -90,8 +125,21 @@ pub async fn monitor_middleware(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    let monitor = state.monitor.clone();
-    let mut log = ProxyRequestLog {
+    // [NEW] 模型降级链: 降级前的目标
+    let fallback_from = response
+        .headers()
+        .get(crate::proxy::model_fallback::FALLBACK_FROM_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+
+    // [NEW] 响应缓存命中情况
+    let cache_status = response
+        .headers()
+        .get(crate::proxy::response_cache::CACHE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+
+    let log = ProxyRequestLog {
         id: uuid::Uuid::new_v4().to_string(),
         timestamp: chrono::Utc::now().timestamp_millis(),
         method,
@@ -100,6 +148,8 @@ pub async fn monitor_middleware(
         duration,
         model,
         mapped_model,
+        fallback_from,
+        cache_status,
         account_email,
         client_ip: None,
         error: None,
@@ -109,11 +159,64 @@ pub async fn monitor_middleware(
         output_tokens: None,
         cached_tokens: None,
         reasoning_tokens: None,
-        protocol: None,
+        protocol: Some(protocol),
+        client_key_id: client_key.as_ref().map(|k| k.id.clone()),
+        client_key_name: client_key.map(|k| k.name),
     };
 
+    capture_response(state.monitor.clone(), log, response, &content_type, keep_bodies).await
+}
+
+/// 从上游 usage / usageMetadata 中提取 Token 计数 (OpenAI / Anthropic / Gemini)
+fn apply_usage(log: &mut ProxyRequestLog, usage: &Value) {
+    log.input_tokens = usage.get("prompt_tokens")
+        .or(usage.get("input_tokens"))
+        .or(usage.get("promptTokenCount"))
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32);
+    log.output_tokens = usage.get("completion_tokens")
+        .or(usage.get("output_tokens"))
+        .or(usage.get("candidatesTokenCount"))
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32);
+
+    // [NEW v4.0.8] Extract Cache & Reasoning tokens
+    log.cached_tokens = usage.get("cache_read_input_tokens")
+        .or(usage.get("cached_tokens"))
+        .or(usage.get("cached_content_token_count"))
+        .or(usage.get("cachedContentTokenCount"))
+        .or(usage.get("prompt_tokens_details").and_then(|d| d.get("cached_tokens")))
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32);
+
+    log.reasoning_tokens = usage.get("reasoning_tokens")
+        .or(usage.get("completion_tokens_details").and_then(|d| d.get("reasoning_tokens")))
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32);
+
+    if log.input_tokens.is_none() && log.output_tokens.is_none() {
+        log.output_tokens = usage.get("total_tokens")
+            .or(usage.get("totalTokenCount"))
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32);
+    }
+}
+
+/// 透传响应并提取 Token 用量，完成后交给 monitor.log_request
+///
+/// keep_bodies 为 false (监控关闭或采集级别为 metadata) 时只提取用量，不保留响应内容；
+/// 失败请求仍记录 error，由监控策略截断与脱敏。
+async fn capture_response(
+    monitor: std::sync::Arc<crate::proxy::monitor::ProxyMonitor>,
+    mut log: ProxyRequestLog,
+    response: Response,
+    content_type: &str,
+    keep_bodies: bool,
+) -> Response {
     if content_type.contains("text/event-stream") {
-        log.response_body = Some("[Stream Data]".to_string());
+        if keep_bodies {
+            log.response_body = Some("[Stream Data]".to_string());
+        }
         let (parts, body) = response.into_parts();
         let mut stream = body.into_data_stream();
         let (tx, rx) = tokio::sync::mpsc::channel(64);
@@ -126,7 +229,7 @@ pub async fn monitor_middleware(
             while let Some(chunk_res) = stream.next().await {
                 if let Ok(chunk) = chunk_res {
                     // Capture first ~2048 bytes of the stream for debugging
-                    if capture_count < 2048 {
+                    if keep_bodies && capture_count < 2048 {
                         if let Ok(s) = std::str::from_utf8(&chunk) {
                             captured_content.push_str(s);
                             capture_count += chunk.len();
@@ -159,37 +262,7 @@ pub async fn monitor_middleware(
                         if let Ok(json) = serde_json::from_str::<Value>(json_str) {
                             // 支持 OpenAI "usage" 或 Gemini "usageMetadata"
                             if let Some(usage) = json.get("usage").or(json.get("usageMetadata")) {
-                                log.input_tokens = usage.get("prompt_tokens")
-                                    .or(usage.get("input_tokens"))
-                                    .or(usage.get("promptTokenCount"))
-                                    .and_then(|v| v.as_u64())
-                                    .map(|v| v as u32);
-                                log.output_tokens = usage.get("completion_tokens")
-                                    .or(usage.get("output_tokens"))
-                                    .or(usage.get("candidatesTokenCount"))
-                                    .and_then(|v| v.as_u64())
-                                    .map(|v| v as u32);
-                                
-                                // [NEW v4.0.8] Extract Cache & Reasoning tokens
-                                log.cached_tokens = usage.get("cache_read_input_tokens")
-                                    .or(usage.get("cached_tokens"))
-                                    .or(usage.get("cached_content_token_count"))
-                                    .or(usage.get("cachedContentTokenCount"))
-                                    .or(usage.get("prompt_tokens_details").and_then(|d| d.get("cached_tokens")))
-                                    .and_then(|v| v.as_u64())
-                                    .map(|v| v as u32);
-                                
-                                log.reasoning_tokens = usage.get("reasoning_tokens")
-                                    .or(usage.get("completion_tokens_details").and_then(|d| d.get("reasoning_tokens")))
-                                    .and_then(|v| v.as_u64())
-                                    .map(|v| v as u32);
-                                
-                                if log.input_tokens.is_none() && log.output_tokens.is_none() {
-                                    log.output_tokens = usage.get("total_tokens")
-                                        .or(usage.get("totalTokenCount"))
-                                        .and_then(|v| v.as_u64())
-                                        .map(|v| v as u32);
-                                }
+                                apply_usage(&mut log, usage);
                                 break;
                             }
                         }
@@ -212,59 +285,91 @@ pub async fn monitor_middleware(
                     if let Ok(json) = serde_json::from_str::<Value>(&s) {
                         // 支持 OpenAI "usage" 或 Gemini "usageMetadata"
                         if let Some(usage) = json.get("usage").or(json.get("usageMetadata")) {
-                            log.input_tokens = usage.get("prompt_tokens")
-                                .or(usage.get("input_tokens"))
-                                .or(usage.get("promptTokenCount"))
-                                .and_then(|v| v.as_u64())
-                                .map(|v| v as u32);
-                            log.output_tokens = usage.get("completion_tokens")
-                                .or(usage.get("output_tokens"))
-                                .or(usage.get("candidatesTokenCount"))
-                                .and_then(|v| v.as_u64())
-                                .map(|v| v as u32);
-                                
-                            // [NEW v4.0.8] Extract Cache & Reasoning tokens
-                            log.cached_tokens = usage.get("cache_read_input_tokens")
-                                .or(usage.get("cached_tokens"))
-                                .or(usage.get("cached_content_token_count"))
-                                .or(usage.get("cachedContentTokenCount"))
-                                .or(usage.get("prompt_tokens_details").and_then(|d| d.get("cached_tokens")))
-                                .and_then(|v| v.as_u64())
-                                .map(|v| v as u32);
-                            
-                            log.reasoning_tokens = usage.get("reasoning_tokens")
-                                .or(usage.get("completion_tokens_details").and_then(|d| d.get("reasoning_tokens")))
-                                .and_then(|v| v.as_u64())
-                                .map(|v| v as u32);
-                                
-                            if log.input_tokens.is_none() && log.output_tokens.is_none() {
-                                log.output_tokens = usage.get("total_tokens")
-                                    .or(usage.get("totalTokenCount"))
-                                    .and_then(|v| v.as_u64())
-                                    .map(|v| v as u32);
-                            }
+                            apply_usage(&mut log, usage);
                         }
                     }
-                    log.response_body = Some(s.to_string());
-                } else {
+                    if keep_bodies {
+                        log.response_body = Some(s.to_string());
+                    }
+                    if log.status >= 400 {
+                        log.error = Some(s.to_string());
+                    }
+                } else if keep_bodies {
                     log.response_body = Some("[Binary Response Data]".to_string());
                 }
-                
-                if log.status >= 400 {
+
+                if log.status >= 400 && log.error.is_none() {
                     log.error = log.response_body.clone();
                 }
                 monitor.log_request(log).await;
                 Response::from_parts(parts, Body::from(bytes))
             }
             Err(_) => {
-                log.response_body = Some("[Response too large (>100MB)]".to_string());
+                if keep_bodies {
+                    log.response_body = Some("[Response too large (>100MB)]".to_string());
+                }
                 monitor.log_request(log).await;
                 Response::from_parts(parts, Body::empty())
             }
         }
     } else {
-        log.response_body = Some(format!("[{}]", content_type));
+        if keep_bodies {
+            log.response_body = Some(format!("[{}]", content_type));
+        }
         monitor.log_request(log).await;
         response
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_usage_recorded_with_monitor_disabled() {
+        let monitor = Arc::new(crate::proxy::monitor::ProxyMonitor::new(16, None));
+        assert!(!monitor.is_enabled());
+
+        let body = r#"{"id":"msg_1","usage":{"input_tokens":120,"output_tokens":30}}"#;
+        let response = Response::builder()
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let log = ProxyRequestLog {
+            id: "log-1".to_string(),
+            timestamp: 0,
+            method: "POST".to_string(),
+            url: "/v1/messages".to_string(),
+            status: 200,
+            duration: 5,
+            model: Some("claude-sonnet-4-5".to_string()),
+            mapped_model: Some("claude-sonnet-4-5".to_string()),
+            fallback_from: None,
+            cache_status: None,
+            account_email: Some("budget@example.com".to_string()),
+            client_ip: None,
+            error: None,
+            request_body: None,
+            response_body: None,
+            input_tokens: None,
+            output_tokens: None,
+            cached_tokens: None,
+            reasoning_tokens: None,
+            protocol: Some("anthropic".to_string()),
+            client_key_id: Some("key-1".to_string()),
+            client_key_name: Some("ci".to_string()),
+        };
+
+        let response = capture_response(monitor.clone(), log, response, "application/json", false).await;
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(bytes, body.as_bytes());
+
+        // 监控关闭时 log_request 仍收到 usage (客户端 Key 预算与指标共用这份数据)，但不保存日志
+        let text = crate::proxy::metrics::render(&monitor.metrics, &[]);
+        assert!(text.contains(
+            "droidgravity_tokens_total{model=\"claude-sonnet-4-5\",account=\"budget@example.com\",type=\"input\"} 120"
+        ));
+        assert!(monitor.logs.read().await.is_empty());
+    }
+}
@@ -6,6 +6,7 @@ pub mod token_manager;
 pub mod project_resolver;
 pub mod server;
 pub mod security;
+pub mod admin_auth;         // 管理端认证 (管理员密码 / 会话 / CSRF)
 
 // 新架构模块
 pub mod mappers;           // 协议转换器
@@ -16,14 +17,22 @@ pub mod common;            // 公共工具
 pub mod providers;         // Extra upstream providers (z.ai, etc.)
 pub mod zai_vision_mcp;    // Built-in Vision MCP server state
 pub mod zai_vision_tools;  // Built-in Vision MCP tools (z.ai vision API)
+pub mod mcp_gateway;       // 通用 MCP 网关 (HTTP / stdio 上游)
 pub mod monitor;           // 监控
+pub mod monitor_policy;    // 监控日志采集级别 / 脱敏 / 保留策略
+pub mod metrics;           // Prometheus 指标
 pub mod rate_limit;        // 限流跟踪
 pub mod sticky_config;     // 粘性调度配置
+pub mod routing_policy;    // 账号路由策略
+pub mod model_fallback;    // 模型降级链 (跨提供商)
+pub mod model_router;      // 模型路由规则 (正则 / 请求特征条件)
+pub mod response_cache;    // 确定性请求的响应缓存 (SQLite, TTL)
 pub mod session_manager;   // 会话指纹管理
 pub mod audio;             // 音频处理模块 (PR #311)
 pub mod signature_cache;   // Signature Cache (v3.3.16)
 pub mod cli_sync;          // CLI 配置同步 (v3.3.35)
 pub mod debug_logger;      // 调试日志
+pub mod tokenizer;         // 离线分词 (按模型家族打包词表)
 
 
 pub use config::ProxyConfig;
@@ -0,0 +1,293 @@
+// 模型降级链 (Model Fallback Chains)
+//
+// resolve_model_route 只把模型映射到一个目标；当该目标的整个账号池都不可用
+// (全部限流 / 配额保护 / 已在本次请求中失败) 时，按配置的降级链依次尝试下一个模型。
+// 每一跳都可以跨提供商:
+//   - `gemini-3-pro-high`      Antigravity (Google) 账号池中的物理模型
+//   - `kiro:claude-opus-4-6`   Kiro 账号池
+//   - `zai` / `zai:glm-4.7`    z.ai (不带模型名时沿用客户端请求的模型，由 z.ai 映射)
+// 未带前缀的模型按 determine_provider_by_model 归属 Kiro 或 Gemini，与主路由一致。
+use axum::http::HeaderValue;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::proxy::common::model_mapping::{determine_provider_by_model, pattern_specificity, wildcard_match};
+use crate::proxy::server::AppState;
+use crate::proxy::TokenManager;
+
+/// 响应头: 发生降级时记录原本的目标 (监控日志读取)
+pub const FALLBACK_FROM_HEADER: &str = "X-Fallback-From";
+
+/// 一条降级链
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelFallbackChain {
+    /// 模型名或通配符 (如 "claude-opus-*")，匹配客户端请求的模型名
+    pub model_pattern: String,
+    /// 按顺序尝试的降级目标
+    pub fallbacks: Vec<String>,
+}
+
+/// 模型降级配置
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ModelFallbackConfig {
+    pub chains: Vec<ModelFallbackChain>,
+}
+
+/// 降级链中的一跳
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FallbackTarget {
+    /// Antigravity 账号池中的物理模型
+    Gemini(String),
+    /// Kiro 账号池中的模型
+    Kiro(String),
+    /// z.ai，None 表示沿用客户端请求的模型
+    Zai(Option<String>),
+}
+
+impl FallbackTarget {
+    /// 解析配置中的一跳，空字符串返回 None
+    pub fn parse(hop: &str) -> Option<Self> {
+        let hop = hop.trim();
+        if hop.is_empty() {
+            return None;
+        }
+        let (prefix, model) = match hop.split_once(':') {
+            Some((p, m)) => (p.trim().to_ascii_lowercase(), m.trim()),
+            None => (hop.to_ascii_lowercase(), ""),
+        };
+        match prefix.as_str() {
+            "zai" => Some(FallbackTarget::Zai((!model.is_empty()).then(|| model.to_string()))),
+            "kiro" if !model.is_empty() => Some(FallbackTarget::Kiro(model.to_string())),
+            "gemini" if !model.is_empty() => Some(FallbackTarget::Gemini(model.to_string())),
+            _ => Some(Self::for_model(hop)),
+        }
+    }
+
+    /// 未带前缀的模型按主路由规则归属提供商
+    pub fn for_model(model: &str) -> Self {
+        match determine_provider_by_model(model) {
+            "kiro" => FallbackTarget::Kiro(model.to_string()),
+            _ => FallbackTarget::Gemini(model.to_string()),
+        }
+    }
+
+    /// 用于 X-Mapped-Model / 日志展示的名称
+    pub fn label(&self) -> String {
+        match self {
+            FallbackTarget::Gemini(m) => m.clone(),
+            FallbackTarget::Kiro(m) => format!("kiro:{}", m),
+            FallbackTarget::Zai(Some(m)) => format!("zai:{}", m),
+            FallbackTarget::Zai(None) => "zai".to_string(),
+        }
+    }
+}
+
+impl ModelFallbackConfig {
+    /// 查找模型的降级链: 精确匹配优先，其次按 specificity 最高的通配符
+    pub fn chain_for(&self, model: &str) -> Option<&ModelFallbackChain> {
+        if let Some(chain) = self.chains.iter().find(|c| c.model_pattern == model) {
+            return Some(chain);
+        }
+        self.chains
+            .iter()
+            .filter(|c| c.model_pattern.contains('*') && wildcard_match(&c.model_pattern, model))
+            .max_by_key(|c| pattern_specificity(&c.model_pattern))
+    }
+
+    /// 模型的降级目标 (已解析，去掉无效项)
+    pub fn targets_for(&self, model: &str) -> Vec<FallbackTarget> {
+        self.chain_for(model)
+            .map(|c| c.fallbacks.iter().filter_map(|h| FallbackTarget::parse(h)).collect())
+            .unwrap_or_default()
+    }
+
+    /// 校验配置 (保存前调用)
+    pub fn validate(&self) -> Result<(), String> {
+        for chain in &self.chains {
+            if chain.model_pattern.trim().is_empty() {
+                return Err("模型降级链的 model_pattern 不能为空".to_string());
+            }
+            if let Some(bad) = chain.fallbacks.iter().find(|h| {
+                let lower = h.trim().to_ascii_lowercase();
+                lower.is_empty() || lower == "kiro" || lower == "kiro:" || lower == "gemini" || lower == "gemini:"
+            }) {
+                return Err(format!(
+                    "模型降级链 {} 中的目标无效: \"{}\"",
+                    chain.model_pattern, bad
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 本次请求实际发生的降级
+#[derive(Debug, Clone)]
+pub struct FallbackApplied {
+    /// 原本的目标 (账号池已不可用)
+    pub from: String,
+    /// 实际使用的目标
+    pub to: String,
+}
+
+impl FallbackApplied {
+    /// 写入 X-Fallback-From；处理器未设置 X-Mapped-Model 时 (如 z.ai 透传) 一并补上
+    pub fn apply_headers(&self, response: &mut Response) {
+        let headers = response.headers_mut();
+        if let Ok(v) = HeaderValue::from_str(&self.from) {
+            headers.insert(FALLBACK_FROM_HEADER, v);
+        }
+        if !headers.contains_key("X-Mapped-Model") {
+            if let Ok(v) = HeaderValue::from_str(&self.to) {
+                headers.insert("X-Mapped-Model", v);
+            }
+        }
+    }
+}
+
+/// 目标当前是否可用: 账号池内还有可用账号，或 z.ai 已启用并配置了 API Key
+pub async fn is_target_available(state: &AppState, target: FallbackTarget, excluded: &HashSet<String>) -> bool {
+    match target {
+        FallbackTarget::Gemini(model) => {
+            state
+                .token_manager
+                .has_available_account_excluding("gemini", &model, Some(excluded))
+                .await
+        }
+        FallbackTarget::Kiro(model) => {
+            state
+                .token_manager
+                .has_available_account_excluding("kiro", &model, Some(excluded))
+                .await
+        }
+        FallbackTarget::Zai(_) => {
+            let zai = state.zai.read().await;
+            zai.enabled
+                && zai.dispatch_mode != crate::proxy::ZaiDispatchMode::Off
+                && !zai.api_key.trim().is_empty()
+        }
+    }
+}
+
+/// 只在 Antigravity 账号池内降级 (OpenAI / Gemini 原生协议无法转发到 Kiro / z.ai)
+///
+/// 每次重试前调用，`excluded` 为本次请求中已失败的账号。
+/// 主模型可用、未配置降级链或没有可用目标时返回 None。
+pub async fn select_gemini_fallback(
+    token_manager: &TokenManager,
+    original_model: &str,
+    mapped_model: &str,
+    excluded: &HashSet<String>,
+) -> Option<FallbackApplied> {
+    let targets: Vec<FallbackTarget> = token_manager
+        .fallback_targets(original_model)
+        .into_iter()
+        .filter(|t| matches!(t, FallbackTarget::Gemini(_)))
+        .collect();
+    if targets.is_empty() {
+        return None;
+    }
+    let primary = FallbackTarget::Gemini(mapped_model.to_string());
+    let picked = select_target(primary, targets, |t| async move {
+        let FallbackTarget::Gemini(model) = t else { return false };
+        token_manager
+            .has_available_account_excluding("gemini", &model, Some(excluded))
+            .await
+    })
+    .await;
+    match picked {
+        Some((hop, target)) if hop > 0 => {
+            tracing::info!(
+                "[ModelFallback] {} 的账号池不可用，降级到第 {} 跳: {}",
+                mapped_model,
+                hop,
+                target.label()
+            );
+            Some(FallbackApplied {
+                from: mapped_model.to_string(),
+                to: target.label(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// 从主目标开始，按顺序返回第一个可用的目标
+///
+/// 返回 (下标, 目标)，下标 0 表示主目标本身；全部不可用时返回 None，由调用方沿用主目标报错。
+pub async fn select_target<F, Fut>(
+    primary: FallbackTarget,
+    fallbacks: Vec<FallbackTarget>,
+    mut is_available: F,
+) -> Option<(usize, FallbackTarget)>
+where
+    F: FnMut(FallbackTarget) -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    for (idx, target) in std::iter::once(primary).chain(fallbacks).enumerate() {
+        if is_available(target.clone()).await {
+            return Some((idx, target));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hops_across_providers() {
+        assert_eq!(FallbackTarget::parse("gemini-3-pro-high"), Some(FallbackTarget::Gemini("gemini-3-pro-high".into())));
+        assert_eq!(FallbackTarget::parse("kiro:claude-opus-4-6"), Some(FallbackTarget::Kiro("claude-opus-4-6".into())));
+        // 未带前缀的 Kiro 模型与主路由一致
+        assert_eq!(FallbackTarget::parse("claude-opus-4-6"), Some(FallbackTarget::Kiro("claude-opus-4-6".into())));
+        assert_eq!(FallbackTarget::parse(" zai "), Some(FallbackTarget::Zai(None)));
+        assert_eq!(FallbackTarget::parse("ZAI:glm-4.7"), Some(FallbackTarget::Zai(Some("glm-4.7".into()))));
+        assert_eq!(FallbackTarget::parse(""), None);
+        assert_eq!(FallbackTarget::Kiro("auto".into()).label(), "kiro:auto");
+    }
+
+    #[test]
+    fn test_chain_lookup_and_selection() {
+        let config: ModelFallbackConfig = serde_json::from_value(serde_json::json!({
+            "chains": [
+                { "model_pattern": "claude-*", "fallbacks": ["zai"] },
+                { "model_pattern": "claude-opus-*", "fallbacks": ["gemini-3-pro-high"] },
+                {
+                    "model_pattern": "claude-opus-4-6-thinking",
+                    "fallbacks": ["claude-sonnet-4-5-thinking", "gemini-3-pro-high", "kiro:claude-opus-4-6"]
+                }
+            ]
+        }))
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.targets_for("claude-opus-4-6-thinking").len(), 3);
+        assert_eq!(config.targets_for("claude-opus-4-5"), [FallbackTarget::Gemini("gemini-3-pro-high".into())]);
+        assert_eq!(config.targets_for("claude-haiku-4"), [FallbackTarget::Zai(None)]);
+        assert!(config.targets_for("gemini-3-flash").is_empty());
+
+        let primary = FallbackTarget::Gemini("claude-opus-4-6-thinking".into());
+        let targets = config.targets_for("claude-opus-4-6-thinking");
+        let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        // 主目标可用时不降级
+        let picked = rt.block_on(select_target(primary.clone(), targets.clone(), |_| async { true }));
+        assert_eq!(picked, Some((0, primary.clone())));
+
+        // 只有 Kiro 池可用: 跳过 Gemini 的各跳
+        let picked = rt.block_on(select_target(primary.clone(), targets.clone(), |t| async move {
+            matches!(t, FallbackTarget::Kiro(_))
+        }));
+        assert_eq!(picked, Some((3, FallbackTarget::Kiro("claude-opus-4-6".into()))));
+
+        assert_eq!(rt.block_on(select_target(primary, targets, |_| async { false })), None);
+
+        let invalid = ModelFallbackConfig {
+            chains: vec![ModelFallbackChain { model_pattern: "x".into(), fallbacks: vec!["kiro:".into()] }],
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
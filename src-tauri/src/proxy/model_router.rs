@@ -0,0 +1,462 @@
+// 模型路由规则 (Model Router Rules)
+//
+// 在自定义映射 (精确 / 通配符) 之前评估的条件规则:
+// - 按 priority 升序评估 (相同优先级按配置顺序)，第一个命中的规则改写模型
+// - 条件: 模型名正则 (支持捕获组)、是否带工具 / 图片 / thinking、预估输入 token、
+//   客户端 API Key、协议；未设置的条件视为满足
+// - target 中可用 $1 / ${name} 引用 model_regex 的捕获组
+// 未命中任何规则时回到 resolve_model_route。
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::proxy::mappers::claude::models::ClaudeRequest;
+use crate::proxy::mappers::context_manager::ContextManager;
+
+fn default_true() -> bool {
+    true
+}
+
+/// 规则条件 (全部满足才命中)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct RouterConditions {
+    /// 匹配客户端请求的模型名 (未锚定，需要整体匹配时请写 ^...$)
+    pub model_regex: Option<String>,
+    pub has_tools: Option<bool>,
+    pub has_images: Option<bool>,
+    pub thinking: Option<bool>,
+    /// 预估输入 token 下限 (含)
+    pub min_prompt_tokens: Option<u32>,
+    /// 预估输入 token 上限 (含)
+    pub max_prompt_tokens: Option<u32>,
+    /// 客户端 API Key 的 ID 或名称，空表示不限
+    pub client_keys: Vec<String>,
+    /// "anthropic" / "openai" / "gemini"，空表示不限
+    pub protocols: Vec<String>,
+}
+
+/// 一条路由规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouterRule {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 数值越小越先评估
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub when: RouterConditions,
+    /// 改写后的模型
+    pub target: String,
+}
+
+/// 路由规则配置
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ModelRouterConfig {
+    pub rules: Vec<RouterRule>,
+}
+
+impl ModelRouterConfig {
+    /// 校验配置 (保存前调用)
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            if rule.name.trim().is_empty() {
+                return Err("路由规则的 name 不能为空".to_string());
+            }
+            if rule.target.trim().is_empty() {
+                return Err(format!("路由规则 {} 的 target 不能为空", rule.name));
+            }
+            if let Some(pattern) = &rule.when.model_regex {
+                Regex::new(pattern)
+                    .map_err(|e| format!("路由规则 {} 的 model_regex 无效: {}", rule.name, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 规则评估所需的请求特征
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct RequestFeatures {
+    pub protocol: Option<String>,
+    pub has_tools: bool,
+    pub has_images: bool,
+    pub thinking: bool,
+    /// 预估输入 token (没有规则使用 token 条件时不计算)
+    pub prompt_tokens: Option<u32>,
+    pub client_key_id: Option<String>,
+    pub client_key_name: Option<String>,
+}
+
+impl RequestFeatures {
+    /// 从原始请求体提取特征 (兼容 Anthropic / OpenAI / Gemini 三种格式)
+    pub fn from_body(protocol: &str, model: &str, body: &Value, count_tokens: bool) -> Self {
+        let has_tools = ["tools", "functions"]
+            .iter()
+            .any(|k| body.get(*k).and_then(|t| t.as_array()).is_some_and(|t| !t.is_empty()));
+        let thinking = model.contains("thinking")
+            || match protocol {
+                "anthropic" => body
+                    .pointer("/thinking/type")
+                    .and_then(|t| t.as_str())
+                    .is_some_and(|t| t != "disabled"),
+                "openai" => body
+                    .get("reasoning_effort")
+                    .or_else(|| body.pointer("/reasoning/effort"))
+                    .and_then(|e| e.as_str())
+                    .is_some_and(|e| e != "none"),
+                _ => body
+                    .pointer("/generationConfig/thinkingConfig")
+                    .or_else(|| body.pointer("/generation_config/thinking_config"))
+                    .is_some_and(|c| {
+                        c.get("includeThoughts").and_then(|v| v.as_bool()).unwrap_or(false)
+                            || c.get("thinkingBudget").and_then(|v| v.as_i64()).is_some_and(|b| b != 0)
+                    }),
+            };
+
+        Self {
+            protocol: Some(protocol.to_string()),
+            has_tools,
+            has_images: contains_image(body),
+            thinking,
+            prompt_tokens: count_tokens.then(|| estimate_prompt_tokens(protocol, model, body)),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_client_key(mut self, key: Option<&crate::proxy::middleware::auth::ClientKeyIdentity>) -> Self {
+        if let Some(key) = key {
+            self.client_key_id = Some(key.id.clone());
+            self.client_key_name = Some(key.name.clone());
+        }
+        self
+    }
+}
+
+/// 请求中是否带图片 (Anthropic image 块 / OpenAI image_url / Gemini inlineData)
+fn contains_image(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => {
+            if matches!(map.get("type").and_then(|t| t.as_str()), Some("image" | "image_url" | "input_image")) {
+                return true;
+            }
+            let inline = map.get("inlineData").or_else(|| map.get("inline_data"));
+            if let Some(mime) = inline.and_then(|i| i.get("mimeType").or_else(|| i.get("mime_type"))) {
+                if mime.as_str().is_some_and(|m| m.starts_with("image/")) {
+                    return true;
+                }
+            }
+            map.values().any(contains_image)
+        }
+        Value::Array(items) => items.iter().any(contains_image),
+        _ => false,
+    }
+}
+
+/// 预估输入 token: Anthropic 走 ContextManager (含校准)，Gemini 走离线分词，
+/// OpenAI 按消息与工具的文本计数
+fn estimate_prompt_tokens(protocol: &str, model: &str, body: &Value) -> u32 {
+    match protocol {
+        "anthropic" => serde_json::from_value::<ClaudeRequest>(body.clone())
+            .map(|req| ContextManager::estimate_token_usage(&req, model))
+            .unwrap_or(0),
+        "gemini" => crate::proxy::tokenizer::count_gemini_request(body, model).total(),
+        _ => {
+            let tokenizer = crate::proxy::tokenizer::for_model(model);
+            let text = |v: Option<&Value>| v.map(|v| tokenizer.count_text(&v.to_string())).unwrap_or(0);
+            text(body.get("messages").or_else(|| body.get("input")))
+                + text(body.get("tools"))
+                + text(body.get("instructions"))
+        }
+    }
+}
+
+struct CompiledRule {
+    rule: RouterRule,
+    regex: Option<Regex>,
+    /// 配置中的位置，同优先级时保持顺序
+    index: usize,
+}
+
+impl CompiledRule {
+    /// 命中时返回改写后的模型，否则返回未满足的条件
+    fn evaluate(&self, model: &str, features: &RequestFeatures) -> Result<String, String> {
+        let when = &self.rule.when;
+
+        let mut target = self.rule.target.clone();
+        if let Some(regex) = &self.regex {
+            let Some(caps) = regex.captures(model) else {
+                return Err(format!("model does not match /{}/", regex.as_str()));
+            };
+            target.clear();
+            caps.expand(&self.rule.target, &mut target);
+        }
+
+        for (name, expected, actual) in [
+            ("has_tools", when.has_tools, features.has_tools),
+            ("has_images", when.has_images, features.has_images),
+            ("thinking", when.thinking, features.thinking),
+        ] {
+            if let Some(expected) = expected {
+                if expected != actual {
+                    return Err(format!("{} is {}", name, actual));
+                }
+            }
+        }
+
+        if when.min_prompt_tokens.is_some() || when.max_prompt_tokens.is_some() {
+            let Some(tokens) = features.prompt_tokens else {
+                return Err("prompt tokens unknown".to_string());
+            };
+            if when.min_prompt_tokens.is_some_and(|min| tokens < min) {
+                return Err(format!("prompt tokens {} < {}", tokens, when.min_prompt_tokens.unwrap_or(0)));
+            }
+            if when.max_prompt_tokens.is_some_and(|max| tokens > max) {
+                return Err(format!("prompt tokens {} > {}", tokens, when.max_prompt_tokens.unwrap_or(0)));
+            }
+        }
+
+        if !when.client_keys.is_empty() {
+            let matched = when.client_keys.iter().any(|k| {
+                features.client_key_id.as_deref() == Some(k.as_str())
+                    || features.client_key_name.as_deref() == Some(k.as_str())
+            });
+            if !matched {
+                return Err("client key not listed".to_string());
+            }
+        }
+
+        if !when.protocols.is_empty() {
+            let protocol = features.protocol.as_deref().unwrap_or("");
+            if !when.protocols.iter().any(|p| p.eq_ignore_ascii_case(protocol)) {
+                return Err(format!("protocol is {}", if protocol.is_empty() { "unknown" } else { protocol }));
+            }
+        }
+
+        Ok(target)
+    }
+}
+
+/// 单条规则的评估结果 (dry-run 展示)
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleTrace {
+    pub rule: String,
+    pub priority: i32,
+    pub matched: bool,
+    /// 未命中的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// 命中的规则
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteDecision {
+    pub rule: String,
+    pub priority: i32,
+    pub target: String,
+}
+
+/// 编译后的路由规则 (正则只编译一次，配置热更新时重建)
+#[derive(Default)]
+pub struct ModelRouter {
+    rules: RwLock<Vec<CompiledRule>>,
+}
+
+static ROUTER: Lazy<ModelRouter> = Lazy::new(ModelRouter::default);
+
+/// 全局路由规则
+pub fn router() -> &'static ModelRouter {
+    &ROUTER
+}
+
+impl ModelRouter {
+    /// 热更新规则 (无效正则的规则被跳过，保存配置时已校验)
+    pub fn configure(&self, config: &ModelRouterConfig) {
+        let mut compiled: Vec<CompiledRule> = Vec::new();
+        for (index, rule) in config.rules.iter().enumerate().filter(|(_, r)| r.enabled) {
+            let regex = match rule.when.model_regex.as_deref().map(Regex::new).transpose() {
+                Ok(regex) => regex,
+                Err(e) => {
+                    tracing::warn!("[ModelRouter] 跳过规则 {}: model_regex 无效: {}", rule.name, e);
+                    continue;
+                }
+            };
+            compiled.push(CompiledRule { rule: rule.clone(), regex, index });
+        }
+        compiled.sort_by_key(|r| (r.rule.priority, r.index));
+        *self.rules.write() = compiled;
+    }
+
+    /// 是否有规则使用 token 条件 (决定是否需要预估 token)
+    pub fn needs_prompt_tokens(&self) -> bool {
+        self.rules
+            .read()
+            .iter()
+            .any(|r| r.rule.when.min_prompt_tokens.is_some() || r.rule.when.max_prompt_tokens.is_some())
+    }
+
+    /// 返回第一个命中的规则
+    pub fn route(&self, model: &str, features: &RequestFeatures) -> Option<RouteDecision> {
+        self.rules.read().iter().find_map(|r| {
+            r.evaluate(model, features).ok().map(|target| RouteDecision {
+                rule: r.rule.name.clone(),
+                priority: r.rule.priority,
+                target,
+            })
+        })
+    }
+
+    /// dry-run: 按评估顺序返回每条规则的结果，直到第一个命中的规则
+    pub fn explain(&self, model: &str, features: &RequestFeatures) -> (Option<RouteDecision>, Vec<RuleTrace>) {
+        let mut trace = Vec::new();
+        for r in self.rules.read().iter() {
+            match r.evaluate(model, features) {
+                Ok(target) => {
+                    trace.push(RuleTrace {
+                        rule: r.rule.name.clone(),
+                        priority: r.rule.priority,
+                        matched: true,
+                        reason: None,
+                    });
+                    let decision = RouteDecision {
+                        rule: r.rule.name.clone(),
+                        priority: r.rule.priority,
+                        target,
+                    };
+                    return (Some(decision), trace);
+                }
+                Err(reason) => trace.push(RuleTrace {
+                    rule: r.rule.name.clone(),
+                    priority: r.rule.priority,
+                    matched: false,
+                    reason: Some(reason),
+                }),
+            }
+        }
+        (None, trace)
+    }
+}
+
+/// 模型路由: 先评估路由规则，未命中时回到自定义映射 / 系统默认映射
+pub fn resolve_model(
+    original_model: &str,
+    custom_mapping: &HashMap<String, String>,
+    features: &RequestFeatures,
+) -> String {
+    if let Some(decision) = router().route(original_model, features) {
+        crate::modules::logger::log_info(&format!(
+            "[Router] 规则 {} 命中: {} -> {}",
+            decision.rule, original_model, decision.target
+        ));
+        return decision.target;
+    }
+    crate::proxy::common::model_mapping::resolve_model_route(original_model, custom_mapping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn router_with(rules: Value) -> ModelRouter {
+        let config: ModelRouterConfig = serde_json::from_value(json!({ "rules": rules })).unwrap();
+        config.validate().unwrap();
+        let router = ModelRouter::default();
+        router.configure(&config);
+        router
+    }
+
+    #[test]
+    fn test_priority_captures_and_conditions() {
+        let router = router_with(json!([
+            { "name": "catch-all", "priority": 100, "when": { "model_regex": "^claude-" }, "target": "gemini-3-flash" },
+            {
+                "name": "opus-with-images",
+                "priority": 10,
+                "when": { "model_regex": "^claude-opus-(?P<ver>[0-9-]+)", "has_images": true },
+                "target": "gemini-3-pro-image"
+            },
+            {
+                "name": "opus-versioned",
+                "priority": 20,
+                "when": { "model_regex": "^claude-opus-(?P<ver>[0-9-]+?)(-thinking)?$", "protocols": ["anthropic"] },
+                "target": "claude-opus-${ver}-thinking"
+            },
+            { "name": "long-context", "priority": 5, "when": { "min_prompt_tokens": 100000 }, "target": "gemini-2.5-pro" },
+            { "name": "disabled", "enabled": false, "priority": 0, "target": "never" }
+        ]));
+
+        let anthropic = RequestFeatures { protocol: Some("anthropic".into()), ..Default::default() };
+        let decision = router.route("claude-opus-4-6", &anthropic).unwrap();
+        assert_eq!((decision.rule.as_str(), decision.target.as_str()), ("opus-versioned", "claude-opus-4-6-thinking"));
+
+        let with_images = RequestFeatures { has_images: true, ..anthropic.clone() };
+        assert_eq!(router.route("claude-opus-4-6", &with_images).unwrap().rule, "opus-with-images");
+
+        // 协议不符时继续评估更低优先级的规则
+        let openai = RequestFeatures { protocol: Some("openai".into()), ..Default::default() };
+        assert_eq!(router.route("claude-opus-4-6", &openai).unwrap().target, "gemini-3-flash");
+
+        let long = RequestFeatures { prompt_tokens: Some(150_000), ..openai.clone() };
+        assert_eq!(router.route("gpt-4o", &long).unwrap().rule, "long-context");
+        assert!(router.route("gpt-4o", &openai).is_none());
+
+        let (decision, trace) = router.explain("claude-opus-4-6", &openai);
+        assert_eq!(decision.unwrap().rule, "catch-all");
+        let reasons: Vec<_> = trace.iter().map(|t| (t.rule.as_str(), t.reason.clone())).collect();
+        assert_eq!(reasons[0], ("long-context", Some("prompt tokens unknown".to_string())));
+        assert_eq!(reasons[1], ("opus-with-images", Some("has_images is false".to_string())));
+        assert_eq!(reasons[2], ("opus-versioned", Some("protocol is openai".to_string())));
+        assert_eq!(trace.len(), 4);
+    }
+
+    #[test]
+    fn test_client_keys_and_features_from_body() {
+        let router = router_with(json!([
+            { "name": "team-a", "when": { "client_keys": ["team-a"], "has_tools": true }, "target": "gemini-3-pro-high" }
+        ]));
+        let body = json!({
+            "model": "claude-sonnet-4-5",
+            "thinking": { "type": "enabled", "budget_tokens": 1024 },
+            "tools": [{ "name": "lookup", "input_schema": { "type": "object" } }],
+            "messages": [{ "role": "user", "content": [
+                { "type": "text", "text": "what is this?" },
+                { "type": "image", "source": { "type": "base64", "media_type": "image/png", "data": "???" } }
+            ]}]
+        });
+        let features = RequestFeatures::from_body("anthropic", "claude-sonnet-4-5", &body, true);
+        assert!(features.has_tools && features.has_images && features.thinking);
+        // thinking 预算计入预估
+        assert!(features.prompt_tokens.unwrap() > 1024);
+
+        assert!(router.route("claude-sonnet-4-5", &features).is_none());
+        let key = crate::proxy::middleware::auth::ClientKeyIdentity { id: "k1".into(), name: "team-a".into() };
+        let features = features.with_client_key(Some(&key));
+        assert_eq!(router.route("claude-sonnet-4-5", &features).unwrap().target, "gemini-3-pro-high");
+
+        let gemini = json!({
+            "contents": [{ "role": "user", "parts": [{ "inlineData": { "mimeType": "image/jpeg", "data": "" } }] }],
+            "generationConfig": { "thinkingConfig": { "thinkingBudget": 0 } }
+        });
+        let features = RequestFeatures::from_body("gemini", "gemini-3-flash", &gemini, false);
+        assert!(features.has_images && !features.thinking && !features.has_tools);
+        assert!(features.prompt_tokens.is_none());
+
+        let invalid = ModelRouterConfig {
+            rules: vec![RouterRule {
+                name: "bad".into(),
+                enabled: true,
+                priority: 0,
+                when: RouterConditions { model_regex: Some("(".into()), ..Default::default() },
+                target: "x".into(),
+            }],
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -3,6 +3,7 @@ use std::collections::VecDeque;
 use tokio::sync::RwLock;
 use tauri::Emitter;
 use std::sync::atomic::{AtomicBool, Ordering};
+use crate::proxy::metrics::{ProxyMetrics, RequestObservation};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyRequestLog {
@@ -14,6 +15,8 @@ pub struct ProxyRequestLog {
     pub duration: u64, // ms
     pub model: Option<String>,        // 客户端请求的模型名
     pub mapped_model: Option<String>, // 实际路由后使用的模型名
+    pub fallback_from: Option<String>, // [NEW] 降级前的目标 (账号池不可用时按降级链切换)
+    pub cache_status: Option<String>,  // [NEW] 响应缓存: "HIT" / "MISS"，不可缓存的请求为空
     pub account_email: Option<String>,
     pub client_ip: Option<String>,    // 客户端 IP 地址
     pub error: Option<String>,
@@ -24,6 +27,8 @@ pub struct ProxyRequestLog {
     pub cached_tokens: Option<u32>,    // [NEW v4.0.8] 缓存命中的输入 Token
     pub reasoning_tokens: Option<u32>, // [NEW v4.0.8] 思考产生的输出 Token
     pub protocol: Option<String>,     // 协议类型: "openai", "anthropic", "gemini"
+    pub client_key_id: Option<String>,   // 发起请求的客户端 API Key ID
+    pub client_key_name: Option<String>, // 发起请求的客户端 API Key 名称
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -38,6 +43,7 @@ pub struct ProxyMonitor {
     pub stats: RwLock<ProxyStats>,
     pub max_logs: usize,
     pub enabled: AtomicBool,
+    pub metrics: ProxyMetrics, // [NEW] Prometheus 指标 (不受 enabled 开关影响)
     app_handle: Option<tauri::AppHandle>,
 }
 
@@ -47,26 +53,19 @@ impl ProxyMonitor {
         if let Err(e) = crate::modules::proxy_db::init_db() {
             tracing::error!("Failed to initialize proxy DB: {}", e);
         }
+        if let Err(e) = crate::modules::token_stats::init_db() {
+            tracing::error!("Failed to initialize token stats DB: {}", e);
+        }
 
-        // Auto cleanup old logs (keep last 30 days)
-        tokio::spawn(async {
-            match crate::modules::proxy_db::cleanup_old_logs(30) {
-                Ok(deleted) => {
-                    if deleted > 0 {
-                        tracing::info!("Auto cleanup: removed {} old logs (>30 days)", deleted);
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("Failed to cleanup old logs: {}", e);
-                }
-            }
-        });
+        // [NEW] 按配置的保留天数 / 数据库大小定期清理 (取代固定的 30 天清理)
+        crate::proxy::monitor_policy::policy().start_retention_task();
 
         Self {
             logs: RwLock::new(VecDeque::with_capacity(max_logs)),
             stats: RwLock::new(ProxyStats::default()),
             max_logs,
             enabled: AtomicBool::new(false), // Default to disabled
+            metrics: ProxyMetrics::new(),
             app_handle,
         }
     }
@@ -79,11 +78,26 @@ impl ProxyMonitor {
         self.enabled.load(Ordering::Relaxed)
     }
 
-    pub async fn log_request(&self, log: ProxyRequestLog) {
+    pub async fn log_request(self: &std::sync::Arc<Self>, log: ProxyRequestLog) {
+        self.metrics.record(&RequestObservation {
+            protocol: log.protocol.clone(),
+            model: log.mapped_model.clone().or_else(|| log.model.clone()),
+            status: log.status,
+            account: log.account_email.clone(),
+            duration_ms: log.duration,
+            input_tokens: log.input_tokens,
+            output_tokens: log.output_tokens,
+            cached_tokens: log.cached_tokens,
+            reasoning_tokens: log.reasoning_tokens,
+            cache_status: log.cache_status.clone(),
+        });
+
+        // 嵌入等只有输入 token 的请求，输出按 0 计
+        let output_tokens = log.output_tokens.or(log.input_tokens.map(|_| 0));
         if let (Some(account), Some(input), Some(output)) = (
             &log.account_email,
             log.input_tokens,
-            log.output_tokens,
+            output_tokens,
         ) {
             let model = log.model.clone().unwrap_or_else(|| "unknown".to_string());
             let account = account.clone();
@@ -96,10 +110,40 @@ impl ProxyMonitor {
             });
         }
 
+        if let (Some(key_id), Some(input), Some(output)) = (
+            &log.client_key_id,
+            log.input_tokens,
+            output_tokens,
+        ) {
+            let key_id = key_id.clone();
+            let key_name = log.client_key_name.clone().unwrap_or_default();
+            tokio::spawn(async move {
+                if let Err(e) = crate::modules::token_stats::record_client_key_tokens(&key_id, &key_name, input, output) {
+                    tracing::debug!("Failed to record client key usage: {}", e);
+                }
+            });
+        }
+
         if !self.is_enabled() {
             return;
         }
         tracing::info!("[Monitor] Logging request: {} {}", log.method, log.url);
+        // 脱敏与保存在后台完成，不阻塞响应返回
+        let monitor = std::sync::Arc::clone(self);
+        tokio::spawn(async move { monitor.store_log(log).await });
+    }
+
+    /// 按策略脱敏后写入内存 / 数据库，并推送到前端
+    async fn store_log(&self, log: ProxyRequestLog) {
+        // [NEW] 进入内存 / 数据库 / 前端之前按策略裁剪与脱敏请求/响应体
+        // 正则脱敏对大请求体是 CPU 密集操作，放到阻塞线程池执行
+        let log = match tokio::task::spawn_blocking(move || crate::proxy::monitor_policy::policy().apply(log)).await {
+            Ok(log) => log,
+            Err(e) => {
+                tracing::error!("Spawn blocking failed for log sanitisation: {}", e);
+                return;
+            }
+        };
         // Update stats
         {
             let mut stats = self.stats.write().await;
@@ -152,7 +196,7 @@ impl ProxyMonitor {
             if let (Some(account), Some(input), Some(output)) = (
                 &log_to_save.account_email,
                 log_to_save.input_tokens,
-                log_to_save.output_tokens,
+                log_to_save.output_tokens.or(log_to_save.input_tokens.map(|_| 0)),
             ) {
                 let model = log_to_save.model.clone().unwrap_or_else(|| "unknown".to_string());
                 let cached = log_to_save.cached_tokens.unwrap_or(0);
@@ -184,6 +228,8 @@ impl ProxyMonitor {
                 duration: log.duration,
                 model: log.model.clone(),
                 mapped_model: log.mapped_model.clone(),
+                fallback_from: log.fallback_from.clone(),
+                cache_status: log.cache_status.clone(),
                 account_email: log.account_email.clone(),
                 client_ip: log.client_ip.clone(),
                 error: log.error.clone(),
@@ -194,6 +240,8 @@ impl ProxyMonitor {
                 cached_tokens: log.cached_tokens,
                 reasoning_tokens: log.reasoning_tokens,
                 protocol: log.protocol.clone(),
+                client_key_id: log.client_key_id.clone(),
+                client_key_name: log.client_key_name.clone(),
             };
             let _ = app.emit("proxy://request", &log_summary);
         }
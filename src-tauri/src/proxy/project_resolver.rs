@@ -3,7 +3,8 @@ use reqwest;
 
 /// 使用 Antigravity 的 loadCodeAssist API 获取 project_id
 /// 这是获取 cloudaicompanionProject 的正确方式
-pub async fn fetch_project_id(access_token: &str) -> Result<String, String> {
+/// `individual_proxy` 为账号独立代理，None 时走全局上游代理
+pub async fn fetch_project_id(access_token: &str, individual_proxy: Option<&str>) -> Result<String, String> {
     // 使用 Prod 环境， Sandbox 不稳定且容易返回 403/429
     let url = "https://cloudcode-pa.googleapis.com/v1internal:loadCodeAssist";
     
@@ -13,7 +14,7 @@ pub async fn fetch_project_id(access_token: &str) -> Result<String, String> {
         }
     });
     
-    let client = crate::utils::http::get_client();
+    let client = crate::utils::http::create_client_for_account(300, individual_proxy)?;
     let response: reqwest::Response = client
         .post(url)
         .bearer_auth(access_token)
@@ -10,7 +10,7 @@ use tokio::time::Duration;
 
 use crate::proxy::server::AppState;
 
-fn map_model_for_zai(original: &str, state: &crate::proxy::ZaiConfig) -> String {
+pub(crate) fn map_model_for_zai(original: &str, state: &crate::proxy::ZaiConfig) -> String {
     let m = original.to_lowercase();
     if let Some(mapped) = state.model_mapping.get(original) {
         return mapped.clone();
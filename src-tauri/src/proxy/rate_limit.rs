@@ -1,4 +1,5 @@
 use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, Duration};
 use regex::Regex;
 
@@ -17,6 +18,30 @@ pub enum RateLimitReason {
     Unknown,
 }
 
+impl RateLimitReason {
+    /// 指标/诊断用的稳定标识
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitReason::QuotaExhausted => "quota_exhausted",
+            RateLimitReason::RateLimitExceeded => "rate_limit_exceeded",
+            RateLimitReason::ModelCapacityExhausted => "model_capacity_exhausted",
+            RateLimitReason::ServerError => "server_error",
+            RateLimitReason::Unknown => "unknown",
+        }
+    }
+
+    /// 从 `as_str()` 标识还原 (持久化/管理接口使用)
+    pub fn from_str_lossy(s: &str) -> Self {
+        match s {
+            "quota_exhausted" => RateLimitReason::QuotaExhausted,
+            "rate_limit_exceeded" => RateLimitReason::RateLimitExceeded,
+            "model_capacity_exhausted" => RateLimitReason::ModelCapacityExhausted,
+            "server_error" => RateLimitReason::ServerError,
+            _ => RateLimitReason::Unknown,
+        }
+    }
+}
+
 /// 限流信息
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -38,14 +63,21 @@ pub struct RateLimitInfo {
     pub model: Option<String>,
 }
 
+/// 持久化快照条目: (account_id, 模型范围, 锁信息)
+pub type LimitEntry = (String, Option<String>, RateLimitInfo);
+/// 持久化快照条目: (account_id, 连续失败次数, 最近失败时间)
+pub type FailureEntry = (String, u32, SystemTime);
+
 /// 失败计数过期时间：1小时（超过此时间未失败则重置计数）
-const FAILURE_COUNT_EXPIRY_SECONDS: u64 = 3600;
+pub const FAILURE_COUNT_EXPIRY_SECONDS: u64 = 3600;
 
 /// 限流跟踪器
 pub struct RateLimitTracker {
     limits: DashMap<String, RateLimitInfo>,
     /// 连续失败计数（用于智能指数退避），带时间戳用于自动过期
     failure_counts: DashMap<String, (u32, SystemTime)>,
+    /// [NEW] 变更计数，持久化任务据此判断是否需要落盘
+    generation: AtomicU64,
 }
 
 impl RateLimitTracker {
@@ -53,8 +85,18 @@ impl RateLimitTracker {
         Self {
             limits: DashMap::new(),
             failure_counts: DashMap::new(),
+            generation: AtomicU64::new(0),
         }
     }
+
+    fn touch(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 当前变更计数
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
     
     /// 生成限流 Key
     /// - 账号级: "account_id"
@@ -96,11 +138,14 @@ impl RateLimitTracker {
     /// 当账号成功完成请求后调用此方法，将其失败计数归零，
     /// 这样下次失败时会从最短的锁定时间（60秒）开始。
     pub fn mark_success(&self, account_id: &str) {
-        if self.failure_counts.remove(account_id).is_some() {
+        let had_failures = self.failure_counts.remove(account_id).is_some();
+        if had_failures {
             tracing::debug!("账号 {} 请求成功，已重置失败计数", account_id);
         }
         // 清除账号级限流
-        self.limits.remove(account_id);
+        if self.limits.remove(account_id).is_some() || had_failures {
+            self.touch();
+        }
         // 注意：我们暂时无法清除该账号下的所有模型级锁，因为我们不知道哪些模型被锁了
         // 除非遍历 limits。考虑到模型级锁通常是 QuotaExhausted，让其自然过期也是可以接受的。
         // 或者我们可以引入索引，但为了简单，暂时只清除 Account 级锁。
@@ -130,6 +175,7 @@ impl RateLimitTracker {
         
         let key = self.get_limit_key(account_id, model.as_deref());
         self.limits.insert(key, info);
+        self.touch();
         
         if let Some(m) = &model {
             tracing::info!(
@@ -312,6 +358,7 @@ impl RateLimitTracker {
         };
 
         self.limits.insert(key, info.clone());
+        self.touch();
         
         tracing::warn!(
             "账号 {} [{}] 限流类型: {:?}, 重置延时: {}秒",
@@ -520,6 +567,32 @@ impl RateLimitTracker {
         }
     }
     
+    /// 获取账号当前生效的全部限流记录 (账号级 + 模型级)
+    pub fn active_limits(&self, account_id: &str) -> Vec<RateLimitInfo> {
+        let now = SystemTime::now();
+        let model_prefix = format!("{}:", account_id);
+        self.limits
+            .iter()
+            .filter(|e| e.key() == account_id || e.key().starts_with(&model_prefix))
+            .filter(|e| e.value().reset_time > now)
+            .map(|e| e.value().clone())
+            .collect()
+    }
+
+    /// 获取账号的连续失败次数 (已过期的计数视为 0)
+    pub fn failure_count(&self, account_id: &str) -> u32 {
+        self.failure_counts
+            .get(account_id)
+            .filter(|e| {
+                SystemTime::now()
+                    .duration_since(e.1)
+                    .map(|d| d.as_secs() <= FAILURE_COUNT_EXPIRY_SECONDS)
+                    .unwrap_or(true)
+            })
+            .map(|e| e.0)
+            .unwrap_or(0)
+    }
+
     /// 清除过期的限流记录
     #[allow(dead_code)]
     pub fn cleanup_expired(&self) -> usize {
@@ -536,6 +609,7 @@ impl RateLimitTracker {
         });
         
         if count > 0 {
+            self.touch();
             tracing::debug!("清除了 {} 个过期的限流记录", count);
         }
         
@@ -544,7 +618,81 @@ impl RateLimitTracker {
     
     /// 清除指定账号的限流记录
     pub fn clear(&self, account_id: &str) -> bool {
-        self.limits.remove(account_id).is_some()
+        let removed = self.limits.remove(account_id).is_some();
+        if removed {
+            self.touch();
+        }
+        removed
+    }
+
+    /// [NEW] 清除指定账号某一范围的锁 (model 为 None 表示账号级锁)
+    pub fn clear_scope(&self, account_id: &str, model: Option<&str>) -> bool {
+        let key = self.get_limit_key(account_id, model);
+        let removed = self.limits.remove(&key).is_some();
+        if removed {
+            self.touch();
+        }
+        removed
+    }
+
+    /// [NEW] 手动设置连续失败计数 (0 表示清零)
+    pub fn set_failure_count(&self, account_id: &str, count: u32) {
+        if count == 0 {
+            self.failure_counts.remove(account_id);
+        } else {
+            self.failure_counts
+                .insert(account_id.to_string(), (count, SystemTime::now()));
+        }
+        self.touch();
+    }
+
+    /// [NEW] 导出全部锁 (account_id, model 范围, 信息) 与失败计数，供持久化
+    pub fn snapshot(&self) -> (Vec<LimitEntry>, Vec<FailureEntry>) {
+        let limits = self
+            .limits
+            .iter()
+            .map(|e| {
+                // Key 格式见 get_limit_key: "account_id" 或 "account_id:model"
+                let (account_id, model) = match e.key().split_once(':') {
+                    Some((a, m)) => (a.to_string(), Some(m.to_string())),
+                    None => (e.key().clone(), None),
+                };
+                (account_id, model, e.value().clone())
+            })
+            .collect();
+        let failures = self
+            .failure_counts
+            .iter()
+            .map(|e| (e.key().clone(), e.value().0, e.value().1))
+            .collect();
+        (limits, failures)
+    }
+
+    /// [NEW] 恢复持久化的锁: 仅当内存中没有更晚的解锁时间时写入
+    pub fn restore_limit(&self, account_id: &str, model: Option<&str>, info: RateLimitInfo) -> bool {
+        if info.reset_time <= SystemTime::now() {
+            return false;
+        }
+        let key = self.get_limit_key(account_id, model);
+        let newer = self
+            .limits
+            .get(&key)
+            .map(|existing| existing.reset_time < info.reset_time)
+            .unwrap_or(true);
+        if newer {
+            self.limits.insert(key, info);
+        }
+        newer
+    }
+
+    /// [NEW] 恢复持久化的失败计数 (内存已有计数时以内存为准)
+    pub fn restore_failure_count(&self, account_id: &str, count: u32, updated_at: SystemTime) -> bool {
+        if count == 0 || self.failure_counts.contains_key(account_id) {
+            return false;
+        }
+        self.failure_counts
+            .insert(account_id.to_string(), (count, updated_at));
+        true
     }
     
     /// 清除所有限流记录 (乐观重置策略)
@@ -554,6 +702,7 @@ impl RateLimitTracker {
     pub fn clear_all(&self) {
         let count = self.limits.len();
         self.limits.clear();
+        self.touch();
         tracing::warn!("🔄 Optimistic reset: Cleared all {} rate limit record(s)", count);
     }
 }
@@ -676,4 +825,43 @@ mod tests {
         let info = tracker.parse_from_error("acc2", 429, None, quota_body, None, &backoff_steps);
         assert_eq!(info.unwrap().retry_after_sec, 7200);
     }
+
+    #[test]
+    fn test_snapshot_restore_keeps_scope_and_later_reset() {
+        let tracker = RateLimitTracker::new();
+        let backoff_steps = vec![60, 300];
+        let quota_body = r#"{"error":{"details":[{"reason":"QUOTA_EXHAUSTED"}]}}"#;
+        tracker.parse_from_error("acc3", 429, None, quota_body, Some("gemini-3-pro".to_string()), &backoff_steps);
+        tracker.set_lockout_until("acc3", SystemTime::now() + Duration::from_secs(600), RateLimitReason::ServerError, None);
+
+        let (limits, failures) = tracker.snapshot();
+        assert_eq!(limits.len(), 2);
+        assert_eq!(failures.len(), 1);
+
+        // 在新的 tracker 中恢复 (模拟重启)
+        let restored = RateLimitTracker::new();
+        for (account_id, model, info) in limits {
+            assert!(restored.restore_limit(&account_id, model.as_deref(), info));
+        }
+        for (account_id, count, updated_at) in failures {
+            assert!(restored.restore_failure_count(&account_id, count, updated_at));
+        }
+        assert!(restored.is_rate_limited("acc3", Some("gemini-3-pro")));
+        assert_eq!(restored.failure_count("acc3"), 1);
+        assert!(restored.clear_scope("acc3", None));
+        assert!(restored.is_rate_limited("acc3", Some("gemini-3-pro")));
+        assert!(!restored.is_rate_limited("acc3", Some("gemini-3-flash")));
+
+        // 较早的持久化记录不会覆盖内存中更晚的锁
+        restored.set_lockout_until("acc3", SystemTime::now() + Duration::from_secs(900), RateLimitReason::Unknown, None);
+        let earlier = RateLimitInfo {
+            reset_time: SystemTime::now() + Duration::from_secs(30),
+            retry_after_sec: 30,
+            detected_at: SystemTime::now(),
+            reason: RateLimitReason::Unknown,
+            model: None,
+        };
+        assert!(!restored.restore_limit("acc3", None, earlier));
+        assert!(restored.get_remaining_wait("acc3", None) > 800);
+    }
 }
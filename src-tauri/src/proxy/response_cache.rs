@@ -0,0 +1,666 @@
+// 响应缓存 (Response Cache)
+//
+// Factory Droid / Claude Code 会反复发送完全相同的标题生成、摘要、环境探测请求，
+// 每次都消耗配额。对确定性请求 (temperature = 0，或识别为后台任务) 按规范化后的
+// 请求内容缓存完整响应:
+// - key = SHA-256(协议, 客户端 Key, 模型, messages / tools / 采样参数)，与字段顺序、
+//   stream 开关及 cache_control 标记无关
+// - 存储在 SQLite (response_cache.db)，带 TTL、条目数与总大小上限
+// - 始终按非流式 JSON 保存；流式请求命中时按协议重放为合成 SSE 流
+// - 响应头 X-Cache: HIT / MISS，由监控记录
+use axum::body::Body;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::Response;
+use bytes::Bytes;
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use crate::modules::response_cache_db::{self, CachedResponse, ResponseCacheStats};
+
+/// 响应头: HIT / MISS (只在可缓存的请求上出现)
+pub const CACHE_HEADER: &str = "X-Cache";
+
+/// 参与哈希的请求字段 (stream / metadata / user 等不影响输出的字段被忽略)
+const KEY_FIELDS: &[&str] = &[
+    // 消息与系统提示
+    "messages", "system", "contents", "systemInstruction", "system_instruction",
+    // 工具
+    "tools", "tool_choice", "functions", "function_call", "toolConfig", "tool_config",
+    // 采样参数
+    "temperature", "top_p", "top_k", "max_tokens", "max_completion_tokens", "stop", "stop_sequences",
+    "seed", "n", "presence_penalty", "frequency_penalty", "response_format", "thinking",
+    "reasoning_effort", "output_config", "generationConfig", "generation_config",
+    "logprobs", "top_logprobs", "logit_bias",
+];
+
+fn default_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_max_entries() -> usize {
+    1000
+}
+
+fn default_max_size_mb() -> u64 {
+    64
+}
+
+/// 响应缓存配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseCacheConfig {
+    pub enabled: bool,
+    /// 缓存有效期 (秒)
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// 最多保留的条目数
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+    /// 缓存总大小上限 (MB)
+    #[serde(default = "default_max_size_mb")]
+    pub max_size_mb: u64,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: default_ttl_secs(),
+            max_entries: default_max_entries(),
+            max_size_mb: default_max_size_mb(),
+        }
+    }
+}
+
+impl ResponseCacheConfig {
+    /// 校验配置 (保存前调用)
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && (self.ttl_secs == 0 || self.max_entries == 0 || self.max_size_mb == 0) {
+            return Err("响应缓存的 ttl_secs / max_entries / max_size_mb 必须大于 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 缓存的协议格式 (决定完整性校验与 SSE 重放格式)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheProtocol {
+    Anthropic,
+    OpenAI,
+    Gemini,
+}
+
+impl CacheProtocol {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheProtocol::Anthropic => "anthropic",
+            CacheProtocol::OpenAI => "openai",
+            CacheProtocol::Gemini => "gemini",
+        }
+    }
+}
+
+/// 规范化 JSON: 对象按键排序，数字统一为浮点 (0 与 0.0 等价)，
+/// 去掉 cache_control (提示缓存标记不影响输出)
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().filter(|k| k.as_str() != "cache_control").collect();
+            keys.sort();
+            let mut out = Map::new();
+            for key in keys {
+                out.insert(key.clone(), canonicalize(&map[key]));
+            }
+            Value::Object(out)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        Value::Number(n) => n.as_f64().map(Value::from).unwrap_or_else(|| value.clone()),
+        other => other.clone(),
+    }
+}
+
+/// 计算缓存 key (十六进制 SHA-256)
+///
+/// Gemini 原生接口的模型在 URL 中，因此由调用方显式传入 model。
+pub fn cache_key(protocol: CacheProtocol, client_key_id: Option<&str>, model: &str, body: &Value) -> String {
+    let mut fields = Map::new();
+    for field in KEY_FIELDS {
+        if let Some(value) = body.get(*field).filter(|v| !v.is_null()) {
+            fields.insert(field.to_string(), canonicalize(value));
+        }
+    }
+    let material = json!({
+        "protocol": protocol.as_str(),
+        // 按客户端 Key 隔离，不同 Key 之间不共享响应
+        "client_key": client_key_id,
+        "model": model,
+        "request": canonicalize(&Value::Object(fields)),
+    });
+    Sha256::digest(material.to_string().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 是否为确定性请求 (temperature = 0)
+pub fn is_deterministic(body: &Value) -> bool {
+    body.get("temperature")
+        .or_else(|| body.pointer("/generationConfig/temperature"))
+        .or_else(|| body.pointer("/generation_config/temperature"))
+        .and_then(|t| t.as_f64())
+        .is_some_and(|t| t == 0.0)
+}
+
+/// 响应是否完整可缓存 (有输出内容且正常结束)
+fn is_complete(protocol: CacheProtocol, response: &Value) -> bool {
+    let non_empty = |v: Option<&Value>| v.and_then(|v| v.as_array()).is_some_and(|a| !a.is_empty());
+    match protocol {
+        CacheProtocol::Anthropic => {
+            response.get("type").and_then(|t| t.as_str()) == Some("message")
+                && non_empty(response.get("content"))
+                && response.get("stop_reason").and_then(|s| s.as_str()).is_some_and(|s| !s.is_empty())
+        }
+        CacheProtocol::OpenAI => {
+            non_empty(response.get("choices"))
+                && response.pointer("/choices/0/finish_reason").is_some_and(|f| f.is_string())
+        }
+        CacheProtocol::Gemini => non_empty(response.pointer("/candidates/0/content/parts")),
+    }
+}
+
+/// 从响应体取出可缓存的 JSON
+fn parse_cacheable(protocol: CacheProtocol, bytes: &[u8]) -> Option<Value> {
+    let value: Value = serde_json::from_slice(bytes).ok()?;
+    is_complete(protocol, &value).then_some(value)
+}
+
+// ===== SSE 重放 =====
+
+fn sse_event(out: &mut String, event: Option<&str>, data: &Value) {
+    if let Some(event) = event {
+        out.push_str("event: ");
+        out.push_str(event);
+        out.push('\n');
+    }
+    out.push_str("data: ");
+    out.push_str(&data.to_string());
+    out.push_str("\n\n");
+}
+
+/// 将缓存的非流式响应重放为对应协议的 SSE 流
+pub fn replay_sse(protocol: CacheProtocol, response: &Value) -> String {
+    let mut out = String::new();
+    match protocol {
+        CacheProtocol::Anthropic => replay_anthropic(&mut out, response),
+        CacheProtocol::OpenAI => replay_openai(&mut out, response),
+        // Gemini 流的每个分片本身就是完整的 GenerateContentResponse
+        CacheProtocol::Gemini => sse_event(&mut out, None, response),
+    }
+    out
+}
+
+fn replay_anthropic(out: &mut String, response: &Value) {
+    let mut message = response.clone();
+    if let Some(obj) = message.as_object_mut() {
+        obj.insert("content".to_string(), json!([]));
+        obj.insert("stop_reason".to_string(), Value::Null);
+        obj.insert("stop_sequence".to_string(), Value::Null);
+        if let Some(usage) = obj.get_mut("usage").and_then(|u| u.as_object_mut()) {
+            usage.insert("output_tokens".to_string(), json!(0));
+        }
+    }
+    sse_event(out, Some("message_start"), &json!({ "type": "message_start", "message": message }));
+
+    let blocks = response.get("content").and_then(|c| c.as_array()).cloned().unwrap_or_default();
+    for (index, block) in blocks.iter().enumerate() {
+        let block_type = block.get("type").and_then(|t| t.as_str()).unwrap_or("");
+        let (start, deltas): (Value, Vec<Value>) = match block_type {
+            "text" => (
+                json!({ "type": "text", "text": "" }),
+                vec![json!({ "type": "text_delta", "text": block.get("text").cloned().unwrap_or(json!("")) })],
+            ),
+            "thinking" => {
+                let mut deltas = vec![json!({
+                    "type": "thinking_delta",
+                    "thinking": block.get("thinking").cloned().unwrap_or(json!(""))
+                })];
+                if let Some(signature) = block.get("signature") {
+                    deltas.push(json!({ "type": "signature_delta", "signature": signature }));
+                }
+                (json!({ "type": "thinking", "thinking": "" }), deltas)
+            }
+            "tool_use" => (
+                json!({ "type": "tool_use", "id": block.get("id"), "name": block.get("name"), "input": {} }),
+                vec![json!({
+                    "type": "input_json_delta",
+                    "partial_json": block.get("input").map(|i| i.to_string()).unwrap_or_else(|| "{}".to_string())
+                })],
+            ),
+            // redacted_thinking 等没有增量形式的块整体放在 content_block_start 中
+            _ => (block.clone(), Vec::new()),
+        };
+        sse_event(
+            out,
+            Some("content_block_start"),
+            &json!({ "type": "content_block_start", "index": index, "content_block": start }),
+        );
+        for delta in deltas {
+            sse_event(
+                out,
+                Some("content_block_delta"),
+                &json!({ "type": "content_block_delta", "index": index, "delta": delta }),
+            );
+        }
+        sse_event(out, Some("content_block_stop"), &json!({ "type": "content_block_stop", "index": index }));
+    }
+
+    sse_event(
+        out,
+        Some("message_delta"),
+        &json!({
+            "type": "message_delta",
+            "delta": { "stop_reason": response.get("stop_reason"), "stop_sequence": response.get("stop_sequence") },
+            "usage": response.get("usage").cloned().unwrap_or(json!({}))
+        }),
+    );
+    sse_event(out, Some("message_stop"), &json!({ "type": "message_stop" }));
+}
+
+fn replay_openai(out: &mut String, response: &Value) {
+    let chunk = |choices: Value| {
+        json!({
+            "id": response.get("id"),
+            "object": "chat.completion.chunk",
+            "created": response.get("created"),
+            "model": response.get("model"),
+            "choices": choices,
+        })
+    };
+
+    let choices = response.get("choices").and_then(|c| c.as_array()).cloned().unwrap_or_default();
+    for (position, choice) in choices.iter().enumerate() {
+        let index = choice.get("index").cloned().unwrap_or(json!(position));
+        let message = choice.get("message").cloned().unwrap_or(json!({}));
+
+        let mut delta = Map::new();
+        delta.insert("role".to_string(), message.get("role").cloned().unwrap_or(json!("assistant")));
+        for field in ["reasoning_content", "content"] {
+            if let Some(value) = message.get(field).filter(|v| v.is_string()) {
+                delta.insert(field.to_string(), value.clone());
+            }
+        }
+        if let Some(tool_calls) = message.get("tool_calls").and_then(|t| t.as_array()) {
+            let indexed: Vec<Value> = tool_calls
+                .iter()
+                .enumerate()
+                .map(|(i, call)| {
+                    let mut call = call.clone();
+                    if let Some(obj) = call.as_object_mut() {
+                        obj.insert("index".to_string(), json!(i));
+                    }
+                    call
+                })
+                .collect();
+            delta.insert("tool_calls".to_string(), Value::Array(indexed));
+        }
+        sse_event(out, None, &chunk(json!([{ "index": index, "delta": delta, "finish_reason": null }])));
+        sse_event(
+            out,
+            None,
+            &chunk(json!([{ "index": index, "delta": {}, "finish_reason": choice.get("finish_reason") }])),
+        );
+    }
+
+    if let Some(usage) = response.get("usage") {
+        let mut last = chunk(json!([]));
+        last["usage"] = usage.clone();
+        sse_event(out, None, &last);
+    }
+    out.push_str("data: [DONE]\n\n");
+}
+
+/// 将客户端收到的 SSE 流还原为非流式响应 (复用各协议的流收集器)
+async fn collect_sse(protocol: CacheProtocol, chunks: Vec<Bytes>) -> Option<Value> {
+    let stream = futures::stream::iter(chunks.into_iter().map(Ok::<Bytes, std::io::Error>));
+    let collected = match protocol {
+        CacheProtocol::Anthropic => crate::proxy::mappers::claude::collect_stream_to_json(stream)
+            .await
+            .ok()
+            .and_then(|r| serde_json::to_value(r).ok()),
+        CacheProtocol::OpenAI => crate::proxy::mappers::openai::collector::collect_stream_to_json(stream)
+            .await
+            .ok()
+            .and_then(|r| serde_json::to_value(r).ok()),
+        CacheProtocol::Gemini => {
+            crate::proxy::mappers::gemini::collector::collect_stream_to_json(stream, "response-cache")
+                .await
+                .ok()
+        }
+    }?;
+    is_complete(protocol, &collected).then_some(collected)
+}
+
+// ===== 运行时 =====
+
+/// 全局响应缓存 (配置热更新)
+#[derive(Default)]
+pub struct ResponseCache {
+    config: RwLock<ResponseCacheConfig>,
+}
+
+static CACHE: Lazy<ResponseCache> = Lazy::new(ResponseCache::default);
+
+/// 全局响应缓存
+pub fn cache() -> &'static ResponseCache {
+    &CACHE
+}
+
+fn db_path() -> Result<PathBuf, String> {
+    crate::modules::account::get_data_dir().map(|dir| response_cache_db::get_response_cache_db_path(&dir))
+}
+
+impl ResponseCache {
+    /// 热更新配置
+    pub fn configure(&self, config: &ResponseCacheConfig) {
+        *self.config.write() = config.clone();
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.read().enabled
+    }
+
+    /// 请求可缓存时返回缓存上下文: 已启用，且 temperature = 0 或为后台任务
+    pub fn prepare(
+        &self,
+        protocol: CacheProtocol,
+        client_key_id: Option<&str>,
+        model: &str,
+        body: &Value,
+        stream: bool,
+        background_task: bool,
+    ) -> Option<CacheContext> {
+        if !self.is_enabled() || !(background_task || is_deterministic(body)) {
+            return None;
+        }
+        // 多候选结果无法从流中完整还原
+        if body.get("n").and_then(|n| n.as_u64()).is_some_and(|n| n > 1) {
+            return None;
+        }
+        Some(CacheContext {
+            key: cache_key(protocol, client_key_id, model, body),
+            protocol,
+            stream,
+        })
+    }
+
+    /// 缓存统计
+    pub async fn stats(&self) -> Result<ResponseCacheStats, String> {
+        let path = db_path()?;
+        let now = chrono::Utc::now().timestamp();
+        tokio::task::spawn_blocking(move || response_cache_db::stats(&path, now))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+
+    /// 清空缓存，返回删除的条目数
+    pub async fn clear(&self) -> Result<usize, String> {
+        let path = db_path()?;
+        tokio::task::spawn_blocking(move || response_cache_db::clear(&path))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+}
+
+/// 一次可缓存请求的上下文
+#[derive(Debug, Clone)]
+pub struct CacheContext {
+    pub key: String,
+    pub protocol: CacheProtocol,
+    /// 客户端是否请求流式响应
+    pub stream: bool,
+}
+
+impl CacheContext {
+    /// 查找缓存，命中时直接构造响应 (流式请求重放为 SSE)
+    pub async fn lookup(&self) -> Option<Response> {
+        let path = db_path().ok()?;
+        let key = self.key.clone();
+        let now = chrono::Utc::now().timestamp();
+        let entry = match tokio::task::spawn_blocking(move || response_cache_db::get_entry(&path, &key, now)).await {
+            Ok(Ok(entry)) => entry?,
+            Ok(Err(e)) => {
+                tracing::warn!("[ResponseCache] 读取缓存失败: {}", e);
+                return None;
+            }
+            Err(_) => return None,
+        };
+        tracing::info!("[ResponseCache] 命中缓存 ({}, {})", self.protocol.as_str(), entry.model);
+
+        let builder = Response::builder()
+            .status(StatusCode::OK)
+            .header(CACHE_HEADER, "HIT")
+            .header("X-Mapped-Model", HeaderValue::from_str(&entry.model).unwrap_or(HeaderValue::from_static("unknown")));
+        let response = if self.stream {
+            builder
+                .header(header::CONTENT_TYPE, "text/event-stream")
+                .header(header::CACHE_CONTROL, "no-cache")
+                .body(Body::from(replay_sse(self.protocol, &entry.response)))
+        } else {
+            builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(entry.response.to_string()))
+        };
+        response.ok()
+    }
+
+    /// 标记 MISS，成功的完整响应写入缓存 (流式响应在客户端读完后还原为 JSON 再写入)
+    pub async fn store(self, mut response: Response) -> Response {
+        response.headers_mut().insert(CACHE_HEADER, HeaderValue::from_static("MISS"));
+        if response.status() != StatusCode::OK {
+            return response;
+        }
+
+        let config = cache().config.read().clone();
+        let max_bytes = config.max_size_mb.saturating_mul(1024 * 1024);
+        let model = response
+            .headers()
+            .get("X-Mapped-Model")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+        let is_sse = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| ct.contains("text/event-stream"));
+
+        let (parts, body) = response.into_parts();
+        if !is_sse {
+            let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    return Response::from_parts(parts, Body::from(format!("Failed to read response: {}", e)));
+                }
+            };
+            if let Some(value) = parse_cacheable(self.protocol, &bytes) {
+                self.spawn_write(value, model, &config, max_bytes);
+            }
+            return Response::from_parts(parts, Body::from(bytes));
+        }
+
+        // 流式: 原样转发给客户端，同时保留副本；流正常结束后再还原并写入
+        let mut stream = body.into_data_stream();
+        let (tx, rx) = tokio::sync::mpsc::channel(64);
+        tokio::spawn(async move {
+            let mut chunks = Vec::new();
+            let mut total = 0u64;
+            let mut complete = true;
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(bytes) => {
+                        total += bytes.len() as u64;
+                        if total <= max_bytes {
+                            chunks.push(bytes.clone());
+                        }
+                        if tx.send(Ok::<_, axum::Error>(bytes)).await.is_err() {
+                            // 客户端断开，不缓存半截响应
+                            complete = false;
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        complete = false;
+                        let _ = tx.send(Err(e)).await;
+                        break;
+                    }
+                }
+            }
+            if !complete || total > max_bytes {
+                return;
+            }
+            if let Some(value) = collect_sse(self.protocol, chunks).await {
+                self.spawn_write(value, model, &config, max_bytes);
+            }
+        });
+        Response::from_parts(parts, Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)))
+    }
+
+    fn spawn_write(&self, response: Value, model: String, config: &ResponseCacheConfig, max_bytes: u64) {
+        let now = chrono::Utc::now().timestamp();
+        let entry = CachedResponse {
+            key: self.key.clone(),
+            protocol: self.protocol.as_str().to_string(),
+            model,
+            created_at: now,
+            expires_at: now + config.ttl_secs as i64,
+            response,
+        };
+        let max_entries = config.max_entries;
+        tokio::task::spawn_blocking(move || {
+            let result = db_path().and_then(|path| response_cache_db::put_entry(&path, &entry, max_entries, max_bytes));
+            if let Err(e) = result {
+                tracing::warn!("[ResponseCache] 写入缓存失败: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_normalisation_and_eligibility() {
+        let a = json!({
+            "model": "claude-sonnet-4-5",
+            "stream": true,
+            "temperature": 0,
+            "max_tokens": 64,
+            "metadata": { "user_id": "session-1" },
+            "system": [{ "type": "text", "text": "Generate a title", "cache_control": { "type": "ephemeral" } }],
+            "messages": [{ "role": "user", "content": "hello" }]
+        });
+        let b = json!({
+            "messages": [{ "content": "hello", "role": "user" }],
+            "system": [{ "text": "Generate a title", "type": "text" }],
+            "max_tokens": 64,
+            "temperature": 0.0,
+            "metadata": { "user_id": "session-2" },
+            "model": "claude-sonnet-4-5"
+        });
+        let key = |body: &Value, client: Option<&str>| cache_key(CacheProtocol::Anthropic, client, "claude-sonnet-4-5", body);
+
+        // 字段顺序、stream、metadata、cache_control 不影响 key
+        assert_eq!(key(&a, None), key(&b, None));
+        // 采样参数、客户端 Key、协议参与 key
+        let mut c = b.clone();
+        c["max_tokens"] = json!(65);
+        assert_ne!(key(&b, None), key(&c, None));
+        assert_ne!(key(&b, None), key(&b, Some("ci")));
+        assert_ne!(key(&b, None), cache_key(CacheProtocol::OpenAI, None, "claude-sonnet-4-5", &b));
+        let mut d = b.clone();
+        d["logprobs"] = json!(true);
+        d["top_logprobs"] = json!(3);
+        assert_ne!(key(&b, None), key(&d, None));
+
+        assert!(is_deterministic(&a));
+        assert!(is_deterministic(&json!({ "generationConfig": { "temperature": 0 } })));
+        assert!(!is_deterministic(&json!({ "temperature": 0.7 })));
+        assert!(!is_deterministic(&json!({})));
+
+        let cache = ResponseCache::default();
+        assert!(cache.prepare(CacheProtocol::Anthropic, None, "m", &a, true, false).is_none());
+        cache.configure(&ResponseCacheConfig { enabled: true, ..Default::default() });
+        assert!(cache.prepare(CacheProtocol::Anthropic, None, "m", &a, true, false).is_some());
+        // 非确定性请求只在识别为后台任务时缓存
+        let sampled = json!({ "temperature": 1, "messages": [] });
+        assert!(cache.prepare(CacheProtocol::OpenAI, None, "m", &sampled, false, false).is_none());
+        assert!(cache.prepare(CacheProtocol::OpenAI, None, "m", &sampled, false, true).is_some());
+        assert!(cache
+            .prepare(CacheProtocol::OpenAI, None, "m", &json!({ "temperature": 0, "n": 2 }), false, false)
+            .is_none());
+    }
+
+    #[test]
+    fn test_sse_replay_roundtrips_through_collectors() {
+        let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        let claude = json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-5",
+            "content": [
+                { "type": "thinking", "thinking": "Short title.", "signature": "sig" },
+                { "type": "text", "text": "Fix login bug" },
+                { "type": "tool_use", "id": "toolu_1", "name": "lookup", "input": { "q": "x" } }
+            ],
+            "stop_reason": "tool_use",
+            "usage": { "input_tokens": 12, "output_tokens": 5 }
+        });
+        let sse = replay_sse(CacheProtocol::Anthropic, &claude);
+        assert!(sse.starts_with("event: message_start\n"));
+        let collected = rt
+            .block_on(collect_sse(CacheProtocol::Anthropic, vec![Bytes::from(sse)]))
+            .unwrap();
+        assert_eq!(collected["content"], claude["content"]);
+        assert_eq!(collected["stop_reason"], "tool_use");
+        assert_eq!(collected["usage"]["output_tokens"], 5);
+
+        let openai = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1_700_000_000,
+            "model": "gemini-3-flash",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Fix login bug" },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 10, "completion_tokens": 4, "total_tokens": 14 }
+        });
+        let sse = replay_sse(CacheProtocol::OpenAI, &openai);
+        assert!(sse.ends_with("data: [DONE]\n\n"));
+        let collected = rt.block_on(collect_sse(CacheProtocol::OpenAI, vec![Bytes::from(sse)])).unwrap();
+        assert_eq!(collected["choices"][0]["message"]["content"], "Fix login bug");
+        assert_eq!(collected["choices"][0]["finish_reason"], "stop");
+        assert_eq!(collected["usage"]["total_tokens"], 14);
+
+        let gemini = json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "Fix login bug" }] }, "finishReason": "STOP", "index": 0 }],
+            "usageMetadata": { "promptTokenCount": 10, "candidatesTokenCount": 4 }
+        });
+        let collected = rt
+            .block_on(collect_sse(CacheProtocol::Gemini, vec![Bytes::from(replay_sse(CacheProtocol::Gemini, &gemini))]))
+            .unwrap();
+        assert_eq!(collected["candidates"][0]["content"]["parts"], gemini["candidates"][0]["content"]["parts"]);
+
+        // 没有结束原因的半截响应不缓存
+        assert!(parse_cacheable(CacheProtocol::Anthropic, br#"{"type":"message","content":[{"type":"text","text":"x"}]}"#).is_none());
+    }
+}
@@ -0,0 +1,395 @@
+// 账号路由策略 (Routing Policy)
+//
+// TokenManager::get_token 负责可用性判断 (限流、配额保护、排除列表)、粘性会话、
+// 固定账号与兜底逻辑；策略只决定"新选账号"时候选账号的尝试顺序。
+// 策略按模型通配符配置，未命中规则时使用 default_policy。
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::proxy::common::model_mapping::wildcard_match;
+
+/// 刷新时间差异 < 10 分钟视为相同优先级
+const RESET_TIME_THRESHOLD_SECS: i64 = 600;
+
+/// 内置策略类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingPolicyKind {
+    /// 现有行为: 等级 > 健康分 > 刷新时间 > 剩余配额 排序后轮询
+    #[default]
+    Default,
+    /// 最久未使用的账号优先
+    LeastRecentlyUsed,
+    /// 按目标模型剩余配额加权随机
+    QuotaWeighted,
+    /// ULTRA > PRO > FREE，同等级内轮询
+    TierFirst,
+    /// 按 Kiro credit 倍率分配: 贵的模型用余额多的账号，便宜的模型先消耗余额少的账号
+    CostAware,
+}
+
+/// 按模型匹配的策略规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingRule {
+    /// 模型名或通配符 (如 "claude-opus-*")，匹配客户端请求的模型名
+    pub model_pattern: String,
+    pub policy: RoutingPolicyKind,
+}
+
+/// 路由策略配置
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct RoutingConfig {
+    /// 未命中任何规则时使用的策略
+    pub default_policy: RoutingPolicyKind,
+    /// 按顺序匹配，第一个命中的规则生效
+    pub rules: Vec<RoutingRule>,
+    /// 覆盖/补充内置的 Kiro credit 倍率 (模型名 -> 倍率)
+    pub credit_multipliers: HashMap<String, f64>,
+}
+
+impl RoutingConfig {
+    pub fn policy_for(&self, model: &str) -> RoutingPolicyKind {
+        self.rules
+            .iter()
+            .find(|r| r.model_pattern == model || (r.model_pattern.contains('*') && wildcard_match(&r.model_pattern, model)))
+            .map(|r| r.policy)
+            .unwrap_or(self.default_policy)
+    }
+
+    /// 查询模型的 credit 倍率 (配置优先，其次内置表，默认 1.0)
+    pub fn credit_multiplier(&self, model: &str) -> f64 {
+        if let Some(m) = self.credit_multipliers.get(model) {
+            return *m;
+        }
+        match model {
+            "claude-sonnet-4" | "claude-sonnet-4-5" => 1.3,
+            "claude-haiku-4-5" => 0.4,
+            "claude-opus-4-5" | "claude-opus-4-6" => 2.2,
+            "deepseek-3" => 0.25,
+            "minimax-2-1" => 0.15,
+            "qwen3-coder-next" => 0.05,
+            _ => 1.0,
+        }
+    }
+}
+
+/// 策略可见的账号信息 (与 ProxyToken 解耦，便于用假账号池测试)
+#[derive(Debug, Clone, Default)]
+pub struct RoutingCandidate {
+    pub account_id: String,
+    pub subscription_tier: Option<String>,
+    pub health_score: f32,
+    pub reset_time: Option<i64>,
+    /// 所有模型中的最大剩余配额百分比
+    pub remaining_quota: Option<i32>,
+    /// 目标模型的剩余配额百分比
+    pub model_quota: Option<i32>,
+    /// Kiro 剩余 credits (月度 + 试用)
+    pub credits_remaining: Option<f64>,
+    /// 上次被选中的时间 (毫秒)
+    pub last_used_at: Option<i64>,
+}
+
+/// 单次选择的上下文
+#[derive(Debug, Clone)]
+pub struct RoutingContext {
+    /// 轮询计数器 (每次选择递增)，用于轮询起点和加权随机的种子
+    pub rotation: usize,
+    /// 目标模型的 credit 倍率
+    pub credit_multiplier: f64,
+}
+
+pub trait RoutingPolicy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 返回候选账号的尝试顺序 (candidates 的下标，需包含全部候选)
+    fn rank(&self, candidates: &[RoutingCandidate], ctx: &RoutingContext) -> Vec<usize>;
+}
+
+impl RoutingPolicyKind {
+    pub fn policy(&self) -> &'static dyn RoutingPolicy {
+        match self {
+            RoutingPolicyKind::Default => &DefaultPolicy,
+            RoutingPolicyKind::LeastRecentlyUsed => &LeastRecentlyUsedPolicy,
+            RoutingPolicyKind::QuotaWeighted => &QuotaWeightedPolicy,
+            RoutingPolicyKind::TierFirst => &TierFirstPolicy,
+            RoutingPolicyKind::CostAware => &CostAwarePolicy,
+        }
+    }
+}
+
+fn tier_rank(tier: Option<&str>) -> u8 {
+    match tier {
+        Some("ULTRA") => 0,
+        Some("PRO") => 1,
+        Some("FREE") => 2,
+        _ => 3,
+    }
+}
+
+/// 基础优先级: 订阅等级 > 健康分 > 刷新时间（越近越优先）> 剩余配额
+pub fn priority_cmp(a: &RoutingCandidate, b: &RoutingCandidate) -> Ordering {
+    let tier_cmp = tier_rank(a.subscription_tier.as_deref()).cmp(&tier_rank(b.subscription_tier.as_deref()));
+    if tier_cmp != Ordering::Equal {
+        return tier_cmp;
+    }
+
+    let health_cmp = b.health_score.partial_cmp(&a.health_score).unwrap_or(Ordering::Equal);
+    if health_cmp != Ordering::Equal {
+        return health_cmp;
+    }
+
+    let reset_a = a.reset_time.unwrap_or(i64::MAX);
+    let reset_b = b.reset_time.unwrap_or(i64::MAX);
+    if reset_a.abs_diff(reset_b) >= RESET_TIME_THRESHOLD_SECS as u64 {
+        let reset_cmp = reset_a.cmp(&reset_b);
+        if reset_cmp != Ordering::Equal {
+            return reset_cmp;
+        }
+    }
+
+    b.remaining_quota.unwrap_or(0).cmp(&a.remaining_quota.unwrap_or(0))
+}
+
+/// 按基础优先级排序后的下标
+pub fn priority_order(candidates: &[RoutingCandidate]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by(|&a, &b| priority_cmp(&candidates[a], &candidates[b]));
+    order
+}
+
+fn rotate(mut order: Vec<usize>, rotation: usize) -> Vec<usize> {
+    if !order.is_empty() {
+        let len = order.len();
+        order.rotate_left(rotation % len);
+    }
+    order
+}
+
+/// 现有调度行为: 基础优先级排序，从轮询起点开始依次尝试
+pub struct DefaultPolicy;
+
+impl RoutingPolicy for DefaultPolicy {
+    fn name(&self) -> &'static str {
+        "default"
+    }
+
+    fn rank(&self, candidates: &[RoutingCandidate], ctx: &RoutingContext) -> Vec<usize> {
+        rotate(priority_order(candidates), ctx.rotation)
+    }
+}
+
+pub struct LeastRecentlyUsedPolicy;
+
+impl RoutingPolicy for LeastRecentlyUsedPolicy {
+    fn name(&self) -> &'static str {
+        "least_recently_used"
+    }
+
+    fn rank(&self, candidates: &[RoutingCandidate], _ctx: &RoutingContext) -> Vec<usize> {
+        // 从未使用过的账号最优先，其次按基础优先级
+        let mut order = priority_order(candidates);
+        order.sort_by_key(|&i| candidates[i].last_used_at.unwrap_or(i64::MIN));
+        order
+    }
+}
+
+pub struct QuotaWeightedPolicy;
+
+impl RoutingPolicy for QuotaWeightedPolicy {
+    fn name(&self) -> &'static str {
+        "quota_weighted"
+    }
+
+    fn rank(&self, candidates: &[RoutingCandidate], ctx: &RoutingContext) -> Vec<usize> {
+        // 加权无放回抽样 (Efraimidis–Spirakis): key = u^(1/w)，按 key 降序
+        // 0% 配额的账号权重极小，只会排在最后作为兜底
+        let mut keyed: Vec<(usize, f64)> = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let weight = c.model_quota.or(c.remaining_quota).unwrap_or(0).max(0) as f64 + 0.01;
+                let u = unit_random(ctx.rotation as u64, &c.account_id);
+                (i, u.powf(1.0 / weight))
+            })
+            .collect();
+        keyed.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        keyed.into_iter().map(|(i, _)| i).collect()
+    }
+}
+
+pub struct TierFirstPolicy;
+
+impl RoutingPolicy for TierFirstPolicy {
+    fn name(&self) -> &'static str {
+        "tier_first"
+    }
+
+    fn rank(&self, candidates: &[RoutingCandidate], ctx: &RoutingContext) -> Vec<usize> {
+        // 先用完高等级账号，同等级内轮询分摊负载
+        let mut groups: [Vec<usize>; 4] = Default::default();
+        for i in priority_order(candidates) {
+            groups[tier_rank(candidates[i].subscription_tier.as_deref()) as usize].push(i);
+        }
+        groups
+            .into_iter()
+            .flat_map(|g| rotate(g, ctx.rotation))
+            .collect()
+    }
+}
+
+pub struct CostAwarePolicy;
+
+impl RoutingPolicy for CostAwarePolicy {
+    fn name(&self) -> &'static str {
+        "cost_aware"
+    }
+
+    fn rank(&self, candidates: &[RoutingCandidate], ctx: &RoutingContext) -> Vec<usize> {
+        let cost = ctx.credit_multiplier;
+        let mut affordable = Vec::new();
+        let mut exhausted = Vec::new();
+        let mut unknown = Vec::new();
+        for i in priority_order(candidates) {
+            match candidates[i].credits_remaining {
+                Some(c) if c >= cost => affordable.push(i),
+                Some(_) => exhausted.push(i),
+                None => unknown.push(i),
+            }
+        }
+
+        let credits = |i: &usize| candidates[*i].credits_remaining.unwrap_or(0.0);
+        if cost >= 1.0 {
+            // 贵的模型: 余额最多的账号优先，避免中途耗尽
+            affordable.sort_by(|a, b| credits(b).partial_cmp(&credits(a)).unwrap_or(Ordering::Equal));
+        } else {
+            // 便宜的模型: 先消耗余额少的账号，把大余额留给贵的模型
+            affordable.sort_by(|a, b| credits(a).partial_cmp(&credits(b)).unwrap_or(Ordering::Equal));
+        }
+
+        // 非 Kiro 账号没有 credits 信息，沿用默认顺序；余额不足的账号只作为兜底
+        affordable
+            .into_iter()
+            .chain(rotate(unknown, ctx.rotation))
+            .chain(exhausted)
+            .collect()
+    }
+}
+
+/// 由种子和账号 ID 生成 (0, 1) 内的确定性伪随机数
+fn unit_random(seed: u64, account_id: &str) -> f64 {
+    // FNV-1a + splitmix64 finalizer
+    let mut h: u64 = 0xcbf29ce484222325 ^ seed.wrapping_mul(0x9E3779B97F4A7C15);
+    for b in account_id.bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+    h ^= h >> 31;
+    ((h >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: &str, tier: Option<&str>) -> RoutingCandidate {
+        RoutingCandidate {
+            account_id: id.to_string(),
+            subscription_tier: tier.map(|s| s.to_string()),
+            health_score: 1.0,
+            remaining_quota: Some(50),
+            ..Default::default()
+        }
+    }
+
+    fn ctx(rotation: usize) -> RoutingContext {
+        RoutingContext { rotation, credit_multiplier: 1.0 }
+    }
+
+    fn ids(pool: &[RoutingCandidate], order: &[usize]) -> Vec<String> {
+        order.iter().map(|&i| pool[i].account_id.clone()).collect()
+    }
+
+    #[test]
+    fn test_default_policy_sorts_then_rotates() {
+        let pool = vec![candidate("free", Some("FREE")), candidate("ultra", Some("ULTRA")), candidate("pro", Some("PRO"))];
+        assert_eq!(ids(&pool, &DefaultPolicy.rank(&pool, &ctx(0))), ["ultra", "pro", "free"]);
+        assert_eq!(ids(&pool, &DefaultPolicy.rank(&pool, &ctx(4))), ["pro", "free", "ultra"]);
+    }
+
+    #[test]
+    fn test_tier_first_rotates_within_tier() {
+        let pool = vec![
+            candidate("pro-a", Some("PRO")),
+            candidate("free", Some("FREE")),
+            candidate("pro-b", Some("PRO")),
+            candidate("ultra", Some("ULTRA")),
+        ];
+        assert_eq!(ids(&pool, &TierFirstPolicy.rank(&pool, &ctx(0))), ["ultra", "pro-a", "pro-b", "free"]);
+        assert_eq!(ids(&pool, &TierFirstPolicy.rank(&pool, &ctx(1))), ["ultra", "pro-b", "pro-a", "free"]);
+    }
+
+    #[test]
+    fn test_lru_prefers_unused_then_oldest() {
+        let mut pool = vec![candidate("recent", None), candidate("old", None), candidate("never", None)];
+        pool[0].last_used_at = Some(2_000);
+        pool[1].last_used_at = Some(1_000);
+        assert_eq!(ids(&pool, &LeastRecentlyUsedPolicy.rank(&pool, &ctx(0))), ["never", "old", "recent"]);
+    }
+
+    #[test]
+    fn test_quota_weighted_follows_model_quota() {
+        let mut pool = vec![candidate("empty", None), candidate("low", None), candidate("high", None)];
+        pool[0].model_quota = Some(0);
+        pool[1].model_quota = Some(10);
+        pool[2].model_quota = Some(90);
+
+        let mut first = HashMap::new();
+        for rotation in 0..1000 {
+            let order = QuotaWeightedPolicy.rank(&pool, &ctx(rotation));
+            assert_eq!(order.len(), 3);
+            *first.entry(pool[order[0]].account_id.clone()).or_insert(0) += 1;
+        }
+        let high = first.get("high").copied().unwrap_or(0);
+        let low = first.get("low").copied().unwrap_or(0);
+        assert!(high > 800 && low > 30, "high={} low={}", high, low);
+        assert!(first.get("empty").copied().unwrap_or(0) < 5);
+    }
+
+    #[test]
+    fn test_cost_aware_packs_cheap_and_spreads_expensive() {
+        let mut pool = vec![candidate("big", None), candidate("small", None), candidate("broke", None), candidate("gemini", None)];
+        pool[0].credits_remaining = Some(400.0);
+        pool[1].credits_remaining = Some(20.0);
+        pool[2].credits_remaining = Some(0.1);
+
+        let config = RoutingConfig::default();
+        let cheap = RoutingContext { rotation: 0, credit_multiplier: config.credit_multiplier("qwen3-coder-next") };
+        assert_eq!(ids(&pool, &CostAwarePolicy.rank(&pool, &cheap)), ["broke", "small", "big", "gemini"]);
+
+        let expensive = RoutingContext { rotation: 0, credit_multiplier: config.credit_multiplier("claude-opus-4-5") };
+        assert_eq!(ids(&pool, &CostAwarePolicy.rank(&pool, &expensive)), ["big", "small", "gemini", "broke"]);
+    }
+
+    #[test]
+    fn test_policy_for_model_pattern() {
+        let config: RoutingConfig = serde_json::from_value(serde_json::json!({
+            "default_policy": "least_recently_used",
+            "rules": [
+                { "model_pattern": "claude-opus-*", "policy": "tier_first" },
+                { "model_pattern": "qwen3-coder-next", "policy": "cost_aware" }
+            ],
+            "credit_multipliers": { "auto": 0.8 }
+        }))
+        .unwrap();
+        assert_eq!(config.policy_for("claude-opus-4-6"), RoutingPolicyKind::TierFirst);
+        assert_eq!(config.policy_for("qwen3-coder-next"), RoutingPolicyKind::CostAware);
+        assert_eq!(config.policy_for("gemini-3-flash"), RoutingPolicyKind::LeastRecentlyUsed);
+        assert_eq!(config.credit_multiplier("auto"), 0.8);
+        assert_eq!(RoutingConfig::default().policy_for("anything"), RoutingPolicyKind::Default);
+    }
+}
@@ -1,10 +1,13 @@
-use crate::proxy::config::{ProxyAuthMode, ProxyConfig, SecurityMonitorConfig};
+use crate::proxy::config::{ClientApiKey, ProxyAuthMode, ProxyConfig, SecurityMonitorConfig};
 
 #[derive(Debug, Clone)]
 pub struct ProxySecurityConfig {
     pub auth_mode: ProxyAuthMode,
     pub api_key: String,
     pub admin_password: Option<String>,
+    pub client_keys: Vec<ClientApiKey>,
+    pub metrics_token: Option<String>,
+    pub trusted_proxies: Vec<String>,
     pub allow_lan_access: bool,
     pub port: u16,
     pub security_monitor: SecurityMonitorConfig,
@@ -16,6 +19,9 @@ impl ProxySecurityConfig {
             auth_mode: config.auth_mode.clone(),
             api_key: config.api_key.clone(),
             admin_password: config.admin_password.clone(),
+            client_keys: config.client_keys.clone(),
+            metrics_token: config.metrics_token.clone(),
+            trusted_proxies: config.trusted_proxies.clone(),
             allow_lan_access: config.allow_lan_access,
             port: config.port,
             security_monitor: config.security_monitor.clone(),
@@ -23,7 +29,12 @@ impl ProxySecurityConfig {
     }
 
     pub fn effective_auth_mode(&self) -> ProxyAuthMode {
-        match self.auth_mode {
+        self.effective_auth_mode_with_tunnel(crate::modules::cloudflared::is_tunnel_active())
+    }
+
+    /// [NEW] Cloudflare 隧道启用时反代暴露在公网，拒绝 `Off` (至少 AllExceptHealth)
+    pub fn effective_auth_mode_with_tunnel(&self, tunnel_active: bool) -> ProxyAuthMode {
+        let mode = match self.auth_mode {
             ProxyAuthMode::Auto => {
                 if self.allow_lan_access {
                     ProxyAuthMode::AllExceptHealth
@@ -32,8 +43,17 @@ impl ProxySecurityConfig {
                 }
             }
             ref other => other.clone(),
+        };
+        match mode {
+            ProxyAuthMode::Off if tunnel_active => ProxyAuthMode::AllExceptHealth,
+            other => other,
         }
     }
+
+    /// 按 Key 值查找客户端 API Key
+    pub fn find_client_key(&self, key: &str) -> Option<&ClientApiKey> {
+        self.client_keys.iter().find(|k| k.key == key)
+    }
 }
 
 #[cfg(test)]
@@ -46,6 +66,9 @@ mod tests {
             auth_mode: ProxyAuthMode::Auto,
             api_key: "sk-test".to_string(),
             admin_password: None,
+            client_keys: Vec::new(),
+            metrics_token: None,
+            trusted_proxies: Vec::new(),
             allow_lan_access: false,
             port: 8080,
             security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
@@ -59,6 +82,9 @@ mod tests {
             auth_mode: ProxyAuthMode::Auto,
             api_key: "sk-test".to_string(),
             admin_password: None,
+            client_keys: Vec::new(),
+            metrics_token: None,
+            trusted_proxies: Vec::new(),
             allow_lan_access: true,
             port: 8080,
             security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
@@ -68,5 +94,64 @@ mod tests {
             ProxyAuthMode::AllExceptHealth
         ));
     }
-}
 
+    #[test]
+    fn active_tunnel_refuses_off() {
+        let mut s = ProxySecurityConfig {
+            auth_mode: ProxyAuthMode::Off,
+            api_key: "sk-test".to_string(),
+            admin_password: None,
+            client_keys: Vec::new(),
+            metrics_token: None,
+            trusted_proxies: Vec::new(),
+            allow_lan_access: false,
+            port: 8080,
+            security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
+        };
+        assert!(matches!(s.effective_auth_mode_with_tunnel(false), ProxyAuthMode::Off));
+        assert!(matches!(
+            s.effective_auth_mode_with_tunnel(true),
+            ProxyAuthMode::AllExceptHealth
+        ));
+        s.auth_mode = ProxyAuthMode::Auto;
+        assert!(matches!(
+            s.effective_auth_mode_with_tunnel(true),
+            ProxyAuthMode::AllExceptHealth
+        ));
+        s.auth_mode = ProxyAuthMode::Strict;
+        assert!(matches!(s.effective_auth_mode_with_tunnel(true), ProxyAuthMode::Strict));
+    }
+
+    #[test]
+    fn client_key_lookup_and_model_allow_list() {
+        let mut key = ClientApiKey::new("alice".to_string());
+        key.allowed_models = vec!["gemini-3-*".to_string(), "claude-sonnet-4-5".to_string()];
+        let raw = key.key.clone();
+        let s = ProxySecurityConfig {
+            auth_mode: ProxyAuthMode::Strict,
+            api_key: "sk-master".to_string(),
+            admin_password: None,
+            client_keys: vec![key],
+            metrics_token: None,
+            trusted_proxies: Vec::new(),
+            allow_lan_access: true,
+            port: 8080,
+            security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
+        };
+
+        let found = s.find_client_key(&raw).expect("client key should be found");
+        assert!(found.allows_model("gemini-3-pro-high"));
+        assert!(found.allows_model("claude-sonnet-4-5"));
+        assert!(!found.allows_model("claude-opus-4-6-thinking"));
+        assert!(s.find_client_key("sk-master").is_none());
+    }
+
+    #[test]
+    fn client_key_expiry() {
+        let mut key = ClientApiKey::new("bob".to_string());
+        assert!(!key.is_expired(i64::MAX - 1));
+        key.expires_at = Some(1_000);
+        assert!(key.is_expired(1_000));
+        assert!(!key.is_expired(999));
+    }
+}
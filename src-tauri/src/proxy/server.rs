@@ -1,11 +1,13 @@
 use crate::models::AppConfig;
-use crate::modules::{account, config, logger, migration, proxy_db, token_stats};
+use crate::modules::{account, audit_db, config, logger, migration, proxy_db, quota_history, token_stats, vault};
+use crate::proxy::admin_auth;
+use crate::proxy::middleware::ip_filter::peer_ip_from_parts;
 use crate::proxy::TokenManager;
 use axum::{
     extract::{DefaultBodyLimit, Path, Query, State},
     http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse, Json, Response},
-    routing::{any, delete, get, post},
+    routing::{any, delete, get, post, put},
     Router,
 };
 use serde::{Deserialize, Serialize};
@@ -69,6 +71,7 @@ pub struct AppState {
     pub zai: Arc<RwLock<crate::proxy::ZaiConfig>>,
     pub provider_rr: Arc<AtomicUsize>,
     pub zai_vision_mcp: Arc<crate::proxy::zai_vision_mcp::ZaiVisionMcpState>,
+    pub mcp_gateway: Arc<crate::proxy::mcp_gateway::McpGateway>, // [NEW] 通用 MCP 网关
     pub monitor: Arc<crate::proxy::monitor::ProxyMonitor>,
     pub experimental: Arc<RwLock<crate::proxy::config::ExperimentalConfig>>,
     pub debug_logging: Arc<RwLock<crate::proxy::config::DebugLoggingConfig>>,
@@ -182,6 +185,7 @@ pub struct AxumServer {
     pub cloudflared_state: Arc<crate::commands::cloudflared::CloudflaredState>,
     pub is_running: Arc<RwLock<bool>>,
     pub token_manager: Arc<TokenManager>, // [NEW] 暴露出 TokenManager 供反代服务复用
+    pub mcp_gateway: Arc<crate::proxy::mcp_gateway::McpGateway>, // [NEW] 通用 MCP 网关
 }
 
 impl AxumServer {
@@ -212,6 +216,13 @@ impl AxumServer {
         tracing::info!("z.ai 配置已热更新");
     }
 
+    pub async fn update_mcp_gateway(&self, config: &crate::proxy::config::ProxyConfig) {
+        self.mcp_gateway
+            .update_config(config.mcp_gateway.clone())
+            .await;
+        tracing::info!("MCP 网关配置已热更新");
+    }
+
     pub async fn update_experimental(&self, config: &crate::proxy::config::ProxyConfig) {
         let mut exp = self.experimental.write().await;
         *exp = config.experimental.clone();
@@ -260,6 +271,7 @@ impl AxumServer {
         let zai_state = Arc::new(RwLock::new(zai_config));
         let provider_rr = Arc::new(AtomicUsize::new(0));
         let zai_vision_mcp_state = Arc::new(crate::proxy::zai_vision_mcp::ZaiVisionMcpState::new());
+        let mcp_gateway_state = Arc::new(crate::proxy::mcp_gateway::McpGateway::default());
         let experimental_state = Arc::new(RwLock::new(experimental_config));
         let debug_logging_state = Arc::new(RwLock::new(debug_logging));
         let is_running_state = Arc::new(RwLock::new(true));
@@ -285,6 +297,7 @@ impl AxumServer {
             zai: zai_state.clone(),
             provider_rr: provider_rr.clone(),
             zai_vision_mcp: zai_vision_mcp_state,
+            mcp_gateway: mcp_gateway_state.clone(),
             monitor: monitor.clone(),
             experimental: experimental_state.clone(),
             debug_logging: debug_logging_state.clone(),
@@ -304,7 +317,7 @@ impl AxumServer {
         use crate::proxy::handlers;
         use crate::proxy::middleware::{
             admin_auth_middleware, auth_middleware, cors_layer, ip_filter_middleware,
-            monitor_middleware, service_status_middleware,
+            metrics_auth_middleware, monitor_middleware, service_status_middleware,
         };
 
         // 1. 构建主 AI 代理路由 (遵循 auth_mode 配置)
@@ -321,7 +334,16 @@ impl AxumServer {
                 "/v1/completions",
                 post(handlers::openai::handle_completions),
             )
-            .route("/v1/responses", post(handlers::openai::handle_completions)) // 兼容 Codex CLI
+            .route("/v1/responses", post(handlers::responses::handle_create_response)) // 兼容 Codex CLI
+            .route(
+                "/v1/responses/:id",
+                get(handlers::responses::handle_get_response)
+                    .delete(handlers::responses::handle_delete_response),
+            )
+            .route(
+                "/v1/responses/:id/input_items",
+                get(handlers::responses::handle_list_input_items),
+            )
             .route(
                 "/v1/images/generations",
                 post(handlers::openai::handle_images_generations),
@@ -334,6 +356,11 @@ impl AxumServer {
                 "/v1/audio/transcriptions",
                 post(handlers::audio::handle_audio_transcription),
             ) // 音频转录 API
+            .route(
+                "/v1/audio/translations",
+                post(handlers::audio::handle_audio_translation),
+            ) // 音频翻译 API
+            .route("/v1/embeddings", post(handlers::embeddings::handle_embeddings)) // 嵌入 API
             // Claude Protocol
             .route("/v1/messages", post(handlers::claude::handle_messages))
             .route(
@@ -357,6 +384,8 @@ impl AxumServer {
                 "/mcp/zai-mcp-server/mcp",
                 any(handlers::mcp::handle_zai_mcp_server),
             )
+            // 通用 MCP 网关 (静态 z.ai 路由优先匹配)
+            .route("/mcp/:name/mcp", any(handlers::mcp::handle_mcp_gateway))
             // Gemini Protocol (Native)
             .route("/v1beta/models", get(handlers::gemini::handle_list_models))
             // Handle both GET (get info) and POST (generateContent with colon) at the same route
@@ -389,6 +418,18 @@ impl AxumServer {
                 ip_filter_middleware,
             ));
 
+        // Prometheus 抓取端点 (独立的 metrics_token，不经过客户端 Key 认证与请求监控)
+        let metrics_routes = Router::new()
+            .route("/metrics", get(metrics_handler))
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                metrics_auth_middleware,
+            ))
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                ip_filter_middleware,
+            ));
+
         // 2. 构建管理 API (强制鉴权)
         let admin_routes = Router::new()
             .route("/health", get(health_check_handler))
@@ -449,16 +490,46 @@ impl AxumServer {
             .route("/proxy/stop", post(admin_stop_proxy_service))
             .route("/proxy/mapping", post(admin_update_model_mapping))
             .route("/proxy/api-key/generate", post(admin_generate_api_key))
+            .route(
+                "/proxy/client-keys",
+                get(admin_list_client_keys).post(admin_create_client_key),
+            )
+            .route(
+                "/proxy/client-keys/:keyId",
+                put(admin_update_client_key).delete(admin_delete_client_key),
+            )
+            .route(
+                "/proxy/client-keys/:keyId/revoke",
+                post(admin_revoke_client_key),
+            )
             .route(
                 "/proxy/session-bindings/clear",
                 post(admin_clear_proxy_session_bindings),
             )
             .route("/proxy/session/abort", post(admin_abort_session)) // [NEW] 中止会话/请求端点
+            // [NEW] 账号独立代理健康状态
+            .route("/proxy/account-proxies", get(admin_get_account_proxies))
+            .route("/proxy/account-proxies/check", post(admin_check_account_proxies))
+            // [NEW] 响应缓存统计 / 清空
+            .route(
+                "/proxy/response-cache",
+                get(admin_get_response_cache_stats).delete(admin_clear_response_cache),
+            )
             .route("/proxy/rate-limits", delete(admin_clear_all_rate_limits))
             .route(
                 "/proxy/rate-limits/:accountId",
                 delete(admin_clear_rate_limit),
             )
+            .route("/proxy/limit-state", get(admin_get_limit_state)) // [NEW] 持久化的限流状态
+            .route("/proxy/mcp-gateway", get(admin_get_mcp_gateway_status)) // [NEW] MCP 网关会话状态
+            .route(
+                "/proxy/limit-state/lockouts",
+                post(admin_set_lockout).delete(admin_delete_lockout),
+            )
+            .route(
+                "/proxy/limit-state/accounts/:accountId",
+                post(admin_update_account_limit_state),
+            )
             .route(
                 "/proxy/preferred-account",
                 get(admin_get_preferred_account).post(admin_set_preferred_account),
@@ -483,11 +554,20 @@ impl AxumServer {
             )
             .route("/proxy/cloudflared/start", post(admin_cloudflared_start))
             .route("/proxy/cloudflared/stop", post(admin_cloudflared_stop))
+            .route(
+                "/proxy/cloudflared/tunnels",
+                post(admin_cloudflared_create_tunnel),
+            )
+            .route(
+                "/proxy/cloudflared/tunnels/:name",
+                delete(admin_cloudflared_delete_tunnel),
+            )
             .route("/system/open-folder", post(admin_open_folder))
             .route("/proxy/stats", get(admin_get_proxy_stats))
             .route("/logs", get(admin_get_proxy_logs_filtered))
             .route("/logs/count", get(admin_get_proxy_logs_count_filtered))
             .route("/logs/clear", post(admin_clear_proxy_logs))
+            .route("/logs/search", get(admin_search_proxy_logs))
             .route("/logs/:logId", get(admin_get_proxy_log_detail))
             .route("/stats/token/clear", post(admin_clear_token_stats))
             .route("/stats/token/hourly", get(admin_get_token_stats_hourly))
@@ -499,6 +579,10 @@ impl AxumServer {
             )
             .route("/stats/token/summary", get(admin_get_token_stats_summary))
             .route("/stats/token/by-model", get(admin_get_token_stats_by_model))
+            .route(
+                "/stats/token/by-client-key",
+                get(admin_get_token_stats_by_client_key),
+            )
             .route(
                 "/stats/token/model-trend/hourly",
                 get(admin_get_token_stats_model_trend_hourly),
@@ -515,8 +599,17 @@ impl AxumServer {
                 "/stats/token/account-trend/daily",
                 get(admin_get_token_stats_account_trend_daily),
             )
+            .route("/stats/quota/history", get(admin_get_quota_history)) // [NEW] 配额时间序列
+            .route("/stats/quota/forecast", get(admin_get_quota_forecast))
+            .route("/stats/quota/clear", post(admin_clear_quota_history))
             .route("/accounts/bulk-delete", post(admin_delete_accounts))
-            .route("/accounts/export", post(admin_export_accounts))
+            .route("/accounts/export/bundle", post(admin_export_accounts_bundle))
+            .route("/accounts/import/bundle", post(admin_import_accounts_bundle))
+            .route("/vault/status", get(admin_get_vault_status))
+            .route("/vault/enable", post(admin_enable_vault))
+            .route("/vault/unlock", post(admin_unlock_vault))
+            .route("/vault/lock", post(admin_lock_vault))
+            .route("/vault/disable", post(admin_disable_vault))
             .route("/accounts/reorder", post(admin_reorder_accounts))
             .route("/accounts/:accountId/quota", get(admin_fetch_account_quota))
             .route(
@@ -548,7 +641,13 @@ impl AxumServer {
             .route("/system/antigravity/args", get(admin_get_antigravity_args))
             // OAuth (Web) - Admin 接口
             .route("/auth/url", get(admin_prepare_oauth_url_web))
-            // 应用管理特定鉴权层 (强制校验)
+            // [NEW] 管理员会话 (与客户端 API Key 分离)
+            .route("/auth/login", post(admin_login))
+            .route("/auth/logout", post(admin_logout))
+            .route("/auth/session", get(admin_get_session))
+            .route("/auth/password", post(admin_change_password))
+            .route("/audit-log", get(admin_get_audit_log))
+            // 应用管理特定鉴权层 (管理员会话 + CSRF + 审计)
             .layer(axum::middleware::from_fn_with_state(
                 state.clone(),
                 admin_auth_middleware,
@@ -565,6 +664,7 @@ impl AxumServer {
         let app = Router::new()
             .nest("/api", admin_routes)
             .merge(proxy_routes)
+            .merge(metrics_routes)
             // 公开路由 (无需鉴权)
             .route("/auth/callback", get(handle_oauth_callback))
             // 应用全局监控与状态层 (外层)
@@ -610,6 +710,7 @@ impl AxumServer {
             cloudflared_state,
             is_running: is_running_state,
             token_manager: token_manager.clone(),
+            mcp_gateway: mcp_gateway_state,
         };
 
         // 在新任务中启动服务器
@@ -685,6 +786,20 @@ async fn health_check_handler() -> Response {
     .into_response()
 }
 
+/// Prometheus 指标端点 (text exposition format 0.0.4)
+async fn metrics_handler(State(state): State<AppState>) -> Response {
+    let accounts = state.token_manager.account_metrics();
+    let body = crate::proxy::metrics::render(&state.monitor.metrics, &accounts);
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        body,
+    )
+        .into_response()
+}
+
 /// 静默成功处理器 (用于拦截遥测日志等)
 async fn silent_ok_handler() -> Response {
     StatusCode::OK.into_response()
@@ -752,25 +867,176 @@ async fn admin_list_accounts(
     }))
 }
 
-/// Export accounts with refresh tokens (for backup/migration)
+/// 口令保护的导出包 (refresh_token 不以明文出现在导出文件中)
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct ExportAccountsRequest {
+struct ExportBundleRequest {
     account_ids: Vec<String>,
+    password: String,
 }
 
-async fn admin_export_accounts(
-    State(_state): State<AppState>,
-    Json(payload): Json<ExportAccountsRequest>,
+async fn admin_export_accounts_bundle(
+    Json(payload): Json<ExportBundleRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let response = account::export_accounts_by_ids(&payload.account_ids).map_err(|e| {
+    let export = account::export_accounts_by_ids(&payload.account_ids).map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse { error: e }),
         )
     })?;
+    // PBKDF2 派生在阻塞线程池中执行
+    let bundle = tokio::task::spawn_blocking(move || vault::seal_bundle(&export, &payload.password))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e.to_string() })))?
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    Ok(Json(bundle))
+}
 
-    Ok(Json(response))
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportBundleRequest {
+    bundle: serde_json::Value,
+    password: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportBundleResponse {
+    imported: Vec<AccountResponse>,
+    failed: Vec<ImportBundleFailure>,
+}
+
+#[derive(Serialize)]
+struct ImportBundleFailure {
+    email: String,
+    error: String,
+}
+
+async fn admin_import_accounts_bundle(
+    State(state): State<AppState>,
+    Json(payload): Json<ImportBundleRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let export = tokio::task::spawn_blocking(move || vault::open_bundle(&payload.bundle, &payload.password))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e.to_string() })))?
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+
+    let mut imported = Vec::new();
+    let mut failed = Vec::new();
+    for item in export.accounts {
+        let result = match item.provider.as_str() {
+            "kiro" => {
+                state
+                    .account_service
+                    .add_kiro_account(
+                        &item.email,
+                        &item.refresh_token,
+                        item.kiro_profile_arn.as_deref(),
+                        item.kiro_user_id.as_deref(),
+                    )
+                    .await
+            }
+            _ => state.account_service.add_account(&item.refresh_token).await,
+        };
+        match result {
+            Ok(account) => imported.push(account),
+            Err(error) => failed.push(ImportBundleFailure {
+                email: item.email,
+                error,
+            }),
+        }
+    }
+
+    let _ = state.token_manager.load_accounts().await;
+
+    let current_id = state.account_service.get_current_id().map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    Ok(Json(ImportBundleResponse {
+        imported: imported
+            .iter()
+            .map(|a| to_account_response(a, &current_id))
+            .collect(),
+        failed,
+    }))
+}
+
+async fn admin_get_vault_status() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    vault::status().map(Json).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VaultChangeResponse {
+    accounts_rewritten: usize,
+    status: vault::VaultStatus,
+}
+
+/// 在阻塞线程池中执行保险库操作 (PBKDF2 派生与账号文件重新加密)
+async fn run_vault_op<F>(op: F) -> Result<usize, String>
+where
+    F: FnOnce() -> Result<usize, String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(op)
+        .await
+        .map_err(|e| format!("保险库任务失败: {}", e))?
+}
+
+/// 保险库状态变化后重新加载账号池，并返回最新状态
+async fn vault_change_response(
+    state: &AppState,
+    result: Result<usize, String>,
+) -> Result<Json<VaultChangeResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let accounts_rewritten =
+        result.map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    if let Err(e) = state.token_manager.load_accounts().await {
+        logger::log_error(&format!("[Vault] Failed to reload accounts: {}", e));
+    }
+    let status = vault::status().map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    Ok(Json(VaultChangeResponse {
+        accounts_rewritten,
+        status,
+    }))
+}
+
+async fn admin_enable_vault(
+    State(state): State<AppState>,
+    Json(credential): Json<vault::VaultCredential>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    vault_change_response(&state, run_vault_op(move || vault::enable(&credential)).await).await
+}
+
+async fn admin_unlock_vault(
+    State(state): State<AppState>,
+    Json(credential): Json<vault::VaultCredential>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    vault_change_response(&state, run_vault_op(move || vault::unlock(&credential)).await).await
+}
+
+async fn admin_lock_vault(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    vault::lock();
+    vault_change_response(&state, Ok(0)).await
+}
+
+async fn admin_disable_vault(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    vault_change_response(&state, run_vault_op(vault::disable).await).await
 }
 
 async fn admin_get_current_account(
@@ -1142,8 +1408,16 @@ async fn admin_save_config(
     State(state): State<AppState>,
     Json(payload): Json<SaveConfigWrapper>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let new_config = payload.config;
-    // 1. 持久化
+    let mut new_config = payload.config;
+    // 1. 持久化 (明文管理员密码先替换为哈希)
+    admin_auth::normalize_admin_password(&mut new_config.proxy).map_err(|e| {
+        (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e }))
+    })?;
+    new_config.proxy.model_fallback.validate()
+        .and_then(|_| new_config.proxy.model_router.validate())
+        .and_then(|_| new_config.proxy.response_cache.validate())
+        .and_then(|_| new_config.proxy.monitor_policy.validate())
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
     config::save_app_config(&new_config).map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -1170,6 +1444,10 @@ async fn admin_save_config(
         let mut proxy = state.upstream_proxy.write().await;
         *proxy = new_config.clone().proxy.upstream_proxy;
     }
+    crate::proxy::upstream::proxy_pool::pool().configure(&new_config.proxy.account_proxy);
+    crate::proxy::model_router::router().configure(&new_config.proxy.model_router);
+    crate::proxy::response_cache::cache().configure(&new_config.proxy.response_cache);
+    crate::proxy::monitor_policy::policy().configure(&new_config.proxy.monitor_policy);
 
     // 更新安全策略
     {
@@ -1183,15 +1461,197 @@ async fn admin_save_config(
         *zai = new_config.clone().proxy.zai;
     }
 
+    // 更新 MCP 网关
+    state
+        .mcp_gateway
+        .update_config(new_config.proxy.mcp_gateway.clone())
+        .await;
+
     // 更新实验性配置
     {
         let mut exp = state.experimental.write().await;
         *exp = new_config.clone().proxy.experimental;
     }
 
+    // 更新路由策略
+    state
+        .token_manager
+        .update_routing_config(new_config.proxy.routing.clone())
+        .await;
+
+    // 更新模型降级链
+    state
+        .token_manager
+        .update_model_fallback_config(new_config.proxy.model_fallback.clone())
+        .await;
+
     Ok(StatusCode::OK)
 }
 
+// ===== [NEW] 管理端认证 =====
+
+#[derive(Deserialize)]
+struct AdminLoginRequest {
+    password: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AdminChangePasswordRequest {
+    current_password: String,
+    new_password: String,
+}
+
+#[derive(Deserialize)]
+struct AuditLogQuery {
+    limit: Option<usize>,
+    offset: Option<usize>,
+    action: Option<String>,
+}
+
+fn auth_error(status: StatusCode, error: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+        }),
+    )
+}
+
+/// 将新的管理员密码哈希写入配置文件与内存中的安全配置
+async fn persist_admin_password_hash(state: &AppState, hash: String) -> Result<(), String> {
+    let mut cfg = config::load_app_config()?;
+    cfg.proxy.admin_password = Some(hash.clone());
+    config::save_app_config(&cfg)?;
+    state.security.write().await.admin_password = Some(hash);
+    Ok(())
+}
+
+async fn admin_login(
+    State(state): State<AppState>,
+    parts: axum::http::request::Parts,
+    Json(payload): Json<AdminLoginRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let now = chrono::Utc::now().timestamp();
+    // 限流按 TCP 对端计，转发头仅在对端是受信任代理时采信 (避免伪造 X-Forwarded-For 绕过)
+    let trusted_proxies = state.security.read().await.trusted_proxies.clone();
+    let client_ip = peer_ip_from_parts(&parts.headers, &parts.extensions, &trusted_proxies);
+    let limiter_key = client_ip.clone().unwrap_or_else(|| "unknown".to_string());
+    let sessions = admin_auth::sessions();
+
+    let stored = state
+        .security
+        .read()
+        .await
+        .admin_password
+        .clone()
+        .filter(|p| !p.is_empty());
+    let Some(stored) = stored else {
+        return Err(auth_error(
+            StatusCode::FORBIDDEN,
+            "Admin password is not configured. Set DROIDGRAVITY_ADMIN_PASSWORD and restart the app",
+        ));
+    };
+
+    // 先原子占位再校验，占位按失败计入，成功后释放
+    if !sessions.reserve_login_attempt(&limiter_key, now) {
+        return Err(auth_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many failed login attempts, try again later",
+        ));
+    }
+
+    if !admin_auth::verify_password_async(stored.clone(), payload.password.clone()).await {
+        admin_auth::audit("anonymous", "auth.login_failed", None, 401, client_ip);
+        return Err(auth_error(StatusCode::UNAUTHORIZED, "Invalid admin password"));
+    }
+    sessions.clear_login_failures(&limiter_key);
+
+    // 旧版明文密码: 登录成功后升级为哈希存储
+    if !admin_auth::is_password_hash(&stored) {
+        let upgraded = match admin_auth::hash_password_async(payload.password.clone()).await {
+            Ok(hash) => persist_admin_password_hash(&state, hash).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = upgraded {
+            tracing::warn!("[AdminAuth] 旧版明文管理员密码升级失败: {}", e);
+        }
+    }
+
+    let (token, session) = sessions
+        .create(client_ip.clone(), now)
+        .map_err(|e| auth_error(StatusCode::INTERNAL_SERVER_ERROR, &e))?;
+    admin_auth::audit(&session.id, "auth.login", None, 200, client_ip);
+
+    Ok((
+        [(
+            axum::http::header::SET_COOKIE,
+            admin_auth::session_cookie(&token, admin_auth::SESSION_TTL_SECS),
+        )],
+        Json(serde_json::json!({
+            "token": token,
+            "csrfToken": session.csrf_token,
+            "expiresAt": session.expires_at,
+        })),
+    ))
+}
+
+async fn admin_logout(
+    axum::Extension(admin): axum::Extension<admin_auth::AuthenticatedAdmin>,
+) -> impl IntoResponse {
+    admin_auth::sessions().revoke(&admin.token);
+    (
+        [(axum::http::header::SET_COOKIE, admin_auth::session_cookie("", 0))],
+        StatusCode::OK,
+    )
+}
+
+async fn admin_get_session(
+    axum::Extension(admin): axum::Extension<admin_auth::AuthenticatedAdmin>,
+) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "sessionId": admin.session.id,
+        "csrfToken": admin.session.csrf_token,
+        "createdAt": admin.session.created_at,
+        "expiresAt": admin.session.expires_at,
+        "clientIp": admin.session.client_ip,
+    }))
+}
+
+/// 修改管理员密码，成功后所有会话失效需重新登录
+async fn admin_change_password(
+    State(state): State<AppState>,
+    Json(payload): Json<AdminChangePasswordRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let stored = state.security.read().await.admin_password.clone().unwrap_or_default();
+    if !admin_auth::verify_password_async(stored, payload.current_password).await {
+        return Err(auth_error(StatusCode::UNAUTHORIZED, "Current password is incorrect"));
+    }
+    let hash = admin_auth::hash_password_async(payload.new_password)
+        .await
+        .map_err(|e| auth_error(StatusCode::BAD_REQUEST, &e))?;
+    persist_admin_password_hash(&state, hash)
+        .await
+        .map_err(|e| auth_error(StatusCode::INTERNAL_SERVER_ERROR, &e))?;
+    admin_auth::sessions().revoke_all();
+    Ok(StatusCode::OK)
+}
+
+async fn admin_get_audit_log(
+    Query(params): Query<AuditLogQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let limit = params.limit.unwrap_or(100).min(1000);
+    let offset = params.offset.unwrap_or(0);
+    let entries = tokio::task::spawn_blocking(move || {
+        let db_path = audit_db::get_audit_db_path(&account::get_data_dir()?);
+        audit_db::list(&db_path, limit, offset, params.action.as_deref())
+    })
+    .await
+    .map_err(|e| auth_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?
+    .map_err(|e| auth_error(StatusCode::INTERNAL_SERVER_ERROR, &e))?;
+    Ok(Json(entries))
+}
+
 async fn admin_get_proxy_status(
     State(state): State<AppState>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
@@ -1283,6 +1743,225 @@ async fn admin_generate_api_key() -> impl IntoResponse {
     Json(new_key)
 }
 
+// --- Client API Key Handlers ---
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientKeyResponse {
+    id: String,
+    name: String,
+    key: String,
+    allowed_models: Vec<String>,
+    daily_token_limit: Option<u64>,
+    daily_request_limit: Option<u64>,
+    expires_at: Option<i64>,
+    revoked: bool,
+    created_at: i64,
+    today_requests: u64,
+    today_tokens: u64,
+}
+
+impl ClientKeyResponse {
+    /// 附带当日用量 (同步读取 token_stats.db，需在 spawn_blocking 中调用)
+    fn with_today_usage(key: crate::proxy::config::ClientApiKey) -> Self {
+        let (today_requests, today_tokens) =
+            token_stats::get_client_key_daily_usage(&key.id).unwrap_or((0, 0));
+        Self {
+            id: key.id,
+            name: key.name,
+            key: key.key,
+            allowed_models: key.allowed_models,
+            daily_token_limit: key.daily_token_limit,
+            daily_request_limit: key.daily_request_limit,
+            expires_at: key.expires_at,
+            revoked: key.revoked,
+            created_at: key.created_at,
+            today_requests,
+            today_tokens,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientKeyRequest {
+    name: String,
+    #[serde(default)]
+    allowed_models: Vec<String>,
+    #[serde(default)]
+    daily_token_limit: Option<u64>,
+    #[serde(default)]
+    daily_request_limit: Option<u64>,
+    #[serde(default)]
+    expires_at: Option<i64>,
+    #[serde(default)]
+    revoked: Option<bool>,
+}
+
+impl ClientKeyRequest {
+    fn apply_to(self, key: &mut crate::proxy::config::ClientApiKey) {
+        key.name = self.name;
+        key.allowed_models = self.allowed_models;
+        key.daily_token_limit = self.daily_token_limit;
+        key.daily_request_limit = self.daily_request_limit;
+        key.expires_at = self.expires_at;
+        if let Some(revoked) = self.revoked {
+            key.revoked = revoked;
+        }
+    }
+}
+
+/// 修改客户端 API Key 列表：持久化到配置文件并热更新安全配置
+async fn update_client_keys<T>(
+    state: &AppState,
+    mutate: impl FnOnce(&mut Vec<crate::proxy::config::ClientApiKey>) -> Result<T, (StatusCode, String)>,
+) -> Result<T, (StatusCode, Json<ErrorResponse>)> {
+    let mut app_config = config::load_app_config().map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+
+    let result = mutate(&mut app_config.proxy.client_keys)
+        .map_err(|(status, error)| (status, Json(ErrorResponse { error })))?;
+
+    config::save_app_config(&app_config).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+
+    {
+        let mut security = state.security.write().await;
+        security.client_keys = app_config.proxy.client_keys.clone();
+    }
+
+    Ok(result)
+}
+
+async fn client_key_response(
+    key: crate::proxy::config::ClientApiKey,
+) -> Result<Json<ClientKeyResponse>, (StatusCode, Json<ErrorResponse>)> {
+    tokio::task::spawn_blocking(move || ClientKeyResponse::with_today_usage(key))
+        .await
+        .map(Json)
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })
+}
+
+async fn admin_list_client_keys(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let keys = state.security.read().await.client_keys.clone();
+    let res = tokio::task::spawn_blocking(move || {
+        keys.into_iter()
+            .map(ClientKeyResponse::with_today_usage)
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    Ok(Json(res))
+}
+
+async fn admin_create_client_key(
+    State(state): State<AppState>,
+    Json(payload): Json<ClientKeyRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if payload.name.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Client key name must not be empty".to_string(),
+            }),
+        ));
+    }
+
+    let key = update_client_keys(&state, |keys| {
+        let mut key = crate::proxy::config::ClientApiKey::new(String::new());
+        payload.apply_to(&mut key);
+        keys.push(key.clone());
+        Ok(key)
+    })
+    .await?;
+
+    logger::log_info(&format!("[API] 已创建客户端 API Key: {}", key.name));
+    client_key_response(key).await
+}
+
+async fn admin_update_client_key(
+    State(state): State<AppState>,
+    Path(key_id): Path<String>,
+    Json(payload): Json<ClientKeyRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let key = update_client_keys(&state, |keys| {
+        let key = keys.iter_mut().find(|k| k.id == key_id).ok_or((
+            StatusCode::NOT_FOUND,
+            format!("Client key {} not found", key_id),
+        ))?;
+        payload.apply_to(key);
+        Ok(key.clone())
+    })
+    .await?;
+
+    logger::log_info(&format!("[API] 已更新客户端 API Key: {}", key.name));
+    client_key_response(key).await
+}
+
+async fn admin_revoke_client_key(
+    State(state): State<AppState>,
+    Path(key_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let name = update_client_keys(&state, |keys| {
+        let key = keys.iter_mut().find(|k| k.id == key_id).ok_or((
+            StatusCode::NOT_FOUND,
+            format!("Client key {} not found", key_id),
+        ))?;
+        key.revoked = true;
+        Ok(key.name.clone())
+    })
+    .await?;
+
+    logger::log_info(&format!("[API] 已吊销客户端 API Key: {}", name));
+    Ok(StatusCode::OK)
+}
+
+async fn admin_delete_client_key(
+    State(state): State<AppState>,
+    Path(key_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    update_client_keys(&state, |keys| {
+        let before = keys.len();
+        keys.retain(|k| k.id != key_id);
+        if keys.len() == before {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("Client key {} not found", key_id),
+            ));
+        }
+        Ok(())
+    })
+    .await?;
+
+    logger::log_info(&format!("[API] 已删除客户端 API Key: {}", key_id));
+    Ok(StatusCode::NO_CONTENT)
+}
+
 async fn admin_clear_proxy_session_bindings(State(state): State<AppState>) -> impl IntoResponse {
     state.token_manager.clear_all_sessions();
     logger::log_info("[API] 已清除所有会话绑定");
@@ -1291,6 +1970,7 @@ async fn admin_clear_proxy_session_bindings(State(state): State<AppState>) -> im
 
 async fn admin_clear_all_rate_limits(State(state): State<AppState>) -> impl IntoResponse {
     state.token_manager.clear_all_rate_limits();
+    let _ = state.token_manager.persist_limit_state().await;
     logger::log_info("[API] 已清除所有限流记录");
     StatusCode::OK
 }
@@ -1301,6 +1981,7 @@ async fn admin_clear_rate_limit(
 ) -> impl IntoResponse {
     let cleared = state.token_manager.clear_rate_limit(&account_id);
     if cleared {
+        let _ = state.token_manager.persist_limit_state().await;
         logger::log_info(&format!("[API] 已清除账号 {} 的限流记录", account_id));
         StatusCode::OK
     } else {
@@ -1308,6 +1989,145 @@ async fn admin_clear_rate_limit(
     }
 }
 
+async fn admin_get_mcp_gateway_status(State(state): State<AppState>) -> impl IntoResponse {
+    Json(serde_json::json!({ "servers": state.mcp_gateway.status().await }))
+}
+
+async fn admin_get_limit_state(State(state): State<AppState>) -> impl IntoResponse {
+    let limit_state = state.token_manager.limit_state();
+    let now = chrono::Utc::now().timestamp();
+    let lockouts: Vec<serde_json::Value> = limit_state
+        .lockouts
+        .iter()
+        .map(|l| {
+            let mut v = serde_json::to_value(l).unwrap_or_default();
+            v["remaining_seconds"] = serde_json::json!((l.reset_time - now).max(0));
+            v
+        })
+        .collect();
+    Json(serde_json::json!({
+        "lockouts": lockouts,
+        "failure_counts": limit_state.failure_counts,
+        "health_scores": limit_state.health_scores,
+        "accounts": state.token_manager.account_emails(),
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetLockoutRequest {
+    account_id: String,
+    model: Option<String>,
+    reason: Option<String>,
+    /// 解锁时间 (Unix 秒)，与 seconds 二选一
+    reset_time: Option<i64>,
+    seconds: Option<i64>,
+}
+
+/// 手动锁定的最长时长 (30 天)
+const MAX_MANUAL_LOCKOUT_SECS: i64 = 30 * 24 * 3600;
+
+/// 计算手动锁定的解除时间: `resetTime` 优先，其次 `seconds`，必须位于 (now, now + 30 天] 之内
+fn lockout_reset_time(
+    now: i64,
+    reset_time: Option<i64>,
+    seconds: Option<i64>,
+) -> Result<i64, &'static str> {
+    let reset_time = match (reset_time, seconds) {
+        (Some(t), _) => t,
+        (None, Some(s)) if s <= 0 => return Err("seconds must be positive"),
+        (None, Some(s)) => now.checked_add(s).ok_or("seconds is too large")?,
+        (None, None) => return Err("resetTime or seconds is required"),
+    };
+    if reset_time <= now {
+        return Err("resetTime or seconds must point to the future");
+    }
+    if reset_time - now > MAX_MANUAL_LOCKOUT_SECS {
+        return Err("lockout cannot exceed 30 days");
+    }
+    Ok(reset_time)
+}
+
+async fn admin_set_lockout(
+    State(state): State<AppState>,
+    Json(payload): Json<SetLockoutRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let now = chrono::Utc::now().timestamp();
+    let reset_time = lockout_reset_time(now, payload.reset_time, payload.seconds).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: e.to_string() }),
+        )
+    })?;
+    let reason = crate::proxy::rate_limit::RateLimitReason::from_str_lossy(
+        payload.reason.as_deref().unwrap_or("unknown"),
+    );
+    let model = payload.model.filter(|m| !m.is_empty());
+
+    state
+        .token_manager
+        .set_rate_limit_lockout(&payload.account_id, model, reason, reset_time);
+    state.token_manager.persist_limit_state().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    logger::log_info(&format!("[API] 已手动锁定账号 {}", payload.account_id));
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteLockoutQuery {
+    account_id: String,
+    model: Option<String>,
+}
+
+async fn admin_delete_lockout(
+    State(state): State<AppState>,
+    Query(q): Query<DeleteLockoutQuery>,
+) -> impl IntoResponse {
+    let model = q.model.as_deref().filter(|m| !m.is_empty());
+    if !state.token_manager.clear_rate_limit_scope(&q.account_id, model) {
+        return StatusCode::NOT_FOUND;
+    }
+    let _ = state.token_manager.persist_limit_state().await;
+    logger::log_info(&format!(
+        "[API] 已清除账号 {} 的锁定 ({})",
+        q.account_id,
+        model.unwrap_or("account")
+    ));
+    StatusCode::OK
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateAccountLimitStateRequest {
+    failure_count: Option<u32>,
+    health_score: Option<f32>,
+}
+
+async fn admin_update_account_limit_state(
+    State(state): State<AppState>,
+    Path(account_id): Path<String>,
+    Json(payload): Json<UpdateAccountLimitStateRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if let Some(count) = payload.failure_count {
+        state.token_manager.set_failure_count(&account_id, count);
+    }
+    if let Some(score) = payload.health_score {
+        state.token_manager.set_health_score(&account_id, Some(score));
+    }
+    state.token_manager.persist_limit_state().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    Ok(StatusCode::OK)
+}
+
 async fn admin_get_preferred_account(State(state): State<AppState>) -> impl IntoResponse {
     let pref = state.token_manager.get_preferred_account().await;
     Json(pref)
@@ -1484,6 +2304,44 @@ struct LogsFilterQuery {
     offset: usize,
 }
 
+#[derive(Deserialize, Debug, Default)]
+struct LogsSearchQuery {
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    limit: usize,
+    #[serde(default)]
+    offset: usize,
+}
+
+/// [NEW] 全文检索日志: GET /api/logs/search?q=...&limit=&offset=
+async fn admin_search_proxy_logs(
+    Query(params): Query<LogsSearchQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let query = crate::modules::log_search::parse_query(&params.q, chrono::Utc::now().timestamp_millis())
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    let limit = if params.limit == 0 { 50 } else { params.limit.min(500) };
+
+    let res = tokio::task::spawn_blocking(move || {
+        crate::modules::proxy_db::search_logs(&query, limit, params.offset)
+    })
+    .await;
+
+    match res {
+        Ok(Ok(result)) => Ok(Json(result)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
 async fn admin_get_proxy_logs_filtered(
     Query(params): Query<LogsFilterQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
@@ -1697,6 +2555,27 @@ async fn admin_get_token_stats_by_model(
     }
 }
 
+async fn admin_get_token_stats_by_client_key(
+    Query(p): Query<StatsPeriodQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let days = p.days.unwrap_or(7);
+    let res = tokio::task::spawn_blocking(move || token_stats::get_client_key_stats(days)).await;
+
+    match res {
+        Ok(Ok(stats)) => Ok(Json(stats)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
 async fn admin_get_token_stats_model_trend_hourly(
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     let res = tokio::task::spawn_blocking(|| {
@@ -1785,6 +2664,70 @@ async fn admin_get_token_stats_account_trend_daily(
     }
 }
 
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct QuotaHistoryQuery {
+    hours: Option<i64>,
+    account_id: Option<String>,
+    model: Option<String>,
+}
+
+async fn admin_get_quota_history(
+    Query(p): Query<QuotaHistoryQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let hours = p.hours.unwrap_or(24);
+    let res = tokio::task::spawn_blocking(move || {
+        quota_history::get_history(hours, p.account_id.as_deref(), p.model.as_deref())
+    })
+    .await;
+
+    match res {
+        Ok(Ok(history)) => Ok(Json(history)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+async fn admin_get_quota_forecast(
+    Query(p): Query<StatsPeriodQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    // 消耗速率的统计窗口，默认最近 6 小时
+    let hours = p.hours.unwrap_or(6);
+    let res = tokio::task::spawn_blocking(move || quota_history::get_forecast(hours)).await;
+
+    match res {
+        Ok(Ok(forecast)) => Ok(Json(forecast)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+async fn admin_clear_quota_history() -> impl IntoResponse {
+    match tokio::task::spawn_blocking(quota_history::clear).await {
+        Ok(Ok(())) => {
+            logger::log_info("[API] 已清除配额历史");
+            StatusCode::OK
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
 async fn admin_clear_token_stats() -> impl IntoResponse {
     let res = tokio::task::spawn_blocking(|| {
         // Clear databases (brute force)
@@ -2183,6 +3126,46 @@ async fn admin_cloudflared_stop(
     }
 }
 
+#[derive(Deserialize)]
+struct CloudflaredCreateTunnelRequest {
+    name: String,
+    #[serde(default)]
+    hostname: Option<String>,
+}
+
+async fn admin_cloudflared_create_tunnel(
+    State(state): State<AppState>,
+    Json(payload): Json<CloudflaredCreateTunnelRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let to_err = |e: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e }));
+    state.cloudflared_state.ensure_manager().await.map_err(to_err)?;
+
+    let lock = state.cloudflared_state.manager.read().await;
+    let manager = lock
+        .as_ref()
+        .ok_or_else(|| to_err("Manager not initialized".to_string()))?;
+    let info = manager.create_named_tunnel(&payload.name).await.map_err(to_err)?;
+    if let Some(hostname) = payload.hostname.filter(|h| !h.trim().is_empty()) {
+        manager.route_dns(&payload.name, &hostname).await.map_err(to_err)?;
+    }
+    Ok(Json(info))
+}
+
+async fn admin_cloudflared_delete_tunnel(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let to_err = |e: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e }));
+    state.cloudflared_state.ensure_manager().await.map_err(to_err)?;
+
+    let lock = state.cloudflared_state.manager.read().await;
+    let manager = lock
+        .as_ref()
+        .ok_or_else(|| to_err("Manager not initialized".to_string()))?;
+    manager.delete_named_tunnel(&name).await.map_err(to_err)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 // --- Supplementary Account Handlers ---
 
 async fn admin_get_device_profiles(
@@ -2435,9 +3418,16 @@ struct CliSyncRequest {
 }
 
 async fn admin_execute_cli_sync(
+    State(state): State<AppState>,
     Json(payload): Json<CliSyncRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    crate::proxy::cli_sync::execute_cli_sync(payload.app_type, payload.proxy_url, payload.api_key)
+    // Droid 的 customModels 需要与当前可用模型列表保持一致
+    let models = if payload.app_type == crate::proxy::cli_sync::CliApp::Droid {
+        crate::proxy::common::model_mapping::get_all_dynamic_models(&state.custom_mapping).await
+    } else {
+        Vec::new()
+    };
+    crate::proxy::cli_sync::execute_cli_sync(payload.app_type, payload.proxy_url, payload.api_key, models)
         .await
         .map(|_| StatusCode::OK)
         .map_err(|e| {
@@ -2701,6 +3691,33 @@ async fn admin_prepare_oauth_url_web(
     })))
 }
 
+async fn admin_get_account_proxies() -> impl IntoResponse {
+    Json(crate::proxy::upstream::proxy_pool::pool().statuses())
+}
+
+/// 立即探测所有账号代理 (不等待后台检查间隔)
+async fn admin_check_account_proxies() -> impl IntoResponse {
+    let pool = crate::proxy::upstream::proxy_pool::pool();
+    pool.check_all().await;
+    Json(pool.statuses())
+}
+
+async fn admin_get_response_cache_stats() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::proxy::response_cache::cache()
+        .stats()
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))
+}
+
+async fn admin_clear_response_cache() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let deleted = crate::proxy::response_cache::cache()
+        .clear()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+    Ok(Json(serde_json::json!({ "deleted": deleted })))
+}
+
 #[derive(Deserialize)]
 struct AbortRequest {
     session_id: String,
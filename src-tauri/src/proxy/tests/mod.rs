@@ -1 +1,2 @@
 pub mod comprehensive;
+pub mod replay;
@@ -0,0 +1,237 @@
+// Cassette 回放测试
+//
+// 每个 cassettes/*.json 是 debug_logger 录制的 upstream_response 文件 (可手工精简)，
+// 再加上一个 `expect` 段。测试启动本地 mock 上游按录制的分块原样下发，
+// 通过真实 handler 处理客户端请求，然后断言下游输出。
+// 线上复现的问题把录制文件放进 cassettes/ 并写上 expect，即成为永久回归用例。
+#[cfg(test)]
+mod tests {
+    use crate::proxy::debug_logger::{Cassette, CassetteUpstream};
+    use crate::proxy::server::AppState;
+    use crate::proxy::TokenManager;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::RwLock;
+    use tower::ServiceExt;
+
+    #[derive(Debug, Deserialize)]
+    struct CassetteCase {
+        #[serde(flatten)]
+        cassette: Cassette,
+        #[serde(default)]
+        expect: Expectation,
+    }
+
+    #[derive(Debug, Default, Deserialize)]
+    struct Expectation {
+        /// 下游 HTTP 状态码 (默认 200)
+        #[serde(default)]
+        status: Option<u16>,
+        /// 下游输出必须包含的片段
+        #[serde(default)]
+        contains: Vec<String>,
+        /// 下游输出不得包含的片段
+        #[serde(default)]
+        not_contains: Vec<String>,
+        /// 必须按顺序出现的 SSE `event:` 名称 (允许中间穿插其他事件)
+        #[serde(default)]
+        events: Vec<String>,
+    }
+
+    fn cassette_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/proxy/tests/cassettes")
+    }
+
+    /// 启动 mock 上游: 任意请求都返回录制的状态码与分块，记录收到的请求体
+    async fn start_mock_upstream(upstream: CassetteUpstream) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let received_clone = received.clone();
+        let app = Router::new().fallback(move |body: axum::body::Bytes| {
+            let upstream = upstream.clone();
+            let received = received_clone.clone();
+            async move {
+                if let Ok(v) = serde_json::from_slice::<Value>(&body) {
+                    received.lock().unwrap().push(v);
+                }
+                let chunks = upstream
+                    .chunk_bytes()
+                    .expect("cassette chunks should decode")
+                    .into_iter()
+                    .map(Ok::<_, std::io::Error>);
+                axum::response::Response::builder()
+                    .status(upstream.status)
+                    .header("content-type", upstream.content_type)
+                    .body(Body::from_stream(futures::stream::iter(chunks)))
+                    .unwrap()
+            }
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        (format!("http://{}/v1internal", addr), received)
+    }
+
+    /// 在临时目录中准备一个不需要刷新的假账号
+    async fn fake_token_manager() -> Arc<TokenManager> {
+        let data_dir = std::env::temp_dir().join(format!("droidgravity-replay-{}", uuid::Uuid::new_v4()));
+        let accounts_dir = data_dir.join("accounts");
+        std::fs::create_dir_all(&accounts_dir).unwrap();
+        let account = json!({
+            "id": "replay-account",
+            "email": "replay@example.com",
+            "provider": "gemini",
+            "token": {
+                "access_token": "replay-access-token",
+                "refresh_token": "replay-refresh-token",
+                "expires_in": 3600,
+                "expiry_timestamp": chrono::Utc::now().timestamp() + 86400,
+                "project_id": "replay-project"
+            }
+        });
+        std::fs::write(accounts_dir.join("replay-account.json"), account.to_string()).unwrap();
+
+        let token_manager = Arc::new(TokenManager::new(data_dir));
+        assert_eq!(token_manager.load_accounts().await.unwrap(), 1);
+        token_manager
+    }
+
+    async fn replay_state(upstream_base: String) -> AppState {
+        let integration = crate::modules::integration::SystemManager::Headless;
+        let proxy_config = crate::proxy::ProxyConfig::default();
+        AppState {
+            token_manager: fake_token_manager().await,
+            custom_mapping: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            request_timeout: 300,
+            thought_signature_map: Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
+            upstream_proxy: Arc::new(RwLock::new(proxy_config.upstream_proxy.clone())),
+            upstream: Arc::new(
+                crate::proxy::upstream::client::UpstreamClient::new(None).with_base_urls(vec![upstream_base]),
+            ),
+            zai: Arc::new(RwLock::new(proxy_config.zai.clone())),
+            provider_rr: Arc::new(AtomicUsize::new(0)),
+            zai_vision_mcp: Arc::new(crate::proxy::zai_vision_mcp::ZaiVisionMcpState::new()),
+            mcp_gateway: Arc::new(crate::proxy::mcp_gateway::McpGateway::default()),
+            monitor: Arc::new(crate::proxy::monitor::ProxyMonitor::new(16, None)),
+            experimental: Arc::new(RwLock::new(proxy_config.experimental.clone())),
+            debug_logging: Arc::new(RwLock::new(Default::default())),
+            switching: Arc::new(RwLock::new(false)),
+            integration: integration.clone(),
+            account_service: Arc::new(crate::modules::account_service::AccountService::new(integration)),
+            security: Arc::new(RwLock::new(crate::proxy::ProxySecurityConfig::from_proxy_config(&proxy_config))),
+            cloudflared_state: Arc::new(crate::commands::cloudflared::CloudflaredState::new()),
+            is_running: Arc::new(RwLock::new(true)),
+            port: 0,
+            abort_tokens: Arc::new(dashmap::DashMap::new()),
+        }
+    }
+
+    /// 按 cassette 的协议把原始请求发给对应 handler
+    fn client_request(cassette: &Cassette) -> Request<Body> {
+        let body = Body::from(cassette.request.to_string());
+        // meta.path 可显式指定端点 (例如 /v1/embeddings)
+        if let Some(path) = cassette.meta.get("path").and_then(|v| v.as_str()) {
+            return Request::post(path)
+                .header("content-type", "application/json")
+                .body(body)
+                .unwrap();
+        }
+        let uri = match cassette.protocol() {
+            "openai" => "/v1/chat/completions".to_string(),
+            "gemini" => {
+                let model = cassette.meta.get("original_model").and_then(|v| v.as_str()).unwrap_or("gemini-3-flash");
+                let method = cassette.meta.get("method").and_then(|v| v.as_str()).unwrap_or("streamGenerateContent");
+                format!("/v1beta/models/{}:{}?alt=sse", model, method)
+            }
+            _ => "/v1/messages".to_string(),
+        };
+        Request::post(uri)
+            .header("content-type", "application/json")
+            .body(body)
+            .unwrap()
+    }
+
+    async fn replay(path: &Path) {
+        let raw = std::fs::read_to_string(path).unwrap();
+        let case: CassetteCase = serde_json::from_str(&raw)
+            .unwrap_or_else(|e| panic!("{}: invalid cassette: {}", path.display(), e));
+
+        let (upstream_base, received) = start_mock_upstream(case.cassette.upstream.clone()).await;
+        let state = replay_state(upstream_base).await;
+
+        use crate::proxy::handlers;
+        let app = Router::new()
+            .route("/v1/messages", post(handlers::claude::handle_messages))
+            .route("/v1/chat/completions", post(handlers::openai::handle_chat_completions))
+            .route("/v1/embeddings", post(handlers::embeddings::handle_embeddings))
+            .route("/v1beta/models/:model", post(handlers::gemini::handle_generate))
+            .with_state(state);
+
+        let response = app.oneshot(client_request(&case.cassette)).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let output = String::from_utf8_lossy(&bytes).to_string();
+        let name = path.file_name().unwrap().to_string_lossy();
+
+        assert!(
+            !received.lock().unwrap().is_empty(),
+            "{}: handler never reached the mock upstream ({}):\n{}",
+            name,
+            status,
+            output
+        );
+        assert_eq!(
+            status,
+            StatusCode::from_u16(case.expect.status.unwrap_or(200)).unwrap(),
+            "{}: unexpected status, body:\n{}",
+            name,
+            output
+        );
+        for needle in &case.expect.contains {
+            assert!(output.contains(needle.as_str()), "{}: missing {:?} in output:\n{}", name, needle, output);
+        }
+        for needle in &case.expect.not_contains {
+            assert!(!output.contains(needle.as_str()), "{}: unexpected {:?} in output:\n{}", name, needle, output);
+        }
+
+        let events: Vec<&str> = output
+            .lines()
+            .filter_map(|l| l.strip_prefix("event:"))
+            .map(|e| e.trim())
+            .collect();
+        let mut remaining = events.iter();
+        for expected in &case.expect.events {
+            assert!(
+                remaining.any(|e| e == expected),
+                "{}: event {:?} missing or out of order in {:?}",
+                name,
+                expected,
+                events
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_cassettes() {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(cassette_dir())
+            .unwrap()
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("json"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty(), "no cassettes found");
+
+        for path in paths {
+            replay(&path).await;
+        }
+    }
+}
@@ -1,13 +1,17 @@
 // 移除冗余的顶层导入，因为这些在代码中已由 full path 或局部导入处理
 use dashmap::DashMap;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use chrono;
 
-use crate::proxy::rate_limit::RateLimitTracker;
+use crate::proxy::rate_limit::{RateLimitInfo, RateLimitReason, RateLimitTracker};
 use crate::proxy::sticky_config::StickySessionConfig;
+use crate::proxy::routing_policy::{self, RoutingCandidate, RoutingConfig, RoutingContext};
+use crate::proxy::model_fallback::{FallbackTarget, ModelFallbackConfig};
+use crate::modules::vault;
+use crate::modules::limit_state_db::{self, FailureCountRecord, HealthScoreRecord, LimitState, LockoutRecord};
 
 #[derive(Debug, Clone)]
 pub struct ProxyToken {
@@ -27,6 +31,8 @@ pub struct ProxyToken {
     pub reset_time: Option<i64>,           // [NEW] 配额刷新时间戳（用于排序优化）
     pub validation_blocked: bool,          // [NEW] Check for validation block (VALIDATION_REQUIRED temporary block)
     pub validation_blocked_until: i64,     // [NEW] Timestamp until which the account is blocked
+    pub model_quotas: HashMap<String, i32>, // [NEW] 各模型剩余配额百分比 (路由策略使用)
+    pub kiro_credits: Option<f64>,         // [NEW] Kiro 剩余 credits (月度 + 试用)
     // Kiro-specific fields
     pub kiro_profile_arn: Option<String>,
     pub kiro_user_id: Option<String>,
@@ -44,30 +50,186 @@ pub struct TokenManager {
     preferred_account_id: Arc<parking_lot::RwLock<Option<String>>>, // [FIX #820] 优先使用的账号ID（固定账号模式）
     health_scores: Arc<DashMap<String, f32>>,                       // account_id -> health_score
     circuit_breaker_config: Arc<parking_lot::RwLock<crate::models::CircuitBreakerConfig>>, // [NEW] 熔断配置缓存
+    routing_config: Arc<parking_lot::RwLock<RoutingConfig>>,       // [NEW] 路由策略配置
+    model_fallback: Arc<parking_lot::RwLock<ModelFallbackConfig>>, // [NEW] 模型降级链配置
+    last_used_at: Arc<DashMap<String, i64>>,                        // [NEW] account_id -> 上次选中时间 (ms)
+    limit_state: Arc<LimitStateStore>,                              // [NEW] 限流状态持久化
+}
+
+/// [NEW] 限流锁定 / 失败计数 / 健康分的持久化 (data_dir/limit_state.db)
+///
+/// 内存为准: 变更计数变化时整体覆盖写入；load_accounts 时合并回内存
+struct LimitStateStore {
+    db_path: PathBuf,
+    tracker: Arc<RateLimitTracker>,
+    health_scores: Arc<DashMap<String, f32>>,
+    health_generation: AtomicU64,
+    persisted_generation: AtomicU64,
+    restored: AtomicBool,
+}
+
+impl LimitStateStore {
+    fn generation(&self) -> u64 {
+        self.tracker.generation() + self.health_generation.load(Ordering::Relaxed)
+    }
+
+    fn mark_health_changed(&self) {
+        self.health_generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn to_unix(t: std::time::SystemTime) -> i64 {
+        t.duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0)
+    }
+
+    fn from_unix(ts: i64) -> std::time::SystemTime {
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(ts.max(0) as u64)
+    }
+
+    fn collect(&self) -> LimitState {
+        let now = std::time::SystemTime::now();
+        let now_ts = chrono::Utc::now().timestamp();
+        let (limits, failures) = self.tracker.snapshot();
+
+        let mut state = LimitState {
+            lockouts: limits
+                .into_iter()
+                .filter(|(_, _, info)| info.reset_time > now)
+                .map(|(account_id, model, info)| LockoutRecord {
+                    account_id,
+                    model,
+                    reason: info.reason.as_str().to_string(),
+                    reset_time: Self::to_unix(info.reset_time),
+                    detected_at: Self::to_unix(info.detected_at),
+                    retry_after_sec: info.retry_after_sec,
+                })
+                .collect(),
+            failure_counts: failures
+                .into_iter()
+                .map(|(account_id, count, updated_at)| FailureCountRecord {
+                    account_id,
+                    count,
+                    updated_at: Self::to_unix(updated_at),
+                })
+                .collect(),
+            health_scores: self
+                .health_scores
+                .iter()
+                .map(|e| HealthScoreRecord {
+                    account_id: e.key().clone(),
+                    score: *e.value(),
+                    updated_at: now_ts,
+                })
+                .collect(),
+        };
+        state.lockouts.sort_by(|a, b| (&a.account_id, &a.model).cmp(&(&b.account_id, &b.model)));
+        state.failure_counts.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+        state.health_scores.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+        state
+    }
+
+    /// 写入数据库；`force = false` 时仅在有变更时写入
+    async fn flush(&self, force: bool) -> Result<bool, String> {
+        let generation = self.generation();
+        if !force && generation == self.persisted_generation.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        let state = self.collect();
+        let db_path = self.db_path.clone();
+        tokio::task::spawn_blocking(move || limit_state_db::save_state(&db_path, &state))
+            .await
+            .map_err(|e| format!("Task join error: {}", e))??;
+        self.persisted_generation.store(generation, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    /// 从数据库合并回内存 (过期记录在读取时被删除)
+    ///
+    /// 首次之后的调用会先落盘，保证内存中已清除的锁不会被旧记录"复活"
+    async fn restore(&self, known_accounts: &HashSet<String>) -> Result<usize, String> {
+        if self.restored.load(Ordering::SeqCst) {
+            self.flush(false).await?;
+        }
+
+        let db_path = self.db_path.clone();
+        let now = chrono::Utc::now().timestamp();
+        let expiry = crate::proxy::rate_limit::FAILURE_COUNT_EXPIRY_SECONDS as i64;
+        let state = tokio::task::spawn_blocking(move || limit_state_db::load_state(&db_path, now, expiry))
+            .await
+            .map_err(|e| format!("Task join error: {}", e))??;
+
+        let mut restored = 0;
+        for l in state.lockouts {
+            let info = RateLimitInfo {
+                reset_time: Self::from_unix(l.reset_time),
+                retry_after_sec: l.retry_after_sec,
+                detected_at: Self::from_unix(l.detected_at),
+                reason: RateLimitReason::from_str_lossy(&l.reason),
+                model: l.model.clone(),
+            };
+            if self.tracker.restore_limit(&l.account_id, l.model.as_deref(), info) {
+                restored += 1;
+            }
+        }
+        for f in state.failure_counts {
+            if self
+                .tracker
+                .restore_failure_count(&f.account_id, f.count, Self::from_unix(f.updated_at))
+            {
+                restored += 1;
+            }
+        }
+        // 已删除账号的健康分不再恢复，下次落盘时随之清除
+        for h in state.health_scores {
+            if known_accounts.contains(&h.account_id) && !self.health_scores.contains_key(&h.account_id) {
+                self.health_scores.insert(h.account_id, h.score.clamp(0.0, 1.0));
+                restored += 1;
+            }
+        }
+
+        self.restored.store(true, Ordering::SeqCst);
+        Ok(restored)
+    }
 }
 
 impl TokenManager {
     /// 创建新的 TokenManager
     pub fn new(data_dir: PathBuf) -> Self {
+        let rate_limit_tracker = Arc::new(RateLimitTracker::new());
+        let health_scores = Arc::new(DashMap::new());
+        let limit_state = Arc::new(LimitStateStore {
+            db_path: limit_state_db::get_limit_state_db_path(&data_dir),
+            tracker: rate_limit_tracker.clone(),
+            health_scores: health_scores.clone(),
+            health_generation: AtomicU64::new(0),
+            persisted_generation: AtomicU64::new(0),
+            restored: AtomicBool::new(false),
+        });
         Self {
             tokens: Arc::new(DashMap::new()),
             current_index: Arc::new(AtomicUsize::new(0)),
             last_used_account: Arc::new(tokio::sync::Mutex::new(None)),
             data_dir,
-            rate_limit_tracker: Arc::new(RateLimitTracker::new()),
+            rate_limit_tracker,
             sticky_config: Arc::new(parking_lot::RwLock::new(StickySessionConfig::default())),
             session_accounts: Arc::new(DashMap::new()),
             preferred_account_id: Arc::new(parking_lot::RwLock::new(None)), // [FIX #820]
-            health_scores: Arc::new(DashMap::new()),
+            health_scores,
             circuit_breaker_config: Arc::new(parking_lot::RwLock::new(
                 crate::models::CircuitBreakerConfig::default(),
             )),
+            routing_config: Arc::new(parking_lot::RwLock::new(RoutingConfig::default())),
+            model_fallback: Arc::new(parking_lot::RwLock::new(ModelFallbackConfig::default())),
+            last_used_at: Arc::new(DashMap::new()),
+            limit_state,
         }
     }
 
     /// 启动限流记录自动清理后台任务（每15秒检查并清除过期记录）
     pub fn start_auto_cleanup(&self) {
         let tracker = self.rate_limit_tracker.clone();
+        let limit_state = self.limit_state.clone();
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(std::time::Duration::from_secs(15));
             loop {
@@ -79,6 +241,10 @@ impl TokenManager {
                         cleaned
                     );
                 }
+                // [NEW] 有变更时落盘，重启后恢复锁定状态
+                if let Err(e) = limit_state.flush(false).await {
+                    tracing::warn!("Failed to persist rate limit state: {}", e);
+                }
             }
         });
         tracing::info!("✅ Rate limit auto-cleanup task started (interval: 15s)");
@@ -129,9 +295,36 @@ impl TokenManager {
             }
         }
 
+        // [NEW] 恢复持久化的限流锁定 / 失败计数 / 健康分
+        let known_accounts: HashSet<String> = self.tokens.iter().map(|e| e.key().clone()).collect();
+        match self.limit_state.restore(&known_accounts).await {
+            Ok(restored) if restored > 0 => {
+                // 账号加载时读取的是恢复前的健康分，这里同步一次
+                for mut entry in self.tokens.iter_mut() {
+                    if let Some(score) = self.health_scores.get(entry.key()) {
+                        entry.value_mut().health_score = *score;
+                    }
+                }
+                tracing::info!("Restored {} persisted rate limit record(s)", restored)
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to restore rate limit state: {}", e),
+        }
+
+        self.sync_proxy_pool();
         Ok(count)
     }
 
+    /// [NEW] 将账号独立代理登记到代理池 (健康检查只探测仍在使用的代理)
+    fn sync_proxy_pool(&self) {
+        let proxies: Vec<String> = self
+            .tokens
+            .iter()
+            .filter_map(|e| e.value().individual_proxy.clone())
+            .collect();
+        crate::proxy::upstream::proxy_pool::pool().sync_accounts(proxies.iter().map(String::as_str));
+    }
+
     /// 重新加载指定账号（用于配额更新后的实时同步）
     pub async fn reload_account(&self, account_id: &str) -> Result<(), String> {
         let path = self
@@ -147,6 +340,7 @@ impl TokenManager {
                 self.tokens.insert(account_id.to_string(), token);
                 // [NEW] 重新加载账号时自动清除该账号的限流记录
                 self.clear_rate_limit(account_id);
+                self.sync_proxy_pool();
                 Ok(())
             }
             Ok(None) => Err("账号加载失败".to_string()),
@@ -164,11 +358,8 @@ impl TokenManager {
 
     /// 加载单个账号
     async fn load_single_account(&self, path: &PathBuf) -> Result<Option<ProxyToken>, String> {
-        let content = std::fs::read_to_string(path)
-            .map_err(|e| format!("读取文件失败: {}", e))?;
-
-        let mut account: serde_json::Value = serde_json::from_str(&content)
-            .map_err(|e| format!("解析 JSON 失败: {}", e))?;
+        // 经由保险库读取，Token 字段透明解密
+        let mut account = vault::read_account_json(path)?;
 
         if account
             .get("disabled")
@@ -260,9 +451,7 @@ impl TokenManager {
                 account["validation_blocked_reason"] = serde_json::Value::Null;
                 
                 // Save cleared state
-                if let Ok(json_str) = serde_json::to_string_pretty(&account) {
-                    let _ = std::fs::write(path, json_str);
-                }
+                let _ = vault::write_account_json(path, &account);
             }
         }
 
@@ -331,6 +520,24 @@ impl TokenManager {
             .and_then(|q| self.calculate_quota_stats(q));
             // .filter(|&r| r > 0); // 移除 >0 过滤，因为 0% 也是有效数据，只是优先级低
 
+        // [NEW] 各模型剩余配额 (路由策略按目标模型加权)
+        let model_quotas: HashMap<String, i32> = account
+            .get("quota")
+            .and_then(|q| q.get("models"))
+            .and_then(|m| m.as_array())
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|m| {
+                        let name = m.get("name").and_then(|v| v.as_str())?;
+                        let pct = m.get("percentage").and_then(|v| v.as_i64())?;
+                        Some((name.to_string(), pct as i32))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let kiro_credits = account.get("quota").and_then(extract_kiro_credits);
+
         // 【新增 #621】提取受限模型列表
         let protected_models: HashSet<String> = account
             .get("protected_models")
@@ -389,6 +596,8 @@ impl TokenManager {
             reset_time,
             validation_blocked: account.get("validation_blocked").and_then(|v| v.as_bool()).unwrap_or(false),
             validation_blocked_until: account.get("validation_blocked_until").and_then(|v| v.as_i64()).unwrap_or(0),
+            model_quotas,
+            kiro_credits,
             kiro_profile_arn,
             kiro_user_id,
             individual_proxy,
@@ -590,8 +799,7 @@ impl TokenManager {
             );
 
             // 3. 写入磁盘
-            std::fs::write(account_path, serde_json::to_string_pretty(account_json).unwrap())
-                .map_err(|e| format!("写入文件失败: {}", e))?;
+            vault::write_account_json(account_path, account_json)?;
 
             return Ok(true);
         }
@@ -638,7 +846,7 @@ impl TokenManager {
 
         account_json["protected_models"] = serde_json::Value::Array(protected_list);
 
-        let _ = std::fs::write(account_path, serde_json::to_string_pretty(account_json).unwrap());
+        let _ = vault::write_account_json(account_path, account_json);
 
         false // 返回 false 表示现在已可以尝试加载该账号（模型级过滤会在 get_token 时发生）
     }
@@ -665,11 +873,7 @@ impl TokenManager {
                     account_id,
                     model_name
                 );
-                std::fs::write(
-                    account_path,
-                    serde_json::to_string_pretty(account_json).unwrap(),
-                )
-                .map_err(|e| format!("写入文件失败: {}", e))?;
+                vault::write_account_json(account_path, account_json)?;
                 return Ok(true);
             }
         }
@@ -775,55 +979,43 @@ impl TokenManager {
             }
         }
         
+        // [NEW] 账号独立代理不可用且回退方式为 skip_account 时，暂不调度该账号
+        let proxy_pool = crate::proxy::upstream::proxy_pool::pool();
+        let before_len = tokens_snapshot.len();
+        tokens_snapshot.retain(|t| !proxy_pool.should_skip_account(t.individual_proxy.as_deref()));
+        if tokens_snapshot.len() != before_len {
+            tracing::debug!(
+                "🚫 [ProxyPool] Skipped {} account(s) whose proxy is down",
+                before_len - tokens_snapshot.len()
+            );
+            if tokens_snapshot.is_empty() {
+                return Err("All available accounts are waiting for their proxy to recover".to_string());
+            }
+        }
+
         // 更新 total 为过滤后的数量
         let total = tokens_snapshot.len();
 
         // ===== 【优化】根据订阅等级、健康分、刷新时间、剩余配额排序 =====
         // 优先级: 订阅等级 > 健康分 > 刷新时间（越近越优先）> 剩余配额
-        // 刷新时间差异 < 10 分钟视为相同优先级
-        const RESET_TIME_THRESHOLD_SECS: i64 = 600; // 10 分钟阈值
-
-        tokens_snapshot.sort_by(|a, b| {
-            let tier_priority = |tier: &Option<String>| match tier.as_deref() {
-                Some("ULTRA") => 0,
-                Some("PRO") => 1,
-                Some("FREE") => 2,
-                _ => 3,
-            };
-
-            // First: compare by subscription tier
-            let tier_cmp = tier_priority(&a.subscription_tier)
-                .cmp(&tier_priority(&b.subscription_tier));
-
-            if tier_cmp != std::cmp::Ordering::Equal {
-                return tier_cmp;
-            }
-
-            // Second: compare by health score (higher is better)
-            let health_cmp = b.health_score.partial_cmp(&a.health_score).unwrap_or(std::cmp::Ordering::Equal);
-
-            if health_cmp != std::cmp::Ordering::Equal {
-                return health_cmp;
-            }
-
-            // Third: compare by reset time (earlier/closer is better)
-            // 差异 < 10 分钟视为相同优先级，避免频繁切换
-            let reset_a = a.reset_time.unwrap_or(i64::MAX);
-            let reset_b = b.reset_time.unwrap_or(i64::MAX);
-            let reset_diff = (reset_a - reset_b).abs();
-
-            if reset_diff >= RESET_TIME_THRESHOLD_SECS {
-                let reset_cmp = reset_a.cmp(&reset_b);
-                if reset_cmp != std::cmp::Ordering::Equal {
-                    return reset_cmp;
-                }
-            }
-
-            // Fourth: compare by remaining quota percentage (higher is better)
-            let quota_a = a.remaining_quota.unwrap_or(0);
-            let quota_b = b.remaining_quota.unwrap_or(0);
-            quota_b.cmp(&quota_a)
-        });
+        // 刷新时间差异 < 10 分钟视为相同优先级 (见 routing_policy::priority_cmp)
+        let normalized_target = crate::proxy::common::model_mapping::normalize_to_standard_id(target_model);
+        let mut candidates: Vec<RoutingCandidate> = tokens_snapshot
+            .iter()
+            .map(|t| self.routing_candidate(t, target_model, &normalized_target))
+            .collect();
+        let order = routing_policy::priority_order(&candidates);
+        tokens_snapshot = order.iter().map(|&i| tokens_snapshot[i].clone()).collect();
+        candidates = order.iter().map(|&i| candidates[i].clone()).collect();
+
+        // [NEW] 按模型选择路由策略 (决定新选账号时的尝试顺序)
+        let (policy, credit_multiplier) = {
+            let routing = self.routing_config.read();
+            (
+                routing.policy_for(target_model).policy(),
+                routing.credit_multiplier(target_model),
+            )
+        };
 
         // 【调试日志】打印排序后的账号顺序
         tracing::debug!(
@@ -888,7 +1080,7 @@ impl TokenManager {
                     if token.provider != "kiro" && now >= token.timestamp - 300 {
                         tracing::debug!("账号 {} 的 token 即将过期，正在刷新...", token.email);
                         
-                        let refresh_result = crate::modules::oauth::refresh_access_token(&token.refresh_token)
+                        let refresh_result = crate::modules::oauth::refresh_access_token(&token.refresh_token, token.individual_proxy.as_deref())
                             .await;
                         
                         match refresh_result {
@@ -917,7 +1109,7 @@ impl TokenManager {
                     let project_id = if let Some(pid) = &token.project_id {
                         pid.clone()
                     } else {
-                        match crate::proxy::project_resolver::fetch_project_id(&token.access_token)
+                        match crate::proxy::project_resolver::fetch_project_id(&token.access_token, token.individual_proxy.as_deref())
                             .await
                         {
                             Ok(pid) => {
@@ -931,6 +1123,7 @@ impl TokenManager {
                         }
                     };
 
+                    self.last_used_at.insert(token.account_id.clone(), chrono::Utc::now().timestamp_millis());
                     return Ok((token.access_token, project_id, token.email, token.account_id, 0));
                 } else {
                     if is_rate_limited {
@@ -964,9 +1157,6 @@ impl TokenManager {
             // ===== 【核心】粘性会话与智能调度逻辑 =====
             let mut target_token: Option<ProxyToken> = None;
 
-            // 归一化目标模型名为标准 ID，用于配额保护检查
-            let normalized_target = crate::proxy::common::model_mapping::normalize_to_standard_id(target_model);
-
             // 模式 A: 粘性会话处理 (CacheFirst 或 Balance 且有 session_id)
             if !rotate
                 && session_id.is_some()
@@ -1063,12 +1253,15 @@ impl TokenManager {
 
                 // 若无锁定，则轮询选择新账号
                 if target_token.is_none() {
-                    let start_idx = self.current_index.fetch_add(1, Ordering::SeqCst) % total;
+                    let ctx = RoutingContext {
+                        rotation: self.current_index.fetch_add(1, Ordering::SeqCst),
+                        credit_multiplier,
+                    };
+                    let ranked = policy.rank(&candidates, &ctx);
                     
                     let mut fallback_token: Option<ProxyToken> = None;
 
-                    for offset in 0..total {
-                        let idx = (start_idx + offset) % total;
+                    for idx in ranked {
                         let candidate = &tokens_snapshot[idx];
                         if attempted.contains(&candidate.account_id) {
                             continue;
@@ -1131,17 +1324,21 @@ impl TokenManager {
                 }
             } else if target_token.is_none() {
                 // 模式 C: 纯轮询模式 (Round-robin) 或强制轮换
-                let start_idx = self.current_index.fetch_add(1, Ordering::SeqCst) % total;
+                let ctx = RoutingContext {
+                    rotation: self.current_index.fetch_add(1, Ordering::SeqCst),
+                    credit_multiplier,
+                };
+                let ranked = policy.rank(&candidates, &ctx);
                 tracing::debug!(
-                    "🔄 [Mode C] Round-robin from idx {}, total: {}",
-                    start_idx,
+                    "🔄 [Mode C] Policy {} order {:?}, total: {}",
+                    policy.name(),
+                    ranked,
                     total
                 );
                 
                 let mut fallback_token: Option<ProxyToken> = None;
 
-                for offset in 0..total {
-                    let idx = (start_idx + offset) % total;
+                for idx in ranked {
                     let candidate = &tokens_snapshot[idx];
 
                     if attempted.contains(&candidate.account_id) {
@@ -1278,7 +1475,7 @@ impl TokenManager {
             if token.provider != "kiro" && now >= token.timestamp - 300 {
                 tracing::debug!("账号 {} 的 token 即将过期，正在刷新...", token.email);
 
-                let refresh_result = crate::modules::oauth::refresh_access_token(&token.refresh_token)
+                let refresh_result = crate::modules::oauth::refresh_access_token(&token.refresh_token, token.individual_proxy.as_deref())
                     .await;
 
                 match refresh_result {
@@ -1343,7 +1540,7 @@ impl TokenManager {
                 pid.clone()
             } else {
                 tracing::debug!("账号 {} 缺少 project_id，尝试获取...", token.email);
-                match crate::proxy::project_resolver::fetch_project_id(&token.access_token).await {
+                match crate::proxy::project_resolver::fetch_project_id(&token.access_token, token.individual_proxy.as_deref()).await {
                     Ok(pid) => {
                         if let Some(mut entry) = self.tokens.get_mut(&token.account_id) {
                             entry.project_id = Some(pid.clone());
@@ -1379,6 +1576,7 @@ impl TokenManager {
                 }
             }
 
+            self.last_used_at.insert(token.account_id.clone(), chrono::Utc::now().timestamp_millis());
             return Ok((token.access_token, project_id, token.email, token.account_id, 0));
         }
 
@@ -1394,18 +1592,14 @@ impl TokenManager {
                 .join(format!("{}.json", account_id))
         };
 
-        let mut content: serde_json::Value = serde_json::from_str(
-            &std::fs::read_to_string(&path).map_err(|e| format!("读取文件失败: {}", e))?,
-        )
-        .map_err(|e| format!("解析 JSON 失败: {}", e))?;
+        let mut content = vault::read_account_json(&path)?;
 
         let now = chrono::Utc::now().timestamp();
         content["disabled"] = serde_json::Value::Bool(true);
         content["disabled_at"] = serde_json::Value::Number(now.into());
         content["disabled_reason"] = serde_json::Value::String(truncate_reason(reason, 800));
 
-        std::fs::write(&path, serde_json::to_string_pretty(&content).unwrap())
-            .map_err(|e| format!("写入文件失败: {}", e))?;
+        vault::write_account_json(&path, &content)?;
 
         // 【修复 Issue #3】从内存中移除禁用的账号，防止被60s锁定逻辑继续使用
         self.tokens.remove(account_id);
@@ -1421,14 +1615,11 @@ impl TokenManager {
 
         let path = &entry.account_path;
 
-        let mut content: serde_json::Value = serde_json::from_str(
-            &std::fs::read_to_string(path).map_err(|e| format!("读取文件失败: {}", e))?
-        ).map_err(|e| format!("解析 JSON 失败: {}", e))?;
+        let mut content = vault::read_account_json(path)?;
 
         content["token"]["project_id"] = serde_json::Value::String(project_id.to_string());
 
-        std::fs::write(path, serde_json::to_string_pretty(&content).unwrap())
-            .map_err(|e| format!("写入文件失败: {}", e))?;
+        vault::write_account_json(path, &content)?;
 
         tracing::debug!("已保存 project_id 到账号 {}", account_id);
         Ok(())
@@ -1440,17 +1631,13 @@ impl TokenManager {
             entry.project_id = None;
             let path = entry.account_path.clone();
 
-            let mut content: serde_json::Value = serde_json::from_str(
-                &std::fs::read_to_string(&path).map_err(|e| format!("读取文件失败: {}", e))?,
-            )
-            .map_err(|e| format!("解析 JSON 失败: {}", e))?;
+            let mut content = vault::read_account_json(&path)?;
 
             if let Some(token_obj) = content.get_mut("token").and_then(|t| t.as_object_mut()) {
                 token_obj.remove("project_id");
             }
 
-            std::fs::write(&path, serde_json::to_string_pretty(&content).unwrap())
-                .map_err(|e| format!("写入文件失败: {}", e))?;
+            vault::write_account_json(&path, &content)?;
 
             tracing::info!("♻️ Cleared project_id for account {} due to 404 error", account_id);
             Ok(())
@@ -1466,9 +1653,7 @@ impl TokenManager {
 
         let path = &entry.account_path;
 
-        let mut content: serde_json::Value = serde_json::from_str(
-            &std::fs::read_to_string(path).map_err(|e| format!("读取文件失败: {}", e))?
-        ).map_err(|e| format!("解析 JSON 失败: {}", e))?;
+        let mut content = vault::read_account_json(path)?;
 
         let now = chrono::Utc::now().timestamp();
 
@@ -1476,8 +1661,7 @@ impl TokenManager {
         content["token"]["expires_in"] = serde_json::Value::Number(token_response.expires_in.into());
         content["token"]["expiry_timestamp"] = serde_json::Value::Number((now + token_response.expires_in).into());
 
-        std::fs::write(path, serde_json::to_string_pretty(&content).unwrap())
-            .map_err(|e| format!("写入文件失败: {}", e))?;
+        vault::write_account_json(path, &content)?;
 
         tracing::debug!("已保存刷新后的 token 到账号 {}", account_id);
         Ok(())
@@ -1502,13 +1686,9 @@ impl TokenManager {
             return Err(format!("Account file not found: {}", account_id));
         }
         
-        let account_json = tokio::fs::read_to_string(&account_path)
-            .await
+        let account = vault::read_account_json(&account_path)
             .map_err(|e| format!("Failed to read account file: {}", e))?;
         
-        let account: serde_json::Value = serde_json::from_str(&account_json)
-            .map_err(|e| format!("Failed to parse account JSON: {}", e))?;
-        
         // Извлекаем kiro_profile_arn
         account
             .get("kiro_profile_arn")
@@ -1567,7 +1747,7 @@ impl TokenManager {
         tracing::info!("[Warmup] Token for {} is expiring, refreshing...", email);
 
         // 调用 OAuth 刷新 token
-        match crate::modules::oauth::refresh_access_token(&refresh_token).await {
+        match crate::modules::oauth::refresh_access_token(&refresh_token, self.get_individual_proxy(&account_id).as_deref()).await {
             Ok(token_response) => {
                 tracing::info!("[Warmup] Token refresh successful for {}", email);
                 let new_now = chrono::Utc::now().timestamp();
@@ -1682,6 +1862,56 @@ impl TokenManager {
         self.rate_limit_tracker.mark_success(account_id);
     }
 
+    /// [NEW] 当前的限流锁定 / 失败计数 / 健康分 (管理接口查看)
+    pub fn limit_state(&self) -> LimitState {
+        self.limit_state.collect()
+    }
+
+    /// [NEW] account_id -> email，便于管理接口展示
+    pub fn account_emails(&self) -> HashMap<String, String> {
+        self.tokens
+            .iter()
+            .map(|e| (e.key().clone(), e.value().email.clone()))
+            .collect()
+    }
+
+    /// [NEW] 立即落盘限流状态 (管理接口修改后 / 停止服务时调用)
+    pub async fn persist_limit_state(&self) -> Result<(), String> {
+        self.limit_state.flush(true).await.map(|_| ())
+    }
+
+    /// [NEW] 手动设置锁定 (model 为 None 表示账号级)
+    pub fn set_rate_limit_lockout(&self, account_id: &str, model: Option<String>, reason: RateLimitReason, reset_time: i64) {
+        let reset_time = LimitStateStore::from_unix(reset_time);
+        self.rate_limit_tracker.set_lockout_until(account_id, reset_time, reason, model);
+    }
+
+    /// [NEW] 清除指定范围的锁定 (model 为 None 表示账号级)
+    pub fn clear_rate_limit_scope(&self, account_id: &str, model: Option<&str>) -> bool {
+        self.rate_limit_tracker.clear_scope(account_id, model)
+    }
+
+    /// [NEW] 手动设置连续失败计数
+    pub fn set_failure_count(&self, account_id: &str, count: u32) {
+        self.rate_limit_tracker.set_failure_count(account_id, count);
+    }
+
+    /// [NEW] 手动设置健康分 (None 表示重置为默认)
+    pub fn set_health_score(&self, account_id: &str, score: Option<f32>) {
+        match score {
+            Some(score) => {
+                self.health_scores.insert(account_id.to_string(), score.clamp(0.0, 1.0));
+            }
+            None => {
+                self.health_scores.remove(account_id);
+            }
+        }
+        if let Some(mut token) = self.tokens.get_mut(account_id) {
+            token.health_score = score.map(|s| s.clamp(0.0, 1.0)).unwrap_or(1.0);
+        }
+        self.limit_state.mark_health_changed();
+    }
+
     /// 检查是否有可用的 Google 账号
     ///
     /// 用于"仅兜底"模式的智能判断:当所有 Google 账号不可用时才使用外部提供商。
@@ -1748,6 +1978,88 @@ impl TokenManager {
         false
     }
 
+    /// 检查指定提供商的账号池是否还有可用账号 (模型降级链使用)
+    ///
+    /// 与 `has_available_account` 不同: 按提供商过滤账号池 (与 get_token 一致)，
+    /// 检查目标模型级限流与账号代理状态，并忽略本次请求中已经失败过的账号。
+    ///
+    /// # 参数
+    /// - `quota_group`: 配额组("claude"、"gemini" 或 "kiro")
+    /// - `target_model`: 目标模型名称,用于模型级限流与配额保护检查
+    /// - `excluded_accounts`: 本次请求中已失败的账号
+    pub async fn has_available_account_excluding(
+        &self,
+        quota_group: &str,
+        target_model: &str,
+        excluded_accounts: Option<&HashSet<String>>,
+    ) -> bool {
+        // 检查配额保护是否启用
+        let quota_protection_enabled = crate::modules::config::load_app_config()
+            .map(|cfg| cfg.quota_protection.enabled)
+            .unwrap_or(false);
+        let normalized_target = crate::proxy::common::model_mapping::normalize_to_standard_id(target_model);
+        let proxy_pool = crate::proxy::upstream::proxy_pool::pool();
+
+        // 与 get_token 相同的账号池过滤
+        let candidates: Vec<ProxyToken> = self
+            .tokens
+            .iter()
+            .map(|e| e.value().clone())
+            .filter(|t| match quota_group {
+                "kiro" => t.provider == "kiro",
+                "gemini" | "claude" => t.provider == "gemini",
+                _ => true,
+            })
+            .filter(|t| !excluded_accounts.is_some_and(|ex| ex.contains(&t.account_id)))
+            .collect();
+
+        // 遍历所有账号,检查是否有可用的
+        for token in &candidates {
+            // 1. 检查是否被限流 (账号级或目标模型级)
+            if self.is_rate_limited(&token.account_id, Some(&normalized_target)).await {
+                tracing::debug!(
+                    "[Fallback Check] Account {} is rate-limited, skipping",
+                    token.email
+                );
+                continue;
+            }
+
+            // 2. 检查是否被配额保护(如果启用)
+            if quota_protection_enabled
+                && (token.protected_models.contains(target_model)
+                    || token.protected_models.contains(&normalized_target))
+            {
+                tracing::debug!(
+                    "[Fallback Check] Account {} is quota-protected for model {}, skipping",
+                    token.email,
+                    target_model
+                );
+                continue;
+            }
+
+            // 3. 账号代理不可用且回退方式为 skip_account
+            if proxy_pool.should_skip_account(token.individual_proxy.as_deref()) {
+                continue;
+            }
+
+            // 找到至少一个可用账号
+            tracing::debug!(
+                "[Fallback Check] Found available account: {} for model {}",
+                token.email,
+                target_model
+            );
+            return true;
+        }
+
+        // 所有账号都不可用
+        tracing::info!(
+            "[Fallback Check] No available {} accounts for model {}, fallback should be triggered",
+            quota_group,
+            target_model
+        );
+        false
+    }
+
     /// 从账号文件获取配额刷新时间
     ///
     /// 返回该账号最近的配额刷新时间字符串（ISO 8601 格式）
@@ -1758,8 +2070,7 @@ impl TokenManager {
         // 直接用 account_id 查找账号文件（文件名是 {account_id}.json）
         let account_path = self.data_dir.join("accounts").join(format!("{}.json", account_id));
 
-        let content = std::fs::read_to_string(&account_path).ok()?;
-        let account: serde_json::Value = serde_json::from_str(&content).ok()?;
+        let account = vault::read_account_json(&account_path).ok()?;
 
         // 获取 quota.models 中最早的 reset_time（最保守的锁定策略）
         account
@@ -1834,7 +2145,7 @@ impl TokenManager {
 
         // 2. 调用配额刷新 API
         tracing::info!("账号 {} 正在实时刷新配额...", email);
-        match crate::modules::quota::fetch_quota(&access_token, email).await {
+        match crate::modules::quota::fetch_quota(&access_token, email, self.get_individual_proxy(&account_id).as_deref()).await {
             Ok((quota_data, _project_id)) => {
                 // 3. 从最新配额中提取 reset_time
                 let earliest_reset = quota_data
@@ -2002,6 +2313,41 @@ impl TokenManager {
         self.circuit_breaker_config.read().clone()
     }
 
+    /// [NEW] 更新路由策略配置
+    pub async fn update_routing_config(&self, config: RoutingConfig) {
+        *self.routing_config.write() = config;
+        tracing::debug!("Routing policy configuration updated");
+    }
+
+    /// [NEW] 更新模型降级链配置
+    pub async fn update_model_fallback_config(&self, config: ModelFallbackConfig) {
+        *self.model_fallback.write() = config;
+        tracing::debug!("Model fallback configuration updated");
+    }
+
+    /// [NEW] 客户端请求模型的降级目标 (未配置时为空)
+    pub fn fallback_targets(&self, model: &str) -> Vec<FallbackTarget> {
+        self.model_fallback.read().targets_for(model)
+    }
+
+    /// 构造路由策略使用的账号视图
+    fn routing_candidate(&self, token: &ProxyToken, target_model: &str, normalized_target: &str) -> RoutingCandidate {
+        RoutingCandidate {
+            account_id: token.account_id.clone(),
+            subscription_tier: token.subscription_tier.clone(),
+            health_score: token.health_score,
+            reset_time: token.reset_time,
+            remaining_quota: token.remaining_quota,
+            model_quota: token
+                .model_quotas
+                .get(target_model)
+                .or_else(|| token.model_quotas.get(normalized_target))
+                .copied(),
+            credits_remaining: token.kiro_credits,
+            last_used_at: self.last_used_at.get(&token.account_id).map(|v| *v),
+        }
+    }
+
     /// 清除特定会话的粘性映射
     #[allow(dead_code)]
     pub fn clear_session_binding(&self, session_id: &str) {
@@ -2053,7 +2399,7 @@ impl TokenManager {
         refresh_token: &str,
     ) -> Result<crate::modules::oauth::UserInfo, String> {
         // 先获取 Access Token
-        let token = crate::modules::oauth::refresh_access_token(refresh_token)
+        let token = crate::modules::oauth::refresh_access_token(refresh_token, None)
             .await
             .map_err(|e| format!("刷新 Access Token 失败: {}", e))?;
 
@@ -2063,12 +2409,12 @@ impl TokenManager {
     /// 添加新账号 (纯后端实现，不依赖 Tauri AppHandle)
     pub async fn add_account(&self, email: &str, refresh_token: &str) -> Result<(), String> {
         // 1. 获取 Access Token (验证 refresh_token 有效性)
-        let token_info = crate::modules::oauth::refresh_access_token(refresh_token)
+        let token_info = crate::modules::oauth::refresh_access_token(refresh_token, None)
             .await
             .map_err(|e| format!("Invalid refresh token: {}", e))?;
 
         // 2. 获取项目 ID (Project ID)
-        let project_id = crate::proxy::project_resolver::fetch_project_id(&token_info.access_token)
+        let project_id = crate::proxy::project_resolver::fetch_project_id(&token_info.access_token, None)
             .await
             .unwrap_or_else(|_| "bamboo-precept-lgxtn".to_string()); // Fallback
 
@@ -2102,6 +2448,7 @@ impl TokenManager {
             .entry(account_id.to_string())
             .and_modify(|s| *s = (*s + 0.05).min(1.0))
             .or_insert(1.0);
+        self.limit_state.mark_health_changed();
         tracing::debug!("📈 Health score increased for account {}", account_id);
     }
 
@@ -2111,9 +2458,71 @@ impl TokenManager {
             .entry(account_id.to_string())
             .and_modify(|s| *s = (*s - 0.2).max(0.0))
             .or_insert(0.8);
+        self.limit_state.mark_health_changed();
         tracing::warn!("📉 Health score decreased for account {}", account_id);
     }
 
+    /// [NEW] 账号池状态快照 (供 /metrics 导出)
+    ///
+    /// 只使用内存状态 (配额为最近一次加载账号时的快照)，可在异步上下文中直接调用
+    pub fn account_metrics(&self) -> Vec<crate::proxy::metrics::AccountSnapshot> {
+        use crate::proxy::metrics::{AccountSnapshot, RateLimitSnapshot};
+
+        let now = std::time::SystemTime::now();
+        let now_ts = chrono::Utc::now().timestamp();
+        let backoff_len = self.circuit_breaker_config.read().backoff_steps.len();
+
+        let mut snapshots: Vec<AccountSnapshot> = self
+            .tokens
+            .iter()
+            .map(|entry| {
+                let token = entry.value();
+                let consecutive_failures = self.rate_limit_tracker.failure_count(&token.account_id);
+                let rate_limits = self
+                    .rate_limit_tracker
+                    .active_limits(&token.account_id)
+                    .into_iter()
+                    .map(|info| RateLimitSnapshot {
+                        model: info.model.clone(),
+                        reason: info.reason.as_str(),
+                        remaining_seconds: info
+                            .reset_time
+                            .duration_since(now)
+                            .map(|d| d.as_secs())
+                            .unwrap_or(0),
+                    })
+                    .collect();
+                // 只读内存中的配额快照，抓取时不做文件读取与解密
+                let mut quota: Vec<(String, i32)> = token
+                    .model_quotas
+                    .iter()
+                    .map(|(model, pct)| (model.clone(), *pct))
+                    .collect();
+                quota.sort();
+
+                AccountSnapshot {
+                    account_id: token.account_id.clone(),
+                    email: token.email.clone(),
+                    provider: token.provider.clone(),
+                    tier: token.subscription_tier.clone(),
+                    health_score: self
+                        .health_scores
+                        .get(&token.account_id)
+                        .map(|v| *v)
+                        .unwrap_or(token.health_score),
+                    validation_blocked: token.validation_blocked && token.validation_blocked_until > now_ts,
+                    consecutive_failures,
+                    circuit_breaker_step: (consecutive_failures as usize).min(backoff_len),
+                    rate_limits,
+                    quota,
+                }
+            })
+            .collect();
+
+        snapshots.sort_by(|a, b| a.email.cmp(&b.email));
+        snapshots
+    }
+
     /// [NEW] 从账号配额信息中提取最近的刷新时间戳
     ///
     /// Claude 模型（sonnet/opus）共用同一个刷新时间，只需取 claude 系列的 reset_time
@@ -2191,12 +2600,9 @@ impl TokenManager {
              return Err(format!("Account file not found: {:?}", path));
         }
 
-        let content = std::fs::read_to_string(&path)
+        let mut account = vault::read_account_json(&path)
              .map_err(|e| format!("Failed to read account file: {}", e))?;
         
-        let mut account: serde_json::Value = serde_json::from_str(&content)
-             .map_err(|e| format!("Failed to parse account JSON: {}", e))?;
-        
         account["validation_blocked"] = serde_json::Value::Bool(true);
         account["validation_blocked_until"] = serde_json::Value::Number(serde_json::Number::from(block_until));
         account["validation_blocked_reason"] = serde_json::Value::String(reason.to_string());
@@ -2204,10 +2610,7 @@ impl TokenManager {
         // Clear sticky session if blocked
         self.session_accounts.retain(|_, v| *v != account_id);
 
-        let json_str = serde_json::to_string_pretty(&account)
-             .map_err(|e| format!("Failed to serialize account JSON: {}", e))?;
-             
-        std::fs::write(&path, json_str)
+        vault::write_account_json(&path, &account)
              .map_err(|e| format!("Failed to write account file: {}", e))?;
              
         tracing::info!(
@@ -2226,6 +2629,23 @@ impl TokenManager {
     }
 }
 
+/// 从 Kiro 配额记录中计算剩余 credits
+/// quota.rs 把额度/已用量以字符串形式存放在 kiro-*-limit / kiro-*-used 的 reset_time 字段中
+fn extract_kiro_credits(quota: &serde_json::Value) -> Option<f64> {
+    let models = quota.get("models")?.as_array()?;
+    let value = |name: &str| -> Option<f64> {
+        models
+            .iter()
+            .find(|m| m.get("name").and_then(|v| v.as_str()) == Some(name))
+            .and_then(|m| m.get("reset_time").and_then(|v| v.as_str()))
+            .and_then(|s| s.parse::<f64>().ok())
+    };
+
+    let monthly = value("kiro-monthly-limit")? - value("kiro-monthly-used").unwrap_or(0.0);
+    let trial = value("kiro-trial-limit").unwrap_or(0.0) - value("kiro-trial-used").unwrap_or(0.0);
+    Some(monthly.max(0.0) + trial.max(0.0))
+}
+
 /// 截断过长的原因字符串
 fn truncate_reason(reason: &str, max_len: usize) -> String {
     if reason.len() <= max_len {
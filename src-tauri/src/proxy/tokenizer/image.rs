@@ -0,0 +1,185 @@
+//! 图片 / PDF token 折算
+//!
+//! 尺寸直接从 base64 数据的文件头解析 (PNG / JPEG / GIF / WebP)，不做完整解码。
+//! - Claude: 长边缩放到 1568 且不超过 ~1.15MP 后，按 宽×高/750 计
+//! - Gemini: 两边都 ≤384 计 258；否则按 min(宽,高)/1.5 (限制在 256..768) 切块，每块 258
+//! - OpenAI: 按 high detail 计，缩放到 2048 以内且短边不超过 768 后按 512 切块，每块 170，另加 85
+
+use base64::Engine;
+
+use super::{ModelFamily, Tokenizer};
+
+/// 解析尺寸时最多解码的 base64 字符数 (JPEG 的 SOF 可能在 EXIF 缩略图之后)
+const HEADER_B64_CHARS: usize = 256 * 1024;
+
+const CLAUDE_MAX_EDGE: f64 = 1568.0;
+const CLAUDE_MAX_PIXELS: f64 = 1_150_000.0;
+const CLAUDE_PIXELS_PER_TOKEN: f64 = 750.0;
+const GEMINI_TILE_TOKENS: u32 = 258;
+const OPENAI_MAX_EDGE: f64 = 2048.0;
+const OPENAI_SHORT_EDGE: f64 = 768.0;
+const OPENAI_TILE: f64 = 512.0;
+const OPENAI_TILE_TOKENS: u32 = 170;
+const OPENAI_BASE_TOKENS: u32 = 85;
+
+/// 去掉 data URL 前缀 (data:image/png;base64,...)
+fn strip_data_url(data: &str) -> &str {
+    match data.split_once(";base64,") {
+        Some((prefix, rest)) if prefix.starts_with("data:") => rest,
+        _ => data,
+    }
+}
+
+/// 解码 base64 的前 `max_chars` 个字符
+fn decode_prefix(data: &str, max_chars: usize) -> Option<Vec<u8>> {
+    let data = strip_data_url(data).trim();
+    let take = if data.len() > max_chars { max_chars - max_chars % 4 } else { data.len() };
+    let chunk = data.get(..take)?;
+    base64::engine::general_purpose::STANDARD
+        .decode(chunk)
+        .or_else(|_| base64::engine::general_purpose::STANDARD_NO_PAD.decode(chunk.trim_end_matches('=')))
+        .ok()
+}
+
+/// 从图片文件头解析 (宽, 高)
+pub fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |i: usize| bytes.get(i..i + 2).map(|b| u16::from_be_bytes([b[0], b[1]]) as u32);
+    let le16 = |i: usize| bytes.get(i..i + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32);
+    let le24 = |i: usize| bytes.get(i..i + 3).map(|b| u32::from_le_bytes([b[0], b[1], b[2], 0]));
+    let be32 = |i: usize| bytes.get(i..i + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+
+    // PNG: IHDR 紧跟签名
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some((be32(16)?, be32(20)?));
+    }
+    // GIF: 逻辑屏幕尺寸
+    if bytes.starts_with(b"GIF8") {
+        return Some((le16(6)?, le16(8)?));
+    }
+    // WebP: VP8 / VP8L / VP8X
+    if bytes.len() > 30 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return match &bytes[12..16] {
+            b"VP8 " => Some((le16(26)? & 0x3FFF, le16(28)? & 0x3FFF)),
+            b"VP8L" => {
+                let b = bytes.get(21..25)?;
+                let w = 1 + (((b[1] as u32 & 0x3F) << 8) | b[0] as u32);
+                let h = 1 + (((b[3] as u32 & 0x0F) << 10) | ((b[2] as u32) << 2) | ((b[1] as u32) >> 6));
+                Some((w, h))
+            }
+            b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+            _ => None,
+        };
+    }
+    // JPEG: 扫描段标记找到 SOFn
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        let mut i = 2;
+        while i + 9 < bytes.len() {
+            if bytes[i] != 0xFF {
+                i += 1;
+                continue;
+            }
+            let marker = bytes[i + 1];
+            if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                return Some((be16(i + 7)?, be16(i + 5)?));
+            }
+            if marker == 0xD8 || marker == 0x01 || (0xD0..=0xD7).contains(&marker) || marker == 0xFF {
+                i += if marker == 0xFF { 1 } else { 2 };
+                continue;
+            }
+            i += 2 + be16(i + 2)? as usize;
+        }
+    }
+    None
+}
+
+/// base64 图片的 token 数 (无法解析尺寸时使用家族兜底值)
+pub fn image_tokens(tokenizer: &Tokenizer, data: &str) -> u32 {
+    decode_prefix(data, HEADER_B64_CHARS)
+        .and_then(|bytes| image_dimensions(&bytes))
+        .map(|(w, h)| tokens_for_dimensions(tokenizer.family(), w, h))
+        .unwrap_or(tokenizer.profile().image_fallback)
+}
+
+/// 按尺寸折算图片 token
+pub fn tokens_for_dimensions(family: ModelFamily, width: u32, height: u32) -> u32 {
+    if width == 0 || height == 0 {
+        return 0;
+    }
+    let (w, h) = (width as f64, height as f64);
+    match family {
+        ModelFamily::Claude => {
+            let mut scale = (CLAUDE_MAX_EDGE / w.max(h)).min(1.0);
+            let pixels = w * h * scale * scale;
+            if pixels > CLAUDE_MAX_PIXELS {
+                scale *= (CLAUDE_MAX_PIXELS / pixels).sqrt();
+            }
+            ((w * scale).round() * (h * scale).round() / CLAUDE_PIXELS_PER_TOKEN).ceil() as u32
+        }
+        ModelFamily::Gemini => {
+            if width <= 384 && height <= 384 {
+                return GEMINI_TILE_TOKENS;
+            }
+            let unit = (w.min(h) / 1.5).floor().clamp(256.0, 768.0);
+            let tiles = (w / unit).ceil() * (h / unit).ceil();
+            tiles as u32 * GEMINI_TILE_TOKENS
+        }
+        ModelFamily::OpenAI => {
+            let mut scale = (OPENAI_MAX_EDGE / w.max(h)).min(1.0);
+            scale *= (OPENAI_SHORT_EDGE / (w.min(h) * scale)).min(1.0);
+            let tiles = ((w * scale) / OPENAI_TILE).ceil() * ((h * scale) / OPENAI_TILE).ceil();
+            tiles as u32 * OPENAI_TILE_TOKENS + OPENAI_BASE_TOKENS
+        }
+    }
+}
+
+/// base64 PDF 的 token 数: 按 /Type /Page 对象数估算页数
+pub fn document_tokens(tokenizer: &Tokenizer, data: &str) -> u32 {
+    let pages = decode_prefix(data, usize::MAX)
+        .map(|bytes| count_pdf_pages(&bytes))
+        .unwrap_or(0)
+        .max(1);
+    pages * tokenizer.profile().document_page
+}
+
+fn count_pdf_pages(bytes: &[u8]) -> u32 {
+    let mut pages = 0;
+    for pattern in [&b"/Type /Page"[..], &b"/Type/Page"[..]] {
+        pages += bytes
+            .windows(pattern.len() + 1)
+            .filter(|w| w.starts_with(pattern) && w[pattern.len()] != b's')
+            .count() as u32;
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dimensions_and_family_formulas() {
+        // 最小 PNG 头: 1000x500
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&1000u32.to_be_bytes());
+        png.extend_from_slice(&500u32.to_be_bytes());
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        assert_eq!(image_dimensions(&png), Some((1000, 500)));
+
+        let b64 = base64::engine::general_purpose::STANDARD.encode(&png);
+        let claude = super::super::for_family(ModelFamily::Claude);
+        assert_eq!(image_tokens(claude, &b64), 667);
+        assert_eq!(image_tokens(claude, &format!("data:image/png;base64,{}", b64)), 667);
+        // 解析失败使用兜底值
+        assert_eq!(image_tokens(claude, "not-an-image"), 1600);
+
+        // Gemini: 小图一块，大图按块计
+        assert_eq!(tokens_for_dimensions(ModelFamily::Gemini, 300, 200), 258);
+        assert_eq!(tokens_for_dimensions(ModelFamily::Gemini, 1000, 500), 258 * 8);
+        // Claude 大图被缩放到 ~1.15MP 上限
+        assert!(tokens_for_dimensions(ModelFamily::Claude, 4000, 3000) <= 1536);
+
+        // OpenAI: 1024x1024 缩到 768x768 => 4 块
+        assert_eq!(tokens_for_dimensions(ModelFamily::OpenAI, 1024, 1024), 765);
+        assert_eq!(tokens_for_dimensions(ModelFamily::OpenAI, 2048, 4096), 170 * 6 + 85);
+    }
+}
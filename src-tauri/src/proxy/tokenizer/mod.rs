@@ -0,0 +1,178 @@
+//! Offline Tokenizer Module
+//!
+//! 离线 token 计数，替代原先 "4 字符/token" 的粗略估算。
+//!
+//! - 按模型家族加载打包进二进制的真实词表 (`vocab/*.kit`，kitoken 序列化格式):
+//!   Claude (Anthropic 公开的 Claude 分词器 BPE)、Gemini (Gemma 3 SentencePiece)、
+//!   OpenAI (o200k_base BPE)；词表来源与许可见 `vocab/README.md`
+//! - 每个家族另有计费参数 (`vocab/<family>.json`): 消息 / 工具 / thinking 的固定开销
+//! - 图片 / PDF 按各家公开的计费公式折算 (见 `image`)
+//! - 请求级计数 (文本、工具 Schema、图片、thinking) 见 `request`
+//!
+//! 注意: Claude 3+ 的分词器未公开，打包的是旧版 Claude 词表；
+//! 剩余误差交给 `EstimationCalibrator` 按模型学习修正。
+
+pub mod image;
+pub mod request;
+
+use kitoken::Kitoken;
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+pub use request::{count_claude_request, count_gemini_request};
+
+const CLAUDE_VOCAB: &[u8] = include_bytes!("vocab/claude.kit");
+const GEMMA_VOCAB: &[u8] = include_bytes!("vocab/gemma.kit");
+const O200K_VOCAB: &[u8] = include_bytes!("vocab/o200k.kit");
+const CLAUDE_PROFILE: &str = include_str!("vocab/claude.json");
+const GEMINI_PROFILE: &str = include_str!("vocab/gemini.json");
+const OPENAI_PROFILE: &str = include_str!("vocab/openai.json");
+
+/// 模型家族，决定使用哪套词表与计费参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModelFamily {
+    Claude,
+    Gemini,
+    OpenAI,
+}
+
+impl ModelFamily {
+    /// 根据模型名判断家族 (未知模型按 Gemini 处理，与默认上游一致)
+    pub fn from_model(model: &str) -> Self {
+        let lower = model.to_lowercase();
+        let name = lower.rsplit('/').next().unwrap_or(&lower);
+        if ["claude", "opus", "sonnet", "haiku"].iter().any(|k| name.contains(k)) {
+            ModelFamily::Claude
+        } else if name.contains("gpt")
+            || name.starts_with("text-embedding")
+            || ["o1", "o3", "o4"].iter().any(|p| name == *p || name.starts_with(&format!("{}-", p)))
+        {
+            ModelFamily::OpenAI
+        } else {
+            ModelFamily::Gemini
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelFamily::Claude => "claude",
+            ModelFamily::Gemini => "gemini",
+            ModelFamily::OpenAI => "openai",
+        }
+    }
+}
+
+/// 家族计费参数 (vocab/<family>.json)
+#[derive(Debug, Clone, Deserialize)]
+pub struct Profile {
+    /// 每个请求的固定开销
+    pub request_overhead: u32,
+    /// 每条消息 (content) 的固定开销
+    pub message_overhead: u32,
+    /// 每个工具定义的固定开销
+    pub tool_overhead: u32,
+    /// 声明了工具时注入的系统提示开销 (仅计一次)
+    pub tools_preamble: u32,
+    /// 每次 tool_use / tool_result (functionCall / functionResponse) 的固定开销
+    pub tool_call_overhead: u32,
+    /// 每个 thinking 签名的开销
+    pub thinking_signature: u32,
+    /// 历史轮次的 thinking 是否计入输入 (Claude 会丢弃历史 thinking)
+    pub count_prior_thinking: bool,
+    /// 无法解析尺寸的图片 / 媒体的兜底 token 数
+    pub image_fallback: u32,
+    /// PDF 每页 token 数
+    pub document_page: u32,
+}
+
+/// 某个模型家族的离线分词器
+pub struct Tokenizer {
+    family: ModelFamily,
+    profile: Profile,
+    vocab: Kitoken,
+}
+
+/// 按家族获取分词器 (首次使用时反序列化词表)
+pub fn for_family(family: ModelFamily) -> &'static Tokenizer {
+    static CLAUDE: OnceLock<Tokenizer> = OnceLock::new();
+    static GEMINI: OnceLock<Tokenizer> = OnceLock::new();
+    static OPENAI: OnceLock<Tokenizer> = OnceLock::new();
+    match family {
+        ModelFamily::Claude => CLAUDE.get_or_init(|| Tokenizer::load(family, CLAUDE_VOCAB, CLAUDE_PROFILE)),
+        ModelFamily::Gemini => GEMINI.get_or_init(|| Tokenizer::load(family, GEMMA_VOCAB, GEMINI_PROFILE)),
+        ModelFamily::OpenAI => OPENAI.get_or_init(|| Tokenizer::load(family, O200K_VOCAB, OPENAI_PROFILE)),
+    }
+}
+
+/// 按模型名获取分词器
+pub fn for_model(model: &str) -> &'static Tokenizer {
+    for_family(ModelFamily::from_model(model))
+}
+
+impl Tokenizer {
+    fn load(family: ModelFamily, vocab: &[u8], profile_json: &str) -> Self {
+        let profile: Profile = serde_json::from_str(profile_json)
+            .unwrap_or_else(|e| panic!("invalid bundled {} tokenizer profile: {}", family.as_str(), e));
+        let vocab = Kitoken::from_slice(vocab)
+            .unwrap_or_else(|e| panic!("invalid bundled {} vocabulary: {:?}", family.as_str(), e));
+        Self { family, profile, vocab }
+    }
+
+    pub fn family(&self) -> ModelFamily {
+        self.family
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// 计算一段文本的 token 数 (特殊 token 字面量按普通文本切分)
+    pub fn count_text(&self, text: &str) -> u32 {
+        if text.is_empty() {
+            return 0;
+        }
+        match self.vocab.encode(text, false) {
+            Ok(tokens) => tokens.len() as u32,
+            Err(e) => {
+                tracing::debug!("[Tokenizer] {} encode failed, falling back to bytes/4: {:?}", self.family.as_str(), e);
+                text.len().div_ceil(4) as u32
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_families() {
+        assert_eq!(ModelFamily::from_model("claude-sonnet-4-5-thinking"), ModelFamily::Claude);
+        assert_eq!(ModelFamily::from_model("models/gemini-3-flash"), ModelFamily::Gemini);
+        assert_eq!(ModelFamily::from_model("gpt-4o-mini"), ModelFamily::OpenAI);
+        assert_eq!(ModelFamily::from_model("o3-mini"), ModelFamily::OpenAI);
+        assert_eq!(ModelFamily::from_model("text-embedding-3-small"), ModelFamily::OpenAI);
+        assert_eq!(ModelFamily::from_model("gemini-embedding-001"), ModelFamily::Gemini);
+    }
+
+    #[test]
+    fn test_bundled_vocabularies() {
+        let claude = for_family(ModelFamily::Claude);
+        let gemini = for_family(ModelFamily::Gemini);
+        let openai = for_family(ModelFamily::OpenAI);
+
+        for tokenizer in [claude, gemini, openai] {
+            assert_eq!(tokenizer.count_text("Hello world"), 2);
+            assert_eq!(tokenizer.count_text(""), 0);
+        }
+        // Gemma 逐位切分数字，Claude / o200k 三位一组
+        assert_eq!(gemini.count_text("2025"), 4);
+        assert_eq!(claude.count_text("2025"), 2);
+        assert_eq!(openai.count_text("2025"), 2);
+        // Gemma / o200k 的词表对中文更紧凑
+        assert!(gemini.count_text("你好世界") < claude.count_text("你好世界"));
+        assert!(openai.count_text("你好世界") < claude.count_text("你好世界"));
+        // 特殊 token 字面量不会被当作单个控制 token
+        assert!(openai.count_text("<|endoftext|>") > 1);
+    }
+}
@@ -0,0 +1,323 @@
+//! 请求级 token 计数 (Claude Messages / Gemini generateContent)
+
+use serde_json::Value;
+
+use super::image::{document_tokens, image_tokens};
+use super::Tokenizer;
+use crate::proxy::mappers::claude::models::{ClaudeRequest, ContentBlock, MessageContent, SystemPrompt};
+
+/// 按来源拆分的 token 计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenCount {
+    /// 系统提示、消息文本、工具调用参数与结果
+    pub text: u32,
+    /// 工具定义 (名称、描述、参数 Schema)
+    pub tools: u32,
+    /// 图片与文档
+    pub images: u32,
+    /// thinking 内容与签名
+    pub thinking: u32,
+    /// 请求 / 消息 / 工具调用的固定开销
+    pub overhead: u32,
+}
+
+impl TokenCount {
+    pub fn total(&self) -> u32 {
+        self.text + self.tools + self.images + self.thinking + self.overhead
+    }
+}
+
+fn count_json(tokenizer: &Tokenizer, value: &Value) -> u32 {
+    match value {
+        Value::String(s) => tokenizer.count_text(s),
+        Value::Null => 0,
+        other => tokenizer.count_text(&other.to_string()),
+    }
+}
+
+/// 计算 Claude Messages 请求的输入 token
+pub fn count_claude_request(request: &ClaudeRequest, model: &str) -> TokenCount {
+    let tokenizer = super::for_model(model);
+    let profile = tokenizer.profile();
+    let mut count = TokenCount {
+        overhead: profile.request_overhead,
+        ..Default::default()
+    };
+
+    match &request.system {
+        Some(SystemPrompt::Text(s)) => count.text += tokenizer.count_text(s),
+        Some(SystemPrompt::Blocks(blocks)) => {
+            for block in blocks {
+                count.text += tokenizer.count_text(&block.text);
+            }
+        }
+        None => {}
+    }
+
+    // 只有最后一条 assistant 消息 (当前工具调用轮) 的 thinking 一定会被计入
+    let last_assistant = request.messages.iter().rposition(|m| m.role == "assistant");
+
+    for (idx, msg) in request.messages.iter().enumerate() {
+        count.overhead += profile.message_overhead;
+        let count_thinking = profile.count_prior_thinking || Some(idx) == last_assistant;
+
+        let blocks = match &msg.content {
+            MessageContent::String(s) => {
+                count.text += tokenizer.count_text(s);
+                continue;
+            }
+            MessageContent::Array(blocks) => blocks,
+        };
+
+        for block in blocks {
+            match block {
+                ContentBlock::Text { text, .. } => count.text += tokenizer.count_text(text),
+                ContentBlock::Thinking { thinking, signature, .. } => {
+                    if count_thinking {
+                        count.thinking += tokenizer.count_text(thinking);
+                        if signature.is_some() {
+                            count.thinking += profile.thinking_signature;
+                        }
+                    }
+                }
+                ContentBlock::RedactedThinking { data } => {
+                    if count_thinking {
+                        count.thinking += tokenizer.count_text(data);
+                    }
+                }
+                ContentBlock::Image { source, .. } => {
+                    count.images += if source.source_type == "base64" {
+                        image_tokens(tokenizer, &source.data)
+                    } else {
+                        profile.image_fallback
+                    };
+                }
+                ContentBlock::Document { source, .. } => {
+                    if source.source_type == "text" || source.media_type.starts_with("text/") {
+                        count.text += tokenizer.count_text(&source.data);
+                    } else {
+                        count.images += document_tokens(tokenizer, &source.data);
+                    }
+                }
+                ContentBlock::ToolUse { name, input, .. } | ContentBlock::ServerToolUse { name, input, .. } => {
+                    count.overhead += profile.tool_call_overhead;
+                    count.text += tokenizer.count_text(name) + count_json(tokenizer, input);
+                }
+                ContentBlock::ToolResult { content, .. } => {
+                    count.overhead += profile.tool_call_overhead;
+                    count_tool_result(tokenizer, content, &mut count);
+                }
+                ContentBlock::WebSearchToolResult { content, .. } => {
+                    count.text += count_json(tokenizer, content);
+                }
+            }
+        }
+    }
+
+    if let Some(tools) = &request.tools {
+        if tools.iter().any(|t| t.input_schema.is_some()) {
+            count.tools += profile.tools_preamble;
+        }
+        for tool in tools {
+            count.tools += profile.tool_overhead;
+            if let Ok(value) = serde_json::to_value(tool) {
+                count.tools += count_json(tokenizer, &value);
+            }
+        }
+    }
+
+    count
+}
+
+/// tool_result 的内容可能是字符串，也可能是 text / image 块数组
+fn count_tool_result(tokenizer: &Tokenizer, content: &Value, count: &mut TokenCount) {
+    let Some(items) = content.as_array() else {
+        count.text += count_json(tokenizer, content);
+        return;
+    };
+    for item in items {
+        match item.get("type").and_then(|t| t.as_str()) {
+            Some("text") => {
+                count.text += tokenizer.count_text(item.get("text").and_then(|t| t.as_str()).unwrap_or(""));
+            }
+            Some("image") => {
+                count.images += match item.pointer("/source/data").and_then(|d| d.as_str()) {
+                    Some(data) => image_tokens(tokenizer, data),
+                    None => tokenizer.profile().image_fallback,
+                };
+            }
+            _ => count.text += count_json(tokenizer, item),
+        }
+    }
+}
+
+/// 同时兼容 camelCase 与 snake_case 字段 (Gemini REST 两种都接受)
+fn field<'a>(value: &'a Value, camel: &str, snake: &str) -> Option<&'a Value> {
+    value.get(camel).or_else(|| value.get(snake))
+}
+
+/// 计算 Gemini generateContent / countTokens 请求的输入 token
+///
+/// 支持 `{contents, systemInstruction, tools}`、`{generateContentRequest: {...}}`
+/// 以及 v1internal 的 `{project, model, request: {...}}` 格式。
+pub fn count_gemini_request(body: &Value, model: &str) -> TokenCount {
+    let tokenizer = super::for_model(model);
+    let profile = tokenizer.profile();
+    let body = field(body, "generateContentRequest", "generate_content_request")
+        .or_else(|| body.get("request").filter(|r| r.is_object()))
+        .unwrap_or(body);
+    let mut count = TokenCount {
+        overhead: profile.request_overhead,
+        ..Default::default()
+    };
+
+    if let Some(parts) = field(body, "systemInstruction", "system_instruction")
+        .and_then(|s| s.get("parts"))
+        .and_then(|p| p.as_array())
+    {
+        for part in parts {
+            count_gemini_part(tokenizer, part, true, &mut count);
+        }
+    }
+
+    let contents = body.get("contents").and_then(|c| c.as_array()).map(|c| c.as_slice()).unwrap_or(&[]);
+    let last_model = contents
+        .iter()
+        .rposition(|c| c.get("role").and_then(|r| r.as_str()) == Some("model"));
+    for (idx, content) in contents.iter().enumerate() {
+        count.overhead += profile.message_overhead;
+        let count_thinking = profile.count_prior_thinking || Some(idx) == last_model;
+        if let Some(parts) = content.get("parts").and_then(|p| p.as_array()) {
+            for part in parts {
+                count_gemini_part(tokenizer, part, count_thinking, &mut count);
+            }
+        }
+    }
+
+    if let Some(tools) = body.get("tools").and_then(|t| t.as_array()) {
+        let mut declared = false;
+        for tool in tools {
+            match field(tool, "functionDeclarations", "function_declarations").and_then(|d| d.as_array()) {
+                Some(decls) => {
+                    declared |= !decls.is_empty();
+                    for decl in decls {
+                        count.tools += profile.tool_overhead + count_json(tokenizer, decl);
+                    }
+                }
+                // googleSearch / codeExecution 等内置工具
+                None => count.tools += profile.tool_overhead,
+            }
+        }
+        if declared {
+            count.tools += profile.tools_preamble;
+        }
+    }
+
+    count
+}
+
+fn count_gemini_part(tokenizer: &Tokenizer, part: &Value, count_thinking: bool, count: &mut TokenCount) {
+    let profile = tokenizer.profile();
+    let is_thought = part.get("thought").and_then(|t| t.as_bool()).unwrap_or(false);
+
+    if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+        if !is_thought {
+            count.text += tokenizer.count_text(text);
+        } else if count_thinking {
+            count.thinking += tokenizer.count_text(text);
+        }
+    }
+    if count_thinking && field(part, "thoughtSignature", "thought_signature").is_some() {
+        count.thinking += profile.thinking_signature;
+    }
+
+    if let Some(inline) = field(part, "inlineData", "inline_data") {
+        let mime = field(inline, "mimeType", "mime_type").and_then(|m| m.as_str()).unwrap_or("");
+        let data = inline.get("data").and_then(|d| d.as_str()).unwrap_or("");
+        count.images += if mime.starts_with("image/") {
+            image_tokens(tokenizer, data)
+        } else if mime == "application/pdf" {
+            document_tokens(tokenizer, data)
+        } else {
+            // 音视频时长无法离线获取，按兜底值计
+            profile.image_fallback
+        };
+    }
+    if field(part, "fileData", "file_data").is_some() {
+        count.images += profile.image_fallback;
+    }
+
+    for (camel, snake, payload) in [
+        ("functionCall", "function_call", "args"),
+        ("functionResponse", "function_response", "response"),
+    ] {
+        if let Some(call) = field(part, camel, snake) {
+            count.overhead += profile.tool_call_overhead;
+            count.text += call.get("name").map(|n| count_json(tokenizer, n)).unwrap_or(0);
+            count.text += call.get(payload).map(|p| count_json(tokenizer, p)).unwrap_or(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_claude_request_buckets() {
+        let request: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "system": "You are a helpful assistant",
+            "tools": [{
+                "name": "get_weather",
+                "description": "Get the weather for a city",
+                "input_schema": { "type": "object", "properties": { "city": { "type": "string" } } }
+            }],
+            "messages": [
+                { "role": "user", "content": "What is the weather in Paris?" },
+                { "role": "assistant", "content": [
+                    { "type": "thinking", "thinking": "I should call the tool", "signature": "sig" },
+                    { "type": "tool_use", "id": "t1", "name": "get_weather", "input": { "city": "Paris" } }
+                ]},
+                { "role": "user", "content": [
+                    { "type": "tool_result", "tool_use_id": "t1", "content": "Sunny, 25 degrees" }
+                ]}
+            ]
+        }))
+        .unwrap();
+
+        let count = count_claude_request(&request, "claude-sonnet-4-5");
+        assert!(count.text > 0);
+        // 声明了工具: 注入的工具系统提示 + 定义本身
+        assert!(count.tools > 346);
+        // 当前工具调用轮的 thinking 计入 (含签名)
+        assert!(count.thinking > 100);
+        assert_eq!(count.images, 0);
+        assert_eq!(count.total(), count.text + count.tools + count.thinking + count.overhead);
+    }
+
+    #[test]
+    fn test_gemini_request_formats() {
+        let contents = json!([
+            { "role": "user", "parts": [{ "text": "Describe this picture" }, { "inlineData": { "mimeType": "image/png", "data": "???" } }] },
+            { "role": "model", "parts": [{ "text": "thinking...", "thought": true }, { "functionCall": { "name": "lookup", "args": { "q": "cat" } } }] }
+        ]);
+        let plain = count_gemini_request(&json!({ "contents": contents }), "gemini-3-flash");
+        let wrapped = count_gemini_request(
+            &json!({ "generateContentRequest": { "model": "models/gemini-3-flash", "contents": contents } }),
+            "gemini-3-flash",
+        );
+        assert_eq!(plain, wrapped);
+        let v1internal = count_gemini_request(
+            &json!({ "project": "p", "model": "gemini-3-flash", "request": { "contents": contents } }),
+            "gemini-3-flash",
+        );
+        assert_eq!(plain, v1internal);
+        // 无法解析的图片按兜底 258 计
+        assert_eq!(plain.images, 258);
+        assert!(plain.thinking > 0);
+        assert_eq!(plain.tools, 0);
+    }
+}
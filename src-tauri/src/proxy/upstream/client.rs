@@ -16,9 +16,13 @@ const V1_INTERNAL_BASE_URL_FALLBACKS: [&str; 2] = [
     V1_INTERNAL_BASE_URL_DAILY,   // 优先级 2: Daily/Sandbox (备用)
 ];
 
+/// 上游请求总超时 (流式响应可能持续较久)
+const UPSTREAM_TIMEOUT_SECS: u64 = 600;
+
 pub struct UpstreamClient {
     http_client: Client,
     user_agent_override: RwLock<Option<String>>,
+    base_urls: Vec<String>, // v1internal 端点 (按顺序回退)
 }
 
 impl UpstreamClient {
@@ -29,7 +33,7 @@ impl UpstreamClient {
             .pool_max_idle_per_host(16)                  // 每主机最多 16 个空闲连接
             .pool_idle_timeout(Duration::from_secs(90))  // 空闲连接保持 90 秒
             .tcp_keepalive(Duration::from_secs(60))      // TCP 保活探测 60 秒
-            .timeout(Duration::from_secs(600))
+            .timeout(Duration::from_secs(UPSTREAM_TIMEOUT_SECS))
             .user_agent(crate::constants::USER_AGENT.as_str());
 
         if let Some(config) = proxy_config {
@@ -46,9 +50,16 @@ impl UpstreamClient {
         Self { 
             http_client,
             user_agent_override: RwLock::new(None),
+            base_urls: V1_INTERNAL_BASE_URL_FALLBACKS.iter().map(|s| s.to_string()).collect(),
         }
     }
 
+    /// 替换 v1internal 端点 (用于 cassette 回放时指向本地 mock 上游)
+    pub fn with_base_urls(mut self, base_urls: Vec<String>) -> Self {
+        self.base_urls = base_urls;
+        self
+    }
+
     /// 设置动态 User-Agent 覆盖
     pub async fn set_user_agent_override(&self, ua: Option<String>) {
         let mut lock = self.user_agent_override.write().await;
@@ -90,14 +101,16 @@ impl UpstreamClient {
     /// 调用 v1internal API（基础方法）
     /// 
     /// 发起基础网络请求，支持多端点自动 Fallback
+    /// `account_proxy` 为账号独立代理 (Account.individual_proxy)，None 时走全局上游代理
     pub async fn call_v1_internal(
         &self,
         method: &str,
         access_token: &str,
         body: Value,
         query_string: Option<&str>,
+        account_proxy: Option<&str>,
     ) -> Result<Response, String> {
-        self.call_v1_internal_with_headers(method, access_token, body, query_string, std::collections::HashMap::new()).await
+        self.call_v1_internal_with_headers(method, access_token, body, query_string, std::collections::HashMap::new(), account_proxy).await
     }
 
     /// [FIX #765] 调用 v1internal API，支持透传额外的 Headers
@@ -108,7 +121,12 @@ impl UpstreamClient {
         body: Value,
         query_string: Option<&str>,
         extra_headers: std::collections::HashMap<String, String>,
+        account_proxy: Option<&str>,
     ) -> Result<Response, String> {
+        // [NEW] 账号独立代理 (不可用时按配置回退)
+        let routed = super::proxy_pool::pool().client_for(account_proxy, UPSTREAM_TIMEOUT_SECS)?;
+        let http_client = routed.client.as_ref().unwrap_or(&self.http_client);
+
         // 构建 Headers (所有端点复用)
         let mut headers = header::HeaderMap::new();
         headers.insert(
@@ -143,17 +161,17 @@ impl UpstreamClient {
         let mut last_err: Option<String> = None;
 
         // 遍历所有端点，失败时自动切换
-        for (idx, base_url) in V1_INTERNAL_BASE_URL_FALLBACKS.iter().enumerate() {
+        for (idx, base_url) in self.base_urls.iter().enumerate() {
             let url = Self::build_url(base_url, method, query_string);
-            let has_next = idx + 1 < V1_INTERNAL_BASE_URL_FALLBACKS.len();
+            let has_next = idx + 1 < self.base_urls.len();
 
-            let response = self
-                .http_client
+            let response = http_client
                 .post(&url)
                 .headers(headers.clone())
                 .json(&body)
                 .send()
                 .await;
+            routed.observe(&response);
 
             match response {
                 Ok(resp) => {
@@ -164,7 +182,7 @@ impl UpstreamClient {
                                 "✓ Upstream fallback succeeded | Endpoint: {} | Status: {} | Next endpoints available: {}",
                                 base_url,
                                 status,
-                                V1_INTERNAL_BASE_URL_FALLBACKS.len() - idx - 1
+                                self.base_urls.len() - idx - 1
                             );
                         } else {
                             tracing::debug!("✓ Upstream request succeeded | Endpoint: {} | Status: {}", base_url, status);
@@ -252,7 +270,7 @@ impl UpstreamClient {
         let mut last_err: Option<String> = None;
 
         // 遍历所有端点，失败时自动切换
-        for (idx, base_url) in V1_INTERNAL_BASE_URL_FALLBACKS.iter().enumerate() {
+        for (idx, base_url) in self.base_urls.iter().enumerate() {
             let url = Self::build_url(base_url, "fetchAvailableModels", None);
 
             let response = self
@@ -284,7 +302,7 @@ impl UpstreamClient {
                     }
 
                     // 如果有下一个端点且当前错误可重试，则切换
-                    let has_next = idx + 1 < V1_INTERNAL_BASE_URL_FALLBACKS.len();
+                    let has_next = idx + 1 < self.base_urls.len();
                     if has_next && Self::should_try_next_endpoint(status) {
                         tracing::warn!(
                             "fetchAvailableModels returned {} at {}, trying next endpoint",
@@ -304,7 +322,7 @@ impl UpstreamClient {
                     last_err = Some(msg);
 
                     // 如果是最后一个端点，退出循环
-                    if idx + 1 >= V1_INTERNAL_BASE_URL_FALLBACKS.len() {
+                    if idx + 1 >= self.base_urls.len() {
                         break;
                     }
                     continue;
@@ -2,5 +2,6 @@
 // 对应上游通讯接口
 
 pub mod client;
+pub mod proxy_pool; // 账号独立代理连接池 (健康检查 / 故障回退)
 pub mod retry;
 pub mod models;
@@ -36,8 +36,12 @@ pub fn create_client_with_proxy(
     builder.build().unwrap_or_else(|_| Client::new())
 }
 
-/// 获取全局默认 HTTP 客户端
-pub fn get_client() -> Client {
-    // 默认 300 秒超时
-    create_client(300)
+/// 创建账号请求使用的 HTTP 客户端
+/// 配置了账号独立代理时复用代理池中的客户端，代理不可用时按回退规则处理
+pub fn create_client_for_account(
+    timeout_secs: u64,
+    individual_proxy: Option<&str>,
+) -> Result<Client, String> {
+    let routed = crate::proxy::upstream::proxy_pool::pool().client_for(individual_proxy, timeout_secs)?;
+    Ok(routed.client.unwrap_or_else(|| create_client(timeout_secs)))
 }
@@ -8,6 +8,7 @@ import ApiProxy from './pages/ApiProxy';
 import Monitor from './pages/Monitor';
 import Security from './pages/Security';
 import ThemeManager from './components/common/ThemeManager';
+import VaultUnlockDialog from './components/common/VaultUnlockDialog';
 import { UpdateNotification } from './components/UpdateNotification';
 import { useEffect, useState } from 'react';
 import { useConfigStore } from './stores/useConfigStore';
@@ -121,6 +122,12 @@ function App() {
   return (
     <>
       <ThemeManager />
+      <VaultUnlockDialog
+        onUnlocked={() => {
+          fetchCurrentAccount();
+          fetchAccounts();
+        }}
+      />
       {showUpdateNotification && (
         <UpdateNotification onClose={() => setShowUpdateNotification(false)} />
       )}
@@ -0,0 +1,118 @@
+import { useEffect, useState } from 'react';
+import { createPortal } from 'react-dom';
+import { Lock } from 'lucide-react';
+import { useTranslation } from 'react-i18next';
+import { invoke } from '@tauri-apps/api/core';
+
+export interface VaultStatus {
+    enabled: boolean;
+    unlocked: boolean;
+    mode: 'passphrase' | 'key_file' | null;
+}
+
+// 选择 "稍后" 后，可通过该事件重新打开解锁弹窗 (例如账号页的锁定提示)
+export const VAULT_UNLOCK_EVENT = 'vault:request-unlock';
+// 解锁成功后广播，便于页面隐藏锁定提示
+export const VAULT_UNLOCKED_EVENT = 'vault:unlocked';
+
+export function isVaultLocked(status: VaultStatus): boolean {
+    return status.enabled && !status.unlocked && status.mode === 'passphrase';
+}
+
+export function requestVaultUnlock() {
+    window.dispatchEvent(new Event(VAULT_UNLOCK_EVENT));
+}
+
+interface VaultUnlockDialogProps {
+    onUnlocked: () => void;
+}
+
+// 口令模式的账号保险库在启动时处于锁定状态，弹窗输入口令解锁
+export default function VaultUnlockDialog({ onUnlocked }: VaultUnlockDialogProps) {
+    const { t } = useTranslation();
+    const [isOpen, setIsOpen] = useState(false);
+    const [passphrase, setPassphrase] = useState('');
+    const [error, setError] = useState<string | null>(null);
+    const [unlocking, setUnlocking] = useState(false);
+
+    useEffect(() => {
+        const openIfLocked = () => {
+            invoke<VaultStatus>('get_vault_status')
+                .then(status => {
+                    if (isVaultLocked(status)) {
+                        setIsOpen(true);
+                    }
+                })
+                .catch(err => console.error('Failed to get vault status:', err));
+        };
+
+        openIfLocked();
+        window.addEventListener(VAULT_UNLOCK_EVENT, openIfLocked);
+        return () => window.removeEventListener(VAULT_UNLOCK_EVENT, openIfLocked);
+    }, []);
+
+    const handleUnlock = async () => {
+        if (!passphrase || unlocking) return;
+        setUnlocking(true);
+        setError(null);
+        try {
+            await invoke<VaultStatus>('unlock_vault', { passphrase });
+            setIsOpen(false);
+            setPassphrase('');
+            window.dispatchEvent(new Event(VAULT_UNLOCKED_EVENT));
+            onUnlocked();
+        } catch (err) {
+            setError(String(err));
+        } finally {
+            setUnlocking(false);
+        }
+    };
+
+    if (!isOpen) return null;
+
+    return createPortal(
+        <div className="modal modal-open z-[100]">
+            <div data-tauri-drag-region className="fixed top-0 left-0 right-0 h-8 z-[110]" />
+
+            <div className="modal-box relative max-w-sm bg-white dark:bg-base-100 shadow-2xl rounded-2xl p-0 overflow-hidden">
+                <div className="flex flex-col items-center text-center p-6 pt-8">
+                    <div className="w-14 h-14 rounded-full flex items-center justify-center mb-4 shadow-sm bg-blue-50 dark:bg-blue-900/20">
+                        <Lock className="w-7 h-7 text-blue-500" />
+                    </div>
+
+                    <h3 className="text-xl font-bold text-gray-900 dark:text-base-content mb-2">{t('vault.unlock_title')}</h3>
+                    <p className="text-gray-500 dark:text-gray-400 text-sm mb-4 leading-relaxed px-4">{t('vault.unlock_desc')}</p>
+
+                    <input
+                        type="password"
+                        autoFocus
+                        className="input input-bordered w-full mb-2"
+                        placeholder={t('vault.passphrase_placeholder')}
+                        value={passphrase}
+                        onChange={e => setPassphrase(e.target.value)}
+                        onKeyDown={e => e.key === 'Enter' && handleUnlock()}
+                    />
+                    {error && <p className="text-red-500 text-xs mb-2 w-full text-left">{error}</p>}
+
+                    <div className="flex gap-3 w-full mt-4">
+                        <button
+                            className="flex-1 px-4 py-2.5 bg-gray-100 dark:bg-base-200 text-gray-700 dark:text-gray-300 font-medium rounded-xl hover:bg-gray-200 dark:hover:bg-base-300 transition-colors"
+                            onClick={() => setIsOpen(false)}
+                        >
+                            {t('vault.later')}
+                        </button>
+                        <button
+                            className="flex-1 px-4 py-2.5 text-white font-medium rounded-xl shadow-md transition-all bg-blue-500 hover:bg-blue-600 disabled:opacity-50"
+                            disabled={!passphrase || unlocking}
+                            onClick={handleUnlock}
+                        >
+                            {unlocking ? t('vault.unlocking') : t('vault.unlock')}
+                        </button>
+                    </div>
+                </div>
+            </div>
+            <div className="modal-backdrop bg-black/40 backdrop-blur-sm fixed inset-0 z-[-1]"></div>
+        </div>,
+        document.body
+    );
+}
@@ -16,6 +16,8 @@ interface ProxyRequestLog {
     duration: number;
     model?: string;
     mapped_model?: string;
+    fallback_from?: string;
+    cache_status?: string;
     error?: string;
     request_body?: string;
     response_body?: string;
@@ -205,7 +207,10 @@ export const ProxyMonitor: React.FC<ProxyMonitorProps> = ({ className }) => {
                     <tbody className="font-mono text-gray-700 dark:text-gray-300">
                         {filteredLogs.map(log => (
                             <tr key={log.id} className="hover:bg-blue-50 dark:hover:bg-blue-900/20 cursor-pointer" onClick={() => setSelectedLog(log)}>
-                                <td><span className={`badge badge-xs text-white border-none ${log.status >= 200 && log.status < 400 ? 'badge-success' : 'badge-error'}`}>{log.status}</span></td>
+                                <td>
+                                    <span className={`badge badge-xs text-white border-none ${log.status >= 200 && log.status < 400 ? 'badge-success' : 'badge-error'}`}>{log.status}</span>
+                                    {log.cache_status === 'HIT' && <span className="badge badge-xs badge-info text-white border-none ml-1">{t('monitor.table.cache_hit')}</span>}
+                                </td>
                                 <td className="font-bold">{log.method}</td>
                                 <td className="text-blue-600 truncate max-w-[180px]">
                                     {log.mapped_model && log.model !== log.mapped_model
@@ -274,6 +279,18 @@ export const ProxyMonitor: React.FC<ProxyMonitorProps> = ({ className }) => {
                                                 <span className="font-mono font-black text-green-600 dark:text-green-400 break-all text-sm">{selectedLog.mapped_model}</span>
                                             </div>
                                         )}
+                                        {selectedLog.fallback_from && (
+                                            <div className="space-y-1.5">
+                                                <span className="block text-gray-500 dark:text-slate-400 uppercase font-black text-[10px] tracking-widest">{t('monitor.details.fallback_from')}</span>
+                                                <span className="font-mono font-black text-orange-600 dark:text-orange-400 break-all text-sm">{selectedLog.fallback_from}</span>
+                                            </div>
+                                        )}
+                                        {selectedLog.cache_status && (
+                                            <div className="space-y-1.5">
+                                                <span className="block text-gray-500 dark:text-slate-400 uppercase font-black text-[10px] tracking-widest">{t('monitor.details.cache_status')}</span>
+                                                <span className={`font-mono font-black break-all text-sm ${selectedLog.cache_status === 'HIT' ? 'text-cyan-600 dark:text-cyan-400' : 'text-gray-600 dark:text-gray-400'}`}>{selectedLog.cache_status}</span>
+                                            </div>
+                                        )}
                                     </div>
                                 </div>
                                 {selectedLog.account_email && (
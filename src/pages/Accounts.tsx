@@ -2,7 +2,7 @@ import { useState, useEffect, useMemo, useRef } from 'react';
 import { save } from '@tauri-apps/plugin-dialog';
 import { request as invoke } from '../utils/request';
 import { join } from '@tauri-apps/api/path';
-import { Search, RefreshCw, Download, Trash2, LayoutGrid, List, ToggleLeft, ToggleRight } from 'lucide-react';
+import { Search, RefreshCw, Download, Trash2, LayoutGrid, List, ToggleLeft, ToggleRight, Lock } from 'lucide-react';
 import { useAccountStore } from '../stores/useAccountStore';
 import { useConfigStore } from '../stores/useConfigStore';
 import AccountTable from '../components/accounts/AccountTable';
@@ -11,6 +11,7 @@ import DeviceFingerprintDialog from '../components/accounts/DeviceFingerprintDia
 import AccountDetailsDialog from '../components/accounts/AccountDetailsDialog';
 import AddAccountDialog from '../components/accounts/AddAccountDialog';
 import ModalDialog from '../components/common/ModalDialog';
+import { VaultStatus, VAULT_UNLOCKED_EVENT, isVaultLocked, requestVaultUnlock } from '../components/common/VaultUnlockDialog';
 import Pagination from '../components/common/Pagination';
 import { showToast } from '../components/common/ToastContainer';
 import { Account } from '../types/account';
@@ -117,6 +118,17 @@ function Accounts() {
         fetchAccounts();
     }, []);
 
+    // 保险库处于锁定状态时显示提示，可重新打开解锁弹窗
+    const [vaultLocked, setVaultLocked] = useState(false);
+    useEffect(() => {
+        invoke<VaultStatus>('get_vault_status')
+            .then(status => setVaultLocked(isVaultLocked(status)))
+            .catch(() => setVaultLocked(false));
+        const onUnlocked = () => setVaultLocked(false);
+        window.addEventListener(VAULT_UNLOCKED_EVENT, onUnlocked);
+        return () => window.removeEventListener(VAULT_UNLOCKED_EVENT, onUnlocked);
+    }, []);
+
     // Reset pagination when view mode changes to avoid empty pages or confusion
     useEffect(() => {
         setCurrentPage(1);
@@ -476,6 +488,19 @@ function Accounts() {
         <div className="h-full flex flex-col p-5 gap-4 max-w-7xl mx-auto w-full">
             {/* 测试按钮 - 在最顶部 */}
 
+            {vaultLocked && (
+                <div className="flex-none flex items-center gap-3 px-4 py-2.5 rounded-xl bg-amber-50 dark:bg-amber-900/20 border border-amber-200 dark:border-amber-800/40">
+                    <Lock className="w-4 h-4 text-amber-600 dark:text-amber-400 shrink-0" />
+                    <span className="flex-1 text-sm text-amber-800 dark:text-amber-200">{t('vault.locked_banner')}</span>
+                    <button
+                        className="px-3 py-1.5 text-xs font-medium text-white bg-blue-500 hover:bg-blue-600 rounded-lg transition-colors"
+                        onClick={requestVaultUnlock}
+                    >
+                        {t('vault.unlock')}
+                    </button>
+                </div>
+            )}
+
             {/* 顶部工具栏：搜索、过滤和操作按钮 */}
             <div className="flex-none flex items-center gap-2">
                 {/* 搜索框 */}
@@ -3,6 +3,15 @@ export interface UpstreamProxyConfig {
     url: string;
 }
 
+export type AccountProxyFallback = 'upstream_proxy' | 'direct' | 'skip_account';
+
+export interface AccountProxyConfig {
+    fallback: AccountProxyFallback;
+    failure_threshold: number;
+    health_check_interval_secs: number;
+    health_check_url: string;
+}
+
 export interface ProxyConfig {
     enabled: boolean;
     allow_lan_access?: boolean;
@@ -14,8 +23,15 @@ export interface ProxyConfig {
     request_timeout: number;
     enable_logging: boolean;
     upstream_proxy: UpstreamProxyConfig;
+    account_proxy?: AccountProxyConfig;
     zai?: ZaiConfig;
     scheduling?: StickySessionConfig;
+    routing?: RoutingConfig;
+    model_fallback?: ModelFallbackConfig;
+    model_router?: ModelRouterConfig;
+    response_cache?: ResponseCacheConfig;
+    monitor_policy?: MonitorPolicyConfig;
+    mcp_gateway?: McpGatewayConfig;
 }
 
 export type SchedulingMode = 'CacheFirst' | 'Balance' | 'PerformanceFirst';
@@ -25,6 +41,108 @@ export interface StickySessionConfig {
     max_wait_seconds: number;
 }
 
+export type RoutingPolicyKind = 'default' | 'least_recently_used' | 'quota_weighted' | 'tier_first' | 'cost_aware';
+
+export interface RoutingRule {
+    model_pattern: string;
+    policy: RoutingPolicyKind;
+}
+
+export interface RoutingConfig {
+    default_policy: RoutingPolicyKind;
+    rules: RoutingRule[];
+    credit_multipliers?: Record<string, number>;
+}
+
+export interface ModelFallbackChain {
+    model_pattern: string;
+    // e.g. "claude-sonnet-4-5-thinking", "kiro:claude-opus-4-6", "zai", "zai:glm-4.7"
+    fallbacks: string[];
+}
+
+export interface ModelFallbackConfig {
+    chains: ModelFallbackChain[];
+}
+
+export type RouterProtocol = 'anthropic' | 'openai' | 'gemini';
+
+export interface RouterConditions {
+    // Unanchored; captures can be used in target as $1 / ${name}
+    model_regex?: string;
+    has_tools?: boolean;
+    has_images?: boolean;
+    thinking?: boolean;
+    min_prompt_tokens?: number;
+    max_prompt_tokens?: number;
+    // Client API key id or name
+    client_keys?: string[];
+    protocols?: RouterProtocol[];
+}
+
+export interface RouterRule {
+    name: string;
+    enabled?: boolean;
+    // Lower runs first; ties keep config order
+    priority?: number;
+    when?: RouterConditions;
+    target: string;
+}
+
+export interface ModelRouterConfig {
+    rules: RouterRule[];
+}
+
+// Caches responses to temperature-0 requests and detected background tasks
+export interface ResponseCacheConfig {
+    enabled: boolean;
+    ttl_secs: number;
+    max_entries: number;
+    max_size_mb: number;
+}
+
+export interface ResponseCacheStats {
+    entries: number;
+    total_bytes: number;
+    hits: number;
+}
+
+// Capture level, redaction and retention for monitor request/response bodies
+export type CaptureLevel = 'metadata' | 'truncated' | 'full';
+
+export interface MonitorPolicyConfig {
+    capture_level: CaptureLevel;
+    protocol_levels: Record<string, CaptureLevel>; // anthropic / openai / gemini / kiro / mcp / other
+    truncate_bytes: number;
+    redact_secrets: boolean;
+    redact_emails: boolean;
+    custom_patterns: string[];
+    strip_images: boolean;
+    retention_days: number; // 0 = keep forever
+    max_db_size_mb: number; // 0 = unlimited
+    vacuum: boolean;
+}
+
+export type McpTransport = 'http' | 'stdio';
+
+export interface McpServerConfig {
+    name: string;
+    enabled: boolean;
+    transport: McpTransport;
+    url?: string;
+    headers?: Record<string, string>;
+    command?: string;
+    args?: string[];
+    env?: Record<string, string>;
+    cwd?: string | null;
+    allow_tools?: string[];
+    deny_tools?: string[];
+}
+
+export interface McpGatewayConfig {
+    enabled: boolean;
+    servers: McpServerConfig[];
+}
+
 export type ZaiDispatchMode = 'off' | 'exclusive' | 'pooled' | 'fallback';
 
 export interface ZaiMcpConfig {